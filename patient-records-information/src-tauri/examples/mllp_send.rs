@@ -0,0 +1,41 @@
+// Loopback HL7 sender for testing the server's MLLP listener.
+//
+// Start the server with MLLP enabled, then send the bundled sample messages:
+//   MLLP_ENABLED=true cargo run --bin server
+//   cargo run --example mllp_send                      # ADT^A04 + ORU^R01 to 127.0.0.1:2575
+//   cargo run --example mllp_send -- 127.0.0.1:2575 message.hl7
+//
+// A message file may use `\r` or `\n` as segment separator.
+
+use patient_records_information_lib::hl7::mllp::send_message;
+use std::env;
+use std::time::Duration;
+
+const SAMPLE_ADT_A04: &str = "MSH|^~\\&|HIS|PARTNER_HOSP|PATIENT_RECORDS|CLINIC|20260101083000||ADT^A04^ADT_A01|ADT0001|P|2.5\r\
+EVN|A04|20260101083000\r\
+PID|1||HOSP-000123^^^PARTNER_HOSP^MR||Dela Cruz^Juan^Santos||19580412|M|||123 Rizal St^^Quezon City^Metro Manila^1100||09171234567\r";
+
+const SAMPLE_ORU_R01: &str = "MSH|^~\\&|LIS|PARTNER_HOSP|PATIENT_RECORDS|CLINIC|20260101101500||ORU^R01^ORU_R01|ORU0001|P|2.5\r\
+PID|1||HOSP-000123^^^PARTNER_HOSP^MR||Dela Cruz^Juan^Santos||19580412|M\r\
+OBR|1|PL-5501|FL-9901|CBC^Complete Blood Count|||20260101093000\r\
+OBX|1|NM|HGB^Hemoglobin||13.2|g/dL|13.5-17.5|L|||F|||20260101100000\r\
+OBX|2|NM|WBC^White Blood Cells||7.4|10*3/uL|4.5-11.0|N|||F|||20260101100000\r";
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let addr = args.first().cloned().unwrap_or_else(|| "127.0.0.1:2575".to_string());
+
+    let messages = match args.get(1) {
+        Some(path) => vec![std::fs::read_to_string(path)?.replace("\r\n", "\r").replace('\n', "\r")],
+        None => vec![SAMPLE_ADT_A04.to_string(), SAMPLE_ORU_R01.to_string()],
+    };
+
+    for message in messages {
+        println!("➡️  Sending to {}:\n{}\n", addr, message.replace('\r', "\n"));
+        let ack = send_message(&addr, &message, Duration::from_secs(10)).await?;
+        println!("⬅️  Received:\n{}\n", ack.replace('\r', "\n"));
+    }
+
+    Ok(())
+}
@@ -16,6 +16,12 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         workers: Some(4),             // Custom worker count
         enable_cloud_sync: true,      // Enable cloud synchronization
         cloud_sync_interval_seconds: 60, // Check cloud health every minute
+        mllp_enabled: true,           // Accept HL7 v2 messages over MLLP
+        mllp_host: "0.0.0.0".to_string(),
+        mllp_port: 2575,
+        retention_enabled: false,     // Scan for records past their retention period
+        retention_rules: "patient:archived:3650".to_string(),
+        retention_scan_interval_seconds: 86400,
     };
     
     println!("🏥 Starting Patient Records Information Server");
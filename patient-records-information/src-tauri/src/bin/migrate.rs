@@ -1,42 +1,160 @@
 use clap::{Parser, Subcommand};
-use patient_records_information_lib::migrations::cli;
+use sea_orm::DbErr;
+use patient_records_information_lib::migrations::cli::{self, DatabaseSelection, Target};
 
 #[derive(Parser)]
 #[command(name = "migrate")]
 #[command(about = "A CLI tool for managing database migrations")]
 struct Cli {
+    /// Connect to this database instead of the configured ones
+    #[arg(long, global = true, conflicts_with = "target")]
+    database_url: Option<String>,
+    /// Configured database(s) to use: DATABASE_URL_LOCAL, DATABASE_URL_CLOUD or both
+    /// [default: local; all for rotate-keys, import-psgc and import-icd10]
+    #[arg(long, global = true, value_enum)]
+    target: Option<Target>,
     #[command(subcommand)]
     command: Commands,
 }
 
+impl Cli {
+    fn selection(&self, default: Target) -> DatabaseSelection {
+        match &self.database_url {
+            Some(url) => DatabaseSelection::Url(url.clone()),
+            None => DatabaseSelection::Target(self.target.unwrap_or(default)),
+        }
+    }
+}
+
 #[derive(Subcommand)]
 enum Commands {
     /// Run all pending migrations
-    Up,
-    /// Rollback the last migration
-    Down,
+    Up {
+        /// Stop after this migration (full name or unique prefix, e.g. m20240101_000005)
+        #[arg(long)]
+        to: Option<String>,
+        /// Print the SQL the migrations would execute instead of applying them
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Rollback the last migration, or the last --steps migrations
+    Down {
+        /// Number of migrations to roll back
+        #[arg(long, default_value_t = 1)]
+        steps: u32,
+        /// Print the SQL the rollback would execute instead of applying it
+        #[arg(long)]
+        dry_run: bool,
+    },
     /// Reset all migrations
     Reset,
+    /// Drop all tables (including ones not created by migrations) and run every migration
+    Fresh,
     /// Show migration status
     Status,
+    /// Compare the entities with the migrated schema; exits non-zero on drift
+    Verify,
+    /// Report orphaned rows, unknown enum values and inconsistent bills; exits non-zero on issues
+    Check {
+        /// Repair the safe cases: bill totals and statuses, orphaned service lines and household links
+        #[arg(long)]
+        fix: bool,
+    },
+    /// Create a timestamped migration from template.rs and register it in mod.rs
+    Generate {
+        /// What the migration does, e.g. "add email to patients"
+        #[arg(required = true, num_args = 1..)]
+        description: Vec<String>,
+    },
+    /// Re-encrypt patient PII with the active key (first entry of PII_ENCRYPTION_KEYS)
+    RotateKeys {
+        /// Number of patients loaded and re-encrypted per batch
+        #[arg(long, default_value_t = 500)]
+        batch_size: u64,
+    },
+    /// Import the PSGC reference table from a CSV export of the PSA publication
+    ImportPsgc {
+        /// CSV file with psgc_code, name and geographic_level columns
+        file: String,
+    },
+    /// Import or refresh the ICD-10 code table from a CSV export
+    ImportIcd10 {
+        /// CSV file with code and description columns
+        file: String,
+    },
+    /// Load the bundled medical services catalog (missing services only)
+    Seed {
+        #[command(subcommand)]
+        command: Option<SeedCommand>,
+    },
+}
+
+#[derive(Subcommand)]
+enum SeedCommand {
+    /// Generate fake patients with medical records, bills and services provided
+    Demo {
+        /// Number of patients to generate
+        #[arg(long, default_value_t = 50)]
+        patients: u32,
+        /// Random seed; the same seed always generates the same data
+        #[arg(long, default_value_t = 42)]
+        seed: u64,
+    },
 }
 
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
+async fn main() {
     let cli = Cli::parse();
+    if let Err(e) = run(&cli).await {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+}
 
+async fn run(cli: &Cli) -> Result<(), DbErr> {
     match &cli.command {
-        Commands::Up => {
-            cli::run_migration_cli().await?;
+        Commands::Up { to, dry_run } => {
+            cli::run_migration_cli(&cli.selection(Target::Local), to.as_deref(), *dry_run).await?;
         }
-        Commands::Down => {
-            cli::rollback_migration_cli().await?;
+        Commands::Down { steps, dry_run } => {
+            cli::rollback_migration_cli(&cli.selection(Target::Local), *steps, *dry_run).await?;
         }
         Commands::Reset => {
-            cli::reset_migration_cli().await?;
+            cli::reset_migration_cli(&cli.selection(Target::Local)).await?;
+        }
+        Commands::Fresh => {
+            cli::fresh_migration_cli(&cli.selection(Target::Local)).await?;
         }
         Commands::Status => {
-            cli::status_migration_cli().await?;
+            cli::status_migration_cli(&cli.selection(Target::Local)).await?;
+        }
+        Commands::Verify => {
+            if !cli::verify_schema_cli(&cli.selection(Target::Local)).await? {
+                std::process::exit(1);
+            }
+        }
+        Commands::Check { fix } => {
+            if !cli::check_integrity_cli(&cli.selection(Target::Local), *fix).await? {
+                std::process::exit(1);
+            }
+        }
+        Commands::Generate { description } => {
+            cli::generate_migration_cli(&description.join(" "))?;
+        }
+        Commands::RotateKeys { batch_size } => {
+            cli::rotate_keys_cli(&cli.selection(Target::All), *batch_size).await?;
+        }
+        Commands::ImportPsgc { file } => {
+            cli::import_psgc_cli(&cli.selection(Target::All), file).await?;
+        }
+        Commands::ImportIcd10 { file } => {
+            cli::import_icd10_cli(&cli.selection(Target::All), file).await?;
+        }
+        Commands::Seed { command: None } => {
+            cli::seed_catalog_cli(&cli.selection(Target::Local)).await?;
+        }
+        Commands::Seed { command: Some(SeedCommand::Demo { patients, seed }) } => {
+            cli::seed_demo_cli(&cli.selection(Target::Local), *patients, *seed).await?;
         }
     }
 
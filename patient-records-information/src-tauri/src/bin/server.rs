@@ -16,6 +16,8 @@ async fn main() -> std::io::Result<()> {
     log::info!("   Port: {}", config.port);
     log::info!("   Cloud Sync: {}", config.enable_cloud_sync);
     log::info!("   Health Check Interval: {}s", config.cloud_sync_interval_seconds);
+    log::info!("   HL7 MLLP: {} ({}:{})", config.mllp_enabled, config.mllp_host, config.mllp_port);
+    log::info!("   Retention job: {} (every {}s)", config.retention_enabled, config.retention_scan_interval_seconds);
     
 
     log::info!("Server is starting");
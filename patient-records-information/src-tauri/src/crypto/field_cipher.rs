@@ -0,0 +1,360 @@
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::env;
+use std::fmt;
+use std::sync::OnceLock;
+
+/// Prefix of every encrypted value: `enc:v2:<key id>:<base64(nonce || ciphertext)>`.
+/// The associated data is the column name and the record key.
+pub const CIPHERTEXT_PREFIX: &str = "enc:v2:";
+
+/// Values written before the record key was bound; the associated data is the
+/// column name only. Still decrypted, and re-encrypted by a key rotation.
+pub const LEGACY_CIPHERTEXT_PREFIX: &str = "enc:v1:";
+
+const KEY_BYTES: usize = 32;
+const NONCE_BYTES: usize = 24;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CryptoError(pub String);
+
+impl fmt::Display for CryptoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "PII encryption error: {}", self.0)
+    }
+}
+
+impl std::error::Error for CryptoError {}
+
+/// Encrypts individual column values with XChaCha20-Poly1305 and computes
+/// HMAC-SHA256 blind indexes for exact-match lookups.
+///
+/// Several data keys can be loaded at once; the first one encrypts, all of them
+/// decrypt, which is what allows re-encrypting rows in place during a rotation.
+/// The blind-index key is separate and is not rotated (changing it requires
+/// recomputing every index).
+pub struct FieldCipher {
+    active_key_id: String,
+    keys: HashMap<String, XChaCha20Poly1305>,
+    blind_index_key: Vec<u8>,
+}
+
+impl fmt::Debug for FieldCipher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut key_ids: Vec<&String> = self.keys.keys().collect();
+        key_ids.sort();
+        f.debug_struct("FieldCipher")
+            .field("active_key_id", &self.active_key_id)
+            .field("key_ids", &key_ids)
+            .finish_non_exhaustive()
+    }
+}
+
+fn decode_key(name: &str, encoded: &str) -> Result<Vec<u8>, CryptoError> {
+    let key = BASE64
+        .decode(encoded.trim())
+        .map_err(|e| CryptoError(format!("{} is not valid base64: {}", name, e)))?;
+    if key.len() != KEY_BYTES {
+        return Err(CryptoError(format!("{} must be {} bytes, got {}", name, KEY_BYTES, key.len())));
+    }
+    Ok(key)
+}
+
+impl FieldCipher {
+    /// `keys` is `id:base64key[,id:base64key...]`, the first entry being the
+    /// active key; `blind_index_key` is a base64 32-byte key.
+    pub fn new(keys: &str, blind_index_key: &str) -> Result<Self, CryptoError> {
+        let mut parsed = HashMap::new();
+        let mut active_key_id = None;
+
+        for entry in keys.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (id, encoded) = entry
+                .split_once(':')
+                .ok_or_else(|| CryptoError(format!("key entry '{}' must look like id:base64key", entry)))?;
+            if id.is_empty() || id.contains(':') {
+                return Err(CryptoError(format!("invalid key id '{}'", id)));
+            }
+            let key = decode_key(&format!("key '{}'", id), encoded)?;
+            let cipher = XChaCha20Poly1305::new_from_slice(&key)
+                .map_err(|_| CryptoError(format!("key '{}' has an invalid length", id)))?;
+            if parsed.insert(id.to_string(), cipher).is_some() {
+                return Err(CryptoError(format!("key id '{}' is listed twice", id)));
+            }
+            active_key_id.get_or_insert_with(|| id.to_string());
+        }
+
+        let active_key_id = active_key_id.ok_or_else(|| CryptoError("no encryption key configured".to_string()))?;
+        let blind_index_key = decode_key("PII_BLIND_INDEX_KEY", blind_index_key)?;
+
+        Ok(Self {
+            active_key_id,
+            keys: parsed,
+            blind_index_key,
+        })
+    }
+
+    /// Reads `PII_ENCRYPTION_KEYS` and `PII_BLIND_INDEX_KEY`. Returns `Ok(None)`
+    /// when no keys are configured (encryption disabled).
+    pub fn from_env() -> Result<Option<Self>, CryptoError> {
+        match env::var("PII_ENCRYPTION_KEYS") {
+            Ok(keys) if !keys.trim().is_empty() => {
+                let blind_index_key = env::var("PII_BLIND_INDEX_KEY").map_err(|_| {
+                    CryptoError("PII_BLIND_INDEX_KEY is required when PII_ENCRYPTION_KEYS is set".to_string())
+                })?;
+                Self::new(&keys, &blind_index_key).map(Some)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    pub fn active_key_id(&self) -> &str {
+        &self.active_key_id
+    }
+
+    /// Encrypts `plaintext`. `context` (the column name) and `record` (the key
+    /// of the row) are bound as associated data, so a value can be moved
+    /// neither to another column nor to another row.
+    pub fn encrypt(&self, context: &str, record: &str, plaintext: &str) -> Result<String, CryptoError> {
+        let cipher = &self.keys[&self.active_key_id];
+        let mut nonce = [0u8; NONCE_BYTES];
+        rand::thread_rng().fill_bytes(&mut nonce);
+
+        let ciphertext = cipher
+            .encrypt(
+                XNonce::from_slice(&nonce),
+                Payload {
+                    msg: plaintext.as_bytes(),
+                    aad: &associated_data(context, Some(record)),
+                },
+            )
+            .map_err(|_| CryptoError(format!("failed to encrypt {}", context)))?;
+
+        let mut blob = nonce.to_vec();
+        blob.extend_from_slice(&ciphertext);
+        Ok(format!("{}{}:{}", CIPHERTEXT_PREFIX, self.active_key_id, BASE64.encode(blob)))
+    }
+
+    /// Decrypts a value produced by [`FieldCipher::encrypt`] for the same
+    /// `context` and `record` (or a legacy `enc:v1:` value of that column).
+    /// Anything else, plaintext included, is an error.
+    pub fn decrypt(&self, context: &str, record: &str, value: &str) -> Result<String, CryptoError> {
+        let Some(parsed) = parse_ciphertext(value) else {
+            return Err(CryptoError(format!("{} is not encrypted", context)));
+        };
+        let cipher = self.keys.get(parsed.key_id).ok_or_else(|| {
+            CryptoError(format!("{} was encrypted with unknown key '{}'", context, parsed.key_id))
+        })?;
+        let blob = BASE64
+            .decode(parsed.payload)
+            .map_err(|_| CryptoError(format!("{} holds malformed ciphertext", context)))?;
+        if blob.len() < NONCE_BYTES {
+            return Err(CryptoError(format!("{} holds truncated ciphertext", context)));
+        }
+        let (nonce, ciphertext) = blob.split_at(NONCE_BYTES);
+        let record = (!parsed.legacy).then_some(record);
+
+        let plaintext = cipher
+            .decrypt(
+                XNonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: &associated_data(context, record),
+                },
+            )
+            .map_err(|_| CryptoError(format!("{} failed authentication (wrong key or tampered value)", context)))?;
+        String::from_utf8(plaintext).map_err(|_| CryptoError(format!("{} is not valid UTF-8", context)))
+    }
+
+    /// True when the value is plaintext, in the legacy format, or encrypted
+    /// with a key other than the active one.
+    pub fn needs_reencryption(&self, value: &str) -> bool {
+        match parse_ciphertext(value) {
+            Some(parsed) => parsed.legacy || parsed.key_id != self.active_key_id,
+            None => true,
+        }
+    }
+
+    /// Deterministic keyed hash of an already normalized value. `context`
+    /// separates indexes of different columns so equal values don't collide.
+    pub fn blind_index(&self, context: &str, normalized: &str) -> String {
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(&self.blind_index_key)
+            .expect("HMAC accepts keys of any length");
+        mac.update(context.as_bytes());
+        mac.update(&[0]);
+        mac.update(normalized.as_bytes());
+        hex::encode(mac.finalize().into_bytes())
+    }
+}
+
+fn associated_data(context: &str, record: Option<&str>) -> Vec<u8> {
+    let mut aad = context.as_bytes().to_vec();
+    if let Some(record) = record {
+        aad.push(0);
+        aad.extend_from_slice(record.as_bytes());
+    }
+    aad
+}
+
+/// The parts of a stored ciphertext.
+pub struct Ciphertext<'a> {
+    pub key_id: &'a str,
+    pub payload: &'a str,
+    /// Written in the `enc:v1:` format.
+    pub legacy: bool,
+}
+
+/// Splits `enc:v2:<key id>:<payload>` (or the legacy `enc:v1:` form) into its parts.
+pub fn parse_ciphertext(value: &str) -> Option<Ciphertext<'_>> {
+    let (rest, legacy) = match value.strip_prefix(CIPHERTEXT_PREFIX) {
+        Some(rest) => (rest, false),
+        None => (value.strip_prefix(LEGACY_CIPHERTEXT_PREFIX)?, true),
+    };
+    let (key_id, payload) = rest.split_once(':')?;
+    Some(Ciphertext { key_id, payload, legacy })
+}
+
+/// Whether a value has the shape of a ciphertext. Only meaningful for rows
+/// stored before `patients_table.pii_encrypted` recorded it; user input is
+/// always encrypted, whatever it looks like.
+pub fn is_encrypted(value: &str) -> bool {
+    parse_ciphertext(value).is_some()
+}
+
+static GLOBAL: OnceLock<Result<Option<FieldCipher>, CryptoError>> = OnceLock::new();
+
+/// Process-wide cipher configured from the environment on first use.
+///
+/// `Ok(None)` means encryption is disabled; an invalid configuration is
+/// reported on every call so that PII is never silently written in clear.
+pub fn global() -> Result<Option<&'static FieldCipher>, CryptoError> {
+    match GLOBAL.get_or_init(FieldCipher::from_env) {
+        Ok(cipher) => Ok(cipher.as_ref()),
+        Err(e) => Err(e.clone()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(byte: u8) -> String {
+        BASE64.encode([byte; KEY_BYTES])
+    }
+
+    fn cipher(keys: &[(&str, u8)]) -> FieldCipher {
+        let keys: Vec<String> = keys.iter().map(|(id, byte)| format!("{}:{}", id, key(*byte))).collect();
+        FieldCipher::new(&keys.join(","), &key(9)).unwrap()
+    }
+
+    /// A value in the `enc:v1:` format, whose associated data is the column only.
+    fn legacy_value(key_byte: u8, key_id: &str, context: &str, plaintext: &str) -> String {
+        let nonce = [7u8; NONCE_BYTES];
+        let ciphertext = XChaCha20Poly1305::new_from_slice(&[key_byte; KEY_BYTES])
+            .unwrap()
+            .encrypt(
+                XNonce::from_slice(&nonce),
+                Payload {
+                    msg: plaintext.as_bytes(),
+                    aad: &associated_data(context, None),
+                },
+            )
+            .unwrap();
+        let mut blob = nonce.to_vec();
+        blob.extend_from_slice(&ciphertext);
+        format!("{}{}:{}", LEGACY_CIPHERTEXT_PREFIX, key_id, BASE64.encode(blob))
+    }
+
+    #[test]
+    fn encrypt_round_trips_with_a_fresh_nonce() {
+        let cipher = cipher(&[("k1", 1)]);
+
+        let first = cipher.encrypt("first_name", "row-1", "Juan").unwrap();
+        let second = cipher.encrypt("first_name", "row-1", "Juan").unwrap();
+
+        assert!(first.starts_with("enc:v2:k1:"));
+        assert_ne!(first, second);
+        assert_eq!(cipher.decrypt("first_name", "row-1", &first).unwrap(), "Juan");
+        assert_eq!(cipher.decrypt("first_name", "row-1", &second).unwrap(), "Juan");
+    }
+
+    #[test]
+    fn decrypt_fails_with_the_wrong_key() {
+        let value = cipher(&[("k1", 1)]).encrypt("first_name", "row-1", "Juan").unwrap();
+
+        // Same id, different key material.
+        assert!(cipher(&[("k1", 2)]).decrypt("first_name", "row-1", &value).is_err());
+        // Key not loaded at all.
+        assert!(cipher(&[("k2", 1)]).decrypt("first_name", "row-1", &value).is_err());
+    }
+
+    #[test]
+    fn decrypt_fails_for_another_column_or_row() {
+        let cipher = cipher(&[("k1", 1)]);
+        let value = cipher.encrypt("first_name", "row-1", "Juan").unwrap();
+
+        assert!(cipher.decrypt("last_name", "row-1", &value).is_err());
+        assert!(cipher.decrypt("first_name", "row-2", &value).is_err());
+    }
+
+    #[test]
+    fn decrypt_rejects_plaintext_and_malformed_values() {
+        let cipher = cipher(&[("k1", 1)]);
+
+        assert!(cipher.decrypt("first_name", "row-1", "Juan").is_err());
+        assert!(cipher.decrypt("first_name", "row-1", "enc:v2:k1:not base64").is_err());
+        assert!(cipher.decrypt("first_name", "row-1", "enc:v2:k1:AAAA").is_err());
+    }
+
+    #[test]
+    fn decrypt_reads_legacy_values_of_the_same_column() {
+        let cipher = cipher(&[("k1", 1)]);
+        let value = legacy_value(1, "k1", "first_name", "Juan");
+
+        assert_eq!(cipher.decrypt("first_name", "any-row", &value).unwrap(), "Juan");
+        assert!(cipher.decrypt("last_name", "any-row", &value).is_err());
+    }
+
+    #[test]
+    fn old_keys_still_decrypt_after_a_new_key_becomes_active() {
+        let value = cipher(&[("k1", 1)]).encrypt("first_name", "row-1", "Juan").unwrap();
+        let rotated = cipher(&[("k2", 2), ("k1", 1)]);
+
+        assert_eq!(rotated.active_key_id(), "k2");
+        assert_eq!(rotated.decrypt("first_name", "row-1", &value).unwrap(), "Juan");
+    }
+
+    #[test]
+    fn needs_reencryption_unless_current_format_and_active_key() {
+        let old = cipher(&[("k1", 1)]);
+        let rotated = cipher(&[("k2", 2), ("k1", 1)]);
+        let current = rotated.encrypt("first_name", "row-1", "Juan").unwrap();
+
+        assert!(!rotated.needs_reencryption(&current));
+        assert!(rotated.needs_reencryption(&old.encrypt("first_name", "row-1", "Juan").unwrap()));
+        assert!(rotated.needs_reencryption(&legacy_value(2, "k2", "first_name", "Juan")));
+        assert!(rotated.needs_reencryption("Juan"));
+    }
+
+    #[test]
+    fn blind_index_is_deterministic_per_column() {
+        let cipher = cipher(&[("k1", 1)]);
+
+        assert_eq!(cipher.blind_index("last_name", "dela cruz"), cipher.blind_index("last_name", "dela cruz"));
+        assert_ne!(cipher.blind_index("last_name", "dela cruz"), cipher.blind_index("first_name", "dela cruz"));
+    }
+
+    #[test]
+    fn new_rejects_invalid_key_configuration() {
+        assert!(FieldCipher::new("", &key(9)).is_err());
+        assert!(FieldCipher::new(&format!("k1{}", key(1)), &key(9)).is_err());
+        assert!(FieldCipher::new(&format!("k1:{},k1:{}", key(1), key(2)), &key(9)).is_err());
+        assert!(FieldCipher::new("k1:AAAA", &key(9)).is_err());
+        assert!(FieldCipher::new(&format!("k1:{}", key(1)), "AAAA").is_err());
+    }
+}
@@ -0,0 +1,5 @@
+pub mod field_cipher;
+pub mod normalize;
+pub mod rotation;
+
+pub use field_cipher::{global, is_encrypted, CryptoError, FieldCipher};
@@ -0,0 +1,25 @@
+//! Canonical forms used before computing blind indexes, so that equivalent
+//! inputs (`+63 917 123 4567` and `09171234567`) hash to the same value.
+
+/// Digits only, with the Philippine country code `63` replaced by the trunk `0`.
+pub fn mobile_number(value: &str) -> String {
+    let digits: String = value.chars().filter(|c| c.is_ascii_digit()).collect();
+    match digits.strip_prefix("63") {
+        Some(rest) if rest.len() == 10 => format!("0{}", rest),
+        _ => digits,
+    }
+}
+
+/// Upper case without spaces and hyphens.
+pub fn csd_id_or_pwd_id(value: &str) -> String {
+    value
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '-')
+        .flat_map(char::to_uppercase)
+        .collect()
+}
+
+/// Lower case, surrounding whitespace trimmed and inner runs collapsed.
+pub fn name(value: &str) -> String {
+    value.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase()
+}
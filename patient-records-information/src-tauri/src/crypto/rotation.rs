@@ -0,0 +1,87 @@
+use sea_orm::{ColumnTrait, DatabaseConnection, DbErr, EntityTrait, IntoActiveModel, QueryFilter, QueryOrder, QuerySelect};
+use sea_orm::ActiveValue::Set;
+use uuid::Uuid;
+
+use crate::crypto::FieldCipher;
+use crate::models::patient_tb::{Column as PatientColumn, Entity as PatientEntity, Model as PatientModel};
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RotationReport {
+    pub scanned: u64,
+    pub reencrypted: u64,
+}
+
+fn needs_rotation(cipher: &FieldCipher, patient: &PatientModel) -> bool {
+    if !patient.pii_encrypted {
+        return true;
+    }
+    [Some(&patient.first_name), Some(&patient.last_name)]
+        .into_iter()
+        .chain([
+            patient.middle_name.as_ref(),
+            patient.csd_id_or_pwd_id.as_ref(),
+            patient.mobile_number.as_ref(),
+            patient.residential_address.as_ref(),
+            patient.address_line.as_ref(),
+        ])
+        .flatten()
+        .any(|value| cipher.needs_reencryption(value))
+}
+
+/// Re-encrypts every patient whose PII is still plaintext, in the legacy
+/// `enc:v1:` format or sealed with a key other than the active one, `batch_size` rows at a time, and recomputes the
+/// blind indexes. Rows are written directly (no entity hooks), so `updated_at`
+/// and therefore ETags are left unchanged.
+///
+/// The old keys must still be configured (after the new active key) until the
+/// rotation has completed on every database.
+pub async fn rotate_patient_keys(
+    db: &DatabaseConnection,
+    cipher: &FieldCipher,
+    batch_size: u64,
+) -> Result<RotationReport, DbErr> {
+    let mut report = RotationReport::default();
+    let mut last_id: Option<Uuid> = None;
+
+    loop {
+        let mut query = PatientEntity::find().order_by_asc(PatientColumn::PatientId).limit(batch_size.max(1));
+        if let Some(last_id) = last_id {
+            query = query.filter(PatientColumn::PatientId.gt(last_id));
+        }
+        let batch = query.all(db).await?;
+        let Some(last) = batch.last() else {
+            break;
+        };
+        last_id = Some(last.patient_id);
+
+        for patient in batch {
+            report.scanned += 1;
+            if !needs_rotation(cipher, &patient) {
+                continue;
+            }
+
+            let patient_id = patient.patient_id;
+            let plain = patient.decrypt_with(cipher).map_err(|e| DbErr::Custom(e.to_string()))?;
+            let mut active = plain.clone().into_active_model();
+            active.first_name = Set(plain.first_name);
+            active.last_name = Set(plain.last_name);
+            active.middle_name = Set(plain.middle_name);
+            active.csd_id_or_pwd_id = Set(plain.csd_id_or_pwd_id);
+            active.mobile_number = Set(plain.mobile_number);
+            active.residential_address = Set(plain.residential_address);
+            active.address_line = Set(plain.address_line);
+            active.encrypt_pii(cipher).map_err(|e| DbErr::Custom(e.to_string()))?;
+
+            PatientEntity::update_many()
+                .set(active)
+                .filter(PatientColumn::PatientId.eq(patient_id))
+                .exec(db)
+                .await?;
+            report.reencrypted += 1;
+        }
+
+        log::info!("🔑 Key rotation: {} patient(s) scanned, {} re-encrypted", report.scanned, report.reencrypted);
+    }
+
+    Ok(report)
+}
@@ -0,0 +1,551 @@
+use actix_web::{http::StatusCode, web, HttpRequest, HttpResponse, HttpResponseBuilder, Result};
+use chrono::NaiveDate;
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, ModelTrait, QueryFilter, QueryOrder};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::fhir::mapping::{
+    lab_result_to_observation, patient_from_fhir, patient_to_fhir, record_to_condition, record_to_encounter,
+    service_result_to_observation, CSD_PWD_ID_SYSTEM, PATIENT_NUMBER_SYSTEM,
+};
+use crate::fhir::resources::{Bundle, Observation, OperationOutcome, Patient};
+use crate::handlers::consent_handlers::{has_consent, patients_with_consent};
+use crate::handlers::lab_result_handlers::{get_lab_result, get_lab_results_for_patient};
+use crate::handlers::{get_patient, search_patients, PatientSearch};
+use crate::server::patient_numbers::create_patient_with_cloud_copy;
+use crate::models::medical_record_tb::{
+    Column as MedicalRecordColumn, Entity as MedicalRecordEntity, Model as MedicalRecordModel,
+};
+use crate::models::consent_tb::ConsentType;
+use crate::models::medical_bill_record::{Column as BillColumn, Entity as BillEntity};
+use crate::models::medical_services_provided::{Column as ServiceProvidedColumn, Entity as ServiceProvidedEntity};
+use crate::models::patient_tb::{Entity as PatientEntity, Model as PatientModel};
+use crate::models::service_result_tb::{Column as ServiceResultColumn, Entity as ServiceResultEntity};
+use crate::server::state::AppState;
+
+pub const FHIR_JSON: &str = "application/fhir+json";
+
+/// Partner systems only see patients with an active consent of this type.
+const SHARING_CONSENT: ConsentType = ConsentType::DataProcessing;
+
+/// Search parameters accepted on `GET /fhir/r4/Patient`.
+#[derive(Debug, Deserialize)]
+pub struct PatientSearchParams {
+    #[serde(rename = "_id")]
+    pub id: Option<String>,
+    pub family: Option<String>,
+    pub given: Option<String>,
+    pub birthdate: Option<NaiveDate>,
+    /// Token search: either `value` or `system|value`.
+    pub identifier: Option<String>,
+    /// Token search on the patient's phone: `value` or `phone|value`.
+    pub telecom: Option<String>,
+    #[serde(rename = "_count")]
+    pub count: Option<u64>,
+}
+
+/// Search parameters accepted on `GET /fhir/r4/Encounter`, `GET /fhir/r4/Condition`
+/// and `GET /fhir/r4/Observation`.
+#[derive(Debug, Deserialize)]
+pub struct PatientCompartmentParams {
+    /// `Patient/{id}` or a bare patient id.
+    pub patient: Option<String>,
+    pub subject: Option<String>,
+}
+
+fn fhir_ok<T: Serialize>(status: StatusCode, body: T) -> HttpResponse {
+    HttpResponseBuilder::new(status)
+        .content_type(FHIR_JSON)
+        .json(body)
+}
+
+fn outcome(status: StatusCode, code: &str, diagnostics: impl Into<String>) -> HttpResponse {
+    fhir_ok(status, OperationOutcome::error(code, diagnostics))
+}
+
+fn db_error(e: sea_orm::DbErr) -> HttpResponse {
+    outcome(StatusCode::INTERNAL_SERVER_ERROR, "exception", format!("Database error: {}", e))
+}
+
+/// Absolute base URL of the facade, used for `Bundle.entry.fullUrl` and `Location`.
+fn base_url(req: &HttpRequest) -> String {
+    let info = req.connection_info();
+    format!("{}://{}/fhir/r4", info.scheme(), info.host())
+}
+
+fn parse_patient_reference(reference: &str) -> Option<Uuid> {
+    let id = reference.strip_prefix("Patient/").unwrap_or(reference);
+    Uuid::parse_str(id).ok()
+}
+
+async fn records_for_patient(
+    db: &DatabaseConnection,
+    patient: &PatientModel,
+) -> Result<Vec<MedicalRecordModel>, sea_orm::DbErr> {
+    patient
+        .find_related(MedicalRecordEntity)
+        .order_by_asc(MedicalRecordColumn::CreatedAt)
+        .all(db)
+        .await
+}
+
+async fn record_with_patient(
+    db: &DatabaseConnection,
+    medical_id: Uuid,
+) -> Result<Option<(MedicalRecordModel, PatientModel)>, sea_orm::DbErr> {
+    let found = MedicalRecordEntity::find_by_id(medical_id)
+        .find_also_related(PatientEntity)
+        .one(db)
+        .await?;
+    Ok(found.and_then(|(record, patient)| patient.map(|p| (record, p))))
+}
+
+/// Answers `403` with an `OperationOutcome` unless the patient consented to
+/// [`SHARING_CONSENT`].
+async fn require_consent(db: &DatabaseConnection, patient_id: Uuid) -> std::result::Result<(), HttpResponse> {
+    match has_consent(db, patient_id, SHARING_CONSENT).await {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(outcome(
+            StatusCode::FORBIDDEN,
+            "forbidden",
+            format!("Patient/{} has not consented to sharing their data", patient_id),
+        )),
+        Err(e) => Err(db_error(e)),
+    }
+}
+
+/// The laboratory results received over HL7 followed by the released results
+/// of services provided at the patient's visits, each as `(id, Observation)`.
+async fn observations_for_patient(
+    db: &DatabaseConnection,
+    patient: &PatientModel,
+) -> Result<Vec<(String, Observation)>, sea_orm::DbErr> {
+    let mut observations: Vec<(String, Observation)> = get_lab_results_for_patient(db, patient.patient_id)
+        .await?
+        .iter()
+        .map(|result| (result.result_id.to_string(), lab_result_to_observation(result)))
+        .collect();
+
+    let visit_of_bill: HashMap<Uuid, Uuid> = BillEntity::find()
+        .filter(BillColumn::PatientId.eq(patient.patient_id))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|bill| (bill.medical_bill_id, bill.medical_id))
+        .collect();
+    let visit_of_service: HashMap<Uuid, Uuid> = ServiceProvidedEntity::find()
+        .filter(ServiceProvidedColumn::MedicalBillId.is_in(visit_of_bill.keys().copied()))
+        .all(db)
+        .await?
+        .into_iter()
+        .filter_map(|service| Some((service.mrs_id, *visit_of_bill.get(&service.medical_bill_id)?)))
+        .collect();
+    let results = ServiceResultEntity::find()
+        .filter(ServiceResultColumn::MrsId.is_in(visit_of_service.keys().copied()))
+        .filter(ServiceResultColumn::ReleasedAt.is_not_null())
+        .order_by_asc(ServiceResultColumn::CreatedAt)
+        .order_by_asc(ServiceResultColumn::ServiceResultId)
+        .all(db)
+        .await?;
+    observations.extend(results.iter().filter_map(|result| {
+        let medical_id = *visit_of_service.get(&result.mrs_id)?;
+        Some((
+            result.service_result_id.to_string(),
+            service_result_to_observation(result, patient.patient_id, medical_id),
+        ))
+    }));
+    Ok(observations)
+}
+
+/// An HL7 laboratory result or a released service result with that id, and
+/// the patient it belongs to.
+async fn observation_by_id(db: &DatabaseConnection, id: Uuid) -> Result<Option<(Observation, Uuid)>, sea_orm::DbErr> {
+    if let Some(result) = get_lab_result(db, id).await? {
+        return Ok(Some((lab_result_to_observation(&result), result.patient_id)));
+    }
+    let Some((result, Some(service))) = ServiceResultEntity::find_by_id(id)
+        .filter(ServiceResultColumn::ReleasedAt.is_not_null())
+        .find_also_related(ServiceProvidedEntity)
+        .one(db)
+        .await?
+    else {
+        return Ok(None);
+    };
+    let bill = BillEntity::find_by_id(service.medical_bill_id).one(db).await?;
+    Ok(bill.map(|bill| (service_result_to_observation(&result, bill.patient_id, bill.medical_id), bill.patient_id)))
+}
+
+/// Resolves the `patient`/`subject` search parameter, answering with an
+/// `OperationOutcome` when it is missing, malformed or unknown, or when the
+/// patient hasn't consented to sharing their data.
+async fn compartment_patient(
+    db: &DatabaseConnection,
+    params: &PatientCompartmentParams,
+) -> std::result::Result<PatientModel, HttpResponse> {
+    let reference = params
+        .patient
+        .as_deref()
+        .or(params.subject.as_deref())
+        .ok_or_else(|| outcome(StatusCode::BAD_REQUEST, "required", "The 'patient' search parameter is required"))?;
+    let patient_id = parse_patient_reference(reference).ok_or_else(|| {
+        outcome(StatusCode::BAD_REQUEST, "invalid", format!("Invalid patient reference '{}'", reference))
+    })?;
+
+    let patient = match get_patient(db, patient_id).await {
+        Ok(Some(patient)) => patient,
+        Ok(None) => {
+            return Err(outcome(StatusCode::NOT_FOUND, "not-found", format!("Patient/{} is not known", patient_id)))
+        }
+        Err(e) => return Err(db_error(e)),
+    };
+    require_consent(db, patient_id).await?;
+    Ok(patient)
+}
+
+/// FHIR capability statement describing the resources served by this facade
+///
+/// # Example
+/// ```
+/// GET /fhir/r4/metadata
+/// Response: 200 OK with a CapabilityStatement
+/// ```
+pub async fn capability_statement() -> Result<HttpResponse> {
+    let search = |params: &[(&str, &str)]| -> Vec<serde_json::Value> {
+        params.iter().map(|(name, kind)| json!({ "name": name, "type": kind })).collect()
+    };
+
+    Ok(fhir_ok(StatusCode::OK, json!({
+        "resourceType": "CapabilityStatement",
+        "status": "active",
+        "date": chrono::Utc::now().to_rfc3339(),
+        "kind": "instance",
+        "fhirVersion": "4.0.1",
+        "format": [FHIR_JSON],
+        "rest": [{
+            "mode": "server",
+            "resource": [
+                {
+                    "type": "Patient",
+                    "interaction": [{ "code": "read" }, { "code": "search-type" }, { "code": "create" }],
+                    "searchParam": search(&[
+                        ("_id", "token"),
+                        ("family", "string"),
+                        ("given", "string"),
+                        ("birthdate", "date"),
+                        ("identifier", "token"),
+                        ("telecom", "token"),
+                    ])
+                },
+                {
+                    "type": "Encounter",
+                    "interaction": [{ "code": "read" }, { "code": "search-type" }],
+                    "searchParam": search(&[("patient", "reference")])
+                },
+                {
+                    "type": "Condition",
+                    "interaction": [{ "code": "read" }, { "code": "search-type" }],
+                    "searchParam": search(&[("patient", "reference")])
+                },
+                {
+                    "type": "Observation",
+                    "interaction": [{ "code": "read" }, { "code": "search-type" }],
+                    "searchParam": search(&[("patient", "reference")])
+                }
+            ]
+        }]
+    })))
+}
+
+/// Searches patients and returns a `searchset` Bundle
+///
+/// # Parameters
+/// - `_id`, `family`, `given`, `birthdate`, `identifier`, `telecom`, `_count` query parameters
+///
+/// With PII encryption enabled `family` is an exact (case-insensitive) match
+/// instead of a prefix match. Patients who haven't consented to sharing their
+/// data are left out.
+///
+/// # Returns
+/// - `200 OK` with a Bundle of matching Patient resources
+/// - `400 Bad Request` with an OperationOutcome for a malformed identifier token
+///
+/// # Example
+/// ```
+/// GET /fhir/r4/Patient?family=dela%20cruz&birthdate=1950-04-12
+/// ```
+pub async fn search_patient_handler(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    query: web::Query<PatientSearchParams>,
+) -> Result<HttpResponse> {
+    let params = query.into_inner();
+    let db = state.get_local_db().await;
+
+    let identifier = match (params.id, params.identifier) {
+        (Some(id), _) => Some(id),
+        (None, Some(token)) => match token.split_once('|') {
+            Some((system, value))
+                if system.is_empty() || system == CSD_PWD_ID_SYSTEM || system == PATIENT_NUMBER_SYSTEM =>
+            {
+                Some(value.to_string())
+            }
+            Some((_, value)) if value.starts_with("urn:uuid:") => Some(value.trim_start_matches("urn:uuid:").to_string()),
+            Some((system, _)) => {
+                return Ok(outcome(
+                    StatusCode::BAD_REQUEST,
+                    "not-supported",
+                    format!("Identifier system '{}' is not supported", system),
+                ))
+            }
+            None => Some(token),
+        },
+        (None, None) => None,
+    };
+
+    let search = PatientSearch {
+        last_name: params.family,
+        first_name: params.given,
+        birth_date: params.birthdate,
+        identifier,
+        patient_number: None,
+        mobile_number: params
+            .telecom
+            .map(|token| token.split_once('|').map_or(token.clone(), |(_, value)| value.to_string())),
+        limit: params.count,
+    };
+
+    let patients = match search_patients(&db, search).await {
+        Ok(patients) => patients,
+        Err(e) => return Ok(db_error(e)),
+    };
+    let patient_ids: Vec<Uuid> = patients.iter().map(|p| p.patient_id).collect();
+    let consenting = match patients_with_consent(&db, &patient_ids, SHARING_CONSENT).await {
+        Ok(consenting) => consenting,
+        Err(e) => return Ok(db_error(e)),
+    };
+    let resources = patients
+        .iter()
+        .filter(|p| consenting.contains(&p.patient_id))
+        .map(|p| (p.patient_id.to_string(), patient_to_fhir(p)))
+        .collect();
+    Ok(fhir_ok(StatusCode::OK, Bundle::searchset(&base_url(&req), "Patient", resources)))
+}
+
+/// Reads a single Patient resource by its UUID
+///
+/// # Returns
+/// - `200 OK` with the Patient resource
+/// - `403 Forbidden` with an OperationOutcome if the patient hasn't consented to sharing their data
+/// - `404 Not Found` with an OperationOutcome if the id is unknown or not a UUID
+pub async fn read_patient_handler(
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> Result<HttpResponse> {
+    let id = path.into_inner();
+    let Ok(patient_id) = Uuid::parse_str(&id) else {
+        return Ok(outcome(StatusCode::NOT_FOUND, "not-found", format!("Patient/{} is not known", id)));
+    };
+
+    let db = state.get_local_db().await;
+    match get_patient(&db, patient_id).await {
+        Ok(Some(patient)) => match require_consent(&db, patient_id).await {
+            Ok(()) => Ok(fhir_ok(StatusCode::OK, patient_to_fhir(&patient))),
+            Err(response) => Ok(response),
+        },
+        Ok(None) => Ok(outcome(StatusCode::NOT_FOUND, "not-found", format!("Patient/{} is not known", id))),
+        Err(e) => Ok(db_error(e)),
+    }
+}
+
+/// Creates a patient from a FHIR Patient resource
+///
+/// # Returns
+/// - `201 Created` with the stored Patient and a `Location` header
+/// - `400 Bad Request` with an OperationOutcome if the body is not a valid Patient
+///
+/// # Synchronization Behavior
+/// - Same as `POST /api/v1/patients`: local first, cloud copy is best effort
+pub async fn create_patient_fhir_handler(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    body: web::Bytes,
+) -> Result<HttpResponse> {
+    let resource: Patient = match serde_json::from_slice(&body) {
+        Ok(resource) => resource,
+        Err(e) => return Ok(outcome(StatusCode::BAD_REQUEST, "structure", format!("Invalid Patient JSON: {}", e))),
+    };
+    let create_req = match patient_from_fhir(&resource) {
+        Ok(create_req) => create_req,
+        Err(msg) => return Ok(outcome(StatusCode::BAD_REQUEST, "invalid", msg)),
+    };
+
+    match create_patient_with_cloud_copy(&state, create_req).await {
+        Ok(patient) => {
+            let location = format!("{}/Patient/{}", base_url(&req), patient.patient_id);
+            Ok(HttpResponse::Created()
+                .content_type(FHIR_JSON)
+                .insert_header(("Location", location))
+                .json(patient_to_fhir(&patient)))
+        }
+        Err(e) => Ok(db_error(e)),
+    }
+}
+
+/// Lists the Encounters (medical records) of a patient
+///
+/// # Example
+/// ```
+/// GET /fhir/r4/Encounter?patient=Patient/{uuid}
+/// ```
+pub async fn search_encounter_handler(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    query: web::Query<PatientCompartmentParams>,
+) -> Result<HttpResponse> {
+    let db = state.get_local_db().await;
+    let patient = match compartment_patient(&db, &query).await {
+        Ok(patient) => patient,
+        Err(response) => return Ok(response),
+    };
+
+    match records_for_patient(&db, &patient).await {
+        Ok(records) => {
+            let resources = records
+                .iter()
+                .map(|r| (r.medical_id.to_string(), record_to_encounter(r, patient.patient_id)))
+                .collect();
+            Ok(fhir_ok(StatusCode::OK, Bundle::searchset(&base_url(&req), "Encounter", resources)))
+        }
+        Err(e) => Ok(db_error(e)),
+    }
+}
+
+/// Reads a single Encounter by medical record id
+pub async fn read_encounter_handler(
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> Result<HttpResponse> {
+    let id = path.into_inner();
+    let Ok(medical_id) = Uuid::parse_str(&id) else {
+        return Ok(outcome(StatusCode::NOT_FOUND, "not-found", format!("Encounter/{} is not known", id)));
+    };
+
+    let db = state.get_local_db().await;
+    match record_with_patient(&db, medical_id).await {
+        Ok(Some((record, patient))) => match require_consent(&db, patient.patient_id).await {
+            Ok(()) => Ok(fhir_ok(StatusCode::OK, record_to_encounter(&record, patient.patient_id))),
+            Err(response) => Ok(response),
+        },
+        Ok(None) => Ok(outcome(StatusCode::NOT_FOUND, "not-found", format!("Encounter/{} is not known", id))),
+        Err(e) => Ok(db_error(e)),
+    }
+}
+
+/// Lists the diagnosed Conditions of a patient, one per record with a diagnosis
+///
+/// # Example
+/// ```
+/// GET /fhir/r4/Condition?patient={uuid}
+/// ```
+pub async fn search_condition_handler(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    query: web::Query<PatientCompartmentParams>,
+) -> Result<HttpResponse> {
+    let db = state.get_local_db().await;
+    let patient = match compartment_patient(&db, &query).await {
+        Ok(patient) => patient,
+        Err(response) => return Ok(response),
+    };
+
+    match records_for_patient(&db, &patient).await {
+        Ok(records) => {
+            let resources = records
+                .iter()
+                .filter_map(|r| record_to_condition(r, patient.patient_id).map(|c| (r.medical_id.to_string(), c)))
+                .collect();
+            Ok(fhir_ok(StatusCode::OK, Bundle::searchset(&base_url(&req), "Condition", resources)))
+        }
+        Err(e) => Ok(db_error(e)),
+    }
+}
+
+/// Reads a single Condition; its id is the id of the medical record carrying the diagnosis
+pub async fn read_condition_handler(
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> Result<HttpResponse> {
+    let id = path.into_inner();
+    let Ok(medical_id) = Uuid::parse_str(&id) else {
+        return Ok(outcome(StatusCode::NOT_FOUND, "not-found", format!("Condition/{} is not known", id)));
+    };
+
+    let db = state.get_local_db().await;
+    match record_with_patient(&db, medical_id).await {
+        Ok(Some((record, patient))) => match record_to_condition(&record, patient.patient_id) {
+            Some(condition) => match require_consent(&db, patient.patient_id).await {
+                Ok(()) => Ok(fhir_ok(StatusCode::OK, condition)),
+                Err(response) => Ok(response),
+            },
+            None => Ok(outcome(StatusCode::NOT_FOUND, "not-found", format!("Condition/{} is not known", id))),
+        },
+        Ok(None) => Ok(outcome(StatusCode::NOT_FOUND, "not-found", format!("Condition/{} is not known", id))),
+        Err(e) => Ok(db_error(e)),
+    }
+}
+
+/// Lists the laboratory Observations of a patient: results received over HL7
+/// and released results of services provided at their visits
+///
+/// # Example
+/// ```
+/// GET /fhir/r4/Observation?patient=Patient/{uuid}
+/// ```
+pub async fn search_observation_handler(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    query: web::Query<PatientCompartmentParams>,
+) -> Result<HttpResponse> {
+    let db = state.get_local_db().await;
+    let patient = match compartment_patient(&db, &query).await {
+        Ok(patient) => patient,
+        Err(response) => return Ok(response),
+    };
+
+    match observations_for_patient(&db, &patient).await {
+        Ok(resources) => Ok(fhir_ok(StatusCode::OK, Bundle::searchset(&base_url(&req), "Observation", resources))),
+        Err(e) => Ok(db_error(e)),
+    }
+}
+
+/// Reads a single Observation; its id is the id of the lab result or service result
+pub async fn read_observation_handler(
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> Result<HttpResponse> {
+    let id = path.into_inner();
+    let Ok(result_id) = Uuid::parse_str(&id) else {
+        return Ok(outcome(StatusCode::NOT_FOUND, "not-found", format!("Observation/{} is not known", id)));
+    };
+
+    let db = state.get_local_db().await;
+    match observation_by_id(&db, result_id).await {
+        Ok(Some((observation, patient_id))) => match require_consent(&db, patient_id).await {
+            Ok(()) => Ok(fhir_ok(StatusCode::OK, observation)),
+            Err(response) => Ok(response),
+        },
+        Ok(None) => Ok(outcome(StatusCode::NOT_FOUND, "not-found", format!("Observation/{} is not known", id))),
+        Err(e) => Ok(db_error(e)),
+    }
+}
+
+/// Fallback for any other `/fhir/r4` path.
+pub async fn unknown_resource_handler(req: HttpRequest) -> Result<HttpResponse> {
+    Ok(outcome(
+        StatusCode::NOT_FOUND,
+        "not-supported",
+        format!("{} {} is not supported by this server", req.method(), req.path()),
+    ))
+}
@@ -0,0 +1,399 @@
+use chrono::NaiveDate;
+use uuid::Uuid;
+
+use crate::fhir::resources::{
+    Address, Annotation, CodeableConcept, Coding, Condition, ContactPoint, Encounter,
+    EncounterParticipant, HumanName, Identifier, Meta, Observation, ObservationReferenceRange, Patient, Period,
+    Quantity, Reference,
+};
+use crate::handlers::{CreatePatientRequest, PatientNumbering};
+use crate::models::lab_result_tb::Model as LabResultModel;
+use crate::models::medical_record_tb::Model as MedicalRecordModel;
+use crate::models::service_result_tb::{Model as ServiceResultModel, ResultFlag};
+use crate::models::patient_tb::{Model as PatientModel, Sex};
+
+/// Identifier system for our own patient UUIDs.
+pub const PATIENT_ID_SYSTEM: &str = "urn:ietf:rfc:3986";
+/// Identifier system for the senior citizen (CSD) or PWD id number.
+pub const CSD_PWD_ID_SYSTEM: &str = "urn:ph:csd-pwd-id";
+/// Identifier system for the human-readable patient number (`PRN-2026-000123`).
+pub const PATIENT_NUMBER_SYSTEM: &str = "urn:patient-records:patient-number";
+
+const ACT_CODE_SYSTEM: &str = "http://terminology.hl7.org/CodeSystem/v3-ActCode";
+const CONDITION_CATEGORY_SYSTEM: &str = "http://terminology.hl7.org/CodeSystem/condition-category";
+const OBSERVATION_CATEGORY_SYSTEM: &str = "http://terminology.hl7.org/CodeSystem/observation-category";
+const INTERPRETATION_SYSTEM: &str = "http://terminology.hl7.org/CodeSystem/v3-ObservationInterpretation";
+
+pub fn patient_to_fhir(patient: &PatientModel) -> Patient {
+    let mut identifier = vec![Identifier {
+        use_: Some("official".to_string()),
+        system: Some(PATIENT_ID_SYSTEM.to_string()),
+        value: Some(format!("urn:uuid:{}", patient.patient_id)),
+    }];
+    if let Some(patient_number) = &patient.patient_number {
+        identifier.push(Identifier {
+            use_: Some("usual".to_string()),
+            system: Some(PATIENT_NUMBER_SYSTEM.to_string()),
+            value: Some(patient_number.clone()),
+        });
+    }
+    if let Some(csd_id) = &patient.csd_id_or_pwd_id {
+        identifier.push(Identifier {
+            use_: Some("secondary".to_string()),
+            system: Some(CSD_PWD_ID_SYSTEM.to_string()),
+            value: Some(csd_id.clone()),
+        });
+    }
+
+    let mut given = vec![patient.first_name.clone()];
+    if let Some(middle_name) = &patient.middle_name {
+        given.push(middle_name.clone());
+    }
+
+    Patient {
+        resource_type: "Patient".to_string(),
+        id: Some(patient.patient_id.to_string()),
+        meta: Some(Meta {
+            last_updated: Some(patient.updated_at.to_rfc3339()),
+        }),
+        identifier,
+        active: Some(!patient.is_archived),
+        name: vec![HumanName {
+            use_: Some("official".to_string()),
+            family: Some(patient.last_name.clone()),
+            given,
+        }],
+        telecom: patient
+            .mobile_number
+            .iter()
+            .map(|number| ContactPoint {
+                system: Some("phone".to_string()),
+                value: Some(number.clone()),
+                use_: Some("mobile".to_string()),
+            })
+            .collect(),
+        gender: Some(
+            match patient.sex {
+                Some(Sex::Male) => "male",
+                Some(Sex::Female) => "female",
+                None => "unknown",
+            }
+            .to_string(),
+        ),
+        birth_date: Some(patient.birth_date.format("%Y-%m-%d").to_string()),
+        address: patient
+            .residential_address
+            .iter()
+            .map(|text| Address {
+                use_: Some("home".to_string()),
+                text: Some(text.clone()),
+            })
+            .collect(),
+    }
+}
+
+/// Converts an incoming FHIR `Patient` into a create request.
+///
+/// Requires an official (or first) name with a family name and at least one given
+/// name, plus a `birthDate`. The second given name becomes the middle name;
+/// a `gender` of `other` or `unknown` leaves the sex unrecorded.
+pub fn patient_from_fhir(patient: &Patient) -> Result<CreatePatientRequest, String> {
+    if patient.resource_type != "Patient" {
+        return Err(format!("Expected resourceType 'Patient', got '{}'", patient.resource_type));
+    }
+
+    let name = patient
+        .name
+        .iter()
+        .find(|n| n.use_.as_deref() == Some("official"))
+        .or_else(|| patient.name.first())
+        .ok_or("Patient.name is required")?;
+    let last_name = name
+        .family
+        .clone()
+        .filter(|f| !f.trim().is_empty())
+        .ok_or("Patient.name.family is required")?;
+    let first_name = name
+        .given
+        .first()
+        .cloned()
+        .filter(|g| !g.trim().is_empty())
+        .ok_or("Patient.name.given is required")?;
+    let middle_name = name.given.get(1).cloned();
+
+    let birth_date = patient
+        .birth_date
+        .as_deref()
+        .ok_or("Patient.birthDate is required")?;
+    let birth_date = NaiveDate::parse_from_str(birth_date, "%Y-%m-%d")
+        .map_err(|_| format!("Patient.birthDate '{}' is not a full date (YYYY-MM-DD)", birth_date))?;
+
+    let csd_id_or_pwd_id = patient
+        .identifier
+        .iter()
+        .find(|i| i.system.as_deref() == Some(CSD_PWD_ID_SYSTEM))
+        .and_then(|i| i.value.clone());
+    let mobile_number = patient
+        .telecom
+        .iter()
+        .find(|t| t.system.as_deref() == Some("phone"))
+        .and_then(|t| t.value.clone());
+    let residential_address = patient.address.first().and_then(|a| a.text.clone());
+    let sex = match patient.gender.as_deref() {
+        Some("male") => Some(Sex::Male),
+        Some("female") => Some(Sex::Female),
+        Some("other") | Some("unknown") | None => None,
+        Some(gender) => return Err(format!("Patient.gender '{}' is not a FHIR administrative gender", gender)),
+    };
+
+    Ok(CreatePatientRequest {
+        first_name,
+        last_name,
+        middle_name,
+        birth_date,
+        sex,
+        csd_id_or_pwd_id,
+        mobile_number,
+        residential_address,
+        address: None,
+        numbering: PatientNumbering::default(),
+    })
+}
+
+fn patient_reference(patient_id: Uuid) -> Reference {
+    Reference {
+        reference: Some(format!("Patient/{}", patient_id)),
+        display: None,
+    }
+}
+
+/// Every medical record is one ambulatory visit.
+pub fn record_to_encounter(record: &MedicalRecordModel, patient_id: Uuid) -> Encounter {
+    let participant = std::iter::once(&record.first_audited_by)
+        .chain(record.last_audited_by.iter())
+        .map(|name| EncounterParticipant {
+            individual: Some(Reference {
+                reference: None,
+                display: Some(name.clone()),
+            }),
+        })
+        .collect();
+
+    Encounter {
+        resource_type: "Encounter".to_string(),
+        id: Some(record.medical_id.to_string()),
+        meta: Some(Meta {
+            last_updated: Some(record.updated_at.to_rfc3339()),
+        }),
+        status: "finished".to_string(),
+        class: Coding {
+            system: Some(ACT_CODE_SYSTEM.to_string()),
+            code: Some("AMB".to_string()),
+            display: Some("ambulatory".to_string()),
+        },
+        subject: Some(patient_reference(patient_id)),
+        participant,
+        period: Some(Period {
+            start: Some(record.created_at.to_rfc3339()),
+            end: None,
+        }),
+    }
+}
+
+/// The free-text diagnosis of a record as a `Condition`; `None` when the record
+/// has no diagnosis yet. The condition shares the record's id.
+pub fn record_to_condition(record: &MedicalRecordModel, patient_id: Uuid) -> Option<Condition> {
+    let diagnosis = record.diagnosis.as_ref().filter(|d| !d.trim().is_empty())?;
+
+    Some(Condition {
+        resource_type: "Condition".to_string(),
+        id: Some(record.medical_id.to_string()),
+        meta: Some(Meta {
+            last_updated: Some(record.updated_at.to_rfc3339()),
+        }),
+        clinical_status: None,
+        category: vec![CodeableConcept {
+            coding: vec![Coding {
+                system: Some(CONDITION_CATEGORY_SYSTEM.to_string()),
+                code: Some("encounter-diagnosis".to_string()),
+                display: Some("Encounter Diagnosis".to_string()),
+            }],
+            text: None,
+        }],
+        code: CodeableConcept {
+            coding: Vec::new(),
+            text: Some(diagnosis.clone()),
+        },
+        subject: patient_reference(patient_id),
+        encounter: Some(Reference {
+            reference: Some(format!("Encounter/{}", record.medical_id)),
+            display: None,
+        }),
+        recorded_date: Some(record.created_at.to_rfc3339()),
+        note: record
+            .assessment
+            .iter()
+            .map(|text| Annotation { text: text.clone() })
+            .collect(),
+    })
+}
+
+fn laboratory_category() -> CodeableConcept {
+    CodeableConcept {
+        coding: vec![Coding {
+            system: Some(OBSERVATION_CATEGORY_SYSTEM.to_string()),
+            code: Some("laboratory".to_string()),
+            display: Some("Laboratory".to_string()),
+        }],
+        text: None,
+    }
+}
+
+/// Our analyte codes are local, so the coding carries no system.
+fn analyte_code(code: &str, name: Option<&str>) -> CodeableConcept {
+    CodeableConcept {
+        coding: vec![Coding {
+            system: None,
+            code: Some(code.to_string()),
+            display: name.map(str::to_string),
+        }],
+        text: Some(name.unwrap_or(code).to_string()),
+    }
+}
+
+/// `H`, `L`, `HH`, `LL`, `A`... are the same codes in HL7 v2 `OBX-8` and the
+/// FHIR interpretation code system; `N` (normal) is left out.
+fn interpretation(flag: Option<&str>) -> Vec<CodeableConcept> {
+    flag.map(str::trim)
+        .filter(|flag| !flag.is_empty() && *flag != "N")
+        .map(|flag| CodeableConcept {
+            coding: vec![Coding {
+                system: Some(INTERPRETATION_SYSTEM.to_string()),
+                code: Some(flag.to_string()),
+                display: None,
+            }],
+            text: None,
+        })
+        .into_iter()
+        .collect()
+}
+
+/// FHIR status of an HL7 v2 result status (`OBX-11`).
+fn observation_status(result_status: Option<&str>) -> &'static str {
+    match result_status.map(str::trim) {
+        Some("F") => "final",
+        Some("C") => "corrected",
+        Some("P") | Some("R") | Some("S") | Some("I") => "preliminary",
+        Some("X") => "cancelled",
+        Some("D") | Some("W") => "entered-in-error",
+        _ => "unknown",
+    }
+}
+
+/// A laboratory result received over HL7 as an `Observation`; numeric (`NM`)
+/// values become a `valueQuantity`, anything else a `valueString`.
+pub fn lab_result_to_observation(result: &LabResultModel) -> Observation {
+    let numeric = result
+        .value_type
+        .as_deref()
+        .filter(|value_type| *value_type == "NM")
+        .and_then(|_| result.value.as_deref()?.trim().parse::<f64>().ok());
+
+    Observation {
+        resource_type: "Observation".to_string(),
+        id: Some(result.result_id.to_string()),
+        meta: Some(Meta {
+            last_updated: Some(result.created_at.to_rfc3339()),
+        }),
+        status: observation_status(result.result_status.as_deref()).to_string(),
+        category: vec![laboratory_category()],
+        code: analyte_code(&result.analyte_code, result.analyte_name.as_deref()),
+        subject: patient_reference(result.patient_id),
+        encounter: None,
+        effective_date_time: result.observed_at.map(|at| at.to_rfc3339()),
+        issued: Some(result.created_at.to_rfc3339()),
+        performer: vec![Reference {
+            reference: None,
+            display: Some(result.source.clone()),
+        }],
+        value_quantity: numeric.map(|value| Quantity {
+            value: Some(value),
+            unit: result.unit.clone(),
+        }),
+        value_string: match numeric {
+            Some(_) => None,
+            None => result.value.clone(),
+        },
+        interpretation: interpretation(result.abnormal_flag.as_deref()),
+        reference_range: result
+            .reference_range
+            .iter()
+            .map(|text| ObservationReferenceRange {
+                low: None,
+                high: None,
+                text: Some(text.clone()),
+            })
+            .collect(),
+    }
+}
+
+fn flag_code(flag: ResultFlag) -> &'static str {
+    match flag {
+        ResultFlag::Low => "L",
+        ResultFlag::High => "H",
+        ResultFlag::CriticalLow => "LL",
+        ResultFlag::CriticalHigh => "HH",
+        ResultFlag::Abnormal => "A",
+    }
+}
+
+/// A result entered for a service provided at a visit (`medical_id`) as an
+/// `Observation`; final once released, preliminary before.
+pub fn service_result_to_observation(result: &ServiceResultModel, patient_id: Uuid, medical_id: Uuid) -> Observation {
+    // Through the shortest decimal form, so that 13.2 doesn't become 13.199999809265137.
+    let quantity = |value: f32| Quantity {
+        value: Some(value.to_string().parse().unwrap_or(f64::from(value))),
+        unit: result.unit.clone(),
+    };
+    let reference_range = (result.reference_range.is_some()
+        || result.reference_low.is_some()
+        || result.reference_high.is_some())
+    .then(|| ObservationReferenceRange {
+        low: result.reference_low.map(quantity),
+        high: result.reference_high.map(quantity),
+        text: result.reference_range.clone(),
+    });
+
+    Observation {
+        resource_type: "Observation".to_string(),
+        id: Some(result.service_result_id.to_string()),
+        meta: Some(Meta {
+            last_updated: Some(result.updated_at.to_rfc3339()),
+        }),
+        status: if result.released_at.is_some() { "final" } else { "preliminary" }.to_string(),
+        category: vec![laboratory_category()],
+        code: analyte_code(&result.analyte_code, Some(&result.analyte_name)),
+        subject: patient_reference(patient_id),
+        encounter: Some(Reference {
+            reference: Some(format!("Encounter/{}", medical_id)),
+            display: None,
+        }),
+        effective_date_time: Some(result.created_at.to_rfc3339()),
+        issued: result.released_at.map(|at| at.to_rfc3339()),
+        performer: result
+            .released_by
+            .iter()
+            .map(|name| Reference {
+                reference: None,
+                display: Some(name.clone()),
+            })
+            .collect(),
+        value_quantity: result.numeric_value.map(quantity),
+        value_string: match result.numeric_value {
+            Some(_) => None,
+            None => Some(result.value.clone()),
+        },
+        interpretation: interpretation(result.flag.map(flag_code)),
+        reference_range: reference_range.into_iter().collect(),
+    }
+}
@@ -0,0 +1,62 @@
+use sea_orm::{ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder, QuerySelect, Set};
+use crate::models::audit_log_tb::{
+    ActiveModel as AuditLogActiveModel, Column as AuditLogColumn, Entity as AuditLogEntity, Model as AuditLogModel,
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateAuditEntry {
+    pub action: String,
+    pub entity: String,
+    pub record_id: Option<String>,
+    pub actor_id: Option<Uuid>,
+    pub details: serde_json::Value,
+}
+
+/// Filters for reading the audit log; newest entries first.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AuditLogQuery {
+    pub action: Option<String>,
+    pub entity: Option<String>,
+    pub record_id: Option<String>,
+    pub limit: Option<u64>,
+}
+
+/// Appends an entry. `audit_id` may be given when the same entry is written to
+/// several databases and must keep one id (e.g. a disposal certificate).
+pub async fn record_audit<C: ConnectionTrait>(
+    db: &C,
+    audit_id: Option<Uuid>,
+    entry: CreateAuditEntry,
+) -> Result<AuditLogModel, sea_orm::DbErr> {
+    let audit = AuditLogActiveModel {
+        audit_id: Set(audit_id.unwrap_or_else(Uuid::new_v4)),
+        action: Set(entry.action),
+        entity: Set(entry.entity),
+        record_id: Set(entry.record_id),
+        actor_id: Set(entry.actor_id),
+        details: Set(entry.details),
+        created_at: Set(Utc::now()),
+    };
+
+    audit.insert(db).await
+}
+
+pub async fn get_audit_log(
+    db: &DatabaseConnection,
+    query: AuditLogQuery,
+) -> Result<Vec<AuditLogModel>, sea_orm::DbErr> {
+    let mut select = AuditLogEntity::find().order_by_desc(AuditLogColumn::CreatedAt);
+    if let Some(action) = query.action {
+        select = select.filter(AuditLogColumn::Action.eq(action));
+    }
+    if let Some(entity) = query.entity {
+        select = select.filter(AuditLogColumn::Entity.eq(entity));
+    }
+    if let Some(record_id) = query.record_id {
+        select = select.filter(AuditLogColumn::RecordId.eq(record_id));
+    }
+    select.limit(query.limit.unwrap_or(100)).all(db).await
+}
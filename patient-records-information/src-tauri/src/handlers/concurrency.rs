@@ -0,0 +1,20 @@
+use chrono::{DateTime, Utc};
+
+/// Outcome of a write guarded by an optimistic-concurrency check.
+#[derive(Debug, Clone)]
+pub enum Conditional<T> {
+    /// The row still had the expected version; carries the written (or deleted) row.
+    Applied(T),
+    /// No row with that id.
+    NotFound,
+    /// The row changed since the caller read it; carries the current row.
+    Modified(T),
+}
+
+/// Version of a row as exposed to clients, derived from its `updated_at`.
+///
+/// Microsecond precision is what Postgres keeps for `timestamptz`, so a value
+/// read back from the database always yields the same version.
+pub fn row_version(updated_at: &DateTime<Utc>) -> i64 {
+    updated_at.timestamp_micros()
+}
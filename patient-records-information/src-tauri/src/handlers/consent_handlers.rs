@@ -0,0 +1,170 @@
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, Iterable, QueryFilter, QueryOrder, Set,
+    TransactionTrait,
+};
+use sea_orm::sea_query::Expr;
+use crate::models::consent_tb::{
+    ActiveModel as ConsentActiveModel, Column as ConsentColumn, ConsentStatus, ConsentType, Entity as ConsentEntity,
+    Model as ConsentModel,
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GrantConsentRequest {
+    pub consent_type: ConsentType,
+    pub consent_version: String,
+    /// Date the form was signed; defaults to today.
+    pub granted_on: Option<chrono::NaiveDate>,
+    pub witnessed_by: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WithdrawConsentRequest {
+    pub reason: Option<String>,
+}
+
+/// Consent state of one type for a patient: the active grant, if any.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConsentSummary {
+    pub consent_type: ConsentType,
+    pub granted: bool,
+    pub current: Option<ConsentModel>,
+}
+
+/// Records a consent. An earlier grant of the same type is marked `superseded`,
+/// so at most one grant per type is active.
+pub async fn grant_consent(
+    db: &DatabaseConnection,
+    patient_id: Uuid,
+    request: GrantConsentRequest,
+) -> Result<ConsentModel, sea_orm::DbErr> {
+    let txn = db.begin().await?;
+    let now = Utc::now();
+
+    ConsentEntity::update_many()
+        .col_expr(ConsentColumn::Status, Expr::value(ConsentStatus::Superseded))
+        .col_expr(ConsentColumn::UpdatedAt, Expr::value(now))
+        .filter(ConsentColumn::PatientId.eq(patient_id))
+        .filter(ConsentColumn::ConsentType.eq(request.consent_type))
+        .filter(ConsentColumn::Status.eq(ConsentStatus::Granted))
+        .exec(&txn)
+        .await?;
+
+    let consent = ConsentActiveModel {
+        consent_id: Set(Uuid::new_v4()),
+        patient_id: Set(patient_id),
+        consent_type: Set(request.consent_type),
+        status: Set(ConsentStatus::Granted),
+        consent_version: Set(request.consent_version),
+        granted_on: Set(request.granted_on.unwrap_or_else(|| now.date_naive())),
+        witnessed_by: Set(request.witnessed_by),
+        withdrawn_at: Set(None),
+        withdrawal_reason: Set(None),
+        created_at: Set(now),
+        updated_at: Set(now),
+    };
+    let consent = consent.insert(&txn).await?;
+    txn.commit().await?;
+    Ok(consent)
+}
+
+/// Withdraws the active consent of `consent_type`. Returns `None` when the
+/// patient has no active consent of that type.
+pub async fn withdraw_consent(
+    db: &DatabaseConnection,
+    patient_id: Uuid,
+    consent_type: ConsentType,
+    request: WithdrawConsentRequest,
+) -> Result<Option<ConsentModel>, sea_orm::DbErr> {
+    let Some(consent) = active_consent(db, patient_id, consent_type).await? else {
+        return Ok(None);
+    };
+
+    let mut consent: ConsentActiveModel = consent.into();
+    consent.status = Set(ConsentStatus::Withdrawn);
+    consent.withdrawn_at = Set(Some(Utc::now()));
+    consent.withdrawal_reason = Set(request.reason);
+    consent.update(db).await.map(Some)
+}
+
+/// All consents of a patient, newest first.
+pub async fn get_consent_history(
+    db: &DatabaseConnection,
+    patient_id: Uuid,
+) -> Result<Vec<ConsentModel>, sea_orm::DbErr> {
+    ConsentEntity::find()
+        .filter(ConsentColumn::PatientId.eq(patient_id))
+        .order_by_desc(ConsentColumn::CreatedAt)
+        .all(db)
+        .await
+}
+
+/// One summary per consent type, in declaration order.
+pub async fn get_consent_summary(
+    db: &DatabaseConnection,
+    patient_id: Uuid,
+) -> Result<Vec<ConsentSummary>, sea_orm::DbErr> {
+    let active = ConsentEntity::find()
+        .filter(ConsentColumn::PatientId.eq(patient_id))
+        .filter(ConsentColumn::Status.eq(ConsentStatus::Granted))
+        .all(db)
+        .await?;
+
+    Ok(ConsentType::iter()
+        .map(|consent_type| {
+            let current = active.iter().find(|c| c.consent_type == consent_type).cloned();
+            ConsentSummary {
+                consent_type,
+                granted: current.is_some(),
+                current,
+            }
+        })
+        .collect())
+}
+
+async fn active_consent(
+    db: &DatabaseConnection,
+    patient_id: Uuid,
+    consent_type: ConsentType,
+) -> Result<Option<ConsentModel>, sea_orm::DbErr> {
+    ConsentEntity::find()
+        .filter(ConsentColumn::PatientId.eq(patient_id))
+        .filter(ConsentColumn::ConsentType.eq(consent_type))
+        .filter(ConsentColumn::Status.eq(ConsentStatus::Granted))
+        .one(db)
+        .await
+}
+
+/// Enforcement hook for features that act on one patient (e.g. sending an SMS).
+/// Consent is opt-in: a patient who never granted it is treated like one who withdrew.
+pub async fn has_consent(
+    db: &DatabaseConnection,
+    patient_id: Uuid,
+    consent_type: ConsentType,
+) -> Result<bool, sea_orm::DbErr> {
+    Ok(active_consent(db, patient_id, consent_type).await?.is_some())
+}
+
+/// Enforcement hook for batch features (e.g. de-identified exports): the subset
+/// of `patient_ids` with an active consent of `consent_type`.
+pub async fn patients_with_consent(
+    db: &DatabaseConnection,
+    patient_ids: &[Uuid],
+    consent_type: ConsentType,
+) -> Result<HashSet<Uuid>, sea_orm::DbErr> {
+    if patient_ids.is_empty() {
+        return Ok(HashSet::new());
+    }
+    Ok(ConsentEntity::find()
+        .filter(ConsentColumn::PatientId.is_in(patient_ids.iter().copied()))
+        .filter(ConsentColumn::ConsentType.eq(consent_type))
+        .filter(ConsentColumn::Status.eq(ConsentStatus::Granted))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|consent| consent.patient_id)
+        .collect())
+}
@@ -0,0 +1,312 @@
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, ModelTrait, QueryFilter, QueryOrder, Set,
+    TransactionTrait,
+};
+use crate::models::household_member_tb::{
+    ActiveModel as MemberActiveModel, Column as MemberColumn, Entity as MemberEntity, HouseholdRole,
+    Model as MemberModel,
+};
+use crate::models::household_tb::{
+    ActiveModel as HouseholdActiveModel, Column as HouseholdColumn, Entity as HouseholdEntity,
+    Model as HouseholdModel,
+};
+use crate::models::medical_bill_record::{
+    Column as BillColumn, Entity as BillEntity, Model as BillModel, PaymentStatus,
+};
+use crate::models::medical_record_tb::{
+    Column as MedicalRecordColumn, Entity as MedicalRecordEntity, Model as MedicalRecordModel,
+};
+use crate::models::patient_tb::{Column as PatientColumn, Entity as PatientEntity, Model as PatientModel};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateHouseholdRequest {
+    pub household_name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttachMemberRequest {
+    pub patient_id: Uuid,
+    pub role: HouseholdRole,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HouseholdMember {
+    pub role: HouseholdRole,
+    pub joined_at: chrono::DateTime<Utc>,
+    pub patient: PatientModel,
+}
+
+/// A household with its members, head first.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HouseholdDetails {
+    #[serde(flatten)]
+    pub household: HouseholdModel,
+    pub members: Vec<HouseholdMember>,
+}
+
+#[derive(Debug, Clone)]
+pub enum AttachOutcome {
+    /// Added, or the role changed when the patient already was a member.
+    Attached(MemberModel),
+    HouseholdNotFound,
+    PatientNotFound,
+    /// The patient has to be detached from this other household first.
+    InOtherHousehold(Uuid),
+    /// The household already has a head: this other patient.
+    HeadTaken(Uuid),
+}
+
+/// One visit (medical record) of a household member.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HouseholdVisit {
+    pub patient_id: Uuid,
+    pub patient_number: Option<String>,
+    pub record: MedicalRecordModel,
+}
+
+/// Bills of all members' visits, with totals for a combined statement.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HouseholdStatement {
+    pub bills: Vec<HouseholdBill>,
+    pub total_amount: f32,
+    /// Sum of the bills not marked `Paid`.
+    pub unpaid_amount: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HouseholdBill {
+    pub patient_id: Uuid,
+    pub patient_number: Option<String>,
+    pub bill: BillModel,
+}
+
+pub async fn create_household(
+    db: &DatabaseConnection,
+    household_id: Uuid,
+    request: CreateHouseholdRequest,
+) -> Result<HouseholdModel, sea_orm::DbErr> {
+    let now = Utc::now();
+    HouseholdActiveModel {
+        household_id: Set(household_id),
+        household_name: Set(request.household_name),
+        created_at: Set(now),
+        updated_at: Set(now),
+    }
+    .insert(db)
+    .await
+}
+
+pub async fn get_all_households(db: &DatabaseConnection) -> Result<Vec<HouseholdModel>, sea_orm::DbErr> {
+    HouseholdEntity::find()
+        .order_by_asc(HouseholdColumn::HouseholdName)
+        .all(db)
+        .await
+}
+
+pub async fn get_household(
+    db: &DatabaseConnection,
+    household_id: Uuid,
+) -> Result<Option<HouseholdDetails>, sea_orm::DbErr> {
+    let Some(household) = HouseholdEntity::find_by_id(household_id).one(db).await? else {
+        return Ok(None);
+    };
+
+    let mut members = Vec::new();
+    for (member, patient) in household
+        .find_related(MemberEntity)
+        .find_also_related(PatientEntity)
+        .order_by_asc(MemberColumn::JoinedAt)
+        .all(db)
+        .await?
+    {
+        // Memberships of deleted patients are skipped rather than shown empty.
+        if let Some(patient) = patient {
+            members.push(HouseholdMember {
+                role: member.role,
+                joined_at: member.joined_at,
+                patient: patient.decrypted()?,
+            });
+        }
+    }
+    members.sort_by_key(|member| member.role != HouseholdRole::Head);
+
+    Ok(Some(HouseholdDetails { household, members }))
+}
+
+/// Deletes the household; its memberships go with it.
+pub async fn delete_household(db: &DatabaseConnection, household_id: Uuid) -> Result<bool, sea_orm::DbErr> {
+    let txn = db.begin().await?;
+    MemberEntity::delete_many()
+        .filter(MemberColumn::HouseholdId.eq(household_id))
+        .exec(&txn)
+        .await?;
+    let result = HouseholdEntity::delete_by_id(household_id).exec(&txn).await?;
+    txn.commit().await?;
+    Ok(result.rows_affected > 0)
+}
+
+/// Adds a patient to a household, or changes their role if they already
+/// belong to it.
+pub async fn attach_member(
+    db: &DatabaseConnection,
+    household_id: Uuid,
+    request: AttachMemberRequest,
+) -> Result<AttachOutcome, sea_orm::DbErr> {
+    let txn = db.begin().await?;
+    if HouseholdEntity::find_by_id(household_id).one(&txn).await?.is_none() {
+        return Ok(AttachOutcome::HouseholdNotFound);
+    }
+    if PatientEntity::find_by_id(request.patient_id).one(&txn).await?.is_none() {
+        return Ok(AttachOutcome::PatientNotFound);
+    }
+
+    let existing = MemberEntity::find_by_id(request.patient_id).one(&txn).await?;
+    if let Some(existing) = &existing {
+        if existing.household_id != household_id {
+            return Ok(AttachOutcome::InOtherHousehold(existing.household_id));
+        }
+    }
+    if request.role == HouseholdRole::Head {
+        let head = MemberEntity::find()
+            .filter(MemberColumn::HouseholdId.eq(household_id))
+            .filter(MemberColumn::Role.eq(HouseholdRole::Head))
+            .filter(MemberColumn::PatientId.ne(request.patient_id))
+            .one(&txn)
+            .await?;
+        if let Some(head) = head {
+            return Ok(AttachOutcome::HeadTaken(head.patient_id));
+        }
+    }
+
+    let member = match existing {
+        Some(existing) => {
+            let mut member: MemberActiveModel = existing.into();
+            member.role = Set(request.role);
+            member.update(&txn).await?
+        }
+        None => {
+            MemberActiveModel {
+                patient_id: Set(request.patient_id),
+                household_id: Set(household_id),
+                role: Set(request.role),
+                joined_at: Set(Utc::now()),
+            }
+            .insert(&txn)
+            .await?
+        }
+    };
+    txn.commit().await?;
+    Ok(AttachOutcome::Attached(member))
+}
+
+/// Removes a patient from a household. Returns `false` when they weren't a member.
+pub async fn detach_member(
+    db: &DatabaseConnection,
+    household_id: Uuid,
+    patient_id: Uuid,
+) -> Result<bool, sea_orm::DbErr> {
+    let result = MemberEntity::delete_many()
+        .filter(MemberColumn::HouseholdId.eq(household_id))
+        .filter(MemberColumn::PatientId.eq(patient_id))
+        .exec(db)
+        .await?;
+    Ok(result.rows_affected > 0)
+}
+
+/// Current members of a household (not decrypted; used for the visit queries).
+async fn member_patients(
+    db: &DatabaseConnection,
+    household_id: Uuid,
+) -> Result<Option<Vec<PatientModel>>, sea_orm::DbErr> {
+    let Some(household) = HouseholdEntity::find_by_id(household_id).one(db).await? else {
+        return Ok(None);
+    };
+    let patients = household
+        .find_related(MemberEntity)
+        .find_also_related(PatientEntity)
+        .all(db)
+        .await?
+        .into_iter()
+        .filter_map(|(_, patient)| patient)
+        .collect();
+    Ok(Some(patients))
+}
+
+/// Visits of every member, newest first. `None` when the household doesn't exist.
+pub async fn get_household_visits(
+    db: &DatabaseConnection,
+    household_id: Uuid,
+) -> Result<Option<Vec<HouseholdVisit>>, sea_orm::DbErr> {
+    let Some(patients) = member_patients(db, household_id).await? else {
+        return Ok(None);
+    };
+    if patients.is_empty() {
+        return Ok(Some(Vec::new()));
+    }
+    let patient_ids: Vec<Uuid> = patients.iter().map(|p| p.patient_id).collect();
+
+    let visits = MedicalRecordEntity::find()
+        .find_also_related(PatientEntity)
+        .filter(PatientColumn::PatientId.is_in(patient_ids))
+        .order_by_desc(MedicalRecordColumn::CreatedAt)
+        .all(db)
+        .await?
+        .into_iter()
+        .filter_map(|(record, patient)| {
+            patient.map(|patient| HouseholdVisit {
+                patient_id: patient.patient_id,
+                patient_number: patient.patient_number,
+                record,
+            })
+        })
+        .collect();
+    Ok(Some(visits))
+}
+
+/// Combined statement of the bills for every member's visits, newest visit
+/// first. `None` when the household doesn't exist.
+pub async fn get_household_statement(
+    db: &DatabaseConnection,
+    household_id: Uuid,
+) -> Result<Option<HouseholdStatement>, sea_orm::DbErr> {
+    let Some(visits) = get_household_visits(db, household_id).await? else {
+        return Ok(None);
+    };
+    let mut statement = HouseholdStatement {
+        bills: Vec::new(),
+        total_amount: 0.0,
+        unpaid_amount: 0.0,
+    };
+    if visits.is_empty() {
+        return Ok(Some(statement));
+    }
+
+    let visit_order: HashMap<Uuid, usize> = visits
+        .iter()
+        .enumerate()
+        .map(|(position, visit)| (visit.record.medical_id, position))
+        .collect();
+    let mut bills = BillEntity::find()
+        .filter(BillColumn::MedicalId.is_in(visit_order.keys().copied()))
+        .all(db)
+        .await?;
+    bills.sort_by_key(|bill| visit_order[&bill.medical_id]);
+
+    for bill in bills {
+        let visit = &visits[visit_order[&bill.medical_id]];
+        statement.total_amount += bill.total_amount;
+        if bill.payment_status != PaymentStatus::Paid {
+            statement.unpaid_amount += bill.total_amount;
+        }
+        statement.bills.push(HouseholdBill {
+            patient_id: visit.patient_id,
+            patient_number: visit.patient_number.clone(),
+            bill,
+        });
+    }
+    Ok(Some(statement))
+}
@@ -0,0 +1,379 @@
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, Condition, DatabaseConnection, EntityTrait, FromQueryResult, JoinType, QueryFilter,
+    QueryOrder, QuerySelect, RelationTrait, Set, TransactionTrait,
+};
+use sea_orm::sea_query::{Expr, Func, OnConflict};
+use crate::handlers::medical_record_handlers::get_medical_record;
+use crate::handlers::psgc_handlers::split_csv_line;
+use crate::models::icd10_code_tb::{
+    ActiveModel as Icd10ActiveModel, Column as Icd10Column, Entity as Icd10Entity, Model as Icd10Model,
+};
+use crate::models::medical_record_diagnosis_tb::{
+    ActiveModel as DiagnosisActiveModel, Column as DiagnosisColumn, DiagnosisRank, Entity as DiagnosisEntity,
+    Relation as DiagnosisRelation,
+};
+use crate::models::medical_record_tb::{
+    ActiveModel as MedicalRecordActiveModel, Column as MedicalRecordColumn, Entity as MedicalRecordEntity,
+};
+use chrono::{NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+const IMPORT_BATCH_SIZE: usize = 500;
+const DEFAULT_SEARCH_LIMIT: u64 = 20;
+const MAX_SEARCH_LIMIT: u64 = 100;
+
+/// Autocomplete query: `q` matches the start of a code (`j06`, `J06.9`) or
+/// anywhere in the description, case-insensitively.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Icd10Search {
+    pub q: Option<String>,
+    pub limit: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiagnosisInput {
+    pub icd10_code: String,
+    pub rank: DiagnosisRank,
+}
+
+/// Replaces all coded diagnoses of a record; an empty list removes them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetDiagnosesRequest {
+    pub diagnoses: Vec<DiagnosisInput>,
+    /// Staff member making the change.
+    pub last_audited_by: String,
+}
+
+/// A coded diagnosis with the code's description.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CodedDiagnosis {
+    pub icd10_code: String,
+    pub description: Option<String>,
+    pub rank: DiagnosisRank,
+}
+
+#[derive(Debug, Clone)]
+pub enum DiagnosesUpdate {
+    Updated(Vec<CodedDiagnosis>),
+    /// The record doesn't exist or isn't the patient's.
+    RecordNotFound,
+    Invalid(String),
+}
+
+/// What diagnosis counts are grouped by.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiagnosisGrouping {
+    /// The full code, e.g. `J06.9`.
+    #[default]
+    Code,
+    /// The three-character category, e.g. `J06`.
+    Category,
+}
+
+/// Records dated `from` to `to` inclusive (UTC days); both optional.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DiagnosisCountQuery {
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    pub rank: Option<DiagnosisRank>,
+    #[serde(default)]
+    pub by: DiagnosisGrouping,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiagnosisCount {
+    pub code: String,
+    pub description: Option<String>,
+    pub records: i64,
+    pub patients: i64,
+}
+
+#[derive(Debug, FromQueryResult)]
+struct CodeCount {
+    code: String,
+    records: i64,
+    patients: i64,
+}
+
+/// Canonical dotted form of a code typed by staff or found in a file
+/// (`j069`, ` J06.9 ` → `J06.9`). `None` when it isn't shaped like an ICD-10
+/// code: a letter, two digits or a digit and a letter, then up to four more.
+pub fn normalize_icd10_code(value: &str) -> Option<String> {
+    let compact: String = value
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '.')
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    let chars: Vec<char> = compact.chars().collect();
+    let shaped = (3..=7).contains(&chars.len())
+        && chars[0].is_ascii_uppercase()
+        && chars[1].is_ascii_digit()
+        && chars[2].is_ascii_alphanumeric()
+        && chars[3..].iter().all(char::is_ascii_alphanumeric);
+    if !shaped {
+        return None;
+    }
+    Some(if chars.len() == 3 {
+        compact
+    } else {
+        format!("{}.{}", &compact[..3], &compact[3..])
+    })
+}
+
+/// Code lookup and autocomplete, ordered by code.
+pub async fn search_icd10_codes(
+    db: &DatabaseConnection,
+    search: Icd10Search,
+) -> Result<Vec<Icd10Model>, sea_orm::DbErr> {
+    let mut query = Icd10Entity::find();
+    if let Some(q) = search.q.as_deref().map(str::trim).filter(|q| !q.is_empty()) {
+        let mut condition = Condition::any().add(
+            Expr::expr(Func::lower(Expr::col(Icd10Column::Description))).like(format!("%{}%", q.to_lowercase())),
+        );
+        if let Some(code) = normalize_icd10_code(q) {
+            condition = condition.add(Icd10Column::Code.starts_with(code));
+        } else if q.len() < 3 {
+            condition = condition.add(Icd10Column::Code.starts_with(q.to_uppercase()));
+        }
+        query = query.filter(condition);
+    }
+    query
+        .order_by_asc(Icd10Column::Code)
+        .limit(search.limit.unwrap_or(DEFAULT_SEARCH_LIMIT).min(MAX_SEARCH_LIMIT))
+        .all(db)
+        .await
+}
+
+pub async fn get_icd10_code(db: &DatabaseConnection, code: &str) -> Result<Option<Icd10Model>, sea_orm::DbErr> {
+    let Some(code) = normalize_icd10_code(code) else {
+        return Ok(None);
+    };
+    Icd10Entity::find_by_id(code).one(db).await
+}
+
+/// Parses an ICD-10 code list exported to CSV. The header must name a code
+/// column (`code`, `icd10_code` or `icd_10_code`) and a description column
+/// (`description`, `title` or `name`); other columns are ignored. Codes may
+/// be given with or without the dot.
+///
+/// Returns the codes and the number of rows skipped for lacking a valid code
+/// or a description.
+pub fn parse_icd10_csv(contents: &str) -> Result<(Vec<Icd10Model>, usize), String> {
+    let mut lines = contents.trim_start_matches('\u{feff}').lines().filter(|l| !l.trim().is_empty());
+    let header: Vec<String> = split_csv_line(lines.next().ok_or("ICD-10 file is empty")?)
+        .iter()
+        .map(|h| h.trim().to_lowercase().replace([' ', '-'], "_"))
+        .collect();
+    let column = |names: &[&str]| header.iter().position(|h| names.contains(&h.as_str()));
+    let code_at = column(&["code", "icd10_code", "icd_10_code"]).ok_or("ICD-10 file has no code column")?;
+    let description_at =
+        column(&["description", "title", "name"]).ok_or("ICD-10 file has no description column")?;
+
+    let mut codes = Vec::new();
+    let mut seen = HashSet::new();
+    let mut skipped = 0;
+    for line in lines {
+        let fields = split_csv_line(line);
+        let field = |at: usize| fields.get(at).map(|f| f.trim()).unwrap_or("");
+        match normalize_icd10_code(field(code_at)) {
+            Some(code) if !field(description_at).is_empty() && seen.insert(code.clone()) => codes.push(Icd10Model {
+                category: code[..3].to_string(),
+                code,
+                description: field(description_at).to_string(),
+            }),
+            _ => skipped += 1,
+        }
+    }
+    Ok((codes, skipped))
+}
+
+/// Inserts or updates `codes` in one transaction, so re-importing a newer
+/// edition updates descriptions in place. Codes dropped from the edition are
+/// kept, since recorded diagnoses may still use them.
+pub async fn import_icd10_codes(db: &DatabaseConnection, codes: Vec<Icd10Model>) -> Result<usize, sea_orm::DbErr> {
+    let txn = db.begin().await?;
+    let total = codes.len();
+    let mut codes = codes.into_iter().peekable();
+    while codes.peek().is_some() {
+        let batch: Vec<Icd10ActiveModel> = codes
+            .by_ref()
+            .take(IMPORT_BATCH_SIZE)
+            .map(|code| Icd10ActiveModel {
+                code: Set(code.code),
+                description: Set(code.description),
+                category: Set(code.category),
+            })
+            .collect();
+        Icd10Entity::insert_many(batch)
+            .on_conflict(
+                OnConflict::column(Icd10Column::Code)
+                    .update_columns([Icd10Column::Description, Icd10Column::Category])
+                    .to_owned(),
+            )
+            .exec_without_returning(&txn)
+            .await?;
+    }
+    txn.commit().await?;
+    Ok(total)
+}
+
+async fn coded_diagnoses<C: sea_orm::ConnectionTrait>(
+    db: &C,
+    medical_id: Uuid,
+) -> Result<Vec<CodedDiagnosis>, sea_orm::DbErr> {
+    Ok(DiagnosisEntity::find()
+        .find_also_related(Icd10Entity)
+        .filter(DiagnosisColumn::MedicalId.eq(medical_id))
+        .order_by_asc(DiagnosisColumn::Sequence)
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|(diagnosis, code)| CodedDiagnosis {
+            icd10_code: diagnosis.icd10_code,
+            description: code.map(|code| code.description),
+            rank: diagnosis.rank,
+        })
+        .collect())
+}
+
+/// Coded diagnoses of a record, primary first. `None` when the record doesn't
+/// exist or isn't the patient's.
+pub async fn get_medical_record_diagnoses(
+    db: &DatabaseConnection,
+    patient_id: Uuid,
+    medical_id: Uuid,
+) -> Result<Option<Vec<CodedDiagnosis>>, sea_orm::DbErr> {
+    if get_medical_record(db, patient_id, medical_id).await?.is_none() {
+        return Ok(None);
+    }
+    coded_diagnoses(db, medical_id).await.map(Some)
+}
+
+/// Replaces the coded diagnoses of a record. A non-empty list needs exactly
+/// one primary diagnosis and codes from the imported ICD-10 table, each once.
+/// The record's `last_audited_by` is updated with the change.
+pub async fn set_medical_record_diagnoses(
+    db: &DatabaseConnection,
+    patient_id: Uuid,
+    medical_id: Uuid,
+    request: SetDiagnosesRequest,
+) -> Result<DiagnosesUpdate, sea_orm::DbErr> {
+    let Some(record) = get_medical_record(db, patient_id, medical_id).await? else {
+        return Ok(DiagnosesUpdate::RecordNotFound);
+    };
+
+    let mut diagnoses = Vec::with_capacity(request.diagnoses.len());
+    for input in request.diagnoses {
+        let Some(code) = normalize_icd10_code(&input.icd10_code) else {
+            return Ok(DiagnosesUpdate::Invalid(format!("'{}' is not an ICD-10 code", input.icd10_code)));
+        };
+        if diagnoses.iter().any(|(existing, _)| *existing == code) {
+            return Ok(DiagnosesUpdate::Invalid(format!("{} is listed twice", code)));
+        }
+        diagnoses.push((code, input.rank));
+    }
+    let primaries = diagnoses.iter().filter(|(_, rank)| *rank == DiagnosisRank::Primary).count();
+    if !diagnoses.is_empty() && primaries != 1 {
+        return Ok(DiagnosesUpdate::Invalid("Exactly one diagnosis must be primary".to_string()));
+    }
+    let codes: Vec<String> = diagnoses.iter().map(|(code, _)| code.clone()).collect();
+    let known: HashSet<String> = Icd10Entity::find()
+        .filter(Icd10Column::Code.is_in(codes.clone()))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|code| code.code)
+        .collect();
+    let unknown: Vec<String> = codes.into_iter().filter(|code| !known.contains(code)).collect();
+    if !unknown.is_empty() {
+        return Ok(DiagnosesUpdate::Invalid(format!("Unknown ICD-10 code(s): {}", unknown.join(", "))));
+    }
+    diagnoses.sort_by_key(|(_, rank)| *rank != DiagnosisRank::Primary);
+
+    let now = Utc::now();
+    let txn = db.begin().await?;
+    DiagnosisEntity::delete_many()
+        .filter(DiagnosisColumn::MedicalId.eq(medical_id))
+        .exec(&txn)
+        .await?;
+    for (sequence, (code, rank)) in diagnoses.into_iter().enumerate() {
+        DiagnosisActiveModel {
+            diagnosis_id: Set(Uuid::new_v4()),
+            medical_id: Set(medical_id),
+            icd10_code: Set(code),
+            rank: Set(rank),
+            sequence: Set(sequence as i32),
+            created_at: Set(now),
+        }
+        .insert(&txn)
+        .await?;
+    }
+    let mut record: MedicalRecordActiveModel = record.into();
+    record.last_audited_by = Set(Some(request.last_audited_by));
+    record.update(&txn).await?;
+    let updated = coded_diagnoses(&txn, medical_id).await?;
+    txn.commit().await?;
+    Ok(DiagnosesUpdate::Updated(updated))
+}
+
+/// Number of records and distinct patients per diagnosis, most frequent first.
+pub async fn count_diagnoses(
+    db: &DatabaseConnection,
+    query: DiagnosisCountQuery,
+) -> Result<Vec<DiagnosisCount>, sea_orm::DbErr> {
+    let group = match query.by {
+        DiagnosisGrouping::Code => Expr::col((DiagnosisEntity, DiagnosisColumn::Icd10Code)),
+        DiagnosisGrouping::Category => Expr::col((Icd10Entity, Icd10Column::Category)),
+    };
+    let mut condition = Condition::all();
+    if let Some(from) = query.from {
+        condition = condition.add(MedicalRecordColumn::CreatedAt.gte(from.and_time(Default::default()).and_utc()));
+    }
+    if let Some(to) = query.to.and_then(|to| to.succ_opt()) {
+        condition = condition.add(MedicalRecordColumn::CreatedAt.lt(to.and_time(Default::default()).and_utc()));
+    }
+    if let Some(rank) = query.rank {
+        condition = condition.add(DiagnosisColumn::Rank.eq(rank));
+    }
+
+    let counts = DiagnosisEntity::find()
+        .select_only()
+        .column_as(group.clone(), "code")
+        .column_as(DiagnosisColumn::MedicalId.count(), "records")
+        .column_as(Expr::col((MedicalRecordEntity, MedicalRecordColumn::PatientId)).count_distinct(), "patients")
+        .join(JoinType::InnerJoin, DiagnosisRelation::MedicalRecord.def())
+        .join(JoinType::InnerJoin, DiagnosisRelation::Icd10Code.def())
+        .filter(condition)
+        .group_by(group)
+        .into_model::<CodeCount>()
+        .all(db)
+        .await?;
+
+    let codes: Vec<String> = counts.iter().map(|count| count.code.clone()).collect();
+    let descriptions: HashMap<String, String> = if codes.is_empty() {
+        HashMap::new()
+    } else {
+        Icd10Entity::find()
+            .filter(Icd10Column::Code.is_in(codes))
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|code| (code.code, code.description))
+            .collect()
+    };
+
+    let mut counts: Vec<DiagnosisCount> = counts
+        .into_iter()
+        .map(|count| DiagnosisCount {
+            description: descriptions.get(&count.code).cloned(),
+            code: count.code,
+            records: count.records,
+            patients: count.patients,
+        })
+        .collect();
+    counts.sort_by(|a, b| b.records.cmp(&a.records).then_with(|| a.code.cmp(&b.code)));
+    Ok(counts)
+}
@@ -0,0 +1,469 @@
+//! Data integrity checks behind `migrate check`: rows pointing at missing
+//! rows, enum values the entities can't read, and bills that don't add up.
+
+use sea_orm::sea_query::{Alias, Expr, Query};
+use sea_orm::{
+    ActiveEnum, ColumnTrait, DatabaseConnection, DbErr, EntityName, EntityTrait, IdenStatic, Iterable, QueryFilter,
+    QuerySelect, RelationTrait, TransactionTrait,
+};
+use crate::handlers::audit_handlers::{record_audit, CreateAuditEntry};
+use crate::models::consent_tb::{Column as ConsentColumn, Entity as ConsentEntity};
+use crate::models::household_member_tb::{Column as HouseholdMemberColumn, Entity as HouseholdMemberEntity};
+use crate::models::household_tb::{Column as HouseholdColumn, Entity as HouseholdEntity};
+use crate::models::icd10_code_tb::{Column as Icd10Column, Entity as Icd10Entity};
+use crate::models::lab_result_tb::{Column as LabResultColumn, Entity as LabResultEntity};
+use crate::models::medical_bill_record::{
+    Column as BillColumn, Entity as BillEntity, PaymentStatus, Relation as BillRelation,
+};
+use crate::models::medical_record_diagnosis_tb::{Column as DiagnosisColumn, Entity as DiagnosisEntity};
+use crate::models::prescription_item_tb::{Column as PrescriptionItemColumn, Entity as PrescriptionItemEntity};
+use crate::models::vital_sign_tb::{Column as VitalSignColumn, Entity as VitalSignEntity};
+use crate::models::medical_record_tb::{Column as MedicalRecordColumn, Entity as MedicalRecordEntity};
+use crate::models::medical_services::{Column as ServiceColumn, Entity as ServiceEntity, ServiceCategory};
+use crate::models::medical_services_provided::{Column as ServiceProvidedColumn, Entity as ServiceProvidedEntity};
+use crate::models::service_analyte_tb::{Column as AnalyteColumn, Entity as AnalyteEntity};
+use crate::models::service_result_tb::{Column as ServiceResultColumn, Entity as ServiceResultEntity};
+use crate::models::patient_tb::{Column as PatientColumn, Entity as PatientEntity};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use uuid::Uuid;
+
+pub const INTEGRITY_FIX_ACTION: &str = "integrity.fix";
+
+/// Amounts are stored as `f32`; anything below a centavo is rounding.
+const AMOUNT_TOLERANCE: f32 = 0.005;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum IntegrityIssue {
+    /// `table.column` of row `row_id` refers to a row that doesn't exist.
+    Orphan { table: String, column: String, row_id: Uuid, missing_id: Uuid },
+    /// A code column naming a reference row that doesn't exist.
+    UnknownCode { table: String, column: String, row_id: Uuid, code: String },
+    /// A value the column's enum doesn't have; the row can't be loaded.
+    InvalidEnumValue { table: String, column: String, row_id: Uuid, value: String },
+    /// `total_amount` differs from the bill's service prices plus its consultation fee.
+    BillTotalMismatch { medical_bill_id: Uuid, total_amount: f32, expected: f32 },
+    /// The bill is billed to another patient than the visit it is for.
+    BillPatientMismatch { medical_bill_id: Uuid, patient_id: Uuid, record_patient_id: Uuid },
+    /// Nothing is due on the bill, yet it isn't marked `Paid`.
+    PaymentStatusMismatch { medical_bill_id: Uuid, payment_status: String, amount_due: f32 },
+}
+
+impl IntegrityIssue {
+    /// Whether [`fix_integrity_issues`] repairs this issue. Only derived values
+    /// are recomputed, and only rows that are meaningless without their parent
+    /// (service lines of a deleted bill and their results, coded diagnoses and
+    /// prescription items of a deleted record, household links) are deleted; clinical data and
+    /// consents are never touched.
+    pub fn is_fixable(&self) -> bool {
+        match self {
+            IntegrityIssue::Orphan { table, column, .. } => {
+                (table == ServiceProvidedEntity.table_name() && column == ServiceProvidedColumn::MedicalBillId.as_str())
+                    || (table == DiagnosisEntity.table_name() && column == DiagnosisColumn::MedicalId.as_str())
+                    || (table == PrescriptionItemEntity.table_name()
+                        && column == PrescriptionItemColumn::MedicalId.as_str())
+                    || (table == ServiceResultEntity.table_name() && column == ServiceResultColumn::MrsId.as_str())
+                    || table == HouseholdMemberEntity.table_name()
+            }
+            IntegrityIssue::BillTotalMismatch { .. } | IntegrityIssue::PaymentStatusMismatch { .. } => true,
+            IntegrityIssue::UnknownCode { .. }
+            | IntegrityIssue::InvalidEnumValue { .. }
+            | IntegrityIssue::BillPatientMismatch { .. } => false,
+        }
+    }
+}
+
+impl fmt::Display for IntegrityIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IntegrityIssue::Orphan { table, column, row_id, missing_id } => {
+                write!(f, "{}.{} of {} points to missing {}", table, column, row_id, missing_id)
+            }
+            IntegrityIssue::UnknownCode { table, column, row_id, code } => {
+                write!(f, "{}.{} of {} is '{}', which is not in the code table", table, column, row_id, code)
+            }
+            IntegrityIssue::InvalidEnumValue { table, column, row_id, value } => {
+                write!(f, "{}.{} of {} has unknown value '{}'", table, column, row_id, value)
+            }
+            IntegrityIssue::BillTotalMismatch { medical_bill_id, total_amount, expected } => write!(
+                f,
+                "bill {}: total_amount is {:.2} but its services and consultation fee come to {:.2}",
+                medical_bill_id, total_amount, expected
+            ),
+            IntegrityIssue::BillPatientMismatch { medical_bill_id, patient_id, record_patient_id } => write!(
+                f,
+                "bill {}: billed to patient {} but its medical record belongs to patient {}",
+                medical_bill_id, patient_id, record_patient_id
+            ),
+            IntegrityIssue::PaymentStatusMismatch { medical_bill_id, payment_status, amount_due } => write!(
+                f,
+                "bill {}: marked '{}' but {:.2} is due",
+                medical_bill_id, payment_status, amount_due
+            ),
+        }
+    }
+}
+
+/// Rows of `E` whose `column` has no matching `parent_id` in `P`.
+async fn orphans<E, P>(
+    db: &DatabaseConnection,
+    id: E::Column,
+    column: E::Column,
+    parent_id: P::Column,
+) -> Result<Vec<IntegrityIssue>, DbErr>
+where
+    E: EntityTrait,
+    P: EntityTrait,
+{
+    let rows: Vec<(Uuid, Uuid)> = E::find()
+        .select_only()
+        .column(id)
+        .column(column)
+        .filter(column.not_in_subquery(Query::select().column(parent_id).from(P::default()).to_owned()))
+        .into_tuple()
+        .all(db)
+        .await?;
+    Ok(rows
+        .into_iter()
+        .map(|(row_id, missing_id)| IntegrityIssue::Orphan {
+            table: E::default().table_name().to_string(),
+            column: column.as_str().to_string(),
+            row_id,
+            missing_id,
+        })
+        .collect())
+}
+
+/// Coded diagnoses whose ICD-10 code isn't in the code table. Codes are
+/// strings, so these can't go through [`orphans`].
+async fn orphan_diagnosis_codes(db: &DatabaseConnection) -> Result<Vec<IntegrityIssue>, DbErr> {
+    let rows: Vec<(Uuid, String)> = DiagnosisEntity::find()
+        .select_only()
+        .column(DiagnosisColumn::DiagnosisId)
+        .column(DiagnosisColumn::Icd10Code)
+        .filter(
+            DiagnosisColumn::Icd10Code
+                .not_in_subquery(Query::select().column(Icd10Column::Code).from(Icd10Entity).to_owned()),
+        )
+        .into_tuple()
+        .all(db)
+        .await?;
+    Ok(rows
+        .into_iter()
+        .map(|(row_id, code)| IntegrityIssue::UnknownCode {
+            table: DiagnosisEntity.table_name().to_string(),
+            column: DiagnosisColumn::Icd10Code.as_str().to_string(),
+            row_id,
+            code,
+        })
+        .collect())
+}
+
+/// Rows of `E` whose enum `column` holds a value outside `A`. The column is
+/// read as text so rows the entity can't decode are still found.
+async fn invalid_enum_values<E, A>(
+    db: &DatabaseConnection,
+    id: E::Column,
+    column: E::Column,
+) -> Result<Vec<IntegrityIssue>, DbErr>
+where
+    E: EntityTrait,
+    A: ActiveEnum<Value = String> + Iterable,
+{
+    let valid: Vec<String> = A::iter().map(|value| value.to_value()).collect();
+    let as_text = Expr::col(column).cast_as(Alias::new("text"));
+    let rows: Vec<(Uuid, String)> = E::find()
+        .select_only()
+        .column(id)
+        .column_as(as_text.clone(), "value")
+        .filter(Expr::expr(as_text).is_not_in(valid))
+        .into_tuple()
+        .all(db)
+        .await?;
+    Ok(rows
+        .into_iter()
+        .map(|(row_id, value)| IntegrityIssue::InvalidEnumValue {
+            table: E::default().table_name().to_string(),
+            column: column.as_str().to_string(),
+            row_id,
+            value,
+        })
+        .collect())
+}
+
+/// Totals and payment status of every bill, against its service lines.
+async fn bill_issues(db: &DatabaseConnection) -> Result<Vec<IntegrityIssue>, DbErr> {
+    let mut services_total: HashMap<Uuid, f32> = HashMap::new();
+    let lines: Vec<(Uuid, f32)> = ServiceProvidedEntity::find()
+        .select_only()
+        .column(ServiceProvidedColumn::MedicalBillId)
+        .column(ServiceProvidedColumn::Price)
+        .into_tuple()
+        .all(db)
+        .await?;
+    for (medical_bill_id, price) in lines {
+        *services_total.entry(medical_bill_id).or_default() += price;
+    }
+
+    let paid = PaymentStatus::Paid.to_value();
+    let valid_statuses: Vec<String> = PaymentStatus::iter().map(|status| status.to_value()).collect();
+    let bills: Vec<(Uuid, Option<f32>, f32, String)> = BillEntity::find()
+        .select_only()
+        .column(BillColumn::MedicalBillId)
+        .column(BillColumn::ConsultationFee)
+        .column(BillColumn::TotalAmount)
+        .column_as(Expr::col(BillColumn::PaymentStatus).cast_as(Alias::new("text")), "payment_status")
+        .into_tuple()
+        .all(db)
+        .await?;
+
+    let mut issues = Vec::new();
+    for (medical_bill_id, consultation_fee, total_amount, payment_status) in bills {
+        let expected = consultation_fee.unwrap_or(0.0) + services_total.get(&medical_bill_id).copied().unwrap_or(0.0);
+        if (total_amount - expected).abs() > AMOUNT_TOLERANCE {
+            issues.push(IntegrityIssue::BillTotalMismatch { medical_bill_id, total_amount, expected });
+        }
+        // No payments are recorded, so only a bill with nothing due can be
+        // shown to be settled.
+        if expected <= AMOUNT_TOLERANCE && payment_status != paid && valid_statuses.contains(&payment_status) {
+            issues.push(IntegrityIssue::PaymentStatusMismatch {
+                medical_bill_id,
+                payment_status,
+                amount_due: expected.max(0.0),
+            });
+        }
+    }
+    Ok(issues)
+}
+
+async fn bill_patient_mismatches(db: &DatabaseConnection) -> Result<Vec<IntegrityIssue>, DbErr> {
+    let rows: Vec<(Uuid, Uuid, Uuid)> = BillEntity::find()
+        .select_only()
+        .column(BillColumn::MedicalBillId)
+        .column(BillColumn::PatientId)
+        .column_as(MedicalRecordColumn::PatientId, "record_patient_id")
+        .join(sea_orm::JoinType::InnerJoin, BillRelation::MedicalRecord.def())
+        .filter(
+            Expr::col((MedicalRecordEntity, MedicalRecordColumn::PatientId))
+                .ne(Expr::col((BillEntity, BillColumn::PatientId))),
+        )
+        .into_tuple()
+        .all(db)
+        .await?;
+    Ok(rows
+        .into_iter()
+        .map(|(medical_bill_id, patient_id, record_patient_id)| IntegrityIssue::BillPatientMismatch {
+            medical_bill_id,
+            patient_id,
+            record_patient_id,
+        })
+        .collect())
+}
+
+/// Scans the patient, billing and household tables. Foreign keys aren't
+/// enforced everywhere (SQLite connections without `foreign_keys`, tables
+/// without constraints), so every relation is checked.
+pub async fn check_integrity(db: &DatabaseConnection) -> Result<Vec<IntegrityIssue>, DbErr> {
+    let mut issues = Vec::new();
+    issues.extend(
+        orphans::<MedicalRecordEntity, PatientEntity>(
+            db,
+            MedicalRecordColumn::MedicalId,
+            MedicalRecordColumn::PatientId,
+            PatientColumn::PatientId,
+        )
+        .await?,
+    );
+    issues.extend(
+        orphans::<LabResultEntity, PatientEntity>(db, LabResultColumn::ResultId, LabResultColumn::PatientId, PatientColumn::PatientId)
+            .await?,
+    );
+    issues.extend(
+        orphans::<ConsentEntity, PatientEntity>(db, ConsentColumn::ConsentId, ConsentColumn::PatientId, PatientColumn::PatientId)
+            .await?,
+    );
+    issues.extend(
+        orphans::<HouseholdMemberEntity, PatientEntity>(
+            db,
+            HouseholdMemberColumn::PatientId,
+            HouseholdMemberColumn::PatientId,
+            PatientColumn::PatientId,
+        )
+        .await?,
+    );
+    issues.extend(
+        orphans::<HouseholdMemberEntity, HouseholdEntity>(
+            db,
+            HouseholdMemberColumn::PatientId,
+            HouseholdMemberColumn::HouseholdId,
+            HouseholdColumn::HouseholdId,
+        )
+        .await?,
+    );
+    issues.extend(
+        orphans::<BillEntity, PatientEntity>(db, BillColumn::MedicalBillId, BillColumn::PatientId, PatientColumn::PatientId)
+            .await?,
+    );
+    issues.extend(
+        orphans::<BillEntity, MedicalRecordEntity>(
+            db,
+            BillColumn::MedicalBillId,
+            BillColumn::MedicalId,
+            MedicalRecordColumn::MedicalId,
+        )
+        .await?,
+    );
+    issues.extend(
+        orphans::<ServiceProvidedEntity, BillEntity>(
+            db,
+            ServiceProvidedColumn::MrsId,
+            ServiceProvidedColumn::MedicalBillId,
+            BillColumn::MedicalBillId,
+        )
+        .await?,
+    );
+    issues.extend(
+        orphans::<ServiceProvidedEntity, ServiceEntity>(
+            db,
+            ServiceProvidedColumn::MrsId,
+            ServiceProvidedColumn::MsId,
+            ServiceColumn::MsId,
+        )
+        .await?,
+    );
+    issues.extend(
+        orphans::<DiagnosisEntity, MedicalRecordEntity>(
+            db,
+            DiagnosisColumn::DiagnosisId,
+            DiagnosisColumn::MedicalId,
+            MedicalRecordColumn::MedicalId,
+        )
+        .await?,
+    );
+    issues.extend(orphan_diagnosis_codes(db).await?);
+    issues.extend(
+        orphans::<PrescriptionItemEntity, MedicalRecordEntity>(
+            db,
+            PrescriptionItemColumn::PrescriptionItemId,
+            PrescriptionItemColumn::MedicalId,
+            MedicalRecordColumn::MedicalId,
+        )
+        .await?,
+    );
+    issues.extend(
+        orphans::<ServiceResultEntity, ServiceProvidedEntity>(
+            db,
+            ServiceResultColumn::ServiceResultId,
+            ServiceResultColumn::MrsId,
+            ServiceProvidedColumn::MrsId,
+        )
+        .await?,
+    );
+    issues.extend(
+        orphans::<AnalyteEntity, ServiceEntity>(db, AnalyteColumn::AnalyteId, AnalyteColumn::MsId, ServiceColumn::MsId)
+            .await?,
+    );
+    issues.extend(
+        orphans::<VitalSignEntity, PatientEntity>(
+            db,
+            VitalSignColumn::VitalSignId,
+            VitalSignColumn::PatientId,
+            PatientColumn::PatientId,
+        )
+        .await?,
+    );
+    issues.extend(
+        orphans::<VitalSignEntity, MedicalRecordEntity>(
+            db,
+            VitalSignColumn::VitalSignId,
+            VitalSignColumn::MedicalId,
+            MedicalRecordColumn::MedicalId,
+        )
+        .await?,
+    );
+
+    issues.extend(invalid_enum_values::<ServiceEntity, ServiceCategory>(db, ServiceColumn::MsId, ServiceColumn::MsCategory).await?);
+    issues.extend(
+        invalid_enum_values::<ServiceProvidedEntity, ServiceCategory>(
+            db,
+            ServiceProvidedColumn::MrsId,
+            ServiceProvidedColumn::ServiceCategory,
+        )
+        .await?,
+    );
+    issues.extend(
+        invalid_enum_values::<BillEntity, PaymentStatus>(db, BillColumn::MedicalBillId, BillColumn::PaymentStatus).await?,
+    );
+
+    issues.extend(bill_patient_mismatches(db).await?);
+    issues.extend(bill_issues(db).await?);
+    Ok(issues)
+}
+
+/// Repairs the issues [`IntegrityIssue::is_fixable`] allows, in one
+/// transaction with an audit entry. Returns how many were fixed.
+pub async fn fix_integrity_issues(db: &DatabaseConnection, issues: &[IntegrityIssue]) -> Result<u64, DbErr> {
+    let txn = db.begin().await?;
+    let mut fixed = 0;
+    for issue in issues.iter().filter(|issue| issue.is_fixable()) {
+        let rows_affected = match issue {
+            IntegrityIssue::Orphan { table, row_id, .. } if table == ServiceProvidedEntity.table_name() => {
+                ServiceProvidedEntity::delete_by_id(*row_id).exec(&txn).await?.rows_affected
+            }
+            IntegrityIssue::Orphan { table, row_id, .. } if table == DiagnosisEntity.table_name() => {
+                DiagnosisEntity::delete_by_id(*row_id).exec(&txn).await?.rows_affected
+            }
+            IntegrityIssue::Orphan { table, row_id, .. } if table == PrescriptionItemEntity.table_name() => {
+                PrescriptionItemEntity::delete_by_id(*row_id).exec(&txn).await?.rows_affected
+            }
+            IntegrityIssue::Orphan { table, row_id, .. } if table == ServiceResultEntity.table_name() => {
+                ServiceResultEntity::delete_by_id(*row_id).exec(&txn).await?.rows_affected
+            }
+            IntegrityIssue::Orphan { row_id, .. } => {
+                HouseholdMemberEntity::delete_by_id(*row_id).exec(&txn).await?.rows_affected
+            }
+            IntegrityIssue::BillTotalMismatch { medical_bill_id, expected, .. } => {
+                BillEntity::update_many()
+                    .col_expr(BillColumn::TotalAmount, Expr::value(*expected))
+                    .filter(BillColumn::MedicalBillId.eq(*medical_bill_id))
+                    .exec(&txn)
+                    .await?
+                    .rows_affected
+            }
+            IntegrityIssue::PaymentStatusMismatch { medical_bill_id, .. } => {
+                BillEntity::update_many()
+                    .col_expr(BillColumn::PaymentStatus, PaymentStatus::Paid.as_enum())
+                    .filter(BillColumn::MedicalBillId.eq(*medical_bill_id))
+                    .exec(&txn)
+                    .await?
+                    .rows_affected
+            }
+            IntegrityIssue::UnknownCode { .. }
+            | IntegrityIssue::InvalidEnumValue { .. }
+            | IntegrityIssue::BillPatientMismatch { .. } => 0,
+        };
+        fixed += rows_affected.min(1);
+    }
+
+    if fixed > 0 {
+        record_audit(
+            &txn,
+            None,
+            CreateAuditEntry {
+                action: INTEGRITY_FIX_ACTION.to_string(),
+                entity: "database".to_string(),
+                record_id: None,
+                actor_id: None,
+                details: serde_json::json!({
+                    "fixed": fixed,
+                    "issues": issues.iter().filter(|issue| issue.is_fixable()).collect::<Vec<_>>(),
+                }),
+            },
+        )
+        .await?;
+    }
+    txn.commit().await?;
+    Ok(fixed)
+}
@@ -0,0 +1,87 @@
+use sea_orm::{DatabaseConnection, EntityTrait, Set, ActiveModelTrait, ColumnTrait, ConnectionTrait, QueryFilter, QueryOrder};
+use crate::models::lab_result_tb::{
+    Entity as LabResultEntity, Model as LabResultModel, ActiveModel as LabResultActiveModel, Column as LabResultColumn,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateLabResultRequest {
+    pub patient_id: Uuid,
+    pub order_number: Option<String>,
+    pub test_code: Option<String>,
+    pub test_name: Option<String>,
+    pub analyte_code: String,
+    pub analyte_name: Option<String>,
+    pub value_type: Option<String>,
+    pub value: Option<String>,
+    pub unit: Option<String>,
+    pub reference_range: Option<String>,
+    pub abnormal_flag: Option<String>,
+    pub result_status: Option<String>,
+    pub observed_at: Option<DateTime<Utc>>,
+    pub source: String,
+    pub message_control_id: Option<String>,
+}
+
+pub async fn create_lab_result<C: ConnectionTrait>(
+    db: &C,
+    request: CreateLabResultRequest,
+) -> Result<LabResultModel, sea_orm::DbErr> {
+    let result = LabResultActiveModel {
+        result_id: Set(Uuid::new_v4()),
+        patient_id: Set(request.patient_id),
+        order_number: Set(request.order_number),
+        test_code: Set(request.test_code),
+        test_name: Set(request.test_name),
+        analyte_code: Set(request.analyte_code),
+        analyte_name: Set(request.analyte_name),
+        value_type: Set(request.value_type),
+        value: Set(request.value),
+        unit: Set(request.unit),
+        reference_range: Set(request.reference_range),
+        abnormal_flag: Set(request.abnormal_flag),
+        result_status: Set(request.result_status),
+        observed_at: Set(request.observed_at),
+        source: Set(request.source),
+        message_control_id: Set(request.message_control_id),
+        created_at: Set(Utc::now()),
+    };
+
+    result.insert(db).await
+}
+
+pub async fn get_lab_result(
+    db: &DatabaseConnection,
+    result_id: Uuid,
+) -> Result<Option<LabResultModel>, sea_orm::DbErr> {
+    LabResultEntity::find_by_id(result_id).one(db).await
+}
+
+pub async fn get_lab_results_for_patient(
+    db: &DatabaseConnection,
+    patient_id: Uuid,
+) -> Result<Vec<LabResultModel>, sea_orm::DbErr> {
+    LabResultEntity::find()
+        .filter(LabResultColumn::PatientId.eq(patient_id))
+        .order_by_asc(LabResultColumn::ObservedAt)
+        .order_by_asc(LabResultColumn::CreatedAt)
+        .all(db)
+        .await
+}
+
+/// Removes the results previously stored from one message, so a retransmitted
+/// message replaces its earlier delivery instead of duplicating it.
+pub async fn delete_lab_results_for_message<C: ConnectionTrait>(
+    db: &C,
+    source: &str,
+    message_control_id: &str,
+) -> Result<u64, sea_orm::DbErr> {
+    let res = LabResultEntity::delete_many()
+        .filter(LabResultColumn::Source.eq(source))
+        .filter(LabResultColumn::MessageControlId.eq(message_control_id))
+        .exec(db)
+        .await?;
+    Ok(res.rows_affected)
+}
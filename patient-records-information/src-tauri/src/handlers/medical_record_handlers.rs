@@ -0,0 +1,203 @@
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, ModelTrait, QueryFilter, QueryOrder, QuerySelect,
+    Set, TransactionTrait,
+};
+use crate::models::medical_record_tb::{
+    ActiveModel as MedicalRecordActiveModel, Column as MedicalRecordColumn, Entity as MedicalRecordEntity,
+    Model as MedicalRecordModel,
+};
+use crate::models::patient_tb::Entity as PatientEntity;
+use crate::handlers::concurrency::{row_version, Conditional};
+use crate::handlers::merge_patch::nullable;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateMedicalRecordRequest {
+    pub assessment: Option<String>,
+    pub diagnosis: Option<String>,
+    pub treatment: Option<String>,
+    pub prescription: Option<String>,
+    /// Staff member writing the record.
+    pub first_audited_by: String,
+}
+
+/// Fields left out stay as they are.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateMedicalRecordRequest {
+    pub assessment: Option<String>,
+    pub diagnosis: Option<String>,
+    pub treatment: Option<String>,
+    pub prescription: Option<String>,
+    /// Staff member making the change.
+    pub last_audited_by: String,
+}
+
+/// RFC 7396 merge patch for a record: absent members are left unchanged and
+/// `null` clears the field.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MedicalRecordMergePatch {
+    #[serde(default, deserialize_with = "nullable")]
+    pub assessment: Option<Option<String>>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub diagnosis: Option<Option<String>>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub treatment: Option<Option<String>>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub prescription: Option<Option<String>>,
+    /// Staff member making the change; required on every patch.
+    pub last_audited_by: String,
+}
+
+/// Adds a record to a patient's chart under `medical_id`, chosen by the caller
+/// so that the local and cloud copies share it. `None` when the patient
+/// doesn't exist.
+pub async fn create_medical_record(
+    db: &DatabaseConnection,
+    patient_id: Uuid,
+    medical_id: Uuid,
+    request: CreateMedicalRecordRequest,
+) -> Result<Option<MedicalRecordModel>, sea_orm::DbErr> {
+    if PatientEntity::find_by_id(patient_id).one(db).await?.is_none() {
+        return Ok(None);
+    }
+
+    let now = Utc::now();
+    let record = MedicalRecordActiveModel {
+        medical_id: Set(medical_id),
+        patient_id: Set(patient_id),
+        assessment: Set(request.assessment),
+        diagnosis: Set(request.diagnosis),
+        treatment: Set(request.treatment),
+        prescription: Set(request.prescription),
+        first_audited_by: Set(request.first_audited_by),
+        last_audited_by: Set(None),
+        created_at: Set(now),
+        updated_at: Set(now),
+    };
+    record.insert(db).await.map(Some)
+}
+
+/// A patient's records, oldest first. `None` when the patient doesn't exist.
+pub async fn get_patient_medical_records(
+    db: &DatabaseConnection,
+    patient_id: Uuid,
+) -> Result<Option<Vec<MedicalRecordModel>>, sea_orm::DbErr> {
+    let Some(patient) = PatientEntity::find_by_id(patient_id).one(db).await? else {
+        return Ok(None);
+    };
+    patient
+        .find_related(MedicalRecordEntity)
+        .order_by_asc(MedicalRecordColumn::CreatedAt)
+        .order_by_asc(MedicalRecordColumn::MedicalId)
+        .all(db)
+        .await
+        .map(Some)
+}
+
+/// One record, only if it belongs to `patient_id`.
+pub async fn get_medical_record(
+    db: &DatabaseConnection,
+    patient_id: Uuid,
+    medical_id: Uuid,
+) -> Result<Option<MedicalRecordModel>, sea_orm::DbErr> {
+    MedicalRecordEntity::find_by_id(medical_id)
+        .filter(MedicalRecordColumn::PatientId.eq(patient_id))
+        .one(db)
+        .await
+}
+
+fn apply_update(record: &mut MedicalRecordActiveModel, request: UpdateMedicalRecordRequest) {
+    if let Some(assessment) = request.assessment {
+        record.assessment = Set(Some(assessment));
+    }
+    if let Some(diagnosis) = request.diagnosis {
+        record.diagnosis = Set(Some(diagnosis));
+    }
+    if let Some(treatment) = request.treatment {
+        record.treatment = Set(Some(treatment));
+    }
+    if let Some(prescription) = request.prescription {
+        record.prescription = Set(Some(prescription));
+    }
+    record.last_audited_by = Set(Some(request.last_audited_by));
+}
+
+fn apply_merge_patch(record: &mut MedicalRecordActiveModel, patch: MedicalRecordMergePatch) {
+    if let Some(assessment) = patch.assessment {
+        record.assessment = Set(assessment);
+    }
+    if let Some(diagnosis) = patch.diagnosis {
+        record.diagnosis = Set(diagnosis);
+    }
+    if let Some(treatment) = patch.treatment {
+        record.treatment = Set(treatment);
+    }
+    if let Some(prescription) = patch.prescription {
+        record.prescription = Set(prescription);
+    }
+    record.last_audited_by = Set(Some(patch.last_audited_by));
+}
+
+/// Applies `apply` to the record only if it belongs to `patient_id` and its
+/// current version (see [`row_version`]) is `expected_version`; `None` skips
+/// the check. The row is locked for the duration of the check.
+async fn modify_medical_record_if_unmodified<F>(
+    db: &DatabaseConnection,
+    patient_id: Uuid,
+    medical_id: Uuid,
+    expected_version: Option<i64>,
+    apply: F,
+) -> Result<Conditional<MedicalRecordModel>, sea_orm::DbErr>
+where
+    F: FnOnce(&mut MedicalRecordActiveModel),
+{
+    let txn = db.begin().await?;
+    let Some(record) = MedicalRecordEntity::find_by_id(medical_id)
+        .filter(MedicalRecordColumn::PatientId.eq(patient_id))
+        .lock_exclusive()
+        .one(&txn)
+        .await?
+    else {
+        return Ok(Conditional::NotFound);
+    };
+    if expected_version.is_some_and(|v| v != row_version(&record.updated_at)) {
+        return Ok(Conditional::Modified(record));
+    }
+
+    let mut record: MedicalRecordActiveModel = record.into();
+    apply(&mut record);
+    let updated_record = record.update(&txn).await?;
+    txn.commit().await?;
+    Ok(Conditional::Applied(updated_record))
+}
+
+/// Updates a record, guarded by an optimistic-concurrency check.
+pub async fn update_medical_record_if_unmodified(
+    db: &DatabaseConnection,
+    patient_id: Uuid,
+    medical_id: Uuid,
+    expected_version: Option<i64>,
+    request: UpdateMedicalRecordRequest,
+) -> Result<Conditional<MedicalRecordModel>, sea_orm::DbErr> {
+    modify_medical_record_if_unmodified(db, patient_id, medical_id, expected_version, |record| {
+        apply_update(record, request)
+    })
+    .await
+}
+
+/// Applies a merge patch, guarded by an optimistic-concurrency check.
+pub async fn patch_medical_record_if_unmodified(
+    db: &DatabaseConnection,
+    patient_id: Uuid,
+    medical_id: Uuid,
+    expected_version: Option<i64>,
+    patch: MedicalRecordMergePatch,
+) -> Result<Conditional<MedicalRecordModel>, sea_orm::DbErr> {
+    modify_medical_record_if_unmodified(db, patient_id, medical_id, expected_version, |record| {
+        apply_merge_patch(record, patch)
+    })
+    .await
+}
@@ -1,10 +1,12 @@
-use sea_orm::{DatabaseConnection, EntityTrait, Set, ActiveModelTrait};
+use sea_orm::{DatabaseConnection, EntityTrait, Set, ActiveModelTrait, QuerySelect, TransactionTrait};
 use crate::models::medical_services::{
     Entity as ServiceEntity, Model as ServiceModel, ActiveModel as ServiceActiveModel
 };
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use crate::models::medical_services::ServiceCategory;
+use crate::handlers::concurrency::{row_version, Conditional};
+use crate::handlers::merge_patch::{nullable, required};
 use chrono::Utc;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -22,14 +24,39 @@ pub struct UpdateServiceRequest {  // Fixed typo: CreateUpdateRequest → Update
     pub ms_price: Option<f32>,
 }
 
+/// RFC 7396 merge patch for a service. Every service column is required, so
+/// `null` is rejected by [`ServiceMergePatch::validate`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ServiceMergePatch {
+    #[serde(default, deserialize_with = "nullable")]
+    pub ms_name: Option<Option<String>>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub ms_category: Option<Option<ServiceCategory>>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub ms_price: Option<Option<f32>>,
+}
+
+impl ServiceMergePatch {
+    pub fn validate(&self) -> Result<(), String> {
+        required("ms_name", &self.ms_name)?;
+        required("ms_category", &self.ms_category)?;
+        required("ms_price", &self.ms_price)?;
+        Ok(())
+    }
+}
+
+/// Adds a service to the catalog under `ms_id`, chosen by the caller so that
+/// the local and cloud copies share it.
 pub async fn create_service(
     db: &DatabaseConnection,
+    ms_id: Uuid,
     request: CreateServiceRequest,
 ) -> Result<ServiceModel, sea_orm::DbErr> {
     let now = Utc::now();
 
     let service = ServiceActiveModel {
-        ms_id: Set(Uuid::new_v4()), 
+        ms_id: Set(ms_id),
         ms_name: Set(request.ms_name),
         ms_category: Set(request.ms_category),
         ms_price: Set(request.ms_price),
@@ -70,6 +97,46 @@ pub async fn update_service(
     }
 }
 
+/// Applies a merge patch only if the service's current version is
+/// `expected_version`; `None` skips the check. The patch must have passed
+/// [`ServiceMergePatch::validate`].
+pub async fn patch_service_if_unmodified(
+    db: &DatabaseConnection,
+    ms_id: Uuid,
+    expected_version: Option<i64>,
+    patch: ServiceMergePatch,
+) -> Result<Conditional<ServiceModel>, sea_orm::DbErr> {
+    let txn = db.begin().await?;
+    let Some(service) = ServiceEntity::find_by_id(ms_id).lock_exclusive().one(&txn).await? else {
+        return Ok(Conditional::NotFound);
+    };
+    if expected_version.is_some_and(|v| v != row_version(&service.updated_at)) {
+        return Ok(Conditional::Modified(service));
+    }
+
+    let mut service: ServiceActiveModel = service.into();
+    if let Some(Some(ms_name)) = patch.ms_name {
+        service.ms_name = Set(ms_name);
+    }
+    if let Some(Some(ms_category)) = patch.ms_category {
+        service.ms_category = Set(ms_category);
+    }
+    if let Some(Some(ms_price)) = patch.ms_price {
+        service.ms_price = Set(ms_price);
+    }
+
+    let updated_service = service.update(&txn).await?;
+    txn.commit().await?;
+    Ok(Conditional::Applied(updated_service))
+}
+
+pub async fn get_service(
+    db: &DatabaseConnection,
+    ms_id: Uuid,
+) -> Result<Option<ServiceModel>, sea_orm::DbErr> {
+    ServiceEntity::find_by_id(ms_id).one(db).await
+}
+
 pub async fn get_all_service(
     db: &DatabaseConnection
 ) -> Result<Vec<ServiceModel>, sea_orm::DbErr> {
@@ -79,5 +146,51 @@ pub async fn get_all_service(
 pub async fn delete_service(db: &DatabaseConnection, ms_id: Uuid) -> Result<bool, sea_orm::DbErr> {
     let res = ServiceEntity::delete_by_id(ms_id).exec(db).await?;
     Ok(res.rows_affected > 0)
-} 
+}
+
+/// Deletes the service only if its current version is `expected_version`;
+/// `None` skips the check.
+pub async fn delete_service_if_unmodified(
+    db: &DatabaseConnection,
+    ms_id: Uuid,
+    expected_version: Option<i64>,
+) -> Result<Conditional<ServiceModel>, sea_orm::DbErr> {
+    let txn = db.begin().await?;
+    let Some(service) = ServiceEntity::find_by_id(ms_id).lock_exclusive().one(&txn).await? else {
+        return Ok(Conditional::NotFound);
+    };
+    if expected_version.is_some_and(|v| v != row_version(&service.updated_at)) {
+        return Ok(Conditional::Modified(service));
+    }
 
+    ServiceEntity::delete_by_id(ms_id).exec(&txn).await?;
+    txn.commit().await?;
+    Ok(Conditional::Applied(service))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::migrations::Migrator;
+    use sea_orm::Database;
+    use sea_orm_migration::MigratorTrait;
+
+    #[tokio::test]
+    async fn create_service_keeps_the_callers_id() {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        Migrator::up(&db, None).await.unwrap();
+        let ms_id = Uuid::new_v4();
+        let request = CreateServiceRequest {
+            ms_name: "Complete Blood Count".to_string(),
+            ms_category: ServiceCategory::Hematology,
+            ms_price: 250.0,
+        };
+
+        let created = create_service(&db, ms_id, request).await.unwrap();
+
+        assert_eq!(created.ms_id, ms_id);
+        let stored = get_service(&db, ms_id).await.unwrap().unwrap();
+        assert_eq!(stored.ms_name, "Complete Blood Count");
+    }
+}
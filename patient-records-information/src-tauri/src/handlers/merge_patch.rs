@@ -0,0 +1,69 @@
+use serde::{Deserialize, Deserializer};
+
+/// Deserializes a merge-patch member into three states (RFC 7396):
+/// absent → `None`, `null` → `Some(None)`, value → `Some(Some(value))`.
+///
+/// Use together with `#[serde(default)]` so absent members stay `None`.
+pub fn nullable<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+/// Rejects `null` for a member whose column is `NOT NULL`.
+pub fn required<T: Clone>(field: &str, value: &Option<Option<T>>) -> Result<Option<T>, String> {
+    match value {
+        None => Ok(None),
+        Some(None) => Err(format!("'{}' cannot be null", field)),
+        Some(Some(v)) => Ok(Some(v.clone())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Deserialize)]
+    struct Patch {
+        #[serde(default, deserialize_with = "nullable")]
+        diagnosis: Option<Option<String>>,
+        #[serde(default, deserialize_with = "nullable")]
+        visits: Option<Option<i32>>,
+    }
+
+    fn patch(json: &str) -> Patch {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn nullable_distinguishes_absent_null_and_value() {
+        let absent = patch("{}");
+        assert_eq!(absent.diagnosis, None);
+        assert_eq!(absent.visits, None);
+
+        let cleared = patch(r#"{"diagnosis": null}"#);
+        assert_eq!(cleared.diagnosis, Some(None));
+        assert_eq!(cleared.visits, None);
+
+        let set = patch(r#"{"diagnosis": "J06.9", "visits": 2}"#);
+        assert_eq!(set.diagnosis, Some(Some("J06.9".to_string())));
+        assert_eq!(set.visits, Some(Some(2)));
+    }
+
+    #[test]
+    fn nullable_still_checks_the_value_type() {
+        assert!(serde_json::from_str::<Patch>(r#"{"visits": "two"}"#).is_err());
+    }
+
+    #[test]
+    fn required_rejects_only_null() {
+        assert_eq!(required::<i32>("visits", &None), Ok(None));
+        assert_eq!(required("visits", &Some(Some(2))), Ok(Some(2)));
+        assert_eq!(
+            required::<i32>("visits", &Some(None)),
+            Err("'visits' cannot be null".to_string())
+        );
+    }
+}
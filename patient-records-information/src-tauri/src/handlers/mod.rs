@@ -2,11 +2,58 @@ pub mod patient_handlers;
 pub use patient_handlers::{
     CreatePatientRequest,
     UpdatePatientRequest,
+    PatientMergePatch,
+    PatientAddress,
+    PatientSearch,
     create_patient,
     get_patient,
     get_all_patients,
+    search_patients,
+    find_patient_by_name_and_birth_date,
     update_patient,
+    update_patient_if_unmodified,
+    patch_patient_if_unmodified,
     delete_patient,
+    delete_patient_if_unmodified,
+    PatientDeletion,
 };
 
-pub mod medical_services_handler;
\ No newline at end of file
+pub mod patient_number_handlers;
+pub use patient_number_handlers::PatientNumbering;
+
+pub mod concurrency;
+pub use concurrency::{Conditional, row_version};
+
+pub mod merge_patch;
+
+pub mod medical_services_handler;
+
+pub mod medical_record_handlers;
+
+pub mod lab_result_handlers;
+
+pub mod audit_handlers;
+
+pub mod retention_handlers;
+
+pub mod consent_handlers;
+
+pub mod psgc_handlers;
+
+pub mod household_handlers;
+
+pub mod seed_handlers;
+
+pub mod integrity_handlers;
+
+pub mod icd10_handlers;
+
+pub mod prescription_handlers;
+
+pub mod vital_sign_handlers;
+
+pub mod service_result_handlers;
+
+pub mod reference_range_handlers;
+
+pub mod unit_conversion_handlers;
\ No newline at end of file
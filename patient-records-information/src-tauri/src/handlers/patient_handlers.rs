@@ -1,5 +1,12 @@
-use sea_orm::{DatabaseConnection, EntityTrait, Set, ActiveModelTrait};
-use crate::models::patient_tb::{Entity as PatientEntity, Model as PatientModel, ActiveModel as PatientActiveModel};
+use sea_orm::{DatabaseConnection, EntityTrait, Set, ActiveModelTrait, ColumnTrait, QueryFilter, QueryOrder, QuerySelect, Condition, ConnectionTrait, TransactionTrait, PaginatorTrait, QueryTrait};
+use sea_orm::sea_query::{Expr, Func, SimpleExpr};
+use crate::crypto::{self, normalize};
+use crate::models::medical_bill_record::{Column as BillColumn, Entity as BillEntity};
+use crate::models::medical_record_tb::{Column as MedicalRecordColumn, Entity as MedicalRecordEntity};
+use crate::models::patient_tb::{Entity as PatientEntity, Model as PatientModel, ActiveModel as PatientActiveModel, Column as PatientColumn, Sex};
+use crate::handlers::concurrency::{row_version, Conditional};
+use crate::handlers::merge_patch::{nullable, required};
+use crate::handlers::patient_number_handlers::{next_patient_number, normalize_patient_number, registration_year, PatientNumbering};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
@@ -9,9 +16,16 @@ pub struct CreatePatientRequest {
     pub last_name: String,
     pub middle_name: Option<String>,
     pub birth_date: chrono::NaiveDate,
+    #[serde(default)]
+    pub sex: Option<Sex>,
     pub csd_id_or_pwd_id: Option<String>,
     pub mobile_number: Option<String>,
     pub residential_address: Option<String>,
+    #[serde(default)]
+    pub address: Option<PatientAddress>,
+    /// Chosen by the server, never by the client.
+    #[serde(skip)]
+    pub numbering: PatientNumbering,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -20,44 +34,348 @@ pub struct UpdatePatientRequest {
     pub last_name: Option<String>,
     pub middle_name: Option<String>,
     pub birth_date: Option<chrono::NaiveDate>,
+    #[serde(default)]
+    pub sex: Option<Sex>,
     pub csd_id_or_pwd_id: Option<String>,
     pub mobile_number: Option<String>,
     pub residential_address: Option<String>,
+    /// Replaces the whole structured address.
+    #[serde(default)]
+    pub address: Option<PatientAddress>,
 }
 
-pub async fn create_patient(
-    db: &DatabaseConnection,
+/// Structured address. Clients may send only the most specific PSGC code they
+/// know; [`resolve_address`](crate::handlers::psgc_handlers::resolve_address)
+/// fills in and checks the enclosing areas before the address is stored.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PatientAddress {
+    pub address_line: Option<String>,
+    pub barangay_code: Option<String>,
+    pub city_municipality_code: Option<String>,
+    pub province_code: Option<String>,
+    pub region_code: Option<String>,
+}
+
+impl PatientAddress {
+    fn apply(self, patient: &mut PatientActiveModel) {
+        patient.address_line = Set(self.address_line);
+        patient.barangay_code = Set(self.barangay_code);
+        patient.city_municipality_code = Set(self.city_municipality_code);
+        patient.province_code = Set(self.province_code);
+        patient.region_code = Set(self.region_code);
+    }
+}
+
+impl From<&PatientModel> for PatientAddress {
+    fn from(patient: &PatientModel) -> Self {
+        Self {
+            address_line: patient.address_line.clone(),
+            barangay_code: patient.barangay_code.clone(),
+            city_municipality_code: patient.city_municipality_code.clone(),
+            province_code: patient.province_code.clone(),
+            region_code: patient.region_code.clone(),
+        }
+    }
+}
+
+/// RFC 7396 merge patch for a patient: absent members are left unchanged and
+/// `null` clears a nullable column. `null` on a required column is rejected by
+/// [`PatientMergePatch::validate`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PatientMergePatch {
+    #[serde(default, deserialize_with = "nullable")]
+    pub first_name: Option<Option<String>>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub last_name: Option<Option<String>>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub middle_name: Option<Option<String>>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub birth_date: Option<Option<chrono::NaiveDate>>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub sex: Option<Option<Sex>>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub csd_id_or_pwd_id: Option<Option<String>>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub mobile_number: Option<Option<String>>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub residential_address: Option<Option<String>>,
+    /// Replaced as a whole rather than merged, since its codes must stay
+    /// consistent with each other; `null` clears it.
+    #[serde(default, deserialize_with = "nullable")]
+    pub address: Option<Option<PatientAddress>>,
+    /// Archiving starts the archive-based retention period (see `retention_handlers`).
+    #[serde(default, deserialize_with = "nullable")]
+    pub is_archived: Option<Option<bool>>,
+}
+
+impl PatientMergePatch {
+    pub fn validate(&self) -> Result<(), String> {
+        required("first_name", &self.first_name)?;
+        required("last_name", &self.last_name)?;
+        required("birth_date", &self.birth_date)?;
+        required("is_archived", &self.is_archived)?;
+        Ok(())
+    }
+}
+
+/// Filters for looking up patients; every field that is set must match.
+///
+/// Name filters are case-insensitive prefix matches, `identifier` matches the
+/// patient UUID, the patient number or the CSD/PWD id exactly, `patient_number`
+/// and `mobile_number` match exactly after normalisation. With PII encryption enabled the last name is matched
+/// through its blind index, i.e. exactly rather than by prefix.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PatientSearch {
+    pub last_name: Option<String>,
+    pub first_name: Option<String>,
+    pub birth_date: Option<chrono::NaiveDate>,
+    pub identifier: Option<String>,
+    pub patient_number: Option<String>,
+    pub mobile_number: Option<String>,
+    pub limit: Option<u64>,
+}
+
+fn crypto_err(e: crypto::CryptoError) -> sea_orm::DbErr {
+    sea_orm::DbErr::Custom(e.to_string())
+}
+
+fn decrypt_all(patients: Vec<PatientModel>) -> Result<Vec<PatientModel>, sea_orm::DbErr> {
+    patients.into_iter().map(PatientModel::decrypted).collect()
+}
+
+fn lower_eq(column: PatientColumn, value: &str) -> SimpleExpr {
+    Expr::expr(Func::lower(Expr::col(column))).eq(value.trim().to_lowercase())
+}
+
+/// Matches an encrypted column through its blind index, or the plaintext
+/// condition for rows written before encryption was enabled.
+fn blind_match(
+    cipher: &crypto::FieldCipher,
+    bidx_column: PatientColumn,
+    context: &str,
+    normalized: &str,
+    plaintext: SimpleExpr,
+) -> Condition {
+    Condition::any()
+        .add(bidx_column.eq(cipher.blind_index(context, normalized)))
+        .add(plaintext)
+}
+
+/// Stores a new patient. The id is chosen by the caller so that the local and
+/// cloud copies share it. Unless `request.numbering` says otherwise, the next
+/// patient number of the registration year is taken in the same transaction.
+pub async fn create_patient<C: ConnectionTrait + TransactionTrait>(
+    db: &C,
+    patient_id: Uuid,
     request: CreatePatientRequest,
 ) -> Result<PatientModel, sea_orm::DbErr> {
-    let patient = PatientActiveModel {
+    let now = chrono::Utc::now();
+    let txn = db.begin().await?;
+    let patient_number = match request.numbering {
+        PatientNumbering::Allocate => Some(next_patient_number(&txn, registration_year(&now)).await?),
+        PatientNumbering::Assigned(number) => Some(number),
+        PatientNumbering::Deferred => None,
+    };
+    let mut patient = PatientActiveModel {
+        patient_id: Set(patient_id),
+        patient_number: Set(patient_number),
         first_name: Set(request.first_name),
         last_name: Set(request.last_name),
         middle_name: Set(request.middle_name),
         birth_date: Set(request.birth_date),
+        sex: Set(request.sex),
         csd_id_or_pwd_id: Set(request.csd_id_or_pwd_id),
         mobile_number: Set(request.mobile_number),
         residential_address: Set(request.residential_address),
+        created_at: Set(now),
+        updated_at: Set(now),
         ..Default::default()
     };
+    request.address.unwrap_or_default().apply(&mut patient);
 
-    patient.insert(db).await
+    let patient = patient.insert(&txn).await?;
+    txn.commit().await?;
+    Ok(patient)
 }
 
-pub async fn get_patient(
-    db: &DatabaseConnection,
+pub async fn get_patient<C: ConnectionTrait>(
+    db: &C,
     patient_id: Uuid,
 ) -> Result<Option<PatientModel>, sea_orm::DbErr> {
-    PatientEntity::find_by_id(patient_id).one(db).await
+    PatientEntity::find_by_id(patient_id)
+        .one(db)
+        .await?
+        .map(PatientModel::decrypted)
+        .transpose()
 }
 
 pub async fn get_all_patients(
     db: &DatabaseConnection,
 ) -> Result<Vec<PatientModel>, sea_orm::DbErr> {
-    PatientEntity::find().all(db).await
+    decrypt_all(PatientEntity::find().all(db).await?)
 }
 
-pub async fn update_patient(
+pub async fn search_patients(
     db: &DatabaseConnection,
+    search: PatientSearch,
+) -> Result<Vec<PatientModel>, sea_orm::DbErr> {
+    let cipher = crypto::global().map_err(crypto_err)?;
+    let mut condition = Condition::all();
+
+    if let Some(last_name) = &search.last_name {
+        let prefix = Expr::expr(Func::lower(Expr::col(PatientColumn::LastName)))
+            .like(format!("{}%", last_name.to_lowercase()));
+        condition = match cipher {
+            Some(cipher) => condition.add(blind_match(
+                cipher,
+                PatientColumn::LastNameBidx,
+                "last_name",
+                &normalize::name(last_name),
+                prefix,
+            )),
+            None => condition.add(prefix),
+        };
+    }
+    if let (Some(first_name), None) = (&search.first_name, cipher) {
+        condition = condition.add(
+            Expr::expr(Func::lower(Expr::col(PatientColumn::FirstName)))
+                .like(format!("{}%", first_name.to_lowercase())),
+        );
+    }
+    if let Some(birth_date) = search.birth_date {
+        condition = condition.add(PatientColumn::BirthDate.eq(birth_date));
+    }
+    if let Some(identifier) = &search.identifier {
+        let mut by_identifier = match cipher {
+            Some(cipher) => blind_match(
+                cipher,
+                PatientColumn::CsdIdOrPwdIdBidx,
+                "csd_id_or_pwd_id",
+                &normalize::csd_id_or_pwd_id(identifier),
+                PatientColumn::CsdIdOrPwdId.eq(identifier.clone()),
+            ),
+            None => Condition::any().add(PatientColumn::CsdIdOrPwdId.eq(identifier.clone())),
+        };
+        by_identifier = by_identifier.add(PatientColumn::PatientNumber.eq(normalize_patient_number(identifier)));
+        if let Ok(patient_id) = Uuid::parse_str(identifier) {
+            by_identifier = by_identifier.add(PatientColumn::PatientId.eq(patient_id));
+        }
+        condition = condition.add(by_identifier);
+    }
+    if let Some(patient_number) = &search.patient_number {
+        condition = condition.add(PatientColumn::PatientNumber.eq(normalize_patient_number(patient_number)));
+    }
+    if let Some(mobile_number) = &search.mobile_number {
+        condition = match cipher {
+            Some(cipher) => condition.add(blind_match(
+                cipher,
+                PatientColumn::MobileNumberBidx,
+                "mobile_number",
+                &normalize::mobile_number(mobile_number),
+                PatientColumn::MobileNumber.eq(mobile_number.clone()),
+            )),
+            None => condition.add(PatientColumn::MobileNumber.eq(mobile_number.clone())),
+        };
+    }
+
+    if cipher.is_none() {
+        let mut query = PatientEntity::find()
+            .filter(condition)
+            .order_by_asc(PatientColumn::LastName)
+            .order_by_asc(PatientColumn::FirstName);
+        if let Some(limit) = search.limit {
+            query = query.limit(limit);
+        }
+        return query.all(db).await;
+    }
+
+    // Ciphertext can't be compared or ordered by the database: name filters are
+    // re-checked (the plaintext prefix could match the ciphertext prefix), and
+    // ordering and limit are applied after decryption.
+    let mut patients = decrypt_all(PatientEntity::find().filter(condition).all(db).await?)?;
+    if let Some(last_name) = &search.last_name {
+        let last_name = normalize::name(last_name);
+        patients.retain(|p| normalize::name(&p.last_name).starts_with(&last_name));
+    }
+    if let Some(first_name) = &search.first_name {
+        let first_name = first_name.to_lowercase();
+        patients.retain(|p| p.first_name.to_lowercase().starts_with(&first_name));
+    }
+    patients.sort_by(|a, b| (&a.last_name, &a.first_name).cmp(&(&b.last_name, &b.first_name)));
+    if let Some(limit) = search.limit {
+        patients.truncate(limit as usize);
+    }
+    Ok(patients)
+}
+
+/// Exact, case-insensitive match on last name, first name and birth date, used
+/// to recognise a patient sent by an external system that doesn't know our UUID.
+pub async fn find_patient_by_name_and_birth_date<C: ConnectionTrait>(
+    db: &C,
+    last_name: &str,
+    first_name: &str,
+    birth_date: chrono::NaiveDate,
+) -> Result<Option<PatientModel>, sea_orm::DbErr> {
+    let Some(cipher) = crypto::global().map_err(crypto_err)? else {
+        return PatientEntity::find()
+            .filter(lower_eq(PatientColumn::LastName, last_name))
+            .filter(lower_eq(PatientColumn::FirstName, first_name))
+            .filter(PatientColumn::BirthDate.eq(birth_date))
+            .one(db)
+            .await;
+    };
+
+    let candidates = PatientEntity::find()
+        .filter(blind_match(
+            cipher,
+            PatientColumn::LastNameBidx,
+            "last_name",
+            &normalize::name(last_name),
+            lower_eq(PatientColumn::LastName, last_name),
+        ))
+        .filter(PatientColumn::BirthDate.eq(birth_date))
+        .all(db)
+        .await?;
+    let first_name = normalize::name(first_name);
+    Ok(decrypt_all(candidates)?
+        .into_iter()
+        .find(|p| normalize::name(&p.first_name) == first_name))
+}
+
+fn apply_update(patient: &mut PatientActiveModel, request: UpdatePatientRequest) {
+    if let Some(first_name) = request.first_name {
+        patient.first_name = Set(first_name);
+    }
+    if let Some(last_name) = request.last_name {
+        patient.last_name = Set(last_name);
+    }
+    if let Some(middle_name) = request.middle_name {
+        patient.middle_name = Set(Some(middle_name));
+    }
+    if let Some(birth_date) = request.birth_date {
+        patient.birth_date = Set(birth_date);
+    }
+    if let Some(sex) = request.sex {
+        patient.sex = Set(Some(sex));
+    }
+    if let Some(csd_id_or_pwd_id) = request.csd_id_or_pwd_id {
+        patient.csd_id_or_pwd_id = Set(Some(csd_id_or_pwd_id));
+    }
+    if let Some(mobile_number) = request.mobile_number {
+        patient.mobile_number = Set(Some(mobile_number));
+    }
+    if let Some(residential_address) = request.residential_address {
+        patient.residential_address = Set(Some(residential_address));
+    }
+    if let Some(address) = request.address {
+        address.apply(patient);
+    }
+}
+
+pub async fn update_patient<C: ConnectionTrait>(
+    db: &C,
     patient_id: Uuid,
     request: UpdatePatientRequest,
 ) -> Result<Option<PatientModel>, sea_orm::DbErr> {
@@ -65,28 +383,7 @@ pub async fn update_patient(
     
     if let Some(patient) = patient {
         let mut patient: PatientActiveModel = patient.into();
-        
-        if let Some(first_name) = request.first_name {
-            patient.first_name = Set(first_name);
-        }
-        if let Some(last_name) = request.last_name {
-            patient.last_name = Set(last_name);
-        }
-        if let Some(middle_name) = request.middle_name {
-            patient.middle_name = Set(Some(middle_name));
-        }
-        if let Some(birth_date) = request.birth_date {
-            patient.birth_date = Set(birth_date);
-        }
-        if let Some(csd_id_or_pwd_id) = request.csd_id_or_pwd_id {
-            patient.csd_id_or_pwd_id = Set(Some(csd_id_or_pwd_id));
-        }
-        if let Some(mobile_number) = request.mobile_number {
-            patient.mobile_number = Set(Some(mobile_number));
-        }
-        if let Some(residential_address) = request.residential_address {
-            patient.residential_address = Set(Some(residential_address));
-        }
+        apply_update(&mut patient, request);
 
         let updated_patient: PatientModel = patient.update(db).await?;
         Ok(Some(updated_patient))
@@ -95,10 +392,217 @@ pub async fn update_patient(
     }
 } 
 
+fn apply_merge_patch(patient: &mut PatientActiveModel, patch: PatientMergePatch) {
+    if let Some(Some(first_name)) = patch.first_name {
+        patient.first_name = Set(first_name);
+    }
+    if let Some(Some(last_name)) = patch.last_name {
+        patient.last_name = Set(last_name);
+    }
+    if let Some(Some(birth_date)) = patch.birth_date {
+        patient.birth_date = Set(birth_date);
+    }
+    if let Some(middle_name) = patch.middle_name {
+        patient.middle_name = Set(middle_name);
+    }
+    if let Some(sex) = patch.sex {
+        patient.sex = Set(sex);
+    }
+    if let Some(csd_id_or_pwd_id) = patch.csd_id_or_pwd_id {
+        patient.csd_id_or_pwd_id = Set(csd_id_or_pwd_id);
+    }
+    if let Some(mobile_number) = patch.mobile_number {
+        patient.mobile_number = Set(mobile_number);
+    }
+    if let Some(residential_address) = patch.residential_address {
+        patient.residential_address = Set(residential_address);
+    }
+    if let Some(address) = patch.address {
+        address.unwrap_or_default().apply(patient);
+    }
+    if let Some(Some(is_archived)) = patch.is_archived {
+        patient.is_archived = Set(is_archived);
+    }
+}
+
+/// Applies `apply` to the patient only if its current version (see
+/// [`row_version`]) is `expected_version`; `None` skips the check. The row is
+/// locked for the duration of the check.
+async fn modify_patient_if_unmodified<F>(
+    db: &DatabaseConnection,
+    patient_id: Uuid,
+    expected_version: Option<i64>,
+    apply: F,
+) -> Result<Conditional<PatientModel>, sea_orm::DbErr>
+where
+    F: FnOnce(&mut PatientActiveModel),
+{
+    let txn = db.begin().await?;
+    let Some(patient) = PatientEntity::find_by_id(patient_id).lock_exclusive().one(&txn).await? else {
+        return Ok(Conditional::NotFound);
+    };
+    if expected_version.is_some_and(|v| v != row_version(&patient.updated_at)) {
+        return Ok(Conditional::Modified(patient.decrypted()?));
+    }
+
+    let mut patient: PatientActiveModel = patient.into();
+    apply(&mut patient);
+    let updated_patient = patient.update(&txn).await?;
+    txn.commit().await?;
+    Ok(Conditional::Applied(updated_patient))
+}
+
+/// [`update_patient`] guarded by an optimistic-concurrency check.
+pub async fn update_patient_if_unmodified(
+    db: &DatabaseConnection,
+    patient_id: Uuid,
+    expected_version: Option<i64>,
+    request: UpdatePatientRequest,
+) -> Result<Conditional<PatientModel>, sea_orm::DbErr> {
+    modify_patient_if_unmodified(db, patient_id, expected_version, |patient| apply_update(patient, request)).await
+}
+
+/// Applies a merge patch, guarded by an optimistic-concurrency check. The
+/// patch must have passed [`PatientMergePatch::validate`].
+pub async fn patch_patient_if_unmodified(
+    db: &DatabaseConnection,
+    patient_id: Uuid,
+    expected_version: Option<i64>,
+    patch: PatientMergePatch,
+) -> Result<Conditional<PatientModel>, sea_orm::DbErr> {
+    modify_patient_if_unmodified(db, patient_id, expected_version, |patient| apply_merge_patch(patient, patch)).await
+}
+
 pub async fn delete_patient(
     db: &DatabaseConnection,
     patient_id: Uuid,
 ) -> Result<bool, sea_orm::DbErr> {
     let result = PatientEntity::delete_by_id(patient_id).exec(db).await?;
     Ok(result.rows_affected > 0)
-}
\ No newline at end of file
+}
+
+/// Outcome of [`delete_patient_if_unmodified`].
+#[derive(Debug, Clone)]
+pub enum PatientDeletion {
+    /// Deleted, with the medical records that cascade from it; carries the patient.
+    Deleted(PatientModel),
+    NotFound,
+    /// The patient changed since the caller read it; carries the current row.
+    Modified(PatientModel),
+    /// The patient has this many bills. Bills are financial records and are
+    /// never deleted along with a patient; only a retention purge disposes of them.
+    HasBills(u64),
+}
+
+/// Deletes the patient only if its current version is `expected_version`
+/// (`None` skips the check) and it has no bills.
+pub async fn delete_patient_if_unmodified(
+    db: &DatabaseConnection,
+    patient_id: Uuid,
+    expected_version: Option<i64>,
+) -> Result<PatientDeletion, sea_orm::DbErr> {
+    let txn = db.begin().await?;
+    let Some(patient) = PatientEntity::find_by_id(patient_id).lock_exclusive().one(&txn).await? else {
+        return Ok(PatientDeletion::NotFound);
+    };
+    if expected_version.is_some_and(|v| v != row_version(&patient.updated_at)) {
+        return Ok(PatientDeletion::Modified(patient.decrypted()?));
+    }
+    let visits = MedicalRecordEntity::find()
+        .select_only()
+        .column(MedicalRecordColumn::MedicalId)
+        .filter(MedicalRecordColumn::PatientId.eq(patient_id))
+        .into_query();
+    let bills = BillEntity::find()
+        .filter(
+            Condition::any()
+                .add(BillColumn::PatientId.eq(patient_id))
+                .add(BillColumn::MedicalId.in_subquery(visits)),
+        )
+        .count(&txn)
+        .await?;
+    if bills > 0 {
+        return Ok(PatientDeletion::HasBills(bills));
+    }
+
+    PatientEntity::delete_by_id(patient_id).exec(&txn).await?;
+    txn.commit().await?;
+    Ok(PatientDeletion::Deleted(patient.decrypted()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::migrations::Migrator;
+    use crate::models::medical_bill_record::{ActiveModel as BillActiveModel, PaymentStatus};
+    use crate::models::medical_record_tb::ActiveModel as MedicalRecordActiveModel;
+    use sea_orm::Database;
+    use sea_orm_migration::MigratorTrait;
+
+    async fn patient_with_visit(db: &DatabaseConnection) -> (Uuid, Uuid) {
+        let now = chrono::Utc::now();
+        let patient_id = Uuid::new_v4();
+        PatientActiveModel {
+            patient_id: Set(patient_id),
+            first_name: Set("Juan".to_string()),
+            last_name: Set("Dela Cruz".to_string()),
+            age: Set(35),
+            birth_date: Set(chrono::NaiveDate::from_ymd_opt(1990, 5, 17).unwrap()),
+            created_at: Set(now),
+            updated_at: Set(now),
+            ..Default::default()
+        }
+        .insert(db)
+        .await
+        .unwrap();
+        let medical_id = Uuid::new_v4();
+        MedicalRecordActiveModel {
+            medical_id: Set(medical_id),
+            patient_id: Set(patient_id),
+            assessment: Set(None),
+            diagnosis: Set(None),
+            treatment: Set(None),
+            prescription: Set(None),
+            first_audited_by: Set("Dr. Santos".to_string()),
+            last_audited_by: Set(None),
+            created_at: Set(now),
+            updated_at: Set(now),
+        }
+        .insert(db)
+        .await
+        .unwrap();
+        (patient_id, medical_id)
+    }
+
+    #[tokio::test]
+    async fn delete_refuses_patients_with_bills() {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        Migrator::up(&db, None).await.unwrap();
+        let (billed, medical_id) = patient_with_visit(&db).await;
+        BillActiveModel {
+            medical_bill_id: Set(Uuid::new_v4()),
+            patient_id: Set(billed),
+            medical_id: Set(medical_id),
+            consultation_fee: Set(Some(300.0)),
+            remarks: Set(None),
+            payment_status: Set(PaymentStatus::Unpaid),
+            total_amount: Set(300.0),
+        }
+        .insert(&db)
+        .await
+        .unwrap();
+        let (unbilled, _) = patient_with_visit(&db).await;
+
+        assert!(matches!(
+            delete_patient_if_unmodified(&db, billed, None).await.unwrap(),
+            PatientDeletion::HasBills(1)
+        ));
+        assert!(get_patient(&db, billed).await.unwrap().is_some());
+
+        assert!(matches!(
+            delete_patient_if_unmodified(&db, unbilled, None).await.unwrap(),
+            PatientDeletion::Deleted(_)
+        ));
+        assert!(get_patient(&db, unbilled).await.unwrap().is_none());
+    }
+}
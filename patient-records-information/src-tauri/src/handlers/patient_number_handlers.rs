@@ -0,0 +1,194 @@
+use sea_orm::{
+    ColumnTrait, ConnectionTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QueryOrder, QuerySelect, Set,
+    TransactionTrait,
+};
+use sea_orm::sea_query::{Expr, OnConflict};
+use crate::models::patient_number_sequence_tb::{
+    ActiveModel as SequenceActiveModel, Column as SequenceColumn, Entity as SequenceEntity,
+};
+use crate::models::patient_tb::{Column as PatientColumn, Entity as PatientEntity, Model as PatientModel};
+use chrono::{Datelike, FixedOffset, Utc};
+use uuid::Uuid;
+
+pub const PATIENT_NUMBER_PREFIX: &str = "PRN";
+
+/// Registration years follow Philippine Standard Time (UTC+8), so a patient
+/// registered just after local midnight on New Year's Day gets the new year.
+const PHILIPPINE_TIME_OFFSET_SECS: i32 = 8 * 3600;
+
+/// How [`create_patient`](crate::handlers::create_patient) fills `patient_number`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum PatientNumbering {
+    /// Take the next number of the database the patient is written to.
+    #[default]
+    Allocate,
+    /// Use a number already taken from the numbering database (copies of a
+    /// patient created there).
+    Assigned(String),
+    /// Leave the number empty; [`assign_pending_patient_numbers`] fills it in
+    /// once the numbering database is reachable.
+    Deferred,
+}
+
+/// `PRN-2026-000123`. Numbers past 999999 simply get wider.
+pub fn format_patient_number(year: i32, number: i64) -> String {
+    format!("{}-{}-{:06}", PATIENT_NUMBER_PREFIX, year, number)
+}
+
+/// Canonical form of a number typed by staff (`prn-2026-000123 ` matches).
+pub fn normalize_patient_number(value: &str) -> String {
+    value.trim().to_uppercase()
+}
+
+pub fn registration_year(created_at: &chrono::DateTime<Utc>) -> i32 {
+    let offset = FixedOffset::east_opt(PHILIPPINE_TIME_OFFSET_SECS).expect("offset is within a day");
+    created_at.with_timezone(&offset).year()
+}
+
+/// Takes the next number of `year`. Must run inside the transaction that
+/// stores it: the counter row stays locked until commit, and a rollback
+/// returns the number, which keeps the sequence gap-free.
+pub async fn next_patient_number<C: ConnectionTrait>(conn: &C, year: i32) -> Result<String, DbErr> {
+    SequenceEntity::insert(SequenceActiveModel {
+        year: Set(year),
+        last_number: Set(0),
+    })
+    .on_conflict(OnConflict::column(SequenceColumn::Year).do_nothing().to_owned())
+    .exec_without_returning(conn)
+    .await?;
+
+    SequenceEntity::update_many()
+        .col_expr(SequenceColumn::LastNumber, Expr::col(SequenceColumn::LastNumber).add(1))
+        .filter(SequenceColumn::Year.eq(year))
+        .exec(conn)
+        .await?;
+
+    let sequence = SequenceEntity::find_by_id(year)
+        .one(conn)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound(format!("patient number sequence {}", year)))?;
+    Ok(format_patient_number(year, sequence.last_number))
+}
+
+/// Stores `number` on a patient that has none yet. Written directly so that
+/// `updated_at`, and with it the patient's `ETag`, is left unchanged.
+async fn set_pending_number<C: ConnectionTrait>(conn: &C, patient_id: Uuid, number: &str) -> Result<bool, DbErr> {
+    let result = PatientEntity::update_many()
+        .col_expr(PatientColumn::PatientNumber, Expr::value(number))
+        .filter(PatientColumn::PatientId.eq(patient_id))
+        .filter(PatientColumn::PatientNumber.is_null())
+        .exec(conn)
+        .await?;
+    Ok(result.rows_affected > 0)
+}
+
+/// Numbers `patient`, already stored in `local` without a number, in the
+/// year it was registered. With `cloud_numbering` the number comes from the
+/// cloud sequence, whose transaction stays open until the local row has it, so
+/// a failure on either side returns the number; otherwise from the local one.
+/// The patient's cloud copy, which shares its id, gets the same number when
+/// there is one. Returns the number, or `None` when the patient already had one.
+pub async fn assign_patient_number(
+    local: &DatabaseConnection,
+    cloud: Option<&DatabaseConnection>,
+    cloud_numbering: bool,
+    patient: &PatientModel,
+) -> Result<Option<String>, DbErr> {
+    let year = registration_year(&patient.created_at);
+    match cloud.filter(|_| cloud_numbering) {
+        Some(cloud) => {
+            let txn = cloud.begin().await?;
+            let number = next_patient_number(&txn, year).await?;
+            // The cloud copy may not exist yet; the sync carries the number over then.
+            set_pending_number(&txn, patient.patient_id, &number).await?;
+            if !set_pending_number(local, patient.patient_id, &number).await? {
+                txn.rollback().await?;
+                return Ok(None);
+            }
+            if let Err(e) = txn.commit().await {
+                // The cloud never recorded the number: take it back so it isn't issued twice.
+                PatientEntity::update_many()
+                    .col_expr(PatientColumn::PatientNumber, Expr::value(Option::<String>::None))
+                    .filter(PatientColumn::PatientId.eq(patient.patient_id))
+                    .filter(PatientColumn::PatientNumber.eq(number))
+                    .exec(local)
+                    .await?;
+                return Err(e);
+            }
+            Ok(Some(number))
+        }
+        None if cloud_numbering => Ok(None),
+        None => {
+            let txn = local.begin().await?;
+            let number = next_patient_number(&txn, year).await?;
+            if !set_pending_number(&txn, patient.patient_id, &number).await? {
+                txn.rollback().await?;
+                return Ok(None);
+            }
+            txn.commit().await?;
+            if let Some(cloud) = cloud {
+                if let Err(e) = set_pending_number(cloud, patient.patient_id, &number).await {
+                    log::warn!("⚠️ Patient number {} not copied to the cloud: {}", number, e);
+                }
+            }
+            Ok(Some(number))
+        }
+    }
+}
+
+/// Numbers up to `limit` patients of `local` that have no number yet, oldest
+/// first (see [`assign_patient_number`]). With `cloud_numbering` nothing is
+/// numbered without `cloud`. Returns how many were numbered.
+pub async fn assign_pending_patient_numbers(
+    local: &DatabaseConnection,
+    cloud: Option<&DatabaseConnection>,
+    cloud_numbering: bool,
+    limit: u64,
+) -> Result<u64, DbErr> {
+    if cloud_numbering && cloud.is_none() {
+        return Ok(0);
+    }
+    let pending = PatientEntity::find()
+        .filter(PatientColumn::PatientNumber.is_null())
+        .order_by_asc(PatientColumn::CreatedAt)
+        .order_by_asc(PatientColumn::PatientId)
+        .limit(limit)
+        .all(local)
+        .await?;
+
+    let mut assigned = 0;
+    for patient in pending {
+        if assign_patient_number(local, cloud, cloud_numbering, &patient).await?.is_some() {
+            assigned += 1;
+        }
+    }
+    Ok(assigned)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn format_patient_number_pads_to_six_digits() {
+        assert_eq!(format_patient_number(2026, 1), "PRN-2026-000001");
+        assert_eq!(format_patient_number(2026, 123), "PRN-2026-000123");
+        assert_eq!(format_patient_number(2026, 999_999), "PRN-2026-999999");
+        assert_eq!(format_patient_number(2026, 1_000_000), "PRN-2026-1000000");
+    }
+
+    #[test]
+    fn normalize_patient_number_matches_the_formatted_number() {
+        assert_eq!(normalize_patient_number(" prn-2026-000123 "), format_patient_number(2026, 123));
+    }
+
+    #[test]
+    fn registration_year_follows_philippine_time() {
+        let utc = |y, m, d, h| Utc.with_ymd_and_hms(y, m, d, h, 0, 0).unwrap();
+
+        assert_eq!(registration_year(&utc(2025, 12, 31, 15)), 2025);
+        assert_eq!(registration_year(&utc(2025, 12, 31, 16)), 2026);
+        assert_eq!(registration_year(&utc(2026, 12, 31, 15)), 2026);
+    }
+}
@@ -0,0 +1,306 @@
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder, QuerySelect, Set,
+};
+use crate::handlers::medical_record_handlers::get_medical_record;
+use crate::handlers::patient_handlers::get_patient;
+use crate::models::medical_record_tb::Model as MedicalRecordModel;
+use crate::models::patient_tb::Model as PatientModel;
+use crate::models::prescription_item_tb::{
+    ActiveModel as PrescriptionItemActiveModel, Column as PrescriptionItemColumn, Entity as PrescriptionItemEntity,
+    MedicationRoute, Model as PrescriptionItemModel,
+};
+use chrono::{Datelike, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreatePrescriptionItemRequest {
+    pub generic_name: String,
+    pub brand_name: Option<String>,
+    pub strength: Option<String>,
+    pub dose: String,
+    pub route: MedicationRoute,
+    pub frequency: String,
+    pub duration: Option<String>,
+    pub quantity: Option<i32>,
+    pub instructions: Option<String>,
+    /// Staff member writing the prescription.
+    pub first_audited_by: String,
+}
+
+/// Fields left out stay as they are.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdatePrescriptionItemRequest {
+    pub generic_name: Option<String>,
+    pub brand_name: Option<String>,
+    pub strength: Option<String>,
+    pub dose: Option<String>,
+    pub route: Option<MedicationRoute>,
+    pub frequency: Option<String>,
+    pub duration: Option<String>,
+    pub quantity: Option<i32>,
+    pub instructions: Option<String>,
+    /// Staff member making the change.
+    pub last_audited_by: String,
+}
+
+/// The line items of a record, in prescription order. `None` when the record
+/// doesn't exist or isn't the patient's.
+pub async fn get_prescription_items(
+    db: &DatabaseConnection,
+    patient_id: Uuid,
+    medical_id: Uuid,
+) -> Result<Option<Vec<PrescriptionItemModel>>, sea_orm::DbErr> {
+    if get_medical_record(db, patient_id, medical_id).await?.is_none() {
+        return Ok(None);
+    }
+    PrescriptionItemEntity::find()
+        .filter(PrescriptionItemColumn::MedicalId.eq(medical_id))
+        .order_by_asc(PrescriptionItemColumn::Sequence)
+        .all(db)
+        .await
+        .map(Some)
+}
+
+/// One line item, only if it belongs to the patient's record.
+pub async fn get_prescription_item(
+    db: &DatabaseConnection,
+    patient_id: Uuid,
+    medical_id: Uuid,
+    item_id: Uuid,
+) -> Result<Option<PrescriptionItemModel>, sea_orm::DbErr> {
+    if get_medical_record(db, patient_id, medical_id).await?.is_none() {
+        return Ok(None);
+    }
+    PrescriptionItemEntity::find_by_id(item_id)
+        .filter(PrescriptionItemColumn::MedicalId.eq(medical_id))
+        .one(db)
+        .await
+}
+
+/// Adds a drug at the end of the record's prescription. `None` when the
+/// record doesn't exist or isn't the patient's.
+pub async fn add_prescription_item(
+    db: &DatabaseConnection,
+    patient_id: Uuid,
+    medical_id: Uuid,
+    request: CreatePrescriptionItemRequest,
+) -> Result<Option<PrescriptionItemModel>, sea_orm::DbErr> {
+    if get_medical_record(db, patient_id, medical_id).await?.is_none() {
+        return Ok(None);
+    }
+
+    let last_sequence: Option<i32> = PrescriptionItemEntity::find()
+        .select_only()
+        .column_as(PrescriptionItemColumn::Sequence.max(), "sequence")
+        .filter(PrescriptionItemColumn::MedicalId.eq(medical_id))
+        .into_tuple()
+        .one(db)
+        .await?
+        .flatten();
+
+    let now = Utc::now();
+    let item = PrescriptionItemActiveModel {
+        prescription_item_id: Set(Uuid::new_v4()),
+        medical_id: Set(medical_id),
+        generic_name: Set(request.generic_name),
+        brand_name: Set(request.brand_name),
+        strength: Set(request.strength),
+        dose: Set(request.dose),
+        route: Set(request.route),
+        frequency: Set(request.frequency),
+        duration: Set(request.duration),
+        quantity: Set(request.quantity),
+        instructions: Set(request.instructions),
+        sequence: Set(last_sequence.map_or(0, |sequence| sequence + 1)),
+        first_audited_by: Set(request.first_audited_by),
+        last_audited_by: Set(None),
+        created_at: Set(now),
+        updated_at: Set(now),
+    };
+    item.insert(db).await.map(Some)
+}
+
+pub async fn update_prescription_item(
+    db: &DatabaseConnection,
+    patient_id: Uuid,
+    medical_id: Uuid,
+    item_id: Uuid,
+    request: UpdatePrescriptionItemRequest,
+) -> Result<Option<PrescriptionItemModel>, sea_orm::DbErr> {
+    let Some(item) = get_prescription_item(db, patient_id, medical_id, item_id).await? else {
+        return Ok(None);
+    };
+
+    let mut item: PrescriptionItemActiveModel = item.into();
+    if let Some(generic_name) = request.generic_name {
+        item.generic_name = Set(generic_name);
+    }
+    if let Some(brand_name) = request.brand_name {
+        item.brand_name = Set(Some(brand_name));
+    }
+    if let Some(strength) = request.strength {
+        item.strength = Set(Some(strength));
+    }
+    if let Some(dose) = request.dose {
+        item.dose = Set(dose);
+    }
+    if let Some(route) = request.route {
+        item.route = Set(route);
+    }
+    if let Some(frequency) = request.frequency {
+        item.frequency = Set(frequency);
+    }
+    if let Some(duration) = request.duration {
+        item.duration = Set(Some(duration));
+    }
+    if let Some(quantity) = request.quantity {
+        item.quantity = Set(Some(quantity));
+    }
+    if let Some(instructions) = request.instructions {
+        item.instructions = Set(Some(instructions));
+    }
+    item.last_audited_by = Set(Some(request.last_audited_by));
+    item.update(db).await.map(Some)
+}
+
+/// Removes a drug from the prescription. `false` when it doesn't exist or
+/// isn't on the patient's record.
+pub async fn delete_prescription_item(
+    db: &DatabaseConnection,
+    patient_id: Uuid,
+    medical_id: Uuid,
+    item_id: Uuid,
+) -> Result<bool, sea_orm::DbErr> {
+    if get_prescription_item(db, patient_id, medical_id, item_id).await?.is_none() {
+        return Ok(false);
+    }
+    let result = PrescriptionItemEntity::delete_by_id(item_id).exec(db).await?;
+    Ok(result.rows_affected > 0)
+}
+
+/// The record's prescription as plain text for printing. `None` when the
+/// patient or record doesn't exist.
+pub async fn get_printable_prescription(
+    db: &DatabaseConnection,
+    patient_id: Uuid,
+    medical_id: Uuid,
+) -> Result<Option<String>, sea_orm::DbErr> {
+    let Some(patient) = get_patient(db, patient_id).await? else {
+        return Ok(None);
+    };
+    let Some(record) = get_medical_record(db, patient_id, medical_id).await? else {
+        return Ok(None);
+    };
+    let items = PrescriptionItemEntity::find()
+        .filter(PrescriptionItemColumn::MedicalId.eq(medical_id))
+        .order_by_asc(PrescriptionItemColumn::Sequence)
+        .all(db)
+        .await?;
+    Ok(Some(render_prescription(&patient, &record, &items)))
+}
+
+fn route_phrase(route: MedicationRoute) -> &'static str {
+    match route {
+        MedicationRoute::Oral => "by mouth",
+        MedicationRoute::Sublingual => "under the tongue",
+        MedicationRoute::Topical => "on the skin",
+        MedicationRoute::Inhalation => "by inhalation",
+        MedicationRoute::Nasal => "in the nose",
+        MedicationRoute::Ophthalmic => "in the eye",
+        MedicationRoute::Otic => "in the ear",
+        MedicationRoute::Rectal => "rectally",
+        MedicationRoute::Vaginal => "vaginally",
+        MedicationRoute::Subcutaneous => "subcutaneously",
+        MedicationRoute::Intramuscular => "intramuscularly",
+        MedicationRoute::Intravenous => "intravenously",
+        MedicationRoute::Other => "",
+    }
+}
+
+pub(crate) fn age_on(birth_date: NaiveDate, date: NaiveDate) -> i32 {
+    let had_birthday = (date.month(), date.day()) >= (birth_date.month(), birth_date.day());
+    date.year() - birth_date.year() - if had_birthday { 0 } else { 1 }
+}
+
+/// `DELA CRUZ, Juan S.`, as a patient's name is printed on forms.
+pub(crate) fn printed_name(patient: &PatientModel) -> String {
+    match patient.middle_name.as_deref().and_then(|m| m.chars().next()) {
+        Some(initial) => format!("{}, {} {}.", patient.last_name.to_uppercase(), patient.first_name, initial),
+        None => format!("{}, {}", patient.last_name.to_uppercase(), patient.first_name),
+    }
+}
+
+/// Lays out a prescription: patient header, one numbered entry per drug with
+/// its sig, and the prescriber (whoever wrote the first item, else the record).
+/// Records without line items print their free-text `prescription` instead.
+pub fn render_prescription(
+    patient: &PatientModel,
+    record: &MedicalRecordModel,
+    items: &[PrescriptionItemModel],
+) -> String {
+    let date = record.created_at.date_naive();
+    let name = printed_name(patient);
+    let address = patient.address_line.as_deref().or(patient.residential_address.as_deref());
+
+    let mut out = String::new();
+    let _ = writeln!(out, "PRESCRIPTION");
+    let _ = writeln!(out);
+    let _ = writeln!(out, "Patient:     {}", name);
+    if let Some(number) = &patient.patient_number {
+        let _ = writeln!(out, "Patient No.: {}", number);
+    }
+    let _ = writeln!(out, "Age:         {}", age_on(patient.birth_date, date));
+    if let Some(address) = address {
+        let _ = writeln!(out, "Address:     {}", address);
+    }
+    let _ = writeln!(out, "Date:        {}", date.format("%B %-d, %Y"));
+    let _ = writeln!(out);
+    let _ = writeln!(out, "Rx");
+
+    if items.is_empty() {
+        match record.prescription.as_deref().map(str::trim).filter(|p| !p.is_empty()) {
+            Some(prescription) => {
+                for line in prescription.lines() {
+                    let _ = writeln!(out, "   {}", line);
+                }
+            }
+            None => {
+                let _ = writeln!(out, "   (no medication prescribed)");
+            }
+        }
+    }
+    for (number, item) in items.iter().enumerate() {
+        let mut drug = item.generic_name.clone();
+        if let Some(brand) = &item.brand_name {
+            let _ = write!(drug, " ({})", brand);
+        }
+        if let Some(strength) = &item.strength {
+            let _ = write!(drug, " {}", strength);
+        }
+        if let Some(quantity) = item.quantity {
+            let _ = write!(drug, "  #{}", quantity);
+        }
+        let _ = writeln!(out, "{:>2}. {}", number + 1, drug);
+
+        let mut sig = format!("Sig: {}", item.dose);
+        let route = route_phrase(item.route);
+        if !route.is_empty() {
+            let _ = write!(sig, " {}", route);
+        }
+        let _ = write!(sig, " {}", item.frequency);
+        if let Some(duration) = &item.duration {
+            let _ = write!(sig, " for {}", duration);
+        }
+        let _ = writeln!(out, "    {}", sig);
+        if let Some(instructions) = item.instructions.as_deref().filter(|i| !i.trim().is_empty()) {
+            let _ = writeln!(out, "    {}", instructions.trim());
+        }
+    }
+
+    let _ = writeln!(out);
+    let prescriber = items.first().map_or(&record.first_audited_by, |item| &item.first_audited_by);
+    let _ = writeln!(out, "Prescribed by: {}", prescriber);
+    out
+}
@@ -0,0 +1,496 @@
+use sea_orm::{
+    ColumnTrait, Condition, DatabaseConnection, EntityTrait, FromQueryResult, QueryFilter, QueryOrder, QuerySelect,
+    Set, TransactionTrait,
+};
+use sea_orm::sea_query::{Expr, Func, OnConflict};
+use crate::handlers::patient_handlers::PatientAddress;
+use crate::models::patient_tb::{Column as PatientColumn, Entity as PatientEntity};
+use crate::models::psgc_area_tb::{
+    ActiveModel as PsgcAreaActiveModel, Column as PsgcAreaColumn, Entity as PsgcAreaEntity, Model as PsgcAreaModel,
+    PsgcLevel,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+const IMPORT_BATCH_SIZE: usize = 500;
+const DEFAULT_SEARCH_LIMIT: u64 = 20;
+const MAX_SEARCH_LIMIT: u64 = 100;
+
+/// Autocomplete query: `q` matches anywhere in the area name, case-insensitively.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PsgcSearch {
+    pub q: Option<String>,
+    pub level: Option<PsgcLevel>,
+    pub parent_code: Option<String>,
+    pub limit: Option<u64>,
+}
+
+/// An area together with the areas enclosing it, region first.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PsgcAreaPath {
+    pub area: PsgcAreaModel,
+    pub ancestors: Vec<PsgcAreaModel>,
+}
+
+/// Outcome of checking a [`PatientAddress`] against the PSGC table.
+#[derive(Debug, Clone)]
+pub enum AddressResolution {
+    /// Codes of every enclosing area filled in.
+    Resolved(PatientAddress),
+    Invalid(String),
+}
+
+/// Patient column an address level is stored in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AddressLevel {
+    Region,
+    Province,
+    CityMunicipality,
+    Barangay,
+}
+
+impl AddressLevel {
+    fn column(self) -> PatientColumn {
+        match self {
+            AddressLevel::Region => PatientColumn::RegionCode,
+            AddressLevel::Province => PatientColumn::ProvinceCode,
+            AddressLevel::CityMunicipality => PatientColumn::CityMunicipalityCode,
+            AddressLevel::Barangay => PatientColumn::BarangayCode,
+        }
+    }
+
+    fn field(self) -> &'static str {
+        match self {
+            AddressLevel::Region => "region_code",
+            AddressLevel::Province => "province_code",
+            AddressLevel::CityMunicipality => "city_municipality_code",
+            AddressLevel::Barangay => "barangay_code",
+        }
+    }
+
+    /// PSGC levels that fill this part of an address. NCR districts stand in
+    /// for provinces.
+    fn accepts(self, level: PsgcLevel) -> bool {
+        match self {
+            AddressLevel::Region => level == PsgcLevel::Reg,
+            AddressLevel::Province => matches!(level, PsgcLevel::Prov | PsgcLevel::Dist),
+            AddressLevel::CityMunicipality => matches!(level, PsgcLevel::City | PsgcLevel::Mun),
+            AddressLevel::Barangay => level == PsgcLevel::Bgy,
+        }
+    }
+
+    fn of(level: PsgcLevel) -> Option<Self> {
+        [
+            AddressLevel::Region,
+            AddressLevel::Province,
+            AddressLevel::CityMunicipality,
+            AddressLevel::Barangay,
+        ]
+        .into_iter()
+        .find(|address_level| address_level.accepts(level))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AreaPatientCount {
+    /// `None` groups patients without a structured address at this level.
+    pub psgc_code: Option<String>,
+    pub name: Option<String>,
+    pub patients: i64,
+}
+
+#[derive(Debug, FromQueryResult)]
+struct CodeCount {
+    psgc_code: Option<String>,
+    patients: i64,
+}
+
+/// Area lookup and autocomplete, ordered by name.
+pub async fn search_psgc_areas(
+    db: &DatabaseConnection,
+    search: PsgcSearch,
+) -> Result<Vec<PsgcAreaModel>, sea_orm::DbErr> {
+    let mut query = PsgcAreaEntity::find();
+    if let Some(q) = search.q.as_deref().map(str::trim).filter(|q| !q.is_empty()) {
+        query = query.filter(Expr::expr(Func::lower(Expr::col(PsgcAreaColumn::Name))).like(format!(
+            "%{}%",
+            q.to_lowercase()
+        )));
+    }
+    if let Some(level) = search.level {
+        query = query.filter(PsgcAreaColumn::GeographicLevel.eq(level));
+    }
+    if let Some(parent_code) = search.parent_code {
+        query = query.filter(PsgcAreaColumn::ParentCode.eq(parent_code));
+    }
+    query
+        .order_by_asc(PsgcAreaColumn::Name)
+        .limit(search.limit.unwrap_or(DEFAULT_SEARCH_LIMIT).min(MAX_SEARCH_LIMIT))
+        .all(db)
+        .await
+}
+
+/// Areas directly inside `psgc_code`, ordered by name.
+pub async fn get_psgc_children(
+    db: &DatabaseConnection,
+    psgc_code: &str,
+) -> Result<Vec<PsgcAreaModel>, sea_orm::DbErr> {
+    PsgcAreaEntity::find()
+        .filter(PsgcAreaColumn::ParentCode.eq(psgc_code))
+        .order_by_asc(PsgcAreaColumn::Name)
+        .all(db)
+        .await
+}
+
+pub async fn get_psgc_area(
+    db: &DatabaseConnection,
+    psgc_code: &str,
+) -> Result<Option<PsgcAreaPath>, sea_orm::DbErr> {
+    let Some(area) = PsgcAreaEntity::find_by_id(psgc_code).one(db).await? else {
+        return Ok(None);
+    };
+    let mut ancestors = Vec::new();
+    let mut parent_code = area.parent_code.clone();
+    while let Some(code) = parent_code {
+        // A parent missing from a partial import ends the path.
+        let Some(parent) = PsgcAreaEntity::find_by_id(code).one(db).await? else {
+            break;
+        };
+        parent_code = parent.parent_code.clone();
+        ancestors.push(parent);
+    }
+    ancestors.reverse();
+    Ok(Some(PsgcAreaPath { area, ancestors }))
+}
+
+/// Checks the PSGC codes of `address` and fills in the ones the client left
+/// out, starting from the most specific code given. Every other code given
+/// must belong to an area enclosing it.
+pub async fn resolve_address(
+    db: &DatabaseConnection,
+    address: PatientAddress,
+) -> Result<AddressResolution, sea_orm::DbErr> {
+    let address_line = address
+        .address_line
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty());
+    let given = [
+        (AddressLevel::Barangay, address.barangay_code),
+        (AddressLevel::CityMunicipality, address.city_municipality_code),
+        (AddressLevel::Province, address.province_code),
+        (AddressLevel::Region, address.region_code),
+    ]
+    .map(|(level, code)| (level, code.map(|c| c.trim().to_string()).filter(|c| !c.is_empty())));
+
+    let Some((most_specific, code)) = given.iter().find_map(|(level, code)| code.clone().map(|c| (*level, c))) else {
+        return Ok(AddressResolution::Resolved(PatientAddress {
+            address_line,
+            ..Default::default()
+        }));
+    };
+
+    let Some(path) = get_psgc_area(db, &code).await? else {
+        return Ok(AddressResolution::Invalid(format!("Unknown PSGC code '{}'", code)));
+    };
+    if !most_specific.accepts(path.area.geographic_level) {
+        return Ok(AddressResolution::Invalid(format!(
+            "{} '{}' is a {:?} area",
+            most_specific.field(),
+            code,
+            path.area.geographic_level
+        )));
+    }
+
+    let mut derived: HashMap<AddressLevel, String> = HashMap::new();
+    for area in path.ancestors.iter().chain([&path.area]) {
+        if let Some(level) = AddressLevel::of(area.geographic_level) {
+            derived.insert(level, area.psgc_code.clone());
+        }
+    }
+    for (level, code) in &given {
+        if let Some(code) = code {
+            if derived.get(level) != Some(code) {
+                return Ok(AddressResolution::Invalid(format!(
+                    "{} '{}' does not contain {} '{}'",
+                    level.field(),
+                    code,
+                    most_specific.field(),
+                    path.area.psgc_code
+                )));
+            }
+        }
+    }
+
+    Ok(AddressResolution::Resolved(PatientAddress {
+        address_line,
+        barangay_code: derived.remove(&AddressLevel::Barangay),
+        city_municipality_code: derived.remove(&AddressLevel::CityMunicipality),
+        province_code: derived.remove(&AddressLevel::Province),
+        region_code: derived.remove(&AddressLevel::Region),
+    }))
+}
+
+/// Number of active (non-archived) patients per area at `level`, optionally
+/// only inside the area `within`. Returns `Ok(None)` when `within` is unknown
+/// or is not a region, province or city/municipality.
+pub async fn count_patients_by_area(
+    db: &DatabaseConnection,
+    level: AddressLevel,
+    within: Option<&str>,
+) -> Result<Option<Vec<AreaPatientCount>>, sea_orm::DbErr> {
+    let mut condition = Condition::all().add(PatientColumn::IsArchived.eq(false));
+    if let Some(within) = within {
+        let Some(area) = PsgcAreaEntity::find_by_id(within).one(db).await? else {
+            return Ok(None);
+        };
+        match AddressLevel::of(area.geographic_level).filter(|l| *l != AddressLevel::Barangay) {
+            Some(within_level) => condition = condition.add(within_level.column().eq(area.psgc_code)),
+            None => return Ok(None),
+        }
+    }
+
+    let counts = PatientEntity::find()
+        .select_only()
+        .column_as(level.column(), "psgc_code")
+        .column_as(PatientColumn::PatientId.count(), "patients")
+        .filter(condition)
+        .group_by(level.column())
+        .into_model::<CodeCount>()
+        .all(db)
+        .await?;
+
+    let codes: Vec<String> = counts.iter().filter_map(|c| c.psgc_code.clone()).collect();
+    let names: HashMap<String, String> = if codes.is_empty() {
+        HashMap::new()
+    } else {
+        PsgcAreaEntity::find()
+            .filter(PsgcAreaColumn::PsgcCode.is_in(codes))
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|area| (area.psgc_code, area.name))
+            .collect()
+    };
+
+    let mut counts: Vec<AreaPatientCount> = counts
+        .into_iter()
+        .map(|count| AreaPatientCount {
+            name: count.psgc_code.as_ref().and_then(|code| names.get(code).cloned()),
+            psgc_code: count.psgc_code,
+            patients: count.patients,
+        })
+        .collect();
+    counts.sort_by(|a, b| b.patients.cmp(&a.patients).then_with(|| a.name.cmp(&b.name)));
+    Ok(Some(counts))
+}
+
+/// Parses a PSGC CSV export. The header must name a code column (`psgc_code`,
+/// `10-digit PSGC` or `code`), `name` and a level column (`geographic_level`
+/// or `level`); `parent_code` is optional. Without it, each area's parent is
+/// the nearest enclosing area present in the file, derived from the code
+/// (region, province and city/municipality segments of the 9- or 10-digit code).
+///
+/// Returns the areas and the number of rows skipped for lacking a usable code,
+/// name or PSGC level.
+pub fn parse_psgc_csv(contents: &str) -> Result<(Vec<PsgcAreaModel>, usize), String> {
+    let mut lines = contents.trim_start_matches('\u{feff}').lines().filter(|l| !l.trim().is_empty());
+    let header: Vec<String> = split_csv_line(lines.next().ok_or("PSGC file is empty")?)
+        .iter()
+        .map(|h| h.trim().to_lowercase().replace([' ', '-'], "_"))
+        .collect();
+    let column = |names: &[&str]| header.iter().position(|h| names.contains(&h.as_str()));
+    let code_at = column(&["psgc_code", "10_digit_psgc", "psgc", "code"]).ok_or("PSGC file has no code column")?;
+    let name_at = column(&["name"]).ok_or("PSGC file has no name column")?;
+    let level_at = column(&["geographic_level", "level"]).ok_or("PSGC file has no geographic level column")?;
+    let parent_at = column(&["parent_code"]);
+
+    let mut areas = Vec::new();
+    let mut skipped = 0;
+    for line in lines {
+        let fields = split_csv_line(line);
+        let field = |at: usize| fields.get(at).map(|f| f.trim()).unwrap_or("");
+        let code: String = field(code_at).chars().filter(char::is_ascii_digit).collect();
+        let level = serde_json::from_value::<PsgcLevel>(serde_json::Value::String(field(level_at).to_string()));
+        match level {
+            Ok(level) if matches!(code.len(), 9 | 10) && !field(name_at).is_empty() => areas.push(PsgcAreaModel {
+                psgc_code: code,
+                name: field(name_at).to_string(),
+                geographic_level: level,
+                parent_code: parent_at.map(field).filter(|p| !p.is_empty()).map(str::to_string),
+            }),
+            _ => skipped += 1,
+        }
+    }
+
+    if parent_at.is_none() {
+        let codes: HashSet<String> = areas.iter().map(|a| a.psgc_code.clone()).collect();
+        for area in &mut areas {
+            area.parent_code = derived_parent(&area.psgc_code, &codes);
+        }
+    }
+    Ok((areas, skipped))
+}
+
+fn derived_parent(code: &str, codes: &HashSet<String>) -> Option<String> {
+    let prefixes: &[usize] = if code.len() == 10 { &[7, 5, 2] } else { &[6, 4, 2] };
+    prefixes
+        .iter()
+        .map(|&len| format!("{}{}", &code[..len], "0".repeat(code.len() - len)))
+        .find(|candidate| candidate != code && codes.contains(candidate))
+}
+
+/// Splits one CSV record, honouring double-quoted fields (`""` is a quote).
+pub(crate) fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, quoted) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            ('"', _) => quoted = !quoted,
+            (',', false) => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+/// Inserts or updates `areas` in one transaction, so re-importing a newer PSGC
+/// release updates names and parents in place.
+pub async fn import_psgc_areas(
+    db: &DatabaseConnection,
+    areas: Vec<PsgcAreaModel>,
+) -> Result<usize, sea_orm::DbErr> {
+    let txn = db.begin().await?;
+    let total = areas.len();
+    let mut areas = areas.into_iter().peekable();
+    while areas.peek().is_some() {
+        let batch: Vec<PsgcAreaActiveModel> = areas
+            .by_ref()
+            .take(IMPORT_BATCH_SIZE)
+            .map(|area| PsgcAreaActiveModel {
+                psgc_code: Set(area.psgc_code),
+                name: Set(area.name),
+                geographic_level: Set(area.geographic_level),
+                parent_code: Set(area.parent_code),
+            })
+            .collect();
+        PsgcAreaEntity::insert_many(batch)
+            .on_conflict(
+                OnConflict::column(PsgcAreaColumn::PsgcCode)
+                    .update_columns([
+                        PsgcAreaColumn::Name,
+                        PsgcAreaColumn::GeographicLevel,
+                        PsgcAreaColumn::ParentCode,
+                    ])
+                    .to_owned(),
+            )
+            .exec_without_returning(&txn)
+            .await?;
+    }
+    txn.commit().await?;
+    Ok(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parents(areas: &[PsgcAreaModel]) -> Vec<(&str, Option<&str>)> {
+        areas
+            .iter()
+            .map(|a| (a.psgc_code.as_str(), a.parent_code.as_deref()))
+            .collect()
+    }
+
+    #[test]
+    fn parse_psgc_csv_reads_the_psa_export_and_derives_parents() {
+        let csv = "\u{feff}10-digit PSGC,Name,Correspondence Code,Geographic Level\n\
+                   0100000000,Region I (Ilocos Region),010000000,Reg\n\
+                   0102800000,Ilocos Norte,012800000,Prov\n\
+                   0102801000,Adams,012801000,Mun\n\
+                   0102801001,Adams (Pob.),012801001,Bgy\n\
+                   \n\
+                   0102899000,\"Dingras, Sample\",,Mun\n";
+
+        let (areas, skipped) = parse_psgc_csv(csv).unwrap();
+
+        assert_eq!(skipped, 0);
+        assert_eq!(areas[0].geographic_level, PsgcLevel::Reg);
+        assert_eq!(areas[4].name, "Dingras, Sample");
+        assert_eq!(
+            parents(&areas),
+            vec![
+                ("0100000000", None),
+                ("0102800000", Some("0100000000")),
+                ("0102801000", Some("0102800000")),
+                ("0102801001", Some("0102801000")),
+                ("0102899000", Some("0102800000")),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_psgc_csv_skips_to_the_nearest_area_present() {
+        // Nine-digit codes, and no province row for the municipality.
+        let csv = "code,name,level\n\
+                   130000000,NCR,Reg\n\
+                   137404000,Quezon City,City\n\
+                   137404001,Alicia,Bgy\n";
+
+        let (areas, _) = parse_psgc_csv(csv).unwrap();
+
+        assert_eq!(
+            parents(&areas),
+            vec![
+                ("130000000", None),
+                ("137404000", Some("130000000")),
+                ("137404001", Some("137404000")),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_psgc_csv_keeps_an_explicit_parent_column() {
+        let csv = "psgc_code,name,geographic_level,parent_code\n\
+                   0100000000,Region I,Reg,\n\
+                   0102800000,Ilocos Norte,Prov,9999999999\n";
+
+        let (areas, _) = parse_psgc_csv(csv).unwrap();
+
+        assert_eq!(parents(&areas), vec![("0100000000", None), ("0102800000", Some("9999999999"))]);
+    }
+
+    #[test]
+    fn parse_psgc_csv_counts_unusable_rows() {
+        let csv = "psgc_code,name,geographic_level\n\
+                   0100000000,Region I,Reg\n\
+                   01000,Short code,Reg\n\
+                   0102800000,,Prov\n\
+                   0102801000,Adams,Town\n";
+
+        let (areas, skipped) = parse_psgc_csv(csv).unwrap();
+
+        assert_eq!(areas.len(), 1);
+        assert_eq!(skipped, 3);
+    }
+
+    #[test]
+    fn parse_psgc_csv_requires_the_key_columns() {
+        assert!(parse_psgc_csv("").is_err());
+        assert!(parse_psgc_csv("name,geographic_level\n").is_err());
+        assert!(parse_psgc_csv("psgc_code,geographic_level\n").is_err());
+        assert!(parse_psgc_csv("psgc_code,name\n").is_err());
+    }
+
+    #[test]
+    fn split_csv_line_honours_quotes() {
+        assert_eq!(split_csv_line(r#"a,"b, c","say ""hi""",,"#), vec!["a", "b, c", r#"say "hi""#, "", ""]);
+    }
+}
@@ -0,0 +1,450 @@
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder, Set,
+    TransactionTrait,
+};
+use crate::handlers::audit_handlers::{record_audit, CreateAuditEntry};
+use crate::handlers::service_result_handlers::{reflag_unreleased, Limits, Subject, MAX_ANALYTE_CODE_LEN};
+use crate::models::analyte_reference_range_tb::{
+    ActiveModel as ReferenceRangeActiveModel, Column as ReferenceRangeColumn, Entity as ReferenceRangeEntity,
+    Model as ReferenceRangeModel,
+};
+use crate::models::patient_tb::Sex;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use uuid::Uuid;
+
+/// A reference range as created or replaced.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SaveReferenceRangeRequest {
+    pub analyte_code: String,
+    /// Leave out for a range that applies to both sexes.
+    #[serde(default)]
+    pub sex: Option<Sex>,
+    #[serde(default)]
+    pub min_age_days: i32,
+    pub max_age_days: Option<i32>,
+    pub unit: String,
+    pub reference_low: Option<f32>,
+    pub reference_high: Option<f32>,
+    pub critical_low: Option<f32>,
+    pub critical_high: Option<f32>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReferenceRangeQuery {
+    pub analyte_code: Option<String>,
+}
+
+/// A saved range and the unreleased results reflagged because of it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReferenceRangeChange {
+    pub range: ReferenceRangeModel,
+    pub results_reflagged: u64,
+}
+
+#[derive(Debug, Clone)]
+pub enum ReferenceRangeOutcome {
+    Saved(ReferenceRangeChange),
+    NotFound,
+    Invalid(String),
+}
+
+/// The range that applies to a result of `analyte_code` in `unit`: one for the
+/// patient's sex if there is one, else one for both sexes, whose age band
+/// includes the patient's age.
+pub fn applicable_range<'a>(
+    ranges: &'a [ReferenceRangeModel],
+    analyte_code: &str,
+    unit: &str,
+    subject: Subject,
+) -> Option<&'a ReferenceRangeModel> {
+    let candidates = || {
+        ranges.iter().filter(move |range| {
+            range.analyte_code == analyte_code
+                && range.unit == unit
+                && subject.age_days >= i64::from(range.min_age_days)
+                && range.max_age_days.is_none_or(|max| subject.age_days < i64::from(max))
+        })
+    };
+    candidates()
+        .find(|range| subject.sex.is_some() && range.sex == subject.sex)
+        .or_else(|| candidates().find(|range| range.sex.is_none()))
+}
+
+/// All ranges of the given analytes.
+pub(crate) async fn reference_ranges_for<C: ConnectionTrait>(
+    db: &C,
+    analyte_codes: &[String],
+) -> Result<Vec<ReferenceRangeModel>, sea_orm::DbErr> {
+    ReferenceRangeEntity::find()
+        .filter(ReferenceRangeColumn::AnalyteCode.is_in(analyte_codes.iter().cloned()))
+        .all(db)
+        .await
+}
+
+/// Ranges by analyte, unit, sex and age band.
+pub async fn get_reference_ranges(
+    db: &DatabaseConnection,
+    query: &ReferenceRangeQuery,
+) -> Result<Vec<ReferenceRangeModel>, sea_orm::DbErr> {
+    let mut select = ReferenceRangeEntity::find();
+    if let Some(code) = &query.analyte_code {
+        select = select.filter(ReferenceRangeColumn::AnalyteCode.eq(code.trim().to_uppercase()));
+    }
+    select
+        .order_by_asc(ReferenceRangeColumn::AnalyteCode)
+        .order_by_asc(ReferenceRangeColumn::Unit)
+        .order_by_asc(ReferenceRangeColumn::Sex)
+        .order_by_asc(ReferenceRangeColumn::MinAgeDays)
+        .all(db)
+        .await
+}
+
+pub async fn get_reference_range(
+    db: &DatabaseConnection,
+    reference_range_id: Uuid,
+) -> Result<Option<ReferenceRangeModel>, sea_orm::DbErr> {
+    ReferenceRangeEntity::find_by_id(reference_range_id).one(db).await
+}
+
+/// Why the range can't be saved, if it can't. `others` are the ranges of the
+/// same analyte besides the one being saved.
+fn invalid_range(request: &SaveReferenceRangeRequest, others: &[ReferenceRangeModel]) -> Option<String> {
+    let code = request.analyte_code.trim().to_uppercase();
+    let unit = request.unit.trim();
+    if code.is_empty() || unit.is_empty() {
+        return Some("analyte_code and unit are required".to_string());
+    }
+    if code.len() > MAX_ANALYTE_CODE_LEN {
+        return Some(format!("Analyte code '{}' is longer than {} characters", code, MAX_ANALYTE_CODE_LEN));
+    }
+    if request.min_age_days < 0 || request.max_age_days.is_some_and(|max| max <= request.min_age_days) {
+        return Some("The age band must go from min_age_days ≥ 0 to a greater max_age_days".to_string());
+    }
+    let limits = Limits {
+        reference_low: request.reference_low,
+        reference_high: request.reference_high,
+        critical_low: request.critical_low,
+        critical_high: request.critical_high,
+    };
+    if limits.is_empty() {
+        return Some("At least one limit is required".to_string());
+    }
+    if let Some(message) = limits.invalid() {
+        return Some(message.to_string());
+    }
+
+    let (min, max) = (request.min_age_days, request.max_age_days.unwrap_or(i32::MAX));
+    others
+        .iter()
+        .find(|range| {
+            range.unit == unit
+                && range.sex == request.sex
+                && range.min_age_days < max
+                && min < range.max_age_days.unwrap_or(i32::MAX)
+        })
+        .map(|range| {
+            format!(
+                "The age band overlaps the {} range from {} days{}",
+                code,
+                range.min_age_days,
+                range.max_age_days.map_or(String::new(), |max| format!(" to {} days", max))
+            )
+        })
+}
+
+async fn same_analyte_ranges<C: ConnectionTrait>(
+    db: &C,
+    request: &SaveReferenceRangeRequest,
+    except: Option<Uuid>,
+) -> Result<Vec<ReferenceRangeModel>, sea_orm::DbErr> {
+    let mut select = ReferenceRangeEntity::find()
+        .filter(ReferenceRangeColumn::AnalyteCode.eq(request.analyte_code.trim().to_uppercase()));
+    if let Some(reference_range_id) = except {
+        select = select.filter(ReferenceRangeColumn::ReferenceRangeId.ne(reference_range_id));
+    }
+    select.all(db).await
+}
+
+/// Audit log action of a deleted range.
+pub const RANGE_DELETED_ACTION: &str = "reference_range.deleted";
+
+/// Adds a range on behalf of `created_by`, the authenticated account, and
+/// reflags the analyte's unreleased results.
+pub async fn create_reference_range(
+    db: &DatabaseConnection,
+    request: SaveReferenceRangeRequest,
+    created_by: Uuid,
+) -> Result<ReferenceRangeOutcome, sea_orm::DbErr> {
+    let txn = db.begin().await?;
+    let others = same_analyte_ranges(&txn, &request, None).await?;
+    if let Some(message) = invalid_range(&request, &others) {
+        return Ok(ReferenceRangeOutcome::Invalid(message));
+    }
+
+    let now = Utc::now();
+    let range = ReferenceRangeActiveModel {
+        reference_range_id: Set(Uuid::new_v4()),
+        analyte_code: Set(request.analyte_code.trim().to_uppercase()),
+        sex: Set(request.sex),
+        min_age_days: Set(request.min_age_days),
+        max_age_days: Set(request.max_age_days),
+        unit: Set(request.unit.trim().to_string()),
+        reference_low: Set(request.reference_low),
+        reference_high: Set(request.reference_high),
+        critical_low: Set(request.critical_low),
+        critical_high: Set(request.critical_high),
+        created_by: Set(created_by),
+        updated_by: Set(None),
+        created_at: Set(now),
+        updated_at: Set(now),
+    }
+    .insert(&txn)
+    .await?;
+    let results_reflagged = reflag_unreleased(&txn, &range.analyte_code).await?;
+    txn.commit().await?;
+    Ok(ReferenceRangeOutcome::Saved(ReferenceRangeChange { range, results_reflagged }))
+}
+
+/// Replaces a range on behalf of `updated_by` and reflags the unreleased
+/// results of its analyte, and of the previous analyte if the code changed.
+pub async fn update_reference_range(
+    db: &DatabaseConnection,
+    reference_range_id: Uuid,
+    request: SaveReferenceRangeRequest,
+    updated_by: Uuid,
+) -> Result<ReferenceRangeOutcome, sea_orm::DbErr> {
+    let txn = db.begin().await?;
+    let Some(current) = ReferenceRangeEntity::find_by_id(reference_range_id).one(&txn).await? else {
+        return Ok(ReferenceRangeOutcome::NotFound);
+    };
+    let others = same_analyte_ranges(&txn, &request, Some(reference_range_id)).await?;
+    if let Some(message) = invalid_range(&request, &others) {
+        return Ok(ReferenceRangeOutcome::Invalid(message));
+    }
+
+    let previous_code = current.analyte_code.clone();
+    let mut range: ReferenceRangeActiveModel = current.into();
+    range.analyte_code = Set(request.analyte_code.trim().to_uppercase());
+    range.sex = Set(request.sex);
+    range.min_age_days = Set(request.min_age_days);
+    range.max_age_days = Set(request.max_age_days);
+    range.unit = Set(request.unit.trim().to_string());
+    range.reference_low = Set(request.reference_low);
+    range.reference_high = Set(request.reference_high);
+    range.critical_low = Set(request.critical_low);
+    range.critical_high = Set(request.critical_high);
+    range.updated_by = Set(Some(updated_by));
+    let range = range.update(&txn).await?;
+
+    let mut results_reflagged = reflag_unreleased(&txn, &range.analyte_code).await?;
+    if previous_code != range.analyte_code {
+        results_reflagged += reflag_unreleased(&txn, &previous_code).await?;
+    }
+    txn.commit().await?;
+    Ok(ReferenceRangeOutcome::Saved(ReferenceRangeChange { range, results_reflagged }))
+}
+
+/// Removes a range and reflags the analyte's unreleased results, which fall
+/// back to another range or the template. The deleted range is kept in the
+/// audit log under `deleted_by`. `None` when it doesn't exist; otherwise how
+/// many results were reflagged.
+pub async fn delete_reference_range(
+    db: &DatabaseConnection,
+    reference_range_id: Uuid,
+    deleted_by: Uuid,
+) -> Result<Option<u64>, sea_orm::DbErr> {
+    let txn = db.begin().await?;
+    let Some(range) = ReferenceRangeEntity::find_by_id(reference_range_id).one(&txn).await? else {
+        return Ok(None);
+    };
+    ReferenceRangeEntity::delete_by_id(reference_range_id).exec(&txn).await?;
+    let entry = CreateAuditEntry {
+        action: RANGE_DELETED_ACTION.to_string(),
+        entity: "reference_range".to_string(),
+        record_id: Some(reference_range_id.to_string()),
+        actor_id: Some(deleted_by),
+        details: json!({ "range": range }),
+    };
+    record_audit(&txn, None, entry).await?;
+    let results_reflagged = reflag_unreleased(&txn, &range.analyte_code).await?;
+    txn.commit().await?;
+    Ok(Some(results_reflagged))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::service_result_handlers::compute_flag;
+    use crate::models::service_analyte_tb::{AnalyteValueType, Model as AnalyteModel};
+    use crate::models::service_result_tb::ResultFlag;
+
+    const YEAR: i32 = 365;
+
+    fn range(sex: Option<Sex>, min_age_days: i32, max_age_days: Option<i32>, low: f32, high: f32) -> ReferenceRangeModel {
+        ReferenceRangeModel {
+            reference_range_id: Uuid::new_v4(),
+            analyte_code: "HGB".to_string(),
+            sex,
+            min_age_days,
+            max_age_days,
+            unit: "g/L".to_string(),
+            reference_low: Some(low),
+            reference_high: Some(high),
+            critical_low: None,
+            critical_high: None,
+            created_by: Uuid::nil(),
+            updated_by: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    /// Hemoglobin: one band for children, adult bands by sex.
+    fn hemoglobin_ranges() -> Vec<ReferenceRangeModel> {
+        vec![
+            range(None, 0, Some(18 * YEAR), 110.0, 145.0),
+            range(None, 18 * YEAR, None, 120.0, 170.0),
+            range(Some(Sex::Male), 18 * YEAR, None, 140.0, 170.0),
+            range(Some(Sex::Female), 18 * YEAR, None, 120.0, 160.0),
+        ]
+    }
+
+    fn subject(sex: Option<Sex>, age_years: i32) -> Subject {
+        Subject { sex, age_days: i64::from(age_years * YEAR) }
+    }
+
+    fn limits_of(range: Option<&ReferenceRangeModel>) -> Option<(f32, f32)> {
+        range.map(|r| (r.reference_low.unwrap(), r.reference_high.unwrap()))
+    }
+
+    #[test]
+    fn applicable_range_prefers_the_patients_sex() {
+        let ranges = hemoglobin_ranges();
+        let find = |subject| limits_of(applicable_range(&ranges, "HGB", "g/L", subject));
+
+        assert_eq!(find(subject(Some(Sex::Male), 40)), Some((140.0, 170.0)));
+        assert_eq!(find(subject(Some(Sex::Female), 40)), Some((120.0, 160.0)));
+        assert_eq!(find(subject(None, 40)), Some((120.0, 170.0)));
+        // No sex-specific band for children.
+        assert_eq!(find(subject(Some(Sex::Male), 10)), Some((110.0, 145.0)));
+    }
+
+    #[test]
+    fn applicable_range_age_bands_include_the_minimum_only() {
+        let ranges = hemoglobin_ranges();
+        let find = |age_days| limits_of(applicable_range(&ranges, "HGB", "g/L", Subject { sex: None, age_days }));
+
+        assert_eq!(find(0), Some((110.0, 145.0)));
+        assert_eq!(find(i64::from(18 * YEAR) - 1), Some((110.0, 145.0)));
+        assert_eq!(find(i64::from(18 * YEAR)), Some((120.0, 170.0)));
+    }
+
+    #[test]
+    fn applicable_range_matches_analyte_and_unit_exactly() {
+        let ranges = hemoglobin_ranges();
+        let adult = subject(Some(Sex::Male), 40);
+
+        assert!(applicable_range(&ranges, "HCT", "g/L", adult).is_none());
+        assert!(applicable_range(&ranges, "HGB", "g/dL", adult).is_none());
+        assert!(applicable_range(&ranges[..1], "HGB", "g/L", adult).is_none());
+    }
+
+    #[test]
+    fn results_are_flagged_against_the_applicable_range() {
+        let ranges = hemoglobin_ranges();
+        let analyte = AnalyteModel {
+            analyte_id: Uuid::nil(),
+            ms_id: Uuid::nil(),
+            code: "HGB".to_string(),
+            name: "Hemoglobin".to_string(),
+            unit: Some("g/L".to_string()),
+            value_type: AnalyteValueType::Numeric,
+            reference_low: Some(120.0),
+            reference_high: Some(160.0),
+            critical_low: None,
+            critical_high: None,
+            normal_text: None,
+            sequence: 1,
+        };
+        let flag = |subject| {
+            let range = applicable_range(&ranges, "HGB", "g/L", subject).unwrap();
+            compute_flag(&analyte, &Limits::from(range), "130", Some(130.0))
+        };
+
+        assert_eq!(flag(subject(Some(Sex::Male), 40)), Some(ResultFlag::Low));
+        assert_eq!(flag(subject(Some(Sex::Female), 40)), None);
+        assert_eq!(flag(subject(Some(Sex::Female), 10)), None);
+    }
+
+    #[test]
+    fn invalid_range_rejects_overlapping_bands_of_the_same_sex() {
+        let ranges = hemoglobin_ranges();
+        let request = |sex, min_age_days, max_age_days| SaveReferenceRangeRequest {
+            analyte_code: "hgb".to_string(),
+            sex,
+            min_age_days,
+            max_age_days,
+            unit: "g/L".to_string(),
+            reference_low: Some(100.0),
+            reference_high: Some(140.0),
+            critical_low: None,
+            critical_high: None,
+        };
+
+        assert!(invalid_range(&request(Some(Sex::Male), 0, Some(18 * YEAR)), &ranges).is_none());
+        assert!(invalid_range(&request(None, 10 * YEAR, Some(20 * YEAR)), &ranges).is_some());
+        assert!(invalid_range(&request(Some(Sex::Male), 60 * YEAR, None), &ranges).is_some());
+        assert!(invalid_range(&request(None, 5, Some(5)), &[]).is_some());
+        assert!(invalid_range(&request(None, -1, None), &[]).is_some());
+        assert!(invalid_range(
+            &SaveReferenceRangeRequest { reference_low: None, reference_high: None, ..request(None, 0, None) },
+            &[]
+        )
+        .is_some());
+    }
+
+    #[tokio::test]
+    async fn ranges_record_the_account_that_changed_them() {
+        use crate::handlers::audit_handlers::{get_audit_log, AuditLogQuery};
+        use crate::migrations::Migrator;
+        use sea_orm::Database;
+        use sea_orm_migration::MigratorTrait;
+
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        Migrator::up(&db, None).await.unwrap();
+        let (creator, editor, remover) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let request = SaveReferenceRangeRequest {
+            analyte_code: "hgb".to_string(),
+            sex: None,
+            min_age_days: 0,
+            max_age_days: None,
+            unit: "g/L".to_string(),
+            reference_low: Some(120.0),
+            reference_high: Some(160.0),
+            critical_low: None,
+            critical_high: None,
+        };
+
+        let ReferenceRangeOutcome::Saved(created) = create_reference_range(&db, request.clone(), creator).await.unwrap()
+        else {
+            panic!("the range should be created");
+        };
+        let id = created.range.reference_range_id;
+        assert_eq!((created.range.created_by, created.range.updated_by), (creator, None));
+        let ReferenceRangeOutcome::Saved(updated) = update_reference_range(&db, id, request, editor).await.unwrap()
+        else {
+            panic!("the range should be updated");
+        };
+        assert_eq!((updated.range.created_by, updated.range.updated_by), (creator, Some(editor)));
+
+        assert_eq!(delete_reference_range(&db, id, remover).await.unwrap(), Some(0));
+        let query = AuditLogQuery { action: Some(RANGE_DELETED_ACTION.to_string()), ..Default::default() };
+        let log = get_audit_log(&db, query).await.unwrap();
+        assert_eq!(log.len(), 1);
+        assert_eq!(log[0].actor_id, Some(remover));
+        assert_eq!(log[0].record_id, Some(id.to_string()));
+        assert_eq!(log[0].details["range"]["updated_by"], json!(editor));
+    }
+}
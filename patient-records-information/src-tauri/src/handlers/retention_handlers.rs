@@ -0,0 +1,665 @@
+use sea_orm::{
+    ActiveEnum, ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseConnection, EntityTrait, ModelTrait, QueryFilter,
+    QueryOrder, QuerySelect, Set, TransactionTrait,
+};
+use crate::handlers::audit_handlers::{record_audit, CreateAuditEntry};
+use crate::models::accounts::{Entity as AccountEntity, Role};
+use crate::models::audit_log_tb::Model as AuditLogModel;
+use crate::models::consent_tb::{Column as ConsentColumn, Entity as ConsentEntity};
+use crate::models::household_member_tb::Entity as HouseholdMemberEntity;
+use crate::models::lab_result_tb::{Column as LabResultColumn, Entity as LabResultEntity};
+use crate::models::medical_record_diagnosis_tb::{Column as DiagnosisColumn, Entity as DiagnosisEntity};
+use crate::models::prescription_item_tb::{Column as PrescriptionItemColumn, Entity as PrescriptionItemEntity};
+use crate::models::vital_sign_tb::{Column as VitalSignColumn, Entity as VitalSignEntity};
+use crate::models::medical_bill_record::{Column as BillColumn, Entity as BillEntity};
+use crate::models::medical_record_tb::{Column as MedicalRecordColumn, Entity as MedicalRecordEntity};
+use crate::models::medical_services_provided::{Column as ServiceProvidedColumn, Entity as ServiceProvidedEntity};
+use crate::models::service_result_tb::{Column as ServiceResultColumn, Entity as ServiceResultEntity};
+use crate::models::patient_tb::{Column as PatientColumn, Entity as PatientEntity};
+use crate::models::retention_candidate_tb::{
+    ActiveModel as CandidateActiveModel, CandidateStatus, Column as CandidateColumn, Entity as CandidateEntity,
+    Model as CandidateModel, RetentionBasis, RetentionEntity,
+};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use uuid::Uuid;
+
+/// Audit log action of a disposal certificate.
+pub const DISPOSAL_ACTION: &str = "retention.disposal";
+
+/// Keep records of `entity` for `retention_days` counted from `basis`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetentionRule {
+    pub entity: RetentionEntity,
+    pub basis: RetentionBasis,
+    pub retention_days: i32,
+}
+
+impl RetentionRule {
+    /// Parses `entity:basis:days[,entity:basis:days...]`, e.g.
+    /// `patient:archived:3650,patient:last_activity:7300`.
+    pub fn parse_list(value: &str) -> Result<Vec<Self>, String> {
+        value
+            .split(',')
+            .map(str::trim)
+            .filter(|rule| !rule.is_empty())
+            .map(|rule| {
+                let parts: Vec<&str> = rule.split(':').map(str::trim).collect();
+                let [entity, basis, days] = parts[..] else {
+                    return Err(format!("Retention rule '{}' must look like entity:basis:days", rule));
+                };
+                let entity = match entity {
+                    "patient" => RetentionEntity::Patient,
+                    "lab_result" => RetentionEntity::LabResult,
+                    other => return Err(format!("Unknown retention entity '{}' (patient, lab_result)", other)),
+                };
+                let basis = match basis {
+                    "last_activity" => RetentionBasis::LastActivity,
+                    "archived" => RetentionBasis::Archived,
+                    other => return Err(format!("Unknown retention basis '{}' (last_activity, archived)", other)),
+                };
+                if entity == RetentionEntity::LabResult && basis == RetentionBasis::Archived {
+                    return Err("Lab results are not archived; use lab_result:last_activity".to_string());
+                }
+                let retention_days = days
+                    .parse::<i32>()
+                    .ok()
+                    .filter(|d| *d > 0)
+                    .ok_or_else(|| format!("Retention period '{}' must be a positive number of days", days))?;
+                Ok(Self { entity, basis, retention_days })
+            })
+            .collect()
+    }
+
+    fn due_at(&self, reference_at: DateTime<Utc>) -> DateTime<Utc> {
+        reference_at + Duration::days(self.retention_days as i64)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RetentionDecisionRequest {
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub enum RetentionDecision {
+    Applied(CandidateModel),
+    NotFound,
+    /// The deciding account is not an Admin.
+    NotAdmin,
+    /// Only pending candidates can be approved or rejected.
+    AlreadyDecided(CandidateModel),
+}
+
+/// Rows removed from one database by a purge.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct PurgeCounts {
+    pub patients: u64,
+    pub medical_records: u64,
+    /// Bills of the patient's visits, with their service lines.
+    pub medical_bills: u64,
+    pub lab_results: u64,
+    pub consents: u64,
+    pub household_memberships: u64,
+}
+
+/// What a purge did to the cloud copy of the record.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum CloudPurge {
+    /// The copy was found, under `record_id`, and deleted.
+    Purged { record_id: Uuid, counts: PurgeCounts },
+    /// No copy was found in the cloud database, so nothing was deleted there.
+    NotFound,
+}
+
+#[derive(Debug, Clone)]
+pub enum PurgeOutcome {
+    Purged {
+        candidate: CandidateModel,
+        certificate: AuditLogModel,
+    },
+    /// The record was no longer due (new activity, un-archived or gone).
+    Cancelled(CandidateModel),
+    NotApproved(CandidateModel),
+    NotFound,
+}
+
+/// Date the retention period of the record currently counts from, or `None`
+/// when the record doesn't exist or the basis doesn't apply (not archived).
+async fn reference_at(
+    db: &DatabaseConnection,
+    entity: RetentionEntity,
+    basis: RetentionBasis,
+    record_id: Uuid,
+) -> Result<Option<DateTime<Utc>>, sea_orm::DbErr> {
+    match (entity, basis) {
+        (RetentionEntity::Patient, RetentionBasis::Archived) => {
+            let patient = PatientEntity::find_by_id(record_id).one(db).await?;
+            Ok(patient
+                .filter(|p| p.is_archived)
+                .map(|p| p.archived_at.unwrap_or(p.updated_at)))
+        }
+        (RetentionEntity::Patient, RetentionBasis::LastActivity) => {
+            let Some(patient) = PatientEntity::find_by_id(record_id).one(db).await? else {
+                return Ok(None);
+            };
+            let latest_result: Option<DateTime<Utc>> = LabResultEntity::find()
+                .select_only()
+                .column_as(LabResultColumn::CreatedAt.max(), "latest")
+                .filter(LabResultColumn::PatientId.eq(record_id))
+                .into_tuple()
+                .one(db)
+                .await?
+                .flatten();
+            let latest_record = patient
+                .find_related(MedicalRecordEntity)
+                .order_by_desc(MedicalRecordColumn::UpdatedAt)
+                .one(db)
+                .await?
+                .map(|record| record.updated_at);
+            Ok([Some(patient.updated_at), latest_result, latest_record].into_iter().flatten().max())
+        }
+        (RetentionEntity::LabResult, _) => Ok(LabResultEntity::find_by_id(record_id)
+            .one(db)
+            .await?
+            .map(|result| result.created_at)),
+    }
+}
+
+/// Records `record_id` as a pending candidate unless it is already waiting for
+/// or has been given a decision for the same reference date.
+async fn propose(
+    db: &DatabaseConnection,
+    rule: &RetentionRule,
+    record_id: Uuid,
+    reference_at: DateTime<Utc>,
+) -> Result<Option<CandidateModel>, sea_orm::DbErr> {
+    let existing = CandidateEntity::find()
+        .filter(CandidateColumn::Entity.eq(rule.entity))
+        .filter(CandidateColumn::RecordId.eq(record_id))
+        .all(db)
+        .await?;
+    let known = existing.iter().any(|c| match c.status {
+        CandidateStatus::Pending | CandidateStatus::Approved => true,
+        CandidateStatus::Rejected | CandidateStatus::Cancelled => c.reference_at == reference_at,
+        CandidateStatus::Purged => false,
+    });
+    if known {
+        return Ok(None);
+    }
+
+    let now = Utc::now();
+    let candidate = CandidateActiveModel {
+        candidate_id: Set(Uuid::new_v4()),
+        entity: Set(rule.entity),
+        record_id: Set(record_id),
+        basis: Set(rule.basis),
+        retention_days: Set(rule.retention_days),
+        reference_at: Set(reference_at),
+        due_at: Set(rule.due_at(reference_at)),
+        status: Set(CandidateStatus::Pending),
+        decided_by: Set(None),
+        decided_at: Set(None),
+        note: Set(None),
+        purged_at: Set(None),
+        created_at: Set(now),
+        updated_at: Set(now),
+    };
+    candidate.insert(db).await.map(Some)
+}
+
+/// Lists records whose retention period has elapsed under any of `rules` and
+/// stores them as pending candidates. Returns the newly proposed candidates.
+pub async fn scan_retention(
+    db: &DatabaseConnection,
+    rules: &[RetentionRule],
+) -> Result<Vec<CandidateModel>, sea_orm::DbErr> {
+    let now = Utc::now();
+    let mut proposed = Vec::new();
+
+    for rule in rules {
+        let cutoff = now - Duration::days(rule.retention_days as i64);
+        let mut due: Vec<(Uuid, DateTime<Utc>)> = Vec::new();
+
+        match (rule.entity, rule.basis) {
+            (RetentionEntity::Patient, RetentionBasis::Archived) => {
+                let archived = PatientEntity::find()
+                    .filter(PatientColumn::IsArchived.eq(true))
+                    .all(db)
+                    .await?;
+                due.extend(archived.into_iter().map(|p| (p.patient_id, p.archived_at.unwrap_or(p.updated_at))));
+            }
+            (RetentionEntity::Patient, RetentionBasis::LastActivity) => {
+                // The patient row itself must be old enough before its records are looked at.
+                let idle = PatientEntity::find()
+                    .filter(PatientColumn::UpdatedAt.lte(cutoff))
+                    .all(db)
+                    .await?;
+                for patient in idle {
+                    if let Some(last_activity) = reference_at(db, rule.entity, rule.basis, patient.patient_id).await? {
+                        due.push((patient.patient_id, last_activity));
+                    }
+                }
+            }
+            (RetentionEntity::LabResult, _) => {
+                let old = LabResultEntity::find()
+                    .filter(LabResultColumn::CreatedAt.lte(cutoff))
+                    .all(db)
+                    .await?;
+                due.extend(old.into_iter().map(|r| (r.result_id, r.created_at)));
+            }
+        }
+
+        for (record_id, reference_at) in due.into_iter().filter(|(_, reference_at)| *reference_at <= cutoff) {
+            if let Some(candidate) = propose(db, rule, record_id, reference_at).await? {
+                proposed.push(candidate);
+            }
+        }
+    }
+
+    Ok(proposed)
+}
+
+pub async fn get_retention_candidates(
+    db: &DatabaseConnection,
+    status: Option<CandidateStatus>,
+) -> Result<Vec<CandidateModel>, sea_orm::DbErr> {
+    let mut query = CandidateEntity::find().order_by_asc(CandidateColumn::DueAt);
+    if let Some(status) = status {
+        query = query.filter(CandidateColumn::Status.eq(status));
+    }
+    query.all(db).await
+}
+
+pub async fn is_admin(db: &DatabaseConnection, account_id: Uuid) -> Result<bool, sea_orm::DbErr> {
+    Ok(AccountEntity::find_by_id(account_id)
+        .one(db)
+        .await?
+        .is_some_and(|account| account.role == Role::Admin))
+}
+
+/// Approves (`approve = true`) or rejects a pending candidate on behalf of
+/// `decided_by`, the authenticated account, which must be an Admin. Approval
+/// alone doesn't delete anything; see [`purge_candidate`].
+pub async fn decide_candidate(
+    db: &DatabaseConnection,
+    candidate_id: Uuid,
+    approve: bool,
+    decided_by: Uuid,
+    request: RetentionDecisionRequest,
+) -> Result<RetentionDecision, sea_orm::DbErr> {
+    let Some(candidate) = CandidateEntity::find_by_id(candidate_id).one(db).await? else {
+        return Ok(RetentionDecision::NotFound);
+    };
+    if !is_admin(db, decided_by).await? {
+        return Ok(RetentionDecision::NotAdmin);
+    }
+    if candidate.status != CandidateStatus::Pending {
+        return Ok(RetentionDecision::AlreadyDecided(candidate));
+    }
+
+    let mut candidate: CandidateActiveModel = candidate.into();
+    candidate.status = Set(if approve { CandidateStatus::Approved } else { CandidateStatus::Rejected });
+    candidate.decided_by = Set(Some(decided_by));
+    candidate.decided_at = Set(Some(Utc::now()));
+    candidate.note = Set(request.note);
+    Ok(RetentionDecision::Applied(candidate.update(db).await?))
+}
+
+async fn purge_record<C: ConnectionTrait>(
+    db: &C,
+    entity: RetentionEntity,
+    record_id: Uuid,
+) -> Result<PurgeCounts, sea_orm::DbErr> {
+    match entity {
+        RetentionEntity::Patient => {
+            let lab_results = LabResultEntity::delete_many()
+                .filter(LabResultColumn::PatientId.eq(record_id))
+                .exec(db)
+                .await?
+                .rows_affected;
+            // Bills restrict deleting the patient and records, so they go first.
+            let bill_ids: Vec<Uuid> = BillEntity::find()
+                .filter(BillColumn::PatientId.eq(record_id))
+                .all(db)
+                .await?
+                .into_iter()
+                .map(|bill| bill.medical_bill_id)
+                .collect();
+            let mrs_ids: Vec<Uuid> = ServiceProvidedEntity::find()
+                .filter(ServiceProvidedColumn::MedicalBillId.is_in(bill_ids.clone()))
+                .all(db)
+                .await?
+                .into_iter()
+                .map(|service| service.mrs_id)
+                .collect();
+            ServiceResultEntity::delete_many()
+                .filter(ServiceResultColumn::MrsId.is_in(mrs_ids))
+                .exec(db)
+                .await?;
+            ServiceProvidedEntity::delete_many()
+                .filter(ServiceProvidedColumn::MedicalBillId.is_in(bill_ids.clone()))
+                .exec(db)
+                .await?;
+            let medical_bills = BillEntity::delete_many()
+                .filter(BillColumn::MedicalBillId.is_in(bill_ids))
+                .exec(db)
+                .await?
+                .rows_affected;
+            VitalSignEntity::delete_many()
+                .filter(VitalSignColumn::PatientId.eq(record_id))
+                .exec(db)
+                .await?;
+            let mut medical_records = 0;
+            if let Some(patient) = PatientEntity::find_by_id(record_id).one(db).await? {
+                let record_ids: Vec<Uuid> = patient
+                    .find_related(MedicalRecordEntity)
+                    .all(db)
+                    .await?
+                    .into_iter()
+                    .map(|record| record.medical_id)
+                    .collect();
+                DiagnosisEntity::delete_many()
+                    .filter(DiagnosisColumn::MedicalId.is_in(record_ids.clone()))
+                    .exec(db)
+                    .await?;
+                PrescriptionItemEntity::delete_many()
+                    .filter(PrescriptionItemColumn::MedicalId.is_in(record_ids.clone()))
+                    .exec(db)
+                    .await?;
+                medical_records = MedicalRecordEntity::delete_many()
+                    .filter(MedicalRecordColumn::MedicalId.is_in(record_ids))
+                    .exec(db)
+                    .await?
+                    .rows_affected;
+            }
+            let consents = ConsentEntity::delete_many()
+                .filter(ConsentColumn::PatientId.eq(record_id))
+                .exec(db)
+                .await?
+                .rows_affected;
+            let household_memberships = HouseholdMemberEntity::delete_by_id(record_id).exec(db).await?.rows_affected;
+            let patients = PatientEntity::delete_by_id(record_id).exec(db).await?.rows_affected;
+            Ok(PurgeCounts {
+                patients,
+                medical_records,
+                medical_bills,
+                lab_results,
+                consents,
+                household_memberships,
+            })
+        }
+        RetentionEntity::LabResult => Ok(PurgeCounts {
+            lab_results: LabResultEntity::delete_by_id(record_id).exec(db).await?.rows_affected,
+            ..Default::default()
+        }),
+    }
+}
+
+/// Id of the cloud copy of a record. Copies share the local id; patients
+/// copied before that are matched by their patient number instead.
+async fn cloud_record_id(
+    local: &DatabaseConnection,
+    cloud: &DatabaseConnection,
+    entity: RetentionEntity,
+    record_id: Uuid,
+) -> Result<Option<Uuid>, sea_orm::DbErr> {
+    match entity {
+        RetentionEntity::Patient => {
+            if PatientEntity::find_by_id(record_id).one(cloud).await?.is_some() {
+                return Ok(Some(record_id));
+            }
+            let Some(number) = PatientEntity::find_by_id(record_id)
+                .one(local)
+                .await?
+                .and_then(|patient| patient.patient_number)
+            else {
+                return Ok(None);
+            };
+            Ok(PatientEntity::find()
+                .filter(PatientColumn::PatientNumber.eq(number))
+                .one(cloud)
+                .await?
+                .map(|patient| patient.patient_id))
+        }
+        RetentionEntity::LabResult => Ok(LabResultEntity::find_by_id(record_id)
+            .one(cloud)
+            .await?
+            .map(|result| result.result_id)),
+    }
+}
+
+/// Deletes an approved candidate's data from `local` and, when given, `cloud`,
+/// and writes a disposal certificate to the audit log of both. The certificate
+/// states whether a cloud copy was found; it never claims one was purged when
+/// nothing matched.
+///
+/// The record is checked again first: if it is no longer due the candidate is
+/// cancelled instead. The cloud copy is removed before the local one, and the
+/// local deletion, certificate and candidate update share one transaction, so
+/// a failure leaves the candidate approved and the purge can simply be retried.
+pub async fn purge_candidate(
+    local: &DatabaseConnection,
+    cloud: Option<&DatabaseConnection>,
+    candidate_id: Uuid,
+) -> Result<PurgeOutcome, sea_orm::DbErr> {
+    let Some(candidate) = CandidateEntity::find_by_id(candidate_id).one(local).await? else {
+        return Ok(PurgeOutcome::NotFound);
+    };
+    if candidate.status != CandidateStatus::Approved {
+        return Ok(PurgeOutcome::NotApproved(candidate));
+    }
+
+    let now = Utc::now();
+    let rule = RetentionRule {
+        entity: candidate.entity,
+        basis: candidate.basis,
+        retention_days: candidate.retention_days,
+    };
+    let still_due = reference_at(local, candidate.entity, candidate.basis, candidate.record_id)
+        .await?
+        .is_some_and(|reference_at| rule.due_at(reference_at) <= now);
+    if !still_due {
+        let mut cancelled: CandidateActiveModel = candidate.into();
+        cancelled.status = Set(CandidateStatus::Cancelled);
+        cancelled.note = Set(Some("Record is no longer due for disposal".to_string()));
+        return Ok(PurgeOutcome::Cancelled(cancelled.update(local).await?));
+    }
+
+    let cloud_purge = match cloud {
+        Some(cloud) => Some(match cloud_record_id(local, cloud, candidate.entity, candidate.record_id).await? {
+            Some(cloud_id) => {
+                let txn = cloud.begin().await?;
+                let counts = purge_record(&txn, candidate.entity, cloud_id).await?;
+                txn.commit().await?;
+                CloudPurge::Purged { record_id: cloud_id, counts }
+            }
+            None => {
+                log::warn!(
+                    "⚠️ No cloud copy of {} {} found to purge",
+                    candidate.entity.to_value(),
+                    candidate.record_id
+                );
+                CloudPurge::NotFound
+            }
+        }),
+        None => None,
+    };
+
+    let certificate_id = Uuid::new_v4();
+    let txn = local.begin().await?;
+    let local_counts = purge_record(&txn, candidate.entity, candidate.record_id).await?;
+    let entry = CreateAuditEntry {
+        action: DISPOSAL_ACTION.to_string(),
+        entity: candidate.entity.to_value(),
+        record_id: Some(candidate.record_id.to_string()),
+        actor_id: candidate.decided_by,
+        details: json!({
+            "certificate_id": certificate_id,
+            "candidate_id": candidate.candidate_id,
+            "rule": rule,
+            "reference_at": candidate.reference_at,
+            "due_at": candidate.due_at,
+            "approved_by": candidate.decided_by,
+            "approved_at": candidate.decided_at,
+            "disposed_at": now,
+            "databases": {
+                "local": local_counts,
+                "cloud": cloud_purge,
+            },
+        }),
+    };
+    let certificate = record_audit(&txn, Some(certificate_id), entry.clone()).await?;
+    let mut purged: CandidateActiveModel = candidate.into();
+    purged.status = Set(CandidateStatus::Purged);
+    purged.purged_at = Set(Some(now));
+    let candidate = purged.update(&txn).await?;
+    txn.commit().await?;
+
+    if let Some(cloud) = cloud {
+        if let Err(e) = record_audit(cloud, Some(certificate_id), entry).await {
+            log::warn!("⚠️ Disposal certificate {} not copied to cloud audit log: {}", certificate_id, e);
+        }
+    }
+
+    Ok(PurgeOutcome::Purged { candidate, certificate })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_list_reads_every_rule() {
+        let rules = RetentionRule::parse_list(" patient:archived:3650 , lab_result:last_activity:1825,").unwrap();
+
+        assert_eq!(
+            rules,
+            vec![
+                RetentionRule {
+                    entity: RetentionEntity::Patient,
+                    basis: RetentionBasis::Archived,
+                    retention_days: 3650,
+                },
+                RetentionRule {
+                    entity: RetentionEntity::LabResult,
+                    basis: RetentionBasis::LastActivity,
+                    retention_days: 1825,
+                },
+            ]
+        );
+        assert_eq!(RetentionRule::parse_list("").unwrap(), vec![]);
+    }
+
+    #[test]
+    fn parse_list_rejects_malformed_rules() {
+        for value in [
+            "patient:archived",
+            "patient:archived:10:extra",
+            "visit:archived:10",
+            "patient:created:10",
+            "lab_result:archived:10",
+            "patient:archived:0",
+            "patient:archived:-5",
+            "patient:archived:ten",
+            "patient:archived:3650,patient:bogus:1",
+        ] {
+            assert!(RetentionRule::parse_list(value).is_err(), "{} should be rejected", value);
+        }
+    }
+
+    #[test]
+    fn due_at_adds_the_retention_period() {
+        let rule = RetentionRule::parse_list("patient:archived:30").unwrap()[0];
+        let archived_at = DateTime::parse_from_rfc3339("2026-01-15T08:00:00Z").unwrap().with_timezone(&Utc);
+
+        assert_eq!(rule.due_at(archived_at).to_rfc3339(), "2026-02-14T08:00:00+00:00");
+    }
+
+    #[tokio::test]
+    async fn purge_disposes_of_the_patients_bills() {
+        use crate::migrations::Migrator;
+        use crate::models::medical_bill_record::{ActiveModel as BillActiveModel, PaymentStatus};
+        use crate::models::medical_record_tb::ActiveModel as MedicalRecordActiveModel;
+        use crate::models::patient_tb::ActiveModel as PatientActiveModel;
+        use sea_orm::{ActiveModelTrait, Database};
+        use sea_orm_migration::MigratorTrait;
+
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        Migrator::up(&db, None).await.unwrap();
+        let now = Utc::now();
+        let archived_at = now - Duration::days(40);
+        let patient_id = Uuid::new_v4();
+        PatientActiveModel {
+            patient_id: Set(patient_id),
+            first_name: Set("Juan".to_string()),
+            last_name: Set("Dela Cruz".to_string()),
+            age: Set(35),
+            birth_date: Set(chrono::NaiveDate::from_ymd_opt(1990, 5, 17).unwrap()),
+            is_archived: Set(true),
+            archived_at: Set(Some(archived_at)),
+            created_at: Set(archived_at),
+            updated_at: Set(archived_at),
+            ..Default::default()
+        }
+        .insert(&db)
+        .await
+        .unwrap();
+        let medical_id = Uuid::new_v4();
+        MedicalRecordActiveModel {
+            medical_id: Set(medical_id),
+            patient_id: Set(patient_id),
+            assessment: Set(None),
+            diagnosis: Set(None),
+            treatment: Set(None),
+            prescription: Set(None),
+            first_audited_by: Set("Dr. Santos".to_string()),
+            last_audited_by: Set(None),
+            created_at: Set(archived_at),
+            updated_at: Set(archived_at),
+        }
+        .insert(&db)
+        .await
+        .unwrap();
+        BillActiveModel {
+            medical_bill_id: Set(Uuid::new_v4()),
+            patient_id: Set(patient_id),
+            medical_id: Set(medical_id),
+            consultation_fee: Set(Some(300.0)),
+            remarks: Set(None),
+            payment_status: Set(PaymentStatus::Paid),
+            total_amount: Set(300.0),
+        }
+        .insert(&db)
+        .await
+        .unwrap();
+        let candidate_id = Uuid::new_v4();
+        CandidateActiveModel {
+            candidate_id: Set(candidate_id),
+            entity: Set(RetentionEntity::Patient),
+            record_id: Set(patient_id),
+            basis: Set(RetentionBasis::Archived),
+            retention_days: Set(30),
+            reference_at: Set(archived_at),
+            due_at: Set(archived_at + Duration::days(30)),
+            status: Set(CandidateStatus::Approved),
+            decided_by: Set(None),
+            decided_at: Set(Some(now)),
+            note: Set(None),
+            purged_at: Set(None),
+            ..Default::default()
+        }
+        .insert(&db)
+        .await
+        .unwrap();
+
+        let PurgeOutcome::Purged { certificate, .. } = purge_candidate(&db, None, candidate_id).await.unwrap() else {
+            panic!("the candidate should be purged");
+        };
+        assert_eq!(certificate.details["databases"]["local"]["medical_bills"], 1);
+        assert!(PatientEntity::find_by_id(patient_id).one(&db).await.unwrap().is_none());
+        assert_eq!(BillEntity::find().all(&db).await.unwrap().len(), 0);
+    }
+}
@@ -0,0 +1,655 @@
+//! Reference and demo data for `migrate seed`: the bundled catalog of
+//! medical services, and a reproducible set of fake patients for testing.
+
+use sea_orm::{ActiveModelTrait, DatabaseConnection, DbErr, EntityTrait, PaginatorTrait, QueryOrder, Set, TransactionTrait};
+use crate::handlers::audit_handlers::{record_audit, CreateAuditEntry};
+use crate::handlers::patient_number_handlers::{next_patient_number, registration_year};
+use crate::handlers::service_result_handlers::{invalid_analyte, replace_analytes, AnalyteInput, MAX_ANALYTE_CODE_LEN};
+use crate::handlers::unit_conversion_handlers::{insert_conversion, invalid_conversion, UnitConversionInput};
+use crate::models::medical_bill_record::{ActiveModel as BillActiveModel, PaymentStatus};
+use crate::models::medical_record_tb::ActiveModel as MedicalRecordActiveModel;
+use crate::models::medical_services::{
+    ActiveModel as ServiceActiveModel, Column as ServiceColumn, Entity as ServiceEntity, Model as ServiceModel,
+    ServiceCategory,
+};
+use crate::models::medical_services_provided::ActiveModel as ServiceProvidedActiveModel;
+use crate::models::patient_tb::{ActiveModel as PatientActiveModel, Entity as PatientEntity};
+use crate::models::service_analyte_tb::Entity as AnalyteEntity;
+use crate::models::unit_conversion_tb::Entity as UnitConversionEntity;
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Utc};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+pub const CATALOG_SEED_ACTION: &str = "seed.service_catalog";
+
+/// `data/medical_services_catalog.json`, compiled in so the CLI works outside a checkout.
+pub const BUNDLED_SERVICE_CATALOG: &str = include_str!("../../data/medical_services_catalog.json");
+
+/// A priced list of services. `version` changes whenever services or prices do.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServiceCatalog {
+    pub version: String,
+    pub currency: String,
+    pub services: Vec<CatalogService>,
+    /// SI ↔ conventional factors of the templates' analytes.
+    #[serde(default)]
+    pub unit_conversions: Vec<CatalogUnitConversion>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CatalogService {
+    pub name: String,
+    pub category: ServiceCategory,
+    pub price: f32,
+    /// Result template of a laboratory service.
+    #[serde(default)]
+    pub analytes: Vec<AnalyteInput>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CatalogUnitConversion {
+    pub analyte_code: String,
+    #[serde(flatten)]
+    pub conversion: UnitConversionInput,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CatalogReport {
+    pub version: String,
+    pub inserted: u64,
+    pub already_present: u64,
+    /// Services that got the catalog's result template.
+    pub templates_added: u64,
+    /// Analytes that got the catalog's unit conversion.
+    pub conversions_added: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DemoReport {
+    pub patients: u64,
+    pub medical_records: u64,
+    pub bills: u64,
+    pub services_provided: u64,
+}
+
+#[derive(Debug, Clone)]
+pub enum DemoOutcome {
+    Seeded(DemoReport),
+    /// The first patient of this seed exists, so the dataset was loaded before.
+    AlreadySeeded,
+}
+
+pub fn parse_service_catalog(json: &str) -> Result<ServiceCatalog, String> {
+    let catalog: ServiceCatalog =
+        serde_json::from_str(json).map_err(|e| format!("Invalid service catalog: {}", e))?;
+    let mut names = HashSet::new();
+    for service in &catalog.services {
+        if service.name.trim().is_empty() {
+            return Err("Invalid service catalog: a service has no name".to_string());
+        }
+        if service.price.is_nan() || service.price < 0.0 {
+            return Err(format!("Invalid service catalog: '{}' has an invalid price", service.name));
+        }
+        if !names.insert(service.name.trim().to_lowercase()) {
+            return Err(format!("Invalid service catalog: '{}' is listed twice", service.name));
+        }
+        let mut codes = HashSet::new();
+        for analyte in &service.analytes {
+            if let Some(message) = invalid_analyte(analyte) {
+                return Err(format!("Invalid service catalog: '{}': {}", service.name, message));
+            }
+            if !codes.insert(analyte.code.trim().to_uppercase()) {
+                return Err(format!("Invalid service catalog: '{}' lists {} twice", service.name, analyte.code));
+            }
+        }
+    }
+    let mut codes = HashSet::new();
+    for entry in &catalog.unit_conversions {
+        let code = entry.analyte_code.trim();
+        if code.is_empty() || code.len() > MAX_ANALYTE_CODE_LEN {
+            return Err(format!("Invalid service catalog: unit conversion for '{}' has an invalid code", code));
+        }
+        if let Some(message) = invalid_conversion(&entry.conversion) {
+            return Err(format!("Invalid service catalog: {}: {}", entry.analyte_code, message));
+        }
+        if !codes.insert(code.to_uppercase()) {
+            return Err(format!("Invalid service catalog: {} has two unit conversions", entry.analyte_code));
+        }
+    }
+    Ok(catalog)
+}
+
+pub fn bundled_service_catalog() -> Result<ServiceCatalog, String> {
+    parse_service_catalog(BUNDLED_SERVICE_CATALOG)
+}
+
+/// Adds the catalog's services that aren't in `medical_services_table` yet,
+/// matched by name ignoring case. Services already there keep the price and
+/// category staff gave them, so re-running with a newer catalog only adds.
+/// Result templates are added the same way, to services that have none, and
+/// unit conversions to analytes that have none. A service gets the same id in
+/// every database (see [`catalog_service_id`]), so local and cloud copies match.
+pub async fn load_service_catalog(db: &DatabaseConnection, catalog: &ServiceCatalog) -> Result<CatalogReport, DbErr> {
+    let txn = db.begin().await?;
+    let existing: HashMap<String, Uuid> = ServiceEntity::find()
+        .all(&txn)
+        .await?
+        .into_iter()
+        .map(|service| (service.ms_name.trim().to_lowercase(), service.ms_id))
+        .collect();
+    let with_template: HashSet<Uuid> = AnalyteEntity::find()
+        .all(&txn)
+        .await?
+        .into_iter()
+        .map(|analyte| analyte.ms_id)
+        .collect();
+
+    let mut report = CatalogReport { version: catalog.version.clone(), ..Default::default() };
+    for service in &catalog.services {
+        let ms_id = match existing.get(&service.name.trim().to_lowercase()) {
+            Some(ms_id) => {
+                report.already_present += 1;
+                *ms_id
+            }
+            None => {
+                report.inserted += 1;
+                ServiceActiveModel {
+                    ms_id: Set(catalog_service_id(&service.name)),
+                    ms_name: Set(service.name.trim().to_string()),
+                    ms_category: Set(service.category.clone()),
+                    ms_price: Set(service.price),
+                    ..Default::default()
+                }
+                .insert(&txn)
+                .await?
+                .ms_id
+            }
+        };
+        if !service.analytes.is_empty() && !with_template.contains(&ms_id) {
+            replace_analytes(&txn, ms_id, service.analytes.clone()).await?;
+            report.templates_added += 1;
+        }
+    }
+
+    let with_conversion: HashSet<String> = UnitConversionEntity::find()
+        .all(&txn)
+        .await?
+        .into_iter()
+        .map(|conversion| conversion.analyte_code)
+        .collect();
+    for entry in &catalog.unit_conversions {
+        if !with_conversion.contains(&entry.analyte_code.trim().to_uppercase()) {
+            insert_conversion(&txn, &entry.analyte_code, entry.conversion.clone(), None).await?;
+            report.conversions_added += 1;
+        }
+    }
+
+    if report.inserted > 0 || report.templates_added > 0 || report.conversions_added > 0 {
+        record_audit(
+            &txn,
+            None,
+            CreateAuditEntry {
+                action: CATALOG_SEED_ACTION.to_string(),
+                entity: "medical_service".to_string(),
+                record_id: None,
+                actor_id: None,
+                details: serde_json::json!({
+                    "version": catalog.version,
+                    "inserted": report.inserted,
+                    "templates_added": report.templates_added,
+                    "conversions_added": report.conversions_added,
+                }),
+            },
+        )
+        .await?;
+    }
+    txn.commit().await?;
+    Ok(report)
+}
+
+const FIRST_NAMES: [&str; 30] = [
+    "Maria", "Jose", "Juan", "Ana", "Mark", "John Paul", "Angelica", "Kristine", "Mary Grace", "Jerome",
+    "Rodel", "Marites", "Ligaya", "Rogelio", "Teresita", "Ernesto", "Corazon", "Nestor", "Rowena", "Jericho",
+    "Princess", "Carlo", "Bea", "Miguel", "Andrea", "Gabriel", "Althea", "Francis", "Jasmine", "Ramon",
+];
+
+const SURNAMES: [&str; 30] = [
+    "Dela Cruz", "Santos", "Reyes", "Cruz", "Bautista", "Ocampo", "Garcia", "Mendoza", "Torres", "Tomas",
+    "Andrada", "Castillo", "Flores", "Villanueva", "Ramos", "Castro", "Rivera", "Aquino", "Navarro", "Salazar",
+    "Mercado", "Gonzales", "Lopez", "Hernandez", "Pascual", "Domingo", "Soriano", "Manalo", "Aguilar", "Dizon",
+];
+
+const STREETS: [&str; 8] = ["Rizal", "Mabini", "Bonifacio", "Luna", "Burgos", "Del Pilar", "Quezon", "Magsaysay"];
+
+const BARANGAYS: [&str; 7] = ["San Isidro", "Poblacion", "San Roque", "Santo Niño", "Bagong Silang", "Malinis", "San Jose"];
+
+const CITIES: [&str; 8] = [
+    "Quezon City", "Manila", "Caloocan City", "Pasig City", "Cebu City", "Davao City", "Iloilo City", "Baguio City",
+];
+
+const STAFF: [&str; 3] = ["Dr. Liza Manalo", "Dr. Paolo Reyes", "Dr. Carmela Santos"];
+
+/// A visit reason with the notes a clinician would write and the catalog
+/// services usually requested for it.
+struct Condition {
+    assessment: &'static str,
+    diagnosis: &'static str,
+    treatment: &'static str,
+    prescription: Option<&'static str>,
+    services: &'static [&'static str],
+}
+
+const CONDITIONS: [Condition; 9] = [
+    Condition {
+        assessment: "Cough and colds for 3 days, afebrile, clear breath sounds",
+        diagnosis: "Acute upper respiratory tract infection",
+        treatment: "Increase oral fluids and rest; return if fever develops",
+        prescription: Some("Paracetamol 500 mg tab every 4 hours as needed for fever"),
+        services: &["Complete Blood Count (CBC) with Platelet Count"],
+    },
+    Condition {
+        assessment: "BP 150/95 on two readings, occasional nape pain",
+        diagnosis: "Essential hypertension",
+        treatment: "Low-salt diet and home BP monitoring",
+        prescription: Some("Amlodipine 5 mg tab once daily"),
+        services: &["Lipid Profile", "Creatinine", "Sodium (Na)", "Potassium (K)", "Electrocardiogram (ECG)"],
+    },
+    Condition {
+        assessment: "Polyuria and polydipsia for 2 months, capillary blood glucose 11.2 mmol/L",
+        diagnosis: "Type 2 diabetes mellitus",
+        treatment: "Diet counseling and 30 minutes of walking daily",
+        prescription: Some("Metformin 500 mg tab twice daily with meals"),
+        services: &["Fasting Blood Sugar (FBS)", "HbA1c", "Lipid Profile", "Creatinine"],
+    },
+    Condition {
+        assessment: "Dysuria and frequency for 2 days, no flank pain",
+        diagnosis: "Urinary tract infection",
+        treatment: "Increase fluid intake; complete the antibiotic course",
+        prescription: Some("Nitrofurantoin 100 mg cap twice daily for 5 days"),
+        services: &["Routine Urinalysis", "Urine Culture and Sensitivity"],
+    },
+    Condition {
+        assessment: "Fever for 3 days with myalgia and retro-orbital pain, no warning signs",
+        diagnosis: "Dengue fever without warning signs",
+        treatment: "Oral rehydration and daily CBC monitoring",
+        prescription: Some("Paracetamol 500 mg tab every 4 hours as needed; avoid NSAIDs"),
+        services: &["Complete Blood Count (CBC) with Platelet Count", "Dengue NS1 Antigen"],
+    },
+    Condition {
+        assessment: "Loose watery stools 4 times a day, mild dehydration",
+        diagnosis: "Acute gastroenteritis",
+        treatment: "Oral rehydration solution after each loose stool",
+        prescription: Some("Oral rehydration salts; zinc sulfate 20 mg once daily for 10 days"),
+        services: &["Routine Fecalysis", "Sodium (Na)", "Potassium (K)"],
+    },
+    Condition {
+        assessment: "Annual physical examination, no complaints",
+        diagnosis: "General medical examination",
+        treatment: "Continue healthy lifestyle",
+        prescription: None,
+        services: &[
+            "Complete Blood Count (CBC) with Platelet Count",
+            "Routine Urinalysis",
+            "Fasting Blood Sugar (FBS)",
+            "Lipid Profile",
+            "Medical Certificate",
+        ],
+    },
+    Condition {
+        assessment: "Painful swelling of the right big toe since last night",
+        diagnosis: "Gout",
+        treatment: "Rest, cold compress; avoid organ meats and alcohol",
+        prescription: Some("Colchicine 500 mcg tab twice daily for 3 days"),
+        services: &["Blood Uric Acid", "Creatinine"],
+    },
+    Condition {
+        assessment: "Palpitations, heat intolerance and weight loss for 3 months",
+        diagnosis: "Hyperthyroidism",
+        treatment: "Referred to endocrinology",
+        prescription: Some("Methimazole 10 mg tab once daily"),
+        services: &["TSH", "Free T4", "Free T3"],
+    },
+];
+
+/// Dates are drawn back from this instead of today, so a seed always produces
+/// the same rows.
+fn demo_reference_time() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).single().expect("valid date")
+}
+
+fn demo_uuid(rng: &mut StdRng) -> Uuid {
+    uuid::Builder::from_random_bytes(rng.gen()).into_uuid()
+}
+
+/// Id of a catalog service, drawn from its name so that every database seeded
+/// from the catalog gives the service the same id.
+pub fn catalog_service_id(name: &str) -> Uuid {
+    let digest = Sha256::digest(format!("medical_service\0{}", name.trim().to_lowercase()));
+    demo_uuid(&mut StdRng::from_seed(digest.into()))
+}
+
+fn pick<'a>(rng: &mut StdRng, values: &[&'a str]) -> &'a str {
+    values.choose(rng).copied().expect("non-empty list")
+}
+
+fn age_on(birth_date: NaiveDate, date: NaiveDate) -> i32 {
+    let mut age = date.year() - birth_date.year();
+    if (date.month(), date.day()) < (birth_date.month(), birth_date.day()) {
+        age -= 1;
+    }
+    age
+}
+
+/// Generates `patients` fake patients with visits and bills from `seed`. The
+/// same seed always produces the same names, dates, notes and amounts, so
+/// tests can rely on them; ids are drawn from the seed too, and a seed whose
+/// first patient already exists is reported as [`DemoOutcome::AlreadySeeded`].
+/// The service catalog is loaded first when the services table is empty.
+pub async fn seed_demo_data(db: &DatabaseConnection, patients: u32, seed: u64) -> Result<DemoOutcome, DbErr> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let first_patient_id = demo_uuid(&mut rng.clone());
+    if PatientEntity::find_by_id(first_patient_id).one(db).await?.is_some() {
+        return Ok(DemoOutcome::AlreadySeeded);
+    }
+
+    if ServiceEntity::find().count(db).await? == 0 {
+        let catalog = bundled_service_catalog().map_err(DbErr::Custom)?;
+        load_service_catalog(db, &catalog).await?;
+    }
+    let services = ServiceEntity::find().order_by_asc(ServiceColumn::MsName).all(db).await?;
+    if services.is_empty() {
+        return Err(DbErr::Custom("No medical services to bill; seed the service catalog first".to_string()));
+    }
+
+    let reference = demo_reference_time();
+    let mut report = DemoReport::default();
+    let txn = db.begin().await?;
+    for _ in 0..patients {
+        let patient_id = demo_uuid(&mut rng);
+        let created_at = reference - Duration::days(rng.gen_range(60..730)) + Duration::seconds(rng.gen_range(0..86_400));
+        let registered_on = created_at.date_naive();
+        let birth_date = registered_on - Duration::days(rng.gen_range(365..90 * 365));
+
+        let first_name = pick(&mut rng, &FIRST_NAMES);
+        let last_name = pick(&mut rng, &SURNAMES);
+        let middle_name = rng.gen_bool(0.85).then(|| pick(&mut rng, &SURNAMES).to_string());
+        let mobile_number = rng
+            .gen_bool(0.9)
+            .then(|| format!("09{:09}", rng.gen_range(100_000_000..1_000_000_000u32)));
+        let age = age_on(birth_date, registered_on);
+        let csd_id_or_pwd_id = (age >= 60 || rng.gen_bool(0.05))
+            .then(|| format!("13-7404-000-{:07}", rng.gen_range(0..10_000_000u32)));
+        let residential_address = format!(
+            "{} {} St., Brgy. {}, {}",
+            rng.gen_range(1..400),
+            pick(&mut rng, &STREETS),
+            pick(&mut rng, &BARANGAYS),
+            pick(&mut rng, &CITIES),
+        );
+
+        PatientActiveModel {
+            patient_id: Set(patient_id),
+            patient_number: Set(Some(next_patient_number(&txn, registration_year(&created_at)).await?)),
+            first_name: Set(first_name.to_string()),
+            last_name: Set(last_name.to_string()),
+            middle_name: Set(middle_name),
+            age: Set(age),
+            birth_date: Set(birth_date),
+            csd_id_or_pwd_id: Set(csd_id_or_pwd_id),
+            mobile_number: Set(mobile_number),
+            residential_address: Set(Some(residential_address)),
+            is_archived: Set(false),
+            created_at: Set(created_at),
+            updated_at: Set(created_at),
+            ..Default::default()
+        }
+        .insert(&txn)
+        .await?;
+        report.patients += 1;
+
+        let mut visit_times: Vec<DateTime<Utc>> = (0..rng.gen_range(1..=4))
+            .map(|_| {
+                let span = (reference - created_at).num_seconds();
+                created_at + Duration::seconds(rng.gen_range(0..span))
+            })
+            .collect();
+        visit_times.sort();
+        for visited_at in visit_times {
+            let condition = &CONDITIONS[rng.gen_range(0..CONDITIONS.len())];
+            let medical_id = demo_uuid(&mut rng);
+            MedicalRecordActiveModel {
+                medical_id: Set(medical_id),
+                patient_id: Set(patient_id),
+                assessment: Set(Some(condition.assessment.to_string())),
+                diagnosis: Set(Some(condition.diagnosis.to_string())),
+                treatment: Set(Some(condition.treatment.to_string())),
+                prescription: Set(condition.prescription.map(str::to_string)),
+                first_audited_by: Set(pick(&mut rng, &STAFF).to_string()),
+                last_audited_by: Set(None),
+                created_at: Set(visited_at),
+                updated_at: Set(visited_at),
+            }
+            .insert(&txn)
+            .await?;
+            report.medical_records += 1;
+
+            if !rng.gen_bool(0.7) {
+                continue;
+            }
+            report.services_provided += seed_demo_bill(&txn, &mut rng, &services, condition, patient_id, medical_id).await?;
+            report.bills += 1;
+        }
+    }
+    txn.commit().await?;
+    Ok(DemoOutcome::Seeded(report))
+}
+
+/// Bills one to three of the services usually requested for `condition`
+/// (any catalog service when none of them is in the catalog). Returns the
+/// number of service lines.
+async fn seed_demo_bill<C: sea_orm::ConnectionTrait>(
+    txn: &C,
+    rng: &mut StdRng,
+    services: &[ServiceModel],
+    condition: &Condition,
+    patient_id: Uuid,
+    medical_id: Uuid,
+) -> Result<u64, DbErr> {
+    let usual: Vec<&ServiceModel> = services
+        .iter()
+        .filter(|service| condition.services.contains(&service.ms_name.as_str()))
+        .collect();
+    let candidates: Vec<&ServiceModel> = if usual.is_empty() { services.iter().collect() } else { usual };
+    let count = rng.gen_range(1..=candidates.len().min(3));
+    let billed: Vec<&ServiceModel> = candidates.choose_multiple(rng, count).copied().collect();
+
+    let consultation_fee = [None, Some(300.0), Some(500.0)].choose(rng).copied().flatten();
+    let total_amount = consultation_fee.unwrap_or(0.0) + billed.iter().map(|service| service.ms_price).sum::<f32>();
+    let payment_status = match rng.gen_range(0..10) {
+        0..=6 => PaymentStatus::Paid,
+        7..=8 => PaymentStatus::Unpaid,
+        _ => PaymentStatus::PartiallyPaid,
+    };
+
+    let medical_bill_id = demo_uuid(rng);
+    BillActiveModel {
+        medical_bill_id: Set(medical_bill_id),
+        patient_id: Set(patient_id),
+        medical_id: Set(medical_id),
+        consultation_fee: Set(consultation_fee),
+        remarks: Set(None),
+        payment_status: Set(payment_status),
+        total_amount: Set(total_amount),
+    }
+    .insert(txn)
+    .await?;
+
+    for service in &billed {
+        ServiceProvidedActiveModel {
+            mrs_id: Set(demo_uuid(rng)),
+            medical_bill_id: Set(medical_bill_id),
+            ms_id: Set(service.ms_id),
+            service_name: Set(service.ms_name.clone()),
+            service_category: Set(service.ms_category.clone()),
+            price: Set(service.ms_price),
+        }
+        .insert(txn)
+        .await?;
+    }
+    Ok(billed.len() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::migrations::Migrator;
+    use crate::models::medical_bill_record::{Column as BillColumn, Entity as BillEntity};
+    use crate::models::medical_record_tb::{Column as MedicalRecordColumn, Entity as MedicalRecordEntity};
+    use crate::models::medical_services_provided::{Column as ServiceProvidedColumn, Entity as ServiceProvidedEntity};
+    use crate::models::patient_tb::Column as PatientColumn;
+    use sea_orm::Database;
+    use sea_orm_migration::MigratorTrait;
+
+    const ANALYTE: &str = r#"{"code": "GLU", "name": "Glucose", "unit": "mmol/L", "value_type": "numeric",
+                              "reference_low": 3.9, "reference_high": 6.1}"#;
+
+    fn catalog(services: &str, unit_conversions: &str) -> Result<ServiceCatalog, String> {
+        parse_service_catalog(&format!(
+            r#"{{"version": "test", "currency": "PHP", "services": [{}], "unit_conversions": [{}]}}"#,
+            services, unit_conversions
+        ))
+    }
+
+    #[test]
+    fn bundled_catalog_is_valid() {
+        let catalog = bundled_service_catalog().unwrap();
+
+        assert!(!catalog.services.is_empty());
+        assert!(catalog.services.iter().any(|s| !s.analytes.is_empty()));
+        assert!(!catalog.unit_conversions.is_empty());
+    }
+
+    #[test]
+    fn parse_service_catalog_accepts_services_with_templates_and_conversions() {
+        let catalog = catalog(
+            &format!(r#"{{"name": "FBS", "category": "ClinicalChemistry", "price": 150.0, "analytes": [{}]}},
+                        {{"name": "X-ray", "category": "Others", "price": 400.0}}"#, ANALYTE),
+            r#"{"analyte_code": "GLU", "si_unit": "mmol/L", "conventional_unit": "mg/dL", "factor": 18.016,
+                "si_decimals": 1, "conventional_decimals": 0}"#,
+        )
+        .unwrap();
+
+        assert_eq!(catalog.services.len(), 2);
+        assert_eq!(catalog.services[0].analytes[0].code, "GLU");
+        assert!(catalog.services[1].analytes.is_empty());
+        assert_eq!(catalog.unit_conversions[0].conversion.factor, 18.016);
+    }
+
+    #[test]
+    fn parse_service_catalog_rejects_invalid_entries() {
+        let service = |name: &str, price: &str| {
+            format!(r#"{{"name": "{}", "category": "ClinicalChemistry", "price": {}}}"#, name, price)
+        };
+        let conversion = |code: &str, factor: &str| {
+            format!(
+                r#"{{"analyte_code": "{}", "si_unit": "mmol/L", "conventional_unit": "mg/dL", "factor": {},
+                     "si_decimals": 1, "conventional_decimals": 0}}"#,
+                code, factor
+            )
+        };
+
+        assert!(catalog(&service("FBS", "10.0"), &conversion("GLU", "18.0")).is_ok());
+        assert!(parse_service_catalog("not json").is_err());
+        assert!(catalog(&service(" ", "10.0"), "").is_err());
+        assert!(catalog(&service("FBS", "-1.0"), "").is_err());
+        assert!(catalog(&format!("{},{}", service("FBS", "1.0"), service("fbs ", "2.0")), "").is_err());
+        assert!(catalog(
+            &format!(r#"{{"name": "FBS", "category": "ClinicalChemistry", "price": 1.0, "analytes": [{0}, {0}]}}"#, ANALYTE),
+            ""
+        )
+        .is_err());
+        assert!(catalog("", &conversion("", "18.0")).is_err());
+        assert!(catalog("", &conversion("GLU", "0.0")).is_err());
+        assert!(catalog("", &format!("{},{}", conversion("GLU", "18.0"), conversion("glu", "18.0"))).is_err());
+    }
+
+    async fn seeded_database(seed: u64) -> DatabaseConnection {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        Migrator::up(&db, None).await.unwrap();
+        assert!(matches!(seed_demo_data(&db, 8, seed).await.unwrap(), DemoOutcome::Seeded(_)));
+        db
+    }
+
+    /// Everything the seed decides.
+    async fn dataset(db: &DatabaseConnection) -> serde_json::Value {
+        let patients = PatientEntity::find().order_by_asc(PatientColumn::PatientId).all(db).await.unwrap();
+        let records = MedicalRecordEntity::find()
+            .order_by_asc(MedicalRecordColumn::MedicalId)
+            .all(db)
+            .await
+            .unwrap();
+        let bills = BillEntity::find().order_by_asc(BillColumn::MedicalBillId).all(db).await.unwrap();
+        let services: Vec<_> = ServiceProvidedEntity::find()
+            .order_by_asc(ServiceProvidedColumn::MrsId)
+            .all(db)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|s| (s.mrs_id, s.medical_bill_id, s.ms_id, s.service_name, s.price))
+            .collect();
+        serde_json::json!({"patients": patients, "records": records, "bills": bills, "services": services})
+    }
+
+    #[tokio::test]
+    async fn same_seed_gives_the_same_dataset() {
+        let first = dataset(&seeded_database(7).await).await;
+        let second = dataset(&seeded_database(7).await).await;
+        let other = dataset(&seeded_database(8).await).await;
+
+        assert_eq!(first["patients"].as_array().unwrap().len(), 8);
+        assert_eq!(first, second);
+        assert_ne!(first["patients"], other["patients"]);
+    }
+
+    #[tokio::test]
+    async fn seeding_twice_is_detected() {
+        let db = seeded_database(7).await;
+
+        assert!(matches!(seed_demo_data(&db, 8, 7).await.unwrap(), DemoOutcome::AlreadySeeded));
+        assert_eq!(PatientEntity::find().count(&db).await.unwrap(), 8);
+        assert!(matches!(seed_demo_data(&db, 2, 8).await.unwrap(), DemoOutcome::Seeded(_)));
+    }
+
+    #[tokio::test]
+    async fn catalog_services_get_the_same_id_in_every_database() {
+        let catalog = bundled_service_catalog().unwrap();
+        let mut loaded = Vec::new();
+        for _ in 0..2 {
+            let db = Database::connect("sqlite::memory:").await.unwrap();
+            Migrator::up(&db, None).await.unwrap();
+            load_service_catalog(&db, &catalog).await.unwrap();
+            let services: Vec<(Uuid, String)> = ServiceEntity::find()
+                .order_by_asc(ServiceColumn::MsName)
+                .all(&db)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|service| (service.ms_id, service.ms_name))
+                .collect();
+            loaded.push(services);
+        }
+
+        assert_eq!(loaded[0].len(), catalog.services.len());
+        assert_eq!(loaded[0], loaded[1]);
+        for (ms_id, name) in &loaded[0] {
+            assert_eq!(*ms_id, catalog_service_id(name));
+        }
+        assert_eq!(catalog_service_id(" Blood Uric Acid "), catalog_service_id("blood uric acid"));
+        assert_ne!(catalog_service_id("Blood Uric Acid"), catalog_service_id("Creatinine"));
+    }
+}
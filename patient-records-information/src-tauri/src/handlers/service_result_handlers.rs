@@ -0,0 +1,924 @@
+use sea_orm::sea_query::Expr;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder, Set,
+    TransactionTrait,
+};
+use crate::handlers::medical_record_handlers::get_medical_record;
+use crate::handlers::patient_handlers::get_patient;
+use crate::handlers::prescription_handlers::{age_on, printed_name};
+use crate::handlers::reference_range_handlers::{applicable_range, reference_ranges_for};
+use crate::handlers::unit_conversion_handlers::{results_in, unit_conversions_for, UnitSystem};
+use crate::models::analyte_reference_range_tb::Model as ReferenceRangeModel;
+use crate::models::medical_bill_record::{Column as BillColumn, Entity as BillEntity, Model as BillModel};
+use crate::models::medical_record_tb::{Entity as MedicalRecordEntity, Model as MedicalRecordModel};
+use crate::models::medical_services::Entity as ServiceEntity;
+use crate::models::medical_services_provided::{
+    Column as ServiceProvidedColumn, Entity as ServiceProvidedEntity, Model as ServiceProvidedModel,
+};
+use crate::models::patient_tb::{Entity as PatientEntity, Model as PatientModel, Sex};
+use crate::models::service_analyte_tb::{
+    ActiveModel as AnalyteActiveModel, AnalyteValueType, Column as AnalyteColumn, Entity as AnalyteEntity,
+    Model as AnalyteModel,
+};
+use crate::models::service_result_tb::{
+    ActiveModel as ServiceResultActiveModel, Column as ServiceResultColumn, Entity as ServiceResultEntity,
+    Model as ServiceResultModel, ResultFlag,
+};
+use crate::models::unit_conversion_tb::Model as UnitConversionModel;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Write};
+use uuid::Uuid;
+
+pub(crate) const MAX_ANALYTE_CODE_LEN: usize = 16;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnalyteInput {
+    pub code: String,
+    pub name: String,
+    pub unit: Option<String>,
+    pub value_type: AnalyteValueType,
+    pub reference_low: Option<f32>,
+    pub reference_high: Option<f32>,
+    pub critical_low: Option<f32>,
+    pub critical_high: Option<f32>,
+    pub normal_text: Option<String>,
+}
+
+/// Replaces the result template of a service; an empty list removes it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetAnalytesRequest {
+    pub analytes: Vec<AnalyteInput>,
+}
+
+#[derive(Debug, Clone)]
+pub enum AnalytesUpdate {
+    Updated(Vec<AnalyteModel>),
+    ServiceNotFound,
+    Invalid(String),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResultInput {
+    pub analyte_code: String,
+    pub value: String,
+    /// Unit of `value` when it isn't the template's; the value is converted
+    /// to the template's unit through the analyte's unit conversion.
+    #[serde(default)]
+    pub unit: Option<String>,
+    /// Overrides the flag computed from the reference range.
+    pub flag: Option<ResultFlag>,
+}
+
+/// Enters or corrects results of one service provided; analytes left out keep
+/// their result.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnterResultsRequest {
+    pub results: Vec<ResultInput>,
+    /// Staff member entering the results.
+    pub entered_by: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReleaseResultsRequest {
+    /// Staff member releasing the results, usually the pathologist.
+    pub released_by: String,
+}
+
+/// The results of one service provided at a visit, in template order.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServiceResults {
+    pub mrs_id: Uuid,
+    pub ms_id: Uuid,
+    pub service_name: String,
+    pub results: Vec<ServiceResultModel>,
+    /// Codes of the template's analytes that have no result yet.
+    pub pending: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub enum ResultsUpdate {
+    Updated(ServiceResults),
+    /// The service wasn't provided at the patient's visit.
+    NotFound,
+    Invalid(String),
+}
+
+/// Numeric limits a result is flagged against: those of the service's
+/// template, or of a reference range for the patient's sex and age.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Limits {
+    pub reference_low: Option<f32>,
+    pub reference_high: Option<f32>,
+    pub critical_low: Option<f32>,
+    pub critical_high: Option<f32>,
+}
+
+impl Limits {
+    fn all(&self) -> [Option<f32>; 4] {
+        [self.reference_low, self.reference_high, self.critical_low, self.critical_high]
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.all().iter().all(Option::is_none)
+    }
+
+    /// Why the limits can't be used, if they can't.
+    pub fn invalid(&self) -> Option<&'static str> {
+        let ordered = |low: Option<f32>, high: Option<f32>| low.zip(high).is_none_or(|(low, high)| low <= high);
+        if self.all().iter().flatten().any(|limit| !limit.is_finite()) {
+            Some("limits must be numbers")
+        } else if !(ordered(self.reference_low, self.reference_high)
+            && ordered(self.critical_low, self.reference_low)
+            && ordered(self.reference_high, self.critical_high)
+            && ordered(self.critical_low, self.critical_high))
+        {
+            Some("limits must go critical_low ≤ reference_low ≤ reference_high ≤ critical_high")
+        } else {
+            None
+        }
+    }
+}
+
+impl From<&AnalyteModel> for Limits {
+    fn from(analyte: &AnalyteModel) -> Self {
+        Self {
+            reference_low: analyte.reference_low,
+            reference_high: analyte.reference_high,
+            critical_low: analyte.critical_low,
+            critical_high: analyte.critical_high,
+        }
+    }
+}
+
+impl From<&ReferenceRangeModel> for Limits {
+    fn from(range: &ReferenceRangeModel) -> Self {
+        Self {
+            reference_low: range.reference_low,
+            reference_high: range.reference_high,
+            critical_low: range.critical_low,
+            critical_high: range.critical_high,
+        }
+    }
+}
+
+/// The flag a result gets: for numbers, critical limits first, then the
+/// reference range; for text, anything but the analyte's expected finding.
+pub fn compute_flag(analyte: &AnalyteModel, limits: &Limits, value: &str, numeric_value: Option<f32>) -> Option<ResultFlag> {
+    match analyte.value_type {
+        AnalyteValueType::Numeric => {
+            let value = numeric_value?;
+            if limits.critical_low.is_some_and(|limit| value < limit) {
+                Some(ResultFlag::CriticalLow)
+            } else if limits.critical_high.is_some_and(|limit| value > limit) {
+                Some(ResultFlag::CriticalHigh)
+            } else if limits.reference_low.is_some_and(|limit| value < limit) {
+                Some(ResultFlag::Low)
+            } else if limits.reference_high.is_some_and(|limit| value > limit) {
+                Some(ResultFlag::High)
+            } else {
+                None
+            }
+        }
+        AnalyteValueType::Text => analyte
+            .normal_text
+            .as_deref()
+            .filter(|normal| !normal.trim().eq_ignore_ascii_case(value.trim()))
+            .map(|_| ResultFlag::Abnormal),
+    }
+}
+
+/// The reference range as printed on a result, e.g. `3.9–6.1`, `≤ 5.2` or
+/// `Negative`.
+pub fn reference_range_text(analyte: &AnalyteModel, limits: &Limits) -> Option<String> {
+    match analyte.value_type {
+        AnalyteValueType::Numeric => range_text(limits.reference_low, limits.reference_high),
+        AnalyteValueType::Text => analyte.normal_text.clone(),
+    }
+}
+
+pub(crate) fn range_text<T: Display>(low: Option<T>, high: Option<T>) -> Option<String> {
+    match (low, high) {
+        (Some(low), Some(high)) => Some(format!("{}–{}", low, high)),
+        (Some(low), None) => Some(format!("≥ {}", low)),
+        (None, Some(high)) => Some(format!("≤ {}", high)),
+        (None, None) => None,
+    }
+}
+
+/// The sex and age of a patient on the day of a visit, which select the
+/// reference range that applies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Subject {
+    pub sex: Option<Sex>,
+    pub age_days: i64,
+}
+
+impl Subject {
+    pub fn at_visit(patient: &PatientModel, record: &MedicalRecordModel) -> Self {
+        Self {
+            sex: patient.sex,
+            age_days: (record.created_at.date_naive() - patient.birth_date).num_days(),
+        }
+    }
+}
+
+/// The limits a numeric result in `unit` is flagged against: the applicable
+/// reference range among `ranges` if there is one, else one in the analyte's
+/// other unit converted, else the template's.
+fn limits_for(
+    analyte: &AnalyteModel,
+    unit: Option<&str>,
+    ranges: &[ReferenceRangeModel],
+    conversion: Option<&UnitConversionModel>,
+    subject: Subject,
+) -> Limits {
+    if analyte.value_type == AnalyteValueType::Text {
+        return Limits::default();
+    }
+    let Some(unit) = unit else {
+        return Limits::from(analyte);
+    };
+    if let Some(range) = applicable_range(ranges, &analyte.code, unit, subject) {
+        return Limits::from(range);
+    }
+    conversion
+        .and_then(|conversion| {
+            let other = match conversion.system_of(unit)? {
+                UnitSystem::Si => &conversion.conventional_unit,
+                UnitSystem::Conventional => &conversion.si_unit,
+            };
+            let range = applicable_range(ranges, &analyte.code, other, subject)?;
+            conversion.convert_limits(&Limits::from(range), other, unit)
+        })
+        .unwrap_or_else(|| Limits::from(analyte))
+}
+
+pub(crate) fn invalid_analyte(analyte: &AnalyteInput) -> Option<String> {
+    let code = analyte.code.trim();
+    if code.is_empty() || analyte.name.trim().is_empty() {
+        return Some("Every analyte needs a code and a name".to_string());
+    }
+    if code.len() > MAX_ANALYTE_CODE_LEN {
+        return Some(format!("Analyte code '{}' is longer than {} characters", code, MAX_ANALYTE_CODE_LEN));
+    }
+    let limits = Limits {
+        reference_low: analyte.reference_low,
+        reference_high: analyte.reference_high,
+        critical_low: analyte.critical_low,
+        critical_high: analyte.critical_high,
+    };
+    match analyte.value_type {
+        AnalyteValueType::Text if !limits.is_empty() => Some(format!("{}: text analytes have no numeric limits", code)),
+        AnalyteValueType::Numeric if analyte.normal_text.is_some() => {
+            Some(format!("{}: numeric analytes have no normal_text", code))
+        }
+        AnalyteValueType::Numeric => limits.invalid().map(|message| format!("{}: {}", code, message)),
+        AnalyteValueType::Text => None,
+    }
+}
+
+/// The result template of a service, in form order. `None` when the service
+/// doesn't exist.
+pub async fn get_service_analytes(
+    db: &DatabaseConnection,
+    ms_id: Uuid,
+) -> Result<Option<Vec<AnalyteModel>>, sea_orm::DbErr> {
+    if ServiceEntity::find_by_id(ms_id).one(db).await?.is_none() {
+        return Ok(None);
+    }
+    analytes_of(db, ms_id).await.map(Some)
+}
+
+async fn analytes_of<C: ConnectionTrait>(db: &C, ms_id: Uuid) -> Result<Vec<AnalyteModel>, sea_orm::DbErr> {
+    AnalyteEntity::find()
+        .filter(AnalyteColumn::MsId.eq(ms_id))
+        .order_by_asc(AnalyteColumn::Sequence)
+        .all(db)
+        .await
+}
+
+/// Replaces the result template of a service. Results already entered keep
+/// the name, unit and range they were entered with.
+pub async fn set_service_analytes(
+    db: &DatabaseConnection,
+    ms_id: Uuid,
+    request: SetAnalytesRequest,
+) -> Result<AnalytesUpdate, sea_orm::DbErr> {
+    if ServiceEntity::find_by_id(ms_id).one(db).await?.is_none() {
+        return Ok(AnalytesUpdate::ServiceNotFound);
+    }
+    let mut codes = HashSet::new();
+    for analyte in &request.analytes {
+        if let Some(message) = invalid_analyte(analyte) {
+            return Ok(AnalytesUpdate::Invalid(message));
+        }
+        if !codes.insert(analyte.code.trim().to_uppercase()) {
+            return Ok(AnalytesUpdate::Invalid(format!("{} is listed twice", analyte.code.trim())));
+        }
+    }
+
+    let txn = db.begin().await?;
+    replace_analytes(&txn, ms_id, request.analytes).await?;
+    let analytes = analytes_of(&txn, ms_id).await?;
+    txn.commit().await?;
+    Ok(AnalytesUpdate::Updated(analytes))
+}
+
+pub(crate) async fn replace_analytes<C: ConnectionTrait>(
+    db: &C,
+    ms_id: Uuid,
+    analytes: Vec<AnalyteInput>,
+) -> Result<(), sea_orm::DbErr> {
+    AnalyteEntity::delete_many().filter(AnalyteColumn::MsId.eq(ms_id)).exec(db).await?;
+    for (sequence, analyte) in analytes.into_iter().enumerate() {
+        AnalyteActiveModel {
+            analyte_id: Set(Uuid::new_v4()),
+            ms_id: Set(ms_id),
+            code: Set(analyte.code.trim().to_uppercase()),
+            name: Set(analyte.name.trim().to_string()),
+            unit: Set(analyte.unit),
+            value_type: Set(analyte.value_type),
+            reference_low: Set(analyte.reference_low),
+            reference_high: Set(analyte.reference_high),
+            critical_low: Set(analyte.critical_low),
+            critical_high: Set(analyte.critical_high),
+            normal_text: Set(analyte.normal_text),
+            sequence: Set(sequence as i32),
+        }
+        .insert(db)
+        .await?;
+    }
+    Ok(())
+}
+
+/// Groups the results of `services` with their templates.
+async fn results_of<C: ConnectionTrait>(
+    db: &C,
+    services: Vec<ServiceProvidedModel>,
+) -> Result<Vec<ServiceResults>, sea_orm::DbErr> {
+    if services.is_empty() {
+        return Ok(Vec::new());
+    }
+    let ms_ids: Vec<Uuid> = services.iter().map(|service| service.ms_id).collect();
+    let mut analytes: HashMap<Uuid, Vec<AnalyteModel>> = HashMap::new();
+    for analyte in AnalyteEntity::find()
+        .filter(AnalyteColumn::MsId.is_in(ms_ids))
+        .order_by_asc(AnalyteColumn::Sequence)
+        .all(db)
+        .await?
+    {
+        analytes.entry(analyte.ms_id).or_default().push(analyte);
+    }
+    let mrs_ids: Vec<Uuid> = services.iter().map(|service| service.mrs_id).collect();
+    let mut results: HashMap<Uuid, Vec<ServiceResultModel>> = HashMap::new();
+    for result in ServiceResultEntity::find()
+        .filter(ServiceResultColumn::MrsId.is_in(mrs_ids))
+        .order_by_asc(ServiceResultColumn::AnalyteCode)
+        .all(db)
+        .await?
+    {
+        results.entry(result.mrs_id).or_default().push(result);
+    }
+
+    Ok(services
+        .into_iter()
+        .map(|service| {
+            let template = analytes.remove(&service.ms_id).unwrap_or_default();
+            let position: HashMap<&str, usize> =
+                template.iter().enumerate().map(|(i, analyte)| (analyte.code.as_str(), i)).collect();
+            let mut entered = results.remove(&service.mrs_id).unwrap_or_default();
+            // Results of analytes since dropped from the template go last.
+            entered.sort_by_key(|result| position.get(result.analyte_code.as_str()).copied().unwrap_or(usize::MAX));
+            let pending = template
+                .iter()
+                .filter(|analyte| !entered.iter().any(|result| result.analyte_code == analyte.code))
+                .map(|analyte| analyte.code.clone())
+                .collect();
+            ServiceResults {
+                mrs_id: service.mrs_id,
+                ms_id: service.ms_id,
+                service_name: service.service_name,
+                results: entered,
+                pending,
+            }
+        })
+        .collect())
+}
+
+/// The services billed for a visit with their results. `None` when the record
+/// doesn't exist or isn't the patient's.
+pub async fn get_visit_results(
+    db: &DatabaseConnection,
+    patient_id: Uuid,
+    medical_id: Uuid,
+) -> Result<Option<Vec<ServiceResults>>, sea_orm::DbErr> {
+    if get_medical_record(db, patient_id, medical_id).await?.is_none() {
+        return Ok(None);
+    }
+    let bill_ids: Vec<Uuid> = BillEntity::find()
+        .filter(BillColumn::MedicalId.eq(medical_id))
+        .filter(BillColumn::PatientId.eq(patient_id))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|bill| bill.medical_bill_id)
+        .collect();
+    let services = ServiceProvidedEntity::find()
+        .filter(ServiceProvidedColumn::MedicalBillId.is_in(bill_ids))
+        .order_by_asc(ServiceProvidedColumn::ServiceName)
+        .order_by_asc(ServiceProvidedColumn::MrsId)
+        .all(db)
+        .await?;
+    results_of(db, services).await.map(Some)
+}
+
+/// The released results of a visit as plain text for printing, shown in
+/// `units` when given. `None` when the patient or record doesn't exist.
+pub async fn get_printable_lab_report(
+    db: &DatabaseConnection,
+    patient_id: Uuid,
+    medical_id: Uuid,
+    units: Option<UnitSystem>,
+) -> Result<Option<String>, sea_orm::DbErr> {
+    let Some(patient) = get_patient(db, patient_id).await? else {
+        return Ok(None);
+    };
+    let Some(record) = get_medical_record(db, patient_id, medical_id).await? else {
+        return Ok(None);
+    };
+    let Some(mut services) = get_visit_results(db, patient_id, medical_id).await? else {
+        return Ok(None);
+    };
+    if let Some(system) = units {
+        services = results_in(db, services, system).await?;
+    }
+    Ok(Some(render_lab_report(&patient, &record, &services, units)))
+}
+
+fn flag_code(flag: Option<ResultFlag>) -> &'static str {
+    match flag {
+        Some(ResultFlag::Low) => "L",
+        Some(ResultFlag::High) => "H",
+        Some(ResultFlag::CriticalLow) => "LL",
+        Some(ResultFlag::CriticalHigh) => "HH",
+        Some(ResultFlag::Abnormal) => "A",
+        None => "",
+    }
+}
+
+/// Lays out a laboratory report: patient header, then per service its results
+/// with flag, unit and reference range, and who released them. Services
+/// without a result template are left out; unreleased results aren't printed.
+pub fn render_lab_report(
+    patient: &PatientModel,
+    record: &MedicalRecordModel,
+    services: &[ServiceResults],
+    units: Option<UnitSystem>,
+) -> String {
+    let date = record.created_at.date_naive();
+    let mut out = String::new();
+    let _ = writeln!(out, "LABORATORY REPORT");
+    let _ = writeln!(out);
+    let _ = writeln!(out, "Patient:     {}", printed_name(patient));
+    if let Some(number) = &patient.patient_number {
+        let _ = writeln!(out, "Patient No.: {}", number);
+    }
+    let _ = writeln!(out, "Age:         {}", age_on(patient.birth_date, date));
+    if let Some(sex) = patient.sex {
+        let _ = writeln!(out, "Sex:         {}", if sex == Sex::Male { "Male" } else { "Female" });
+    }
+    let _ = writeln!(out, "Date:        {}", date.format("%B %-d, %Y"));
+    match units {
+        Some(UnitSystem::Si) => {
+            let _ = writeln!(out, "Units:       SI");
+        }
+        Some(UnitSystem::Conventional) => {
+            let _ = writeln!(out, "Units:       Conventional");
+        }
+        None => {}
+    }
+
+    let services: Vec<&ServiceResults> = services
+        .iter()
+        .filter(|service| !service.results.is_empty() || !service.pending.is_empty())
+        .collect();
+    if services.is_empty() {
+        let _ = writeln!(out);
+        let _ = writeln!(out, "   (no laboratory services at this visit)");
+    }
+    for service in services {
+        let _ = writeln!(out);
+        let _ = writeln!(out, "{}", service.service_name);
+        let released = service.results.first().and_then(|result| result.released_at.zip(result.released_by.as_ref()));
+        let Some((released_at, released_by)) = released else {
+            let _ = writeln!(out, "   (results not released yet)");
+            continue;
+        };
+        let _ = writeln!(out, "   Test                             Result     Unit       Reference");
+        for result in &service.results {
+            let line = format!(
+                "   {:<28} {:>10} {:<2}  {:<10} {}",
+                result.analyte_name,
+                result.value,
+                flag_code(result.flag),
+                result.unit.as_deref().unwrap_or(""),
+                result.reference_range.as_deref().unwrap_or("")
+            );
+            let _ = writeln!(out, "{}", line.trim_end());
+        }
+        let _ = writeln!(out, "   Released by {} on {}", released_by, released_at.format("%Y-%m-%d %H:%M UTC"));
+    }
+    out
+}
+
+/// A service provided, only if it is billed on the patient's visit.
+async fn visit_service(
+    db: &DatabaseConnection,
+    patient_id: Uuid,
+    medical_id: Uuid,
+    mrs_id: Uuid,
+) -> Result<Option<ServiceProvidedModel>, sea_orm::DbErr> {
+    Ok(ServiceProvidedEntity::find_by_id(mrs_id)
+        .find_also_related(BillEntity)
+        .one(db)
+        .await?
+        .filter(|(_, bill)| bill.as_ref().is_some_and(|bill| bill.medical_id == medical_id && bill.patient_id == patient_id))
+        .map(|(service, _)| service))
+}
+
+/// Enters or corrects results of a service provided at a visit, until they are
+/// released. Each analyte must be in the service's template, and numeric
+/// analytes need a number, which is stored in the template's unit (converted
+/// when entered in the other unit of the analyte's conversion). Unless given,
+/// the flag is computed from the reference range for the patient's sex and
+/// age at the visit, or else from the template.
+pub async fn enter_service_results(
+    db: &DatabaseConnection,
+    patient_id: Uuid,
+    medical_id: Uuid,
+    mrs_id: Uuid,
+    request: EnterResultsRequest,
+) -> Result<ResultsUpdate, sea_orm::DbErr> {
+    let Some(service) = visit_service(db, patient_id, medical_id, mrs_id).await? else {
+        return Ok(ResultsUpdate::NotFound);
+    };
+    let Some(subject) = subject_of(db, patient_id, medical_id).await? else {
+        return Ok(ResultsUpdate::NotFound);
+    };
+    let template = analytes_of(db, service.ms_id).await?;
+    if template.is_empty() {
+        return Ok(ResultsUpdate::Invalid(format!("{} has no result template", service.service_name)));
+    }
+    let codes: Vec<String> = template.iter().map(|analyte| analyte.code.clone()).collect();
+    let ranges = reference_ranges_for(db, &codes).await?;
+    let conversions = unit_conversions_for(db, &codes).await?;
+
+    let mut entries = Vec::with_capacity(request.results.len());
+    let mut seen = HashSet::new();
+    for input in request.results {
+        let code = input.analyte_code.trim().to_uppercase();
+        let Some(analyte) = template.iter().find(|analyte| analyte.code == code) else {
+            return Ok(ResultsUpdate::Invalid(format!("{} is not an analyte of {}", code, service.service_name)));
+        };
+        if !seen.insert(code.clone()) {
+            return Ok(ResultsUpdate::Invalid(format!("{} is listed twice", code)));
+        }
+        let value = input.value.trim().to_string();
+        if value.is_empty() {
+            return Ok(ResultsUpdate::Invalid(format!("{} has no value", code)));
+        }
+        let numeric_value = match analyte.value_type {
+            AnalyteValueType::Numeric => match value.parse::<f32>() {
+                Ok(number) if number.is_finite() => Some(number),
+                _ => return Ok(ResultsUpdate::Invalid(format!("{}: '{}' is not a number", code, value))),
+            },
+            AnalyteValueType::Text => None,
+        };
+        let conversion = conversions.get(&code);
+        let (value, numeric_value) = match input.unit.as_deref().map(str::trim).filter(|unit| !unit.is_empty()) {
+            Some(unit) if !analyte.unit.as_deref().is_some_and(|stored| stored.eq_ignore_ascii_case(unit)) => {
+                let converted = numeric_value
+                    .zip(analyte.unit.as_deref())
+                    .zip(conversion)
+                    .and_then(|((number, stored), conversion)| conversion.convert_rounded(number, unit, stored));
+                match converted {
+                    Some((value, number)) => (value, Some(number)),
+                    None => {
+                        return Ok(ResultsUpdate::Invalid(format!(
+                            "{}: {} can't be converted to {}",
+                            code,
+                            unit,
+                            analyte.unit.as_deref().unwrap_or("a unitless result")
+                        )))
+                    }
+                }
+            }
+            _ => (value, numeric_value),
+        };
+        let limits = limits_for(analyte, analyte.unit.as_deref(), &ranges, conversion, subject);
+        let flag = input.flag.or_else(|| compute_flag(analyte, &limits, &value, numeric_value));
+        entries.push((analyte, value, numeric_value, flag, input.flag.is_some(), limits));
+    }
+
+    let now = Utc::now();
+    let txn = db.begin().await?;
+    let existing: HashMap<String, ServiceResultModel> = ServiceResultEntity::find()
+        .filter(ServiceResultColumn::MrsId.eq(mrs_id))
+        .all(&txn)
+        .await?
+        .into_iter()
+        .map(|result| (result.analyte_code.clone(), result))
+        .collect();
+    if let Some(released_at) = existing.values().find_map(|result| result.released_at) {
+        return Ok(ResultsUpdate::Invalid(format!(
+            "Results of {} were released on {} and can't be changed",
+            service.service_name,
+            released_at.format("%Y-%m-%d %H:%M UTC")
+        )));
+    }
+    for (analyte, value, numeric_value, flag, flag_overridden, limits) in entries {
+        let reference_range = reference_range_text(analyte, &limits);
+        match existing.get(&analyte.code) {
+            Some(result) => {
+                let mut result: ServiceResultActiveModel = result.clone().into();
+                result.analyte_name = Set(analyte.name.clone());
+                result.value = Set(value);
+                result.numeric_value = Set(numeric_value);
+                result.unit = Set(analyte.unit.clone());
+                result.reference_range = Set(reference_range);
+                result.reference_low = Set(limits.reference_low);
+                result.reference_high = Set(limits.reference_high);
+                result.flag = Set(flag);
+                result.flag_overridden = Set(flag_overridden);
+                result.last_audited_by = Set(Some(request.entered_by.clone()));
+                result.update(&txn).await?;
+            }
+            None => {
+                ServiceResultActiveModel {
+                    service_result_id: Set(Uuid::new_v4()),
+                    mrs_id: Set(mrs_id),
+                    analyte_code: Set(analyte.code.clone()),
+                    analyte_name: Set(analyte.name.clone()),
+                    value: Set(value),
+                    numeric_value: Set(numeric_value),
+                    unit: Set(analyte.unit.clone()),
+                    reference_range: Set(reference_range),
+                    reference_low: Set(limits.reference_low),
+                    reference_high: Set(limits.reference_high),
+                    flag: Set(flag),
+                    flag_overridden: Set(flag_overridden),
+                    entered_by: Set(request.entered_by.clone()),
+                    last_audited_by: Set(None),
+                    released_at: Set(None),
+                    released_by: Set(None),
+                    created_at: Set(now),
+                    updated_at: Set(now),
+                }
+                .insert(&txn)
+                .await?;
+            }
+        }
+    }
+    let mut results = results_of(&txn, vec![service]).await?;
+    txn.commit().await?;
+    Ok(ResultsUpdate::Updated(results.remove(0)))
+}
+
+/// Releases the results of a service provided at a visit to the doctor. Every
+/// analyte of the template needs a result first, and released results can't
+/// be changed or reflagged.
+pub async fn release_service_results(
+    db: &DatabaseConnection,
+    patient_id: Uuid,
+    medical_id: Uuid,
+    mrs_id: Uuid,
+    request: ReleaseResultsRequest,
+) -> Result<ResultsUpdate, sea_orm::DbErr> {
+    let Some(service) = visit_service(db, patient_id, medical_id, mrs_id).await? else {
+        return Ok(ResultsUpdate::NotFound);
+    };
+    let txn = db.begin().await?;
+    let current = results_of(&txn, vec![service.clone()]).await?.remove(0);
+    if current.results.is_empty() {
+        return Ok(ResultsUpdate::Invalid(format!("{} has no results to release", service.service_name)));
+    }
+    if !current.pending.is_empty() {
+        return Ok(ResultsUpdate::Invalid(format!(
+            "{} has no result yet for {}",
+            service.service_name,
+            current.pending.join(", ")
+        )));
+    }
+    if current.results.iter().all(|result| result.released_at.is_some()) {
+        return Ok(ResultsUpdate::Invalid(format!("Results of {} are already released", service.service_name)));
+    }
+
+    ServiceResultEntity::update_many()
+        .col_expr(ServiceResultColumn::ReleasedAt, Expr::value(Utc::now()))
+        .col_expr(ServiceResultColumn::ReleasedBy, Expr::value(request.released_by))
+        .filter(ServiceResultColumn::MrsId.eq(mrs_id))
+        .filter(ServiceResultColumn::ReleasedAt.is_null())
+        .exec(&txn)
+        .await?;
+    let mut results = results_of(&txn, vec![service]).await?;
+    txn.commit().await?;
+    Ok(ResultsUpdate::Updated(results.remove(0)))
+}
+
+async fn subject_of<C: ConnectionTrait>(
+    db: &C,
+    patient_id: Uuid,
+    medical_id: Uuid,
+) -> Result<Option<Subject>, sea_orm::DbErr> {
+    let patient = PatientEntity::find_by_id(patient_id).one(db).await?;
+    let record = MedicalRecordEntity::find_by_id(medical_id).one(db).await?;
+    Ok(patient.zip(record).map(|(patient, record)| Subject::at_visit(&patient, &record)))
+}
+
+/// Recomputes the flag and printed range of the unreleased numeric results of
+/// an analyte after its reference ranges or unit conversion changed; flags set by hand are kept.
+/// Returns how many results changed.
+pub(crate) async fn reflag_unreleased<C: ConnectionTrait>(db: &C, analyte_code: &str) -> Result<u64, sea_orm::DbErr> {
+    let results = ServiceResultEntity::find()
+        .filter(ServiceResultColumn::AnalyteCode.eq(analyte_code))
+        .filter(ServiceResultColumn::ReleasedAt.is_null())
+        .filter(ServiceResultColumn::NumericValue.is_not_null())
+        .all(db)
+        .await?;
+    if results.is_empty() {
+        return Ok(0);
+    }
+    let services: HashMap<Uuid, ServiceProvidedModel> = ServiceProvidedEntity::find()
+        .filter(ServiceProvidedColumn::MrsId.is_in(results.iter().map(|result| result.mrs_id)))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|service| (service.mrs_id, service))
+        .collect();
+    let bills: HashMap<Uuid, BillModel> = BillEntity::find()
+        .filter(BillColumn::MedicalBillId.is_in(services.values().map(|service| service.medical_bill_id)))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|bill| (bill.medical_bill_id, bill))
+        .collect();
+    let templates: HashMap<Uuid, AnalyteModel> = AnalyteEntity::find()
+        .filter(AnalyteColumn::Code.eq(analyte_code))
+        .filter(AnalyteColumn::MsId.is_in(services.values().map(|service| service.ms_id)))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|analyte| (analyte.ms_id, analyte))
+        .collect();
+    let ranges = reference_ranges_for(db, &[analyte_code.to_string()]).await?;
+    let conversion = unit_conversions_for(db, &[analyte_code.to_string()]).await?.remove(analyte_code);
+
+    let mut subjects: HashMap<Uuid, Option<Subject>> = HashMap::new();
+    let mut changed = 0;
+    for result in results {
+        // Results whose service, bill or template analyte is gone are left as entered.
+        let Some(service) = services.get(&result.mrs_id) else { continue };
+        let Some(bill) = bills.get(&service.medical_bill_id) else { continue };
+        let Some(analyte) = templates.get(&service.ms_id) else { continue };
+        let subject = match subjects.get(&bill.medical_bill_id) {
+            Some(subject) => *subject,
+            None => {
+                let subject = subject_of(db, bill.patient_id, bill.medical_id).await?;
+                subjects.insert(bill.medical_bill_id, subject);
+                subject
+            }
+        };
+        let Some(subject) = subject else { continue };
+
+        let limits = limits_for(analyte, result.unit.as_deref(), &ranges, conversion.as_ref(), subject);
+        let flag = if result.flag_overridden {
+            result.flag
+        } else {
+            compute_flag(analyte, &limits, &result.value, result.numeric_value)
+        };
+        let reference_range = reference_range_text(analyte, &limits);
+        if flag == result.flag
+            && reference_range == result.reference_range
+            && limits.reference_low == result.reference_low
+            && limits.reference_high == result.reference_high
+        {
+            continue;
+        }
+        let mut result: ServiceResultActiveModel = result.into();
+        result.flag = Set(flag);
+        result.reference_range = Set(reference_range);
+        result.reference_low = Set(limits.reference_low);
+        result.reference_high = Set(limits.reference_high);
+        result.update(db).await?;
+        changed += 1;
+    }
+    Ok(changed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn numeric(reference: (f32, f32), critical: (f32, f32)) -> AnalyteModel {
+        AnalyteModel {
+            analyte_id: Uuid::nil(),
+            ms_id: Uuid::nil(),
+            code: "GLU".to_string(),
+            name: "Glucose".to_string(),
+            unit: Some("mmol/L".to_string()),
+            value_type: AnalyteValueType::Numeric,
+            reference_low: Some(reference.0),
+            reference_high: Some(reference.1),
+            critical_low: Some(critical.0),
+            critical_high: Some(critical.1),
+            normal_text: None,
+            sequence: 1,
+        }
+    }
+
+    fn text(normal_text: Option<&str>) -> AnalyteModel {
+        AnalyteModel {
+            code: "PROT".to_string(),
+            name: "Protein".to_string(),
+            unit: None,
+            value_type: AnalyteValueType::Text,
+            reference_low: None,
+            reference_high: None,
+            critical_low: None,
+            critical_high: None,
+            normal_text: normal_text.map(str::to_string),
+            ..numeric((0.0, 0.0), (0.0, 0.0))
+        }
+    }
+
+    fn flag(analyte: &AnalyteModel, value: f32) -> Option<ResultFlag> {
+        compute_flag(analyte, &Limits::from(analyte), &value.to_string(), Some(value))
+    }
+
+    #[test]
+    fn compute_flag_checks_critical_limits_before_the_reference_range() {
+        let glucose = numeric((3.9, 6.1), (2.2, 25.0));
+
+        assert_eq!(flag(&glucose, 2.0), Some(ResultFlag::CriticalLow));
+        assert_eq!(flag(&glucose, 3.0), Some(ResultFlag::Low));
+        assert_eq!(flag(&glucose, 3.9), None);
+        assert_eq!(flag(&glucose, 6.1), None);
+        assert_eq!(flag(&glucose, 7.0), Some(ResultFlag::High));
+        assert_eq!(flag(&glucose, 30.0), Some(ResultFlag::CriticalHigh));
+    }
+
+    #[test]
+    fn compute_flag_uses_only_the_limits_given() {
+        let glucose = numeric((3.9, 6.1), (2.2, 25.0));
+        let upper_only = Limits { reference_high: Some(5.2), ..Limits::default() };
+
+        assert_eq!(compute_flag(&glucose, &upper_only, "2.0", Some(2.0)), None);
+        assert_eq!(compute_flag(&glucose, &upper_only, "5.3", Some(5.3)), Some(ResultFlag::High));
+        assert_eq!(compute_flag(&glucose, &Limits::default(), "99", Some(99.0)), None);
+    }
+
+    #[test]
+    fn compute_flag_leaves_non_numeric_values_of_numeric_analytes_unflagged() {
+        let glucose = numeric((3.9, 6.1), (2.2, 25.0));
+
+        assert_eq!(compute_flag(&glucose, &Limits::from(&glucose), "hemolyzed", None), None);
+    }
+
+    #[test]
+    fn compute_flag_compares_text_with_the_expected_finding() {
+        let protein = text(Some("Negative"));
+        let limits = Limits::default();
+
+        assert_eq!(compute_flag(&protein, &limits, " negative ", None), None);
+        assert_eq!(compute_flag(&protein, &limits, "Trace", None), Some(ResultFlag::Abnormal));
+        assert_eq!(compute_flag(&text(None), &limits, "Trace", None), None);
+    }
+
+    #[test]
+    fn limits_must_be_ordered() {
+        let limits = |reference: (f32, f32), critical: (f32, f32)| Limits::from(&numeric(reference, critical));
+
+        assert_eq!(limits((3.9, 6.1), (2.2, 25.0)).invalid(), None);
+        assert_eq!(limits((3.9, 3.9), (3.9, 3.9)).invalid(), None);
+        assert!(limits((6.1, 3.9), (2.2, 25.0)).invalid().is_some());
+        assert!(limits((3.9, 6.1), (4.0, 25.0)).invalid().is_some());
+        assert!(limits((3.9, 6.1), (2.2, 6.0)).invalid().is_some());
+        assert!(limits((3.9, f32::NAN), (2.2, 25.0)).invalid().is_some());
+    }
+
+    #[test]
+    fn reference_range_text_prints_open_ranges() {
+        let glucose = numeric((3.9, 6.1), (2.2, 25.0));
+        let printed = |low, high| {
+            reference_range_text(&glucose, &Limits { reference_low: low, reference_high: high, ..Limits::default() })
+        };
+
+        assert_eq!(printed(Some(3.9), Some(6.1)).as_deref(), Some("3.9–6.1"));
+        assert_eq!(printed(Some(3.9), None).as_deref(), Some("≥ 3.9"));
+        assert_eq!(printed(None, Some(5.2)).as_deref(), Some("≤ 5.2"));
+        assert_eq!(printed(None, None), None);
+        assert_eq!(reference_range_text(&text(Some("Negative")), &Limits::default()).as_deref(), Some("Negative"));
+    }
+}
@@ -0,0 +1,467 @@
+//! The unit registry: per-analyte factors between SI and conventional units.
+//! Results are stored in the unit of the service's template; these convert
+//! them when entered in the other unit and when shown in a requested system.
+
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder, Set,
+    TransactionTrait,
+};
+use crate::handlers::audit_handlers::{record_audit, CreateAuditEntry};
+use crate::handlers::service_result_handlers::{range_text, reflag_unreleased, Limits, ServiceResults, MAX_ANALYTE_CODE_LEN};
+use crate::models::service_result_tb::Model as ServiceResultModel;
+use crate::models::unit_conversion_tb::{
+    ActiveModel as UnitConversionActiveModel, Column as UnitConversionColumn, Entity as UnitConversionEntity,
+    Model as UnitConversionModel,
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Decimal places a value can be shown with; more is noise for any analyte.
+const MAX_DECIMALS: i32 = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnitSystem {
+    Si,
+    Conventional,
+}
+
+/// `?units=si` or `?units=conventional`; left out, results are shown in the
+/// unit they were stored in.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UnitsQuery {
+    pub units: Option<UnitSystem>,
+}
+
+/// An analyte's conversion, as created or replaced.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnitConversionInput {
+    pub si_unit: String,
+    pub conventional_unit: String,
+    /// `conventional = si × factor`, e.g. 18.016 for glucose in mmol/L to mg/dL.
+    pub factor: f32,
+    pub si_decimals: i32,
+    pub conventional_decimals: i32,
+}
+
+/// A saved conversion and the unreleased results reflagged because of it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnitConversionChange {
+    pub conversion: UnitConversionModel,
+    pub results_reflagged: u64,
+}
+
+#[derive(Debug, Clone)]
+pub enum UnitConversionOutcome {
+    Saved(UnitConversionChange),
+    Invalid(String),
+}
+
+fn same_unit(a: &str, b: &str) -> bool {
+    a.trim().eq_ignore_ascii_case(b.trim())
+}
+
+impl UnitConversionModel {
+    pub fn unit(&self, system: UnitSystem) -> &str {
+        match system {
+            UnitSystem::Si => &self.si_unit,
+            UnitSystem::Conventional => &self.conventional_unit,
+        }
+    }
+
+    /// The system `unit` belongs to, if it is one of the conversion's units.
+    pub fn system_of(&self, unit: &str) -> Option<UnitSystem> {
+        if same_unit(unit, &self.si_unit) {
+            Some(UnitSystem::Si)
+        } else if same_unit(unit, &self.conventional_unit) {
+            Some(UnitSystem::Conventional)
+        } else {
+            None
+        }
+    }
+
+    /// `value` in `from` converted to `to`; `None` unless both are units of
+    /// the conversion.
+    pub fn convert(&self, value: f32, from: &str, to: &str) -> Option<f32> {
+        match (self.system_of(from)?, self.system_of(to)?) {
+            (UnitSystem::Si, UnitSystem::Conventional) => Some(value * self.factor),
+            (UnitSystem::Conventional, UnitSystem::Si) => Some(value / self.factor),
+            _ => Some(value),
+        }
+    }
+
+    /// Like `convert`, rounded to the decimals of `to`'s system, and as text.
+    pub fn convert_rounded(&self, value: f32, from: &str, to: &str) -> Option<(String, f32)> {
+        let converted = self.convert(value, from, to)?;
+        let decimals = match self.system_of(to)? {
+            UnitSystem::Si => self.si_decimals,
+            UnitSystem::Conventional => self.conventional_decimals,
+        };
+        let text = format!("{:.*}", decimals.clamp(0, MAX_DECIMALS) as usize, converted);
+        let rounded = text.parse().unwrap_or(converted);
+        Some((text, rounded))
+    }
+
+    /// `limits` in `from` converted to `to`, rounded like values in `to`.
+    pub fn convert_limits(&self, limits: &Limits, from: &str, to: &str) -> Option<Limits> {
+        let convert = |limit: Option<f32>| match limit {
+            Some(limit) => self.convert_rounded(limit, from, to).map(|(_, limit)| Some(limit)),
+            None => Some(None),
+        };
+        Some(Limits {
+            reference_low: convert(limits.reference_low)?,
+            reference_high: convert(limits.reference_high)?,
+            critical_low: convert(limits.critical_low)?,
+            critical_high: convert(limits.critical_high)?,
+        })
+    }
+}
+
+pub(crate) fn invalid_conversion(conversion: &UnitConversionInput) -> Option<String> {
+    let (si, conventional) = (conversion.si_unit.trim(), conversion.conventional_unit.trim());
+    if si.is_empty() || conventional.is_empty() {
+        return Some("si_unit and conventional_unit are required".to_string());
+    }
+    if same_unit(si, conventional) {
+        return Some("si_unit and conventional_unit must differ".to_string());
+    }
+    if !conversion.factor.is_finite() || conversion.factor <= 0.0 {
+        return Some("factor must be a positive number".to_string());
+    }
+    let decimals = 0..=MAX_DECIMALS;
+    if !decimals.contains(&conversion.si_decimals) || !decimals.contains(&conversion.conventional_decimals) {
+        return Some(format!("Decimals must be from 0 to {}", MAX_DECIMALS));
+    }
+    None
+}
+
+/// The whole registry, by analyte code.
+pub async fn get_unit_conversions(db: &DatabaseConnection) -> Result<Vec<UnitConversionModel>, sea_orm::DbErr> {
+    UnitConversionEntity::find()
+        .order_by_asc(UnitConversionColumn::AnalyteCode)
+        .all(db)
+        .await
+}
+
+/// Conversions of the given analytes, keyed by code.
+pub(crate) async fn unit_conversions_for<C: ConnectionTrait>(
+    db: &C,
+    analyte_codes: &[String],
+) -> Result<HashMap<String, UnitConversionModel>, sea_orm::DbErr> {
+    Ok(UnitConversionEntity::find()
+        .filter(UnitConversionColumn::AnalyteCode.is_in(analyte_codes.iter().cloned()))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|conversion| (conversion.analyte_code.clone(), conversion))
+        .collect())
+}
+
+/// Audit log action of a deleted conversion.
+pub const CONVERSION_DELETED_ACTION: &str = "unit_conversion.deleted";
+
+/// Creates or replaces an analyte's conversion on behalf of `updated_by`, the
+/// authenticated account. Stored results don't change, but unreleased ones are
+/// reflagged, since reference ranges in the other unit apply through the
+/// conversion.
+pub async fn save_unit_conversion(
+    db: &DatabaseConnection,
+    analyte_code: &str,
+    input: UnitConversionInput,
+    updated_by: Uuid,
+) -> Result<UnitConversionOutcome, sea_orm::DbErr> {
+    let code = analyte_code.trim().to_uppercase();
+    if code.is_empty() || code.len() > MAX_ANALYTE_CODE_LEN {
+        return Ok(UnitConversionOutcome::Invalid(format!(
+            "Analyte code must have 1 to {} characters",
+            MAX_ANALYTE_CODE_LEN
+        )));
+    }
+    if let Some(message) = invalid_conversion(&input) {
+        return Ok(UnitConversionOutcome::Invalid(message));
+    }
+
+    let txn = db.begin().await?;
+    let conversion = match UnitConversionEntity::find_by_id(code.clone()).one(&txn).await? {
+        Some(current) => {
+            let mut conversion: UnitConversionActiveModel = current.into();
+            conversion.si_unit = Set(input.si_unit.trim().to_string());
+            conversion.conventional_unit = Set(input.conventional_unit.trim().to_string());
+            conversion.factor = Set(input.factor);
+            conversion.si_decimals = Set(input.si_decimals);
+            conversion.conventional_decimals = Set(input.conventional_decimals);
+            conversion.updated_by = Set(Some(updated_by));
+            conversion.update(&txn).await?
+        }
+        None => insert_conversion(&txn, &code, input, Some(updated_by)).await?,
+    };
+    let results_reflagged = reflag_unreleased(&txn, &code).await?;
+    txn.commit().await?;
+    Ok(UnitConversionOutcome::Saved(UnitConversionChange { conversion, results_reflagged }))
+}
+
+pub(crate) async fn insert_conversion<C: ConnectionTrait>(
+    db: &C,
+    analyte_code: &str,
+    input: UnitConversionInput,
+    updated_by: Option<Uuid>,
+) -> Result<UnitConversionModel, sea_orm::DbErr> {
+    UnitConversionActiveModel {
+        analyte_code: Set(analyte_code.trim().to_uppercase()),
+        si_unit: Set(input.si_unit.trim().to_string()),
+        conventional_unit: Set(input.conventional_unit.trim().to_string()),
+        factor: Set(input.factor),
+        si_decimals: Set(input.si_decimals),
+        conventional_decimals: Set(input.conventional_decimals),
+        updated_by: Set(updated_by),
+        updated_at: Set(Utc::now()),
+    }
+    .insert(db)
+    .await
+}
+
+/// Removes an analyte's conversion; its results can then only be shown and
+/// entered in their stored unit. The deleted conversion is kept in the audit
+/// log under `deleted_by`. `None` when it doesn't exist; otherwise how many
+/// unreleased results were reflagged.
+pub async fn delete_unit_conversion(
+    db: &DatabaseConnection,
+    analyte_code: &str,
+    deleted_by: Uuid,
+) -> Result<Option<u64>, sea_orm::DbErr> {
+    let code = analyte_code.trim().to_uppercase();
+    let txn = db.begin().await?;
+    let Some(conversion) = UnitConversionEntity::find_by_id(code.clone()).one(&txn).await? else {
+        return Ok(None);
+    };
+    UnitConversionEntity::delete_by_id(code.clone()).exec(&txn).await?;
+    let entry = CreateAuditEntry {
+        action: CONVERSION_DELETED_ACTION.to_string(),
+        entity: "unit_conversion".to_string(),
+        record_id: Some(code.clone()),
+        actor_id: Some(deleted_by),
+        details: json!({ "conversion": conversion }),
+    };
+    record_audit(&txn, None, entry).await?;
+    let results_reflagged = reflag_unreleased(&txn, &code).await?;
+    txn.commit().await?;
+    Ok(Some(results_reflagged))
+}
+
+/// A result as shown in `system`: value, unit and reference range converted.
+/// Text results, analytes without a conversion and results in a unit the
+/// conversion doesn't know are shown as stored. Results entered before the
+/// range limits were kept lose their range when converted.
+pub fn result_in(result: &ServiceResultModel, conversion: Option<&UnitConversionModel>, system: UnitSystem) -> ServiceResultModel {
+    let (Some(conversion), Some(value), Some(unit)) = (conversion, result.numeric_value, result.unit.as_deref()) else {
+        return result.clone();
+    };
+    let target = conversion.unit(system);
+    if same_unit(unit, target) {
+        return result.clone();
+    }
+    let Some((text, number)) = conversion.convert_rounded(value, unit, target) else {
+        return result.clone();
+    };
+    let limit = |limit: Option<f32>| limit.and_then(|limit| conversion.convert_rounded(limit, unit, target));
+    let (low, high) = (limit(result.reference_low), limit(result.reference_high));
+    ServiceResultModel {
+        value: text,
+        numeric_value: Some(number),
+        unit: Some(target.to_string()),
+        reference_range: range_text(low.as_ref().map(|(text, _)| text), high.as_ref().map(|(text, _)| text)),
+        reference_low: low.map(|(_, number)| number),
+        reference_high: high.map(|(_, number)| number),
+        ..result.clone()
+    }
+}
+
+/// The results of a visit as shown in `system`.
+pub async fn results_in<C: ConnectionTrait>(
+    db: &C,
+    services: Vec<ServiceResults>,
+    system: UnitSystem,
+) -> Result<Vec<ServiceResults>, sea_orm::DbErr> {
+    let codes: Vec<String> = services
+        .iter()
+        .flat_map(|service| service.results.iter().map(|result| result.analyte_code.clone()))
+        .collect();
+    let conversions = unit_conversions_for(db, &codes).await?;
+    Ok(services
+        .into_iter()
+        .map(|service| ServiceResults {
+            results: service
+                .results
+                .iter()
+                .map(|result| result_in(result, conversions.get(&result.analyte_code), system))
+                .collect(),
+            ..service
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::seed_handlers::bundled_service_catalog;
+
+    fn conversion(code: &str) -> UnitConversionModel {
+        let entry = bundled_service_catalog()
+            .unwrap()
+            .unit_conversions
+            .into_iter()
+            .find(|entry| entry.analyte_code == code)
+            .unwrap();
+        UnitConversionModel {
+            analyte_code: entry.analyte_code,
+            si_unit: entry.conversion.si_unit,
+            conventional_unit: entry.conversion.conventional_unit,
+            factor: entry.conversion.factor,
+            si_decimals: entry.conversion.si_decimals,
+            conventional_decimals: entry.conversion.conventional_decimals,
+            updated_by: None,
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn approx(actual: f32, expected: f32) -> bool {
+        (actual - expected).abs() <= expected.abs() * 1e-3
+    }
+
+    #[test]
+    fn bundled_factors_match_the_molar_masses() {
+        // conventional per SI unit, from the analyte's molar mass.
+        for (code, factor) in [
+            ("GLU", 180.16 / 10.0),
+            ("CHOL", 386.65 / 10.0),
+            ("TG", 885.7 / 10.0),
+            ("BUN", 28.014 / 10.0),
+            ("CREA", 113.12 / 10_000.0),
+            ("URIC", 168.11 / 10_000.0),
+            ("FT4", 776.87 / 10_000.0),
+            ("HGB", 0.1),
+            ("HCT", 100.0),
+            ("NA", 1.0),
+        ] {
+            assert!(approx(conversion(code).factor, factor), "{} factor {}", code, conversion(code).factor);
+        }
+    }
+
+    #[test]
+    fn bundled_conversions_cover_the_template_units() {
+        let catalog = bundled_service_catalog().unwrap();
+        for entry in &catalog.unit_conversions {
+            let analyte = catalog
+                .services
+                .iter()
+                .flat_map(|service| &service.analytes)
+                .find(|analyte| analyte.code == entry.analyte_code)
+                .unwrap_or_else(|| panic!("{} has no template", entry.analyte_code));
+            let unit = analyte.unit.as_deref().unwrap_or_default();
+            assert!(
+                conversion(&entry.analyte_code).system_of(unit).is_some(),
+                "{} template unit {} is not converted",
+                entry.analyte_code,
+                unit
+            );
+        }
+    }
+
+    #[test]
+    fn convert_goes_both_ways() {
+        let glucose = conversion("GLU");
+
+        assert!(approx(glucose.convert(5.5, "mmol/L", "mg/dL").unwrap(), 99.088));
+        assert!(approx(glucose.convert(99.088, "mg/dL", "mmol/L").unwrap(), 5.5));
+        assert_eq!(glucose.convert(5.5, "mmol/L", "mmol/L"), Some(5.5));
+        assert_eq!(glucose.convert(5.5, " MMOL/l ", "mmol/L"), Some(5.5));
+        assert_eq!(glucose.convert(5.5, "mmol/L", "g/L"), None);
+    }
+
+    #[test]
+    fn convert_rounded_uses_the_target_systems_decimals() {
+        let glucose = conversion("GLU");
+        let creatinine = conversion("CREA");
+
+        assert_eq!(glucose.convert_rounded(5.5, "mmol/L", "mg/dL"), Some(("99".to_string(), 99.0)));
+        assert_eq!(glucose.convert_rounded(99.0, "mg/dL", "mmol/L"), Some(("5.5".to_string(), 5.5)));
+        assert_eq!(creatinine.convert_rounded(88.0, "umol/L", "mg/dL"), Some(("1.00".to_string(), 1.0)));
+    }
+
+    #[test]
+    fn convert_limits_converts_every_limit() {
+        let hemoglobin = conversion("HGB");
+        let limits = Limits {
+            reference_low: Some(120.0),
+            reference_high: Some(160.0),
+            critical_low: Some(70.0),
+            critical_high: None,
+        };
+
+        assert_eq!(
+            hemoglobin.convert_limits(&limits, "g/L", "g/dL"),
+            Some(Limits {
+                reference_low: Some(12.0),
+                reference_high: Some(16.0),
+                critical_low: Some(7.0),
+                critical_high: None,
+            })
+        );
+        assert_eq!(hemoglobin.convert_limits(&limits, "g/L", "mmol/L"), None);
+    }
+
+    #[test]
+    fn invalid_conversion_checks_units_factor_and_decimals() {
+        let valid = UnitConversionInput {
+            si_unit: "mmol/L".to_string(),
+            conventional_unit: "mg/dL".to_string(),
+            factor: 18.016,
+            si_decimals: 1,
+            conventional_decimals: 0,
+        };
+
+        assert_eq!(invalid_conversion(&valid), None);
+        assert!(invalid_conversion(&UnitConversionInput { si_unit: " ".to_string(), ..valid.clone() }).is_some());
+        assert!(invalid_conversion(&UnitConversionInput { conventional_unit: "MMOL/L".to_string(), ..valid.clone() }).is_some());
+        assert!(invalid_conversion(&UnitConversionInput { factor: 0.0, ..valid.clone() }).is_some());
+        assert!(invalid_conversion(&UnitConversionInput { factor: f32::INFINITY, ..valid.clone() }).is_some());
+        assert!(invalid_conversion(&UnitConversionInput { si_decimals: MAX_DECIMALS + 1, ..valid.clone() }).is_some());
+        assert!(invalid_conversion(&UnitConversionInput { conventional_decimals: -1, ..valid }).is_some());
+    }
+
+    #[tokio::test]
+    async fn conversions_record_the_account_that_changed_them() {
+        use crate::handlers::audit_handlers::{get_audit_log, AuditLogQuery};
+        use crate::migrations::Migrator;
+        use sea_orm::Database;
+        use sea_orm_migration::MigratorTrait;
+
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        Migrator::up(&db, None).await.unwrap();
+        let (editor, remover) = (Uuid::new_v4(), Uuid::new_v4());
+        let input = UnitConversionInput {
+            si_unit: "mmol/L".to_string(),
+            conventional_unit: "mg/dL".to_string(),
+            factor: 18.016,
+            si_decimals: 1,
+            conventional_decimals: 0,
+        };
+
+        let UnitConversionOutcome::Saved(saved) = save_unit_conversion(&db, "glu", input, editor).await.unwrap() else {
+            panic!("the conversion should be saved");
+        };
+        assert_eq!(saved.conversion.updated_by, Some(editor));
+
+        assert_eq!(delete_unit_conversion(&db, "GLU", remover).await.unwrap(), Some(0));
+        assert_eq!(delete_unit_conversion(&db, "GLU", remover).await.unwrap(), None);
+        let query = AuditLogQuery { action: Some(CONVERSION_DELETED_ACTION.to_string()), ..Default::default() };
+        let log = get_audit_log(&db, query).await.unwrap();
+        assert_eq!(log.len(), 1);
+        assert_eq!(log[0].actor_id, Some(remover));
+        assert_eq!(log[0].record_id.as_deref(), Some("GLU"));
+        assert_eq!(log[0].details["conversion"]["factor"].as_f64().map(|f| f as f32), Some(18.016));
+    }
+}
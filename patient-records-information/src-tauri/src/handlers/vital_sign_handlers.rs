@@ -0,0 +1,389 @@
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, Condition, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder, Set,
+};
+use crate::handlers::medical_record_handlers::get_medical_record;
+use crate::models::patient_tb::Entity as PatientEntity;
+use crate::models::vital_sign_tb::{
+    ActiveModel as VitalSignActiveModel, Column as VitalSignColumn, Entity as VitalSignEntity,
+    Model as VitalSignModel,
+};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::ops::RangeInclusive;
+use uuid::Uuid;
+
+// Values outside these are typing mistakes or wrong units (°F, lb, inches),
+// not readings.
+const SYSTOLIC_BP: RangeInclusive<i32> = 50..=300;
+const DIASTOLIC_BP: RangeInclusive<i32> = 20..=200;
+const PULSE_RATE: RangeInclusive<i32> = 20..=250;
+const TEMPERATURE_C: RangeInclusive<f32> = 30.0..=45.0;
+const WEIGHT_KG: RangeInclusive<f32> = 0.3..=500.0;
+const HEIGHT_CM: RangeInclusive<f32> = 20.0..=250.0;
+/// Clock difference tolerated between the workstation and the server.
+const FUTURE_TOLERANCE_MINUTES: i64 = 5;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordVitalsRequest {
+    /// The visit the vitals were taken at; must be the patient's.
+    pub medical_id: Option<Uuid>,
+    pub systolic_bp: Option<i32>,
+    pub diastolic_bp: Option<i32>,
+    pub pulse_rate: Option<i32>,
+    pub temperature_c: Option<f32>,
+    pub weight_kg: Option<f32>,
+    pub height_cm: Option<f32>,
+    /// Defaults to now.
+    pub measured_at: Option<DateTime<Utc>>,
+    /// Staff member who took the vitals.
+    pub recorded_by: String,
+}
+
+#[derive(Debug, Clone)]
+pub enum VitalsOutcome {
+    Recorded(VitalSignModel),
+    PatientNotFound,
+    /// `medical_id` doesn't exist or isn't the patient's.
+    RecordNotFound,
+    Invalid(String),
+}
+
+/// A charted measurement; blood pressure charts systolic and diastolic together.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VitalType {
+    Bp,
+    Pulse,
+    Temperature,
+    Weight,
+    Height,
+    Bmi,
+}
+
+impl VitalType {
+    pub fn unit(self) -> &'static str {
+        match self {
+            VitalType::Bp => "mmHg",
+            VitalType::Pulse => "/min",
+            VitalType::Temperature => "°C",
+            VitalType::Weight => "kg",
+            VitalType::Height => "cm",
+            VitalType::Bmi => "kg/m²",
+        }
+    }
+}
+
+/// Readings measured `from` to `to` inclusive (UTC days); both optional.
+/// With `type`, only readings that include that measurement, as a series.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct VitalsQuery {
+    #[serde(rename = "type")]
+    pub vital_type: Option<VitalType>,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VitalPoint {
+    pub measured_at: DateTime<Utc>,
+    pub medical_id: Option<Uuid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub systolic: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub diastolic: Option<i32>,
+}
+
+/// One measurement over time, oldest first, for charting.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VitalSeries {
+    #[serde(rename = "type")]
+    pub vital_type: VitalType,
+    pub unit: String,
+    pub points: Vec<VitalPoint>,
+}
+
+fn out_of_range<T: PartialOrd + std::fmt::Display>(
+    name: &str,
+    value: Option<T>,
+    range: &RangeInclusive<T>,
+    unit: &str,
+) -> Option<String> {
+    value.filter(|v| !range.contains(v)).map(|v| {
+        format!("{} {} is outside {}–{} {}", name, v, range.start(), range.end(), unit)
+    })
+}
+
+/// Why the readings can't be right, if they can't.
+fn implausible(request: &RecordVitalsRequest, now: DateTime<Utc>) -> Option<String> {
+    let measurements = [
+        request.systolic_bp.is_some(),
+        request.diastolic_bp.is_some(),
+        request.pulse_rate.is_some(),
+        request.temperature_c.is_some(),
+        request.weight_kg.is_some(),
+        request.height_cm.is_some(),
+    ];
+    if !measurements.contains(&true) {
+        return Some("At least one measurement is required".to_string());
+    }
+    match (request.systolic_bp, request.diastolic_bp) {
+        (Some(systolic), Some(diastolic)) if diastolic >= systolic => {
+            return Some("diastolic_bp must be lower than systolic_bp".to_string());
+        }
+        (Some(_), None) | (None, Some(_)) => {
+            return Some("systolic_bp and diastolic_bp go together".to_string());
+        }
+        _ => {}
+    }
+    if request.measured_at.is_some_and(|at| at > now + Duration::minutes(FUTURE_TOLERANCE_MINUTES)) {
+        return Some("measured_at is in the future".to_string());
+    }
+    out_of_range("systolic_bp", request.systolic_bp, &SYSTOLIC_BP, "mmHg")
+        .or_else(|| out_of_range("diastolic_bp", request.diastolic_bp, &DIASTOLIC_BP, "mmHg"))
+        .or_else(|| out_of_range("pulse_rate", request.pulse_rate, &PULSE_RATE, "/min"))
+        .or_else(|| out_of_range("temperature_c", request.temperature_c, &TEMPERATURE_C, "°C"))
+        .or_else(|| out_of_range("weight_kg", request.weight_kg, &WEIGHT_KG, "kg"))
+        .or_else(|| out_of_range("height_cm", request.height_cm, &HEIGHT_CM, "cm"))
+}
+
+/// Body mass index rounded to one decimal.
+pub fn body_mass_index(weight_kg: f32, height_cm: f32) -> f32 {
+    let height_m = height_cm / 100.0;
+    (weight_kg / (height_m * height_m) * 10.0).round() / 10.0
+}
+
+/// Saves a set of vitals after checking they are plausible. The BMI is
+/// computed when a weight is given, with the height taken at the same time or
+/// else the patient's latest height measured before.
+pub async fn record_vitals(
+    db: &DatabaseConnection,
+    patient_id: Uuid,
+    request: RecordVitalsRequest,
+) -> Result<VitalsOutcome, sea_orm::DbErr> {
+    let now = Utc::now();
+    if let Some(message) = implausible(&request, now) {
+        return Ok(VitalsOutcome::Invalid(message));
+    }
+    if PatientEntity::find_by_id(patient_id).one(db).await?.is_none() {
+        return Ok(VitalsOutcome::PatientNotFound);
+    }
+    if let Some(medical_id) = request.medical_id {
+        if get_medical_record(db, patient_id, medical_id).await?.is_none() {
+            return Ok(VitalsOutcome::RecordNotFound);
+        }
+    }
+
+    let measured_at = request.measured_at.unwrap_or(now);
+    let height_cm = match request.height_cm {
+        Some(height) => Some(height),
+        None if request.weight_kg.is_some() => VitalSignEntity::find()
+            .filter(VitalSignColumn::PatientId.eq(patient_id))
+            .filter(VitalSignColumn::HeightCm.is_not_null())
+            .filter(VitalSignColumn::MeasuredAt.lte(measured_at))
+            .order_by_desc(VitalSignColumn::MeasuredAt)
+            .one(db)
+            .await?
+            .and_then(|vitals| vitals.height_cm),
+        None => None,
+    };
+    let bmi = request.weight_kg.zip(height_cm).map(|(weight, height)| body_mass_index(weight, height));
+
+    let vitals = VitalSignActiveModel {
+        vital_sign_id: Set(Uuid::new_v4()),
+        patient_id: Set(patient_id),
+        medical_id: Set(request.medical_id),
+        systolic_bp: Set(request.systolic_bp),
+        diastolic_bp: Set(request.diastolic_bp),
+        pulse_rate: Set(request.pulse_rate),
+        temperature_c: Set(request.temperature_c),
+        weight_kg: Set(request.weight_kg),
+        height_cm: Set(request.height_cm),
+        bmi: Set(bmi),
+        measured_at: Set(measured_at),
+        recorded_by: Set(request.recorded_by),
+        created_at: Set(now),
+    };
+    Ok(VitalsOutcome::Recorded(vitals.insert(db).await?))
+}
+
+/// A patient's vitals, oldest first. `None` when the patient doesn't exist.
+pub async fn get_patient_vitals(
+    db: &DatabaseConnection,
+    patient_id: Uuid,
+    query: &VitalsQuery,
+) -> Result<Option<Vec<VitalSignModel>>, sea_orm::DbErr> {
+    if PatientEntity::find_by_id(patient_id).one(db).await?.is_none() {
+        return Ok(None);
+    }
+    let mut condition = Condition::all().add(VitalSignColumn::PatientId.eq(patient_id));
+    if let Some(from) = query.from {
+        condition = condition.add(VitalSignColumn::MeasuredAt.gte(from.and_time(Default::default()).and_utc()));
+    }
+    if let Some(to) = query.to.and_then(|to| to.succ_opt()) {
+        condition = condition.add(VitalSignColumn::MeasuredAt.lt(to.and_time(Default::default()).and_utc()));
+    }
+    condition = match query.vital_type {
+        Some(VitalType::Bp) => condition.add(VitalSignColumn::SystolicBp.is_not_null()),
+        Some(VitalType::Pulse) => condition.add(VitalSignColumn::PulseRate.is_not_null()),
+        Some(VitalType::Temperature) => condition.add(VitalSignColumn::TemperatureC.is_not_null()),
+        Some(VitalType::Weight) => condition.add(VitalSignColumn::WeightKg.is_not_null()),
+        Some(VitalType::Height) => condition.add(VitalSignColumn::HeightCm.is_not_null()),
+        Some(VitalType::Bmi) => condition.add(VitalSignColumn::Bmi.is_not_null()),
+        None => condition,
+    };
+    VitalSignEntity::find()
+        .filter(condition)
+        .order_by_asc(VitalSignColumn::MeasuredAt)
+        .order_by_asc(VitalSignColumn::VitalSignId)
+        .all(db)
+        .await
+        .map(Some)
+}
+
+/// One measurement of a patient over time. `None` when the patient doesn't exist.
+pub async fn get_vital_series(
+    db: &DatabaseConnection,
+    patient_id: Uuid,
+    vital_type: VitalType,
+    query: &VitalsQuery,
+) -> Result<Option<VitalSeries>, sea_orm::DbErr> {
+    let query = VitalsQuery { vital_type: Some(vital_type), ..query.clone() };
+    let Some(vitals) = get_patient_vitals(db, patient_id, &query).await? else {
+        return Ok(None);
+    };
+    let points = vitals
+        .into_iter()
+        .map(|vitals| {
+            let value = match vital_type {
+                VitalType::Bp => None,
+                VitalType::Pulse => vitals.pulse_rate.map(|pulse| pulse as f32),
+                VitalType::Temperature => vitals.temperature_c,
+                VitalType::Weight => vitals.weight_kg,
+                VitalType::Height => vitals.height_cm,
+                VitalType::Bmi => vitals.bmi,
+            };
+            let is_bp = vital_type == VitalType::Bp;
+            VitalPoint {
+                measured_at: vitals.measured_at,
+                medical_id: vitals.medical_id,
+                value,
+                systolic: vitals.systolic_bp.filter(|_| is_bp),
+                diastolic: vitals.diastolic_bp.filter(|_| is_bp),
+            }
+        })
+        .collect();
+    Ok(Some(VitalSeries { vital_type, unit: vital_type.unit().to_string(), points }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vitals() -> RecordVitalsRequest {
+        RecordVitalsRequest {
+            medical_id: None,
+            systolic_bp: Some(120),
+            diastolic_bp: Some(80),
+            pulse_rate: Some(72),
+            temperature_c: Some(36.8),
+            weight_kg: Some(65.0),
+            height_cm: Some(165.0),
+            measured_at: None,
+            recorded_by: "Nurse Joy".to_string(),
+        }
+    }
+
+    fn now() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2026-10-19T08:00:00Z").unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn body_mass_index_is_rounded_to_one_decimal() {
+        assert_eq!(body_mass_index(65.0, 165.0), 23.9);
+        assert_eq!(body_mass_index(70.0, 175.0), 22.9);
+        assert_eq!(body_mass_index(3.2, 50.0), 12.8);
+    }
+
+    #[test]
+    fn plausible_vitals_pass() {
+        assert_eq!(implausible(&vitals(), now()), None);
+        assert_eq!(
+            implausible(
+                &RecordVitalsRequest {
+                    systolic_bp: None,
+                    diastolic_bp: None,
+                    pulse_rate: None,
+                    temperature_c: None,
+                    height_cm: None,
+                    measured_at: Some(now() + Duration::minutes(FUTURE_TOLERANCE_MINUTES)),
+                    ..vitals()
+                },
+                now()
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn implausible_requires_a_measurement() {
+        let empty = RecordVitalsRequest {
+            systolic_bp: None,
+            diastolic_bp: None,
+            pulse_rate: None,
+            temperature_c: None,
+            weight_kg: None,
+            height_cm: None,
+            ..vitals()
+        };
+
+        assert_eq!(implausible(&empty, now()).as_deref(), Some("At least one measurement is required"));
+    }
+
+    #[test]
+    fn implausible_checks_blood_pressure_as_a_pair() {
+        let inverted = RecordVitalsRequest { systolic_bp: Some(80), diastolic_bp: Some(80), ..vitals() };
+        let systolic_only = RecordVitalsRequest { diastolic_bp: None, ..vitals() };
+        let diastolic_only = RecordVitalsRequest { systolic_bp: None, ..vitals() };
+
+        assert_eq!(
+            implausible(&inverted, now()).as_deref(),
+            Some("diastolic_bp must be lower than systolic_bp")
+        );
+        assert_eq!(
+            implausible(&systolic_only, now()).as_deref(),
+            Some("systolic_bp and diastolic_bp go together")
+        );
+        assert_eq!(
+            implausible(&diastolic_only, now()).as_deref(),
+            Some("systolic_bp and diastolic_bp go together")
+        );
+    }
+
+    #[test]
+    fn implausible_rejects_future_readings() {
+        let later = RecordVitalsRequest {
+            measured_at: Some(now() + Duration::minutes(FUTURE_TOLERANCE_MINUTES + 1)),
+            ..vitals()
+        };
+
+        assert_eq!(implausible(&later, now()).as_deref(), Some("measured_at is in the future"));
+    }
+
+    #[test]
+    fn implausible_rejects_readings_in_the_wrong_unit() {
+        let fahrenheit = RecordVitalsRequest { temperature_c: Some(98.6), ..vitals() };
+        let pounds = RecordVitalsRequest { weight_kg: Some(650.0), ..vitals() };
+        let inches = RecordVitalsRequest { height_cm: Some(5.5), ..vitals() };
+        let pulse = RecordVitalsRequest { pulse_rate: Some(300), ..vitals() };
+
+        assert_eq!(
+            implausible(&fahrenheit, now()).as_deref(),
+            Some("temperature_c 98.6 is outside 30–45 °C")
+        );
+        assert_eq!(implausible(&pounds, now()).as_deref(), Some("weight_kg 650 is outside 0.3–500 kg"));
+        assert_eq!(implausible(&inches, now()).as_deref(), Some("height_cm 5.5 is outside 20–250 cm"));
+        assert_eq!(implausible(&pulse, now()).as_deref(), Some("pulse_rate 300 is outside 20–250 /min"));
+    }
+}
@@ -0,0 +1,110 @@
+use chrono::Utc;
+use uuid::Uuid;
+
+use crate::hl7::message::{format_datetime, Delimiters, Message};
+
+/// Sending application/facility we put in `MSH-3`/`MSH-4` of our replies.
+pub const RECEIVING_APPLICATION: &str = "PATIENT_RECORDS";
+pub const RECEIVING_FACILITY: &str = "CLINIC";
+
+/// `MSA-1` acknowledgment codes (original acknowledgment mode).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AckCode {
+    /// Message accepted and processed.
+    Accept,
+    /// Message understood but could not be processed (NAK, sender may retry after a fix).
+    Error,
+    /// Message rejected: unsupported type or unparseable (NAK).
+    Reject,
+}
+
+impl AckCode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AckCode::Accept => "AA",
+            AckCode::Error => "AE",
+            AckCode::Reject => "AR",
+        }
+    }
+}
+
+/// Builds the `ACK` reply for `original`. When the incoming message could not
+/// be parsed at all, pass `None` and a generic NAK with default delimiters is
+/// produced.
+pub fn build_ack(original: Option<&Message>, code: AckCode, text: &str) -> String {
+    let delimiters = original.map(|m| m.delimiters).unwrap_or_default();
+    let f = delimiters.field;
+    let esc = |value: &str| Message::escape(&delimiters, value);
+
+    let (sending_app, sending_facility, trigger, control_id, processing_id, version) = match original {
+        Some(message) => {
+            let msh = message.msh();
+            (
+                msh.field(3).to_string(),
+                msh.field(4).to_string(),
+                message.trigger_event(),
+                message.control_id(),
+                non_empty(msh.field(11), "P"),
+                non_empty(msh.field(12), "2.5"),
+            )
+        }
+        None => (
+            String::new(),
+            String::new(),
+            String::new(),
+            String::new(),
+            "P".to_string(),
+            "2.5".to_string(),
+        ),
+    };
+
+    let message_type = if trigger.is_empty() {
+        "ACK".to_string()
+    } else {
+        format!("ACK{c}{}{c}ACK", esc(&trigger), c = delimiters.component)
+    };
+
+    let mut segments = vec![
+        [
+            "MSH".to_string(),
+            delimiters.encoding_characters(),
+            RECEIVING_APPLICATION.to_string(),
+            RECEIVING_FACILITY.to_string(),
+            sending_app,
+            sending_facility,
+            format_datetime(Utc::now()),
+            String::new(),
+            message_type,
+            Uuid::new_v4().simple().to_string(),
+            processing_id,
+            version,
+        ]
+        .join(&f.to_string()),
+        ["MSA", code.as_str(), &esc(&control_id), &esc(text)].join(&f.to_string()),
+    ];
+
+    if code != AckCode::Accept {
+        segments.push(err_segment(&delimiters, code, text));
+    }
+
+    segments.join("\r") + "\r"
+}
+
+fn err_segment(delimiters: &Delimiters, code: AckCode, text: &str) -> String {
+    let c = delimiters.component;
+    // HL7 table 0357: 200 = unsupported message type, 207 = application internal error.
+    let condition = match code {
+        AckCode::Reject => format!("200{c}Unsupported message type{c}HL70357"),
+        _ => format!("207{c}Application internal error{c}HL70357"),
+    };
+    let f = delimiters.field.to_string();
+    ["ERR", "", "", &condition, "E", "", "", "", &Message::escape(delimiters, text)].join(&f)
+}
+
+fn non_empty(value: &str, default: &str) -> String {
+    if value.is_empty() {
+        default.to_string()
+    } else {
+        value.to_string()
+    }
+}
@@ -0,0 +1,387 @@
+use sea_orm::{ConnectionTrait, DatabaseConnection, DbErr, TransactionTrait};
+use std::fmt;
+use uuid::Uuid;
+
+use crate::handlers::lab_result_handlers::{
+    create_lab_result, delete_lab_results_for_message, CreateLabResultRequest,
+};
+use crate::handlers::{
+    create_patient, find_patient_by_name_and_birth_date, get_patient, update_patient,
+    CreatePatientRequest, PatientAddress, PatientNumbering, UpdatePatientRequest,
+};
+use crate::hl7::ack::{build_ack, AckCode};
+use crate::hl7::message::{parse_date, parse_datetime, Message, Segment};
+use crate::models::patient_tb::{Model as PatientModel, Sex};
+
+#[derive(Debug)]
+pub enum IngestError {
+    /// Message type/trigger we don't handle; answered with `AR`.
+    Unsupported(String),
+    /// Required data missing or malformed; answered with `AE`.
+    Invalid(String),
+    Database(DbErr),
+}
+
+impl IngestError {
+    pub fn ack_code(&self) -> AckCode {
+        match self {
+            IngestError::Unsupported(_) => AckCode::Reject,
+            IngestError::Invalid(_) | IngestError::Database(_) => AckCode::Error,
+        }
+    }
+}
+
+impl fmt::Display for IngestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IngestError::Unsupported(msg) | IngestError::Invalid(msg) => write!(f, "{}", msg),
+            IngestError::Database(e) => write!(f, "Database error: {}", e),
+        }
+    }
+}
+
+impl From<DbErr> for IngestError {
+    fn from(e: DbErr) -> Self {
+        IngestError::Database(e)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct IngestOutcome {
+    pub patient: PatientModel,
+    pub patient_created: bool,
+    pub results_stored: usize,
+}
+
+/// Patient demographics carried in a `PID` segment.
+#[derive(Debug, Clone)]
+struct PidDemographics {
+    identifiers: Vec<String>,
+    last_name: String,
+    first_name: String,
+    middle_name: Option<String>,
+    birth_date: chrono::NaiveDate,
+    sex: Option<Sex>,
+    mobile_number: Option<String>,
+    residential_address: Option<String>,
+}
+
+impl PidDemographics {
+    fn update_request(self) -> UpdatePatientRequest {
+        UpdatePatientRequest {
+            first_name: Some(self.first_name),
+            last_name: Some(self.last_name),
+            middle_name: self.middle_name,
+            birth_date: Some(self.birth_date),
+            sex: self.sex,
+            csd_id_or_pwd_id: None,
+            mobile_number: self.mobile_number,
+            residential_address: self.residential_address,
+            address: None,
+        }
+    }
+}
+
+/// Parses and processes one raw message and returns the ACK/NAK to send back.
+/// `numbering` applies to patients the message creates. The message is
+/// committed locally before it is acknowledged; the cloud copy, when a cloud
+/// database is given, is best effort.
+pub async fn handle_raw_message(
+    db: &DatabaseConnection,
+    cloud_db: Option<&DatabaseConnection>,
+    raw: &str,
+    numbering: PatientNumbering,
+) -> String {
+    let message = match Message::parse(raw) {
+        Ok(message) => message,
+        Err(e) => {
+            log::warn!("⚠️ Rejected unparseable HL7 message: {}", e);
+            return build_ack(None, AckCode::Reject, &e.to_string());
+        }
+    };
+
+    match ingest(db, &message, numbering).await {
+        Ok(outcome) => {
+            log::info!(
+                "📥 HL7 {}^{} {} processed: patient {} ({}), {} result(s) stored",
+                message.message_type(),
+                message.trigger_event(),
+                message.control_id(),
+                outcome.patient.patient_id,
+                if outcome.patient_created { "created" } else { "updated" },
+                outcome.results_stored
+            );
+            if let Some(cloud_db) = cloud_db {
+                if let Err(e) = copy_to_cloud(cloud_db, &message, &outcome.patient).await {
+                    log::warn!("⚠️ HL7 {} not copied to the cloud: {}", message.control_id(), e);
+                }
+            }
+            build_ack(Some(&message), AckCode::Accept, "")
+        }
+        Err(e) => {
+            log::warn!(
+                "⚠️ HL7 {}^{} {} not processed: {}",
+                message.message_type(),
+                message.trigger_event(),
+                message.control_id(),
+                e
+            );
+            build_ack(Some(&message), e.ack_code(), &e.to_string())
+        }
+    }
+}
+
+/// Applies a parsed message in one transaction: `ADT^A04`/`ADT^A08` upsert
+/// the patient, `ORU^R01` upserts the patient and stores every `OBX` as a lab
+/// result. Nothing is kept unless the whole message is.
+pub async fn ingest(
+    db: &DatabaseConnection,
+    message: &Message,
+    numbering: PatientNumbering,
+) -> Result<IngestOutcome, IngestError> {
+    let stores_results = match (message.message_type().as_str(), message.trigger_event().as_str()) {
+        ("ADT", "A04") | ("ADT", "A08") => false,
+        ("ORU", "R01") => true,
+        (message_type, trigger) => {
+            return Err(IngestError::Unsupported(format!(
+                "Unsupported message type {}^{}",
+                message_type, trigger
+            )))
+        }
+    };
+
+    let txn = db.begin().await?;
+    let (patient, created) = upsert_patient(&txn, message, numbering).await?;
+    let results_stored = if stores_results {
+        store_results(&txn, message, patient.patient_id).await?
+    } else {
+        0
+    };
+    txn.commit().await?;
+
+    Ok(IngestOutcome {
+        patient,
+        patient_created: created,
+        results_stored,
+    })
+}
+
+/// Writes an ingested message to the cloud database in one transaction, under
+/// the local patient's id: an existing cloud copy gets the message's
+/// demographics, a missing one is copied from `patient` with its number, and
+/// results replace the cloud copies of an earlier delivery like they do locally.
+async fn copy_to_cloud(cloud_db: &DatabaseConnection, message: &Message, patient: &PatientModel) -> Result<(), IngestError> {
+    let demographics = parse_pid(message)?;
+
+    let txn = cloud_db.begin().await?;
+    if get_patient(&txn, patient.patient_id).await?.is_some() {
+        update_patient(&txn, patient.patient_id, demographics.update_request()).await?;
+    } else {
+        let create = CreatePatientRequest {
+            first_name: patient.first_name.clone(),
+            last_name: patient.last_name.clone(),
+            middle_name: patient.middle_name.clone(),
+            birth_date: patient.birth_date,
+            sex: patient.sex,
+            csd_id_or_pwd_id: patient.csd_id_or_pwd_id.clone(),
+            mobile_number: patient.mobile_number.clone(),
+            residential_address: patient.residential_address.clone(),
+            address: Some(PatientAddress::from(patient)),
+            // Never number a copy separately: it would differ from the local number
+            numbering: patient
+                .patient_number
+                .clone()
+                .map_or(PatientNumbering::Deferred, PatientNumbering::Assigned),
+        };
+        create_patient(&txn, patient.patient_id, create).await?;
+    }
+    if message.message_type() == "ORU" {
+        store_results(&txn, message, patient.patient_id).await?;
+    }
+    txn.commit().await?;
+    Ok(())
+}
+
+fn non_empty(value: String) -> Option<String> {
+    // `""` is HL7's explicit null; we treat it like an absent value.
+    let value = value.trim().to_string();
+    if value.is_empty() || value == "\"\"" {
+        None
+    } else {
+        Some(value)
+    }
+}
+
+fn parse_pid(message: &Message) -> Result<PidDemographics, IngestError> {
+    let pid = message
+        .segment("PID")
+        .ok_or_else(|| IngestError::Invalid("PID segment is required".to_string()))?;
+
+    let identifiers = message
+        .repetitions(pid.field(3))
+        .into_iter()
+        .filter_map(|cx| non_empty(message.component(cx, 1)))
+        .collect();
+
+    let name = message.repetitions(pid.field(5)).into_iter().next().unwrap_or("");
+    let last_name = non_empty(message.component(name, 1))
+        .ok_or_else(|| IngestError::Invalid("PID-5 family name is required".to_string()))?;
+    let first_name = non_empty(message.component(name, 2))
+        .ok_or_else(|| IngestError::Invalid("PID-5 given name is required".to_string()))?;
+    let middle_name = non_empty(message.component(name, 3));
+
+    let birth_date = parse_date(pid.field(7))
+        .ok_or_else(|| IngestError::Invalid(format!("PID-7 birth date '{}' is not a valid date", pid.field(7))))?;
+
+    // HL7 table 0001; other codes (ambiguous, unknown, ...) leave it unrecorded.
+    let sex = match pid.field(8).trim() {
+        "M" => Some(Sex::Male),
+        "F" => Some(Sex::Female),
+        _ => None,
+    };
+
+    let mobile_number = message
+        .repetitions(pid.field(13))
+        .into_iter()
+        .find_map(|xtn| non_empty(message.component(xtn, 1)).or_else(|| non_empty(message.component(xtn, 12))));
+
+    let residential_address = message.repetitions(pid.field(11)).into_iter().next().and_then(|xad| {
+        let parts: Vec<String> = [1, 2, 3, 4, 5]
+            .iter()
+            .filter_map(|i| non_empty(message.component(xad, *i)))
+            .collect();
+        if parts.is_empty() {
+            None
+        } else {
+            Some(parts.join(", "))
+        }
+    });
+
+    Ok(PidDemographics {
+        identifiers,
+        last_name,
+        first_name,
+        middle_name,
+        birth_date,
+        sex,
+        mobile_number,
+        residential_address,
+    })
+}
+
+/// Finds the patient by one of our UUIDs in `PID-3`, then by name and birth
+/// date; updates the demographics when found, creates the patient otherwise.
+async fn upsert_patient<C: ConnectionTrait + TransactionTrait>(
+    db: &C,
+    message: &Message,
+    numbering: PatientNumbering,
+) -> Result<(PatientModel, bool), IngestError> {
+    let pid = parse_pid(message)?;
+
+    let mut existing = None;
+    for identifier in &pid.identifiers {
+        if let Ok(patient_id) = Uuid::parse_str(identifier) {
+            existing = get_patient(db, patient_id).await?;
+            if existing.is_some() {
+                break;
+            }
+        }
+    }
+    if existing.is_none() {
+        existing = find_patient_by_name_and_birth_date(db, &pid.last_name, &pid.first_name, pid.birth_date).await?;
+    }
+
+    match existing {
+        Some(patient) => {
+            let updated = update_patient(db, patient.patient_id, pid.update_request())
+                .await?
+                .ok_or_else(|| IngestError::Invalid(format!("Patient {} disappeared during update", patient.patient_id)))?;
+            Ok((updated, false))
+        }
+        None => {
+            let create = CreatePatientRequest {
+                first_name: pid.first_name,
+                last_name: pid.last_name,
+                middle_name: pid.middle_name,
+                birth_date: pid.birth_date,
+                sex: pid.sex,
+                csd_id_or_pwd_id: None,
+                mobile_number: pid.mobile_number,
+                residential_address: pid.residential_address,
+                address: None,
+                numbering,
+            };
+            Ok((create_patient(db, Uuid::new_v4(), create).await?, true))
+        }
+    }
+}
+
+/// Stores each `OBX` under the `OBR` that precedes it. Results from an earlier
+/// delivery of the same message (same sender and `MSH-10`) are replaced.
+async fn store_results<C: ConnectionTrait>(db: &C, message: &Message, patient_id: Uuid) -> Result<usize, IngestError> {
+    let msh = message.msh();
+    let sender = non_empty(message.component(msh.field(4), 1))
+        .or_else(|| non_empty(message.component(msh.field(3), 1)))
+        .unwrap_or_else(|| "unknown".to_string());
+    let source = format!("HL7:{}", sender);
+    let control_id = non_empty(message.control_id());
+
+    if let Some(control_id) = &control_id {
+        delete_lab_results_for_message(db, &source, control_id).await?;
+    }
+
+    let mut current_obr: Option<&Segment> = None;
+    let mut stored = 0;
+    for segment in &message.segments {
+        match segment.name.as_str() {
+            "OBR" => current_obr = Some(segment),
+            "OBX" => {
+                let analyte = segment.field(3);
+                let Some(analyte_code) = non_empty(message.component(analyte, 1)) else {
+                    return Err(IngestError::Invalid("OBX-3 observation identifier is required".to_string()));
+                };
+
+                let (order_number, test_code, test_name, obr_time) = match current_obr {
+                    Some(obr) => (
+                        non_empty(message.component(obr.field(3), 1))
+                            .or_else(|| non_empty(message.component(obr.field(2), 1))),
+                        non_empty(message.component(obr.field(4), 1)),
+                        non_empty(message.component(obr.field(4), 2)),
+                        parse_datetime(obr.field(7)),
+                    ),
+                    None => (None, None, None, None),
+                };
+
+                let value = message
+                    .repetitions(segment.field(5))
+                    .into_iter()
+                    .map(|rep| message.unescape(rep))
+                    .collect::<Vec<_>>()
+                    .join("\n");
+
+                let request = CreateLabResultRequest {
+                    patient_id,
+                    order_number,
+                    test_code,
+                    test_name,
+                    analyte_code,
+                    analyte_name: non_empty(message.component(analyte, 2)),
+                    value_type: non_empty(segment.field(2).to_string()),
+                    value: non_empty(value),
+                    unit: non_empty(message.component(segment.field(6), 1)),
+                    reference_range: non_empty(message.unescape(segment.field(7))),
+                    abnormal_flag: non_empty(message.component(segment.field(8), 1)),
+                    result_status: non_empty(segment.field(11).to_string()),
+                    observed_at: parse_datetime(segment.field(14)).or(obr_time),
+                    source: source.clone(),
+                    message_control_id: control_id.clone(),
+                };
+                create_lab_result(db, request).await?;
+                stored += 1;
+            }
+            _ => {}
+        }
+    }
+
+    Ok(stored)
+}
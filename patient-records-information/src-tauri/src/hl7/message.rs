@@ -0,0 +1,341 @@
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use std::fmt;
+
+/// Delimiters declared in `MSH-1`/`MSH-2`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Delimiters {
+    pub field: char,
+    pub component: char,
+    pub repetition: char,
+    pub escape: char,
+    pub subcomponent: char,
+}
+
+impl Default for Delimiters {
+    fn default() -> Self {
+        Self {
+            field: '|',
+            component: '^',
+            repetition: '~',
+            escape: '\\',
+            subcomponent: '&',
+        }
+    }
+}
+
+impl Delimiters {
+    /// `MSH-2` as it appears on the wire, e.g. `^~\&`.
+    pub fn encoding_characters(&self) -> String {
+        [self.component, self.repetition, self.escape, self.subcomponent]
+            .iter()
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError(pub String);
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "HL7 parse error: {}", self.0)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// One segment with its raw (still escaped) fields.
+///
+/// Fields are numbered as in the HL7 specification: `field(1)` is the first
+/// field after the segment name. For `MSH`, `field(1)` is the field separator
+/// and `field(2)` the encoding characters, so `MSH-9` is `field(9)` as well.
+#[derive(Debug, Clone)]
+pub struct Segment {
+    pub name: String,
+    fields: Vec<String>,
+}
+
+impl Segment {
+    /// Raw field value, `""` when absent.
+    pub fn field(&self, index: usize) -> &str {
+        self.fields.get(index).map(String::as_str).unwrap_or("")
+    }
+}
+
+/// A parsed HL7 v2 message (ER7 "pipe" encoding).
+#[derive(Debug, Clone)]
+pub struct Message {
+    pub delimiters: Delimiters,
+    pub segments: Vec<Segment>,
+}
+
+impl Message {
+    /// Parses a message whose segments are separated by `\r` (bare `\n` and
+    /// `\r\n` are accepted too). The first segment must be `MSH`.
+    pub fn parse(raw: &str) -> Result<Self, ParseError> {
+        let raw = raw.trim_start_matches(['\u{feff}', '\r', '\n']);
+        if !raw.starts_with("MSH") || raw.len() < 8 {
+            return Err(ParseError("message must start with an MSH segment".to_string()));
+        }
+
+        let mut chars = raw[3..].chars();
+        let field = chars.next().unwrap_or('|');
+        let encoding: Vec<char> = chars.take(4).collect();
+        if encoding.len() < 4 {
+            return Err(ParseError("MSH-2 must declare four encoding characters".to_string()));
+        }
+        let delimiters = Delimiters {
+            field,
+            component: encoding[0],
+            repetition: encoding[1],
+            escape: encoding[2],
+            subcomponent: encoding[3],
+        };
+
+        let segments = raw
+            .split(['\r', '\n'])
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                let mut parts: Vec<String> = line.split(delimiters.field).map(str::to_string).collect();
+                let name = parts.remove(0);
+                let fields = if name == "MSH" {
+                    // MSH-1 is the separator itself, so the first split part is MSH-2.
+                    let mut fields = vec![String::new(), delimiters.field.to_string()];
+                    fields.extend(parts);
+                    fields
+                } else {
+                    let mut fields = vec![String::new()];
+                    fields.extend(parts);
+                    fields
+                };
+                Segment { name, fields }
+            })
+            .collect();
+
+        Ok(Self { delimiters, segments })
+    }
+
+    pub fn segment(&self, name: &str) -> Option<&Segment> {
+        self.segments.iter().find(|s| s.name == name)
+    }
+
+    pub fn msh(&self) -> &Segment {
+        // `parse` guarantees the first segment is MSH.
+        &self.segments[0]
+    }
+
+    /// `MSH-9.1`, e.g. `ADT`.
+    pub fn message_type(&self) -> String {
+        self.component(self.msh().field(9), 1)
+    }
+
+    /// `MSH-9.2`, e.g. `A04`.
+    pub fn trigger_event(&self) -> String {
+        self.component(self.msh().field(9), 2)
+    }
+
+    /// `MSH-10`.
+    pub fn control_id(&self) -> String {
+        self.unescape(self.msh().field(10))
+    }
+
+    /// Splits a raw field into its repetitions.
+    pub fn repetitions<'a>(&self, field: &'a str) -> Vec<&'a str> {
+        field.split(self.delimiters.repetition).collect()
+    }
+
+    /// Unescaped component (1-based) of a raw field or repetition, `""` when absent.
+    pub fn component(&self, field: &str, index: usize) -> String {
+        field
+            .split(self.delimiters.component)
+            .nth(index.saturating_sub(1))
+            .map(|c| self.unescape(c.split(self.delimiters.subcomponent).next().unwrap_or("")))
+            .unwrap_or_default()
+    }
+
+    /// Decodes the standard `\F\ \S\ \T\ \R\ \E\` escape sequences; other
+    /// escapes (formatting, hex) are dropped.
+    pub fn unescape(&self, value: &str) -> String {
+        let esc = self.delimiters.escape;
+        if !value.contains(esc) {
+            return value.to_string();
+        }
+
+        let mut out = String::with_capacity(value.len());
+        let mut rest = value;
+        while let Some(start) = rest.find(esc) {
+            out.push_str(&rest[..start]);
+            let after = &rest[start + esc.len_utf8()..];
+            match after.find(esc) {
+                Some(end) => {
+                    match &after[..end] {
+                        "F" => out.push(self.delimiters.field),
+                        "S" => out.push(self.delimiters.component),
+                        "T" => out.push(self.delimiters.subcomponent),
+                        "R" => out.push(self.delimiters.repetition),
+                        "E" => out.push(esc),
+                        _ => {}
+                    }
+                    rest = &after[end + esc.len_utf8()..];
+                }
+                None => {
+                    out.push_str(&rest[start..]);
+                    rest = "";
+                }
+            }
+        }
+        out.push_str(rest);
+        out
+    }
+
+    /// Escapes delimiter characters so `value` can be embedded in a field.
+    pub fn escape(delimiters: &Delimiters, value: &str) -> String {
+        let mut out = String::with_capacity(value.len());
+        for c in value.chars() {
+            let code = match c {
+                c if c == delimiters.escape => Some('E'),
+                c if c == delimiters.field => Some('F'),
+                c if c == delimiters.component => Some('S'),
+                c if c == delimiters.subcomponent => Some('T'),
+                c if c == delimiters.repetition => Some('R'),
+                _ => None,
+            };
+            match code {
+                Some(code) => {
+                    out.push(delimiters.escape);
+                    out.push(code);
+                    out.push(delimiters.escape);
+                }
+                None if c == '\r' || c == '\n' => out.push(' '),
+                None => out.push(c),
+            }
+        }
+        out
+    }
+}
+
+/// Parses an HL7 `DT`/`TS` value (`YYYYMMDD[HHMM[SS[.S+]]][+/-ZZZZ]`) as a date.
+pub fn parse_date(value: &str) -> Option<NaiveDate> {
+    let digits = value.get(..8)?;
+    NaiveDate::parse_from_str(digits, "%Y%m%d").ok()
+}
+
+/// Parses an HL7 `TS`/`DTM` value. Missing time parts default to zero and a
+/// missing offset is treated as UTC.
+pub fn parse_datetime(value: &str) -> Option<DateTime<Utc>> {
+    let value = value.trim();
+    let (stamp, offset) = match value.find(['+', '-']) {
+        Some(pos) => (&value[..pos], Some(&value[pos..])),
+        None => (value, None),
+    };
+    let stamp = stamp.split('.').next().unwrap_or(stamp);
+    if stamp.len() < 8 || !stamp.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    let padded = format!("{:0<14}", stamp.get(..14).unwrap_or(stamp));
+    let naive = NaiveDateTime::parse_from_str(&padded, "%Y%m%d%H%M%S").ok()?;
+
+    let offset_seconds = match offset {
+        Some(offset) if offset.len() == 5 => {
+            let sign = if offset.starts_with('-') { -1 } else { 1 };
+            let hours: i32 = offset[1..3].parse().ok()?;
+            let minutes: i32 = offset[3..5].parse().ok()?;
+            sign * (hours * 3600 + minutes * 60)
+        }
+        _ => 0,
+    };
+    let offset = chrono::FixedOffset::east_opt(offset_seconds)?;
+    offset
+        .from_local_datetime(&naive)
+        .single()
+        .map(|dt| dt.with_timezone(&Utc))
+}
+
+/// Formats a timestamp as an HL7 `TS` (`YYYYMMDDHHMMSS+0000`).
+pub fn format_datetime(value: DateTime<Utc>) -> String {
+    value.format("%Y%m%d%H%M%S+0000").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ORU: &str = "MSH|^~\\&|LIS|LAB|EMR|CLINIC|20261019083000||ORU^R01|MSG0001|P|2.5\r\
+                       PID|1||PAT-1~PAT-2||Dela Cruz^Juan^Santos||19800115|M\r\
+                       OBX|1|ST|NOTE^Comment||Fasting \\F\\ 8h\\S\\12h|";
+
+    #[test]
+    fn parse_reads_header_and_numbers_fields_like_the_spec() {
+        let message = Message::parse(ORU).unwrap();
+
+        assert_eq!(message.delimiters, Delimiters::default());
+        assert_eq!(message.segments.len(), 3);
+        assert_eq!(message.msh().field(1), "|");
+        assert_eq!(message.msh().field(2), "^~\\&");
+        assert_eq!(message.message_type(), "ORU");
+        assert_eq!(message.trigger_event(), "R01");
+        assert_eq!(message.control_id(), "MSG0001");
+
+        let pid = message.segment("PID").unwrap();
+        assert_eq!(pid.field(1), "1");
+        assert_eq!(message.repetitions(pid.field(3)), vec!["PAT-1", "PAT-2"]);
+        assert_eq!(message.component(pid.field(5), 2), "Juan");
+        assert_eq!(message.component(pid.field(5), 4), "");
+        assert_eq!(pid.field(30), "");
+    }
+
+    #[test]
+    fn parse_accepts_line_feeds_and_custom_delimiters() {
+        let message = Message::parse("MSH#*~!@#LIS\nPID#1##A*B\r\n").unwrap();
+
+        assert_eq!(message.delimiters.field, '#');
+        assert_eq!(message.delimiters.component, '*');
+        assert_eq!(message.delimiters.escape, '!');
+        let pid = message.segment("PID").unwrap();
+        assert_eq!(message.component(pid.field(3), 2), "B");
+    }
+
+    #[test]
+    fn parse_rejects_messages_without_msh() {
+        assert!(Message::parse("PID|1||PAT-1").is_err());
+        assert!(Message::parse("MSH|^~").is_err());
+    }
+
+    #[test]
+    fn unescape_decodes_delimiters_and_drops_other_escapes() {
+        let message = Message::parse(ORU).unwrap();
+        let obx = message.segment("OBX").unwrap();
+
+        assert_eq!(message.unescape(obx.field(5)), "Fasting | 8h^12h");
+        assert_eq!(message.unescape("a\\T\\b\\R\\c\\E\\d"), "a&b~c\\d");
+        assert_eq!(message.unescape("bold \\H\\text\\N\\"), "bold text");
+        assert_eq!(message.unescape("dangling \\F"), "dangling \\F");
+    }
+
+    #[test]
+    fn escape_round_trips_through_unescape() {
+        let message = Message::parse(ORU).unwrap();
+        let value = "A|B^C&D~E\\F";
+
+        assert_eq!(message.unescape(&Message::escape(&message.delimiters, value)), value);
+    }
+
+    #[test]
+    fn parse_datetime_pads_missing_parts_and_applies_the_offset() {
+        let utc = |s: &str| DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc);
+
+        assert_eq!(parse_datetime("20261019"), Some(utc("2026-10-19T00:00:00Z")));
+        assert_eq!(parse_datetime("202610190830"), Some(utc("2026-10-19T08:30:00Z")));
+        assert_eq!(parse_datetime("20261019083015.1234"), Some(utc("2026-10-19T08:30:15Z")));
+        assert_eq!(parse_datetime("20261019083015+0800"), Some(utc("2026-10-19T00:30:15Z")));
+        assert_eq!(parse_datetime("20261019083015-0130"), Some(utc("2026-10-19T10:00:15Z")));
+    }
+
+    #[test]
+    fn parse_datetime_rejects_malformed_values() {
+        assert_eq!(parse_datetime(""), None);
+        assert_eq!(parse_datetime("2026"), None);
+        assert_eq!(parse_datetime("2026AB19"), None);
+        assert_eq!(parse_datetime("20261332"), None);
+    }
+}
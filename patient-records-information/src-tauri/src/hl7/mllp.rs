@@ -0,0 +1,182 @@
+use std::io;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::hl7::ingest::handle_raw_message;
+use crate::server::state::AppState;
+
+/// MLLP block characters: `<VT> message <FS><CR>`.
+pub const START_BLOCK: u8 = 0x0B;
+pub const END_BLOCK: u8 = 0x1C;
+pub const CARRIAGE_RETURN: u8 = 0x0D;
+
+/// Frames larger than this are treated as a protocol error and the connection is dropped.
+pub const MAX_FRAME_BYTES: usize = 4 * 1024 * 1024;
+
+/// Wraps a message in an MLLP frame.
+pub fn frame(message: &str) -> Vec<u8> {
+    let mut framed = Vec::with_capacity(message.len() + 3);
+    framed.push(START_BLOCK);
+    framed.extend_from_slice(message.as_bytes());
+    framed.push(END_BLOCK);
+    framed.push(CARRIAGE_RETURN);
+    framed
+}
+
+/// Incremental decoder for a stream of MLLP frames.
+#[derive(Debug, Default)]
+pub struct FrameDecoder {
+    buffer: Vec<u8>,
+}
+
+impl FrameDecoder {
+    pub fn push(&mut self, data: &[u8]) {
+        self.buffer.extend_from_slice(data);
+    }
+
+    /// Returns the next complete frame payload, if one has been received.
+    /// Bytes before a start block are discarded.
+    pub fn next_frame(&mut self) -> io::Result<Option<Vec<u8>>> {
+        match self.buffer.iter().position(|b| *b == START_BLOCK) {
+            Some(start) => {
+                self.buffer.drain(..start);
+            }
+            None => {
+                self.buffer.clear();
+                return Ok(None);
+            }
+        }
+
+        let end = self
+            .buffer
+            .windows(2)
+            .position(|w| w[0] == END_BLOCK && w[1] == CARRIAGE_RETURN);
+        match end {
+            Some(end) => {
+                let payload = self.buffer[1..end].to_vec();
+                self.buffer.drain(..end + 2);
+                Ok(Some(payload))
+            }
+            None if self.buffer.len() > MAX_FRAME_BYTES => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("MLLP frame exceeds {} bytes", MAX_FRAME_BYTES),
+            )),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Accepts MLLP connections on `addr` and answers every message with an ACK/NAK.
+/// Runs until the listener fails to bind.
+pub async fn run_listener(addr: String, app_state: AppState) -> io::Result<()> {
+    let listener = TcpListener::bind(&addr).await?;
+    log::info!("🩺 HL7 MLLP listener on {}", addr);
+
+    loop {
+        match listener.accept().await {
+            Ok((stream, peer)) => {
+                let state = app_state.clone();
+                tokio::spawn(async move {
+                    log::info!("🔌 MLLP connection from {}", peer);
+                    if let Err(e) = handle_connection(stream, state).await {
+                        log::warn!("⚠️ MLLP connection from {} closed with error: {}", peer, e);
+                    }
+                });
+            }
+            Err(e) => {
+                log::warn!("⚠️ Failed to accept MLLP connection: {}", e);
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+        }
+    }
+}
+
+async fn handle_connection(mut stream: TcpStream, app_state: AppState) -> io::Result<()> {
+    let mut decoder = FrameDecoder::default();
+    let mut chunk = [0u8; 8192];
+
+    loop {
+        let read = stream.read(&mut chunk).await?;
+        if read == 0 {
+            return Ok(());
+        }
+        decoder.push(&chunk[..read]);
+
+        while let Some(payload) = decoder.next_frame()? {
+            let raw = String::from_utf8_lossy(&payload);
+            let db = app_state.get_local_db().await;
+            let cloud_db = app_state.get_cloud_db().await;
+            let ack = handle_raw_message(&db, cloud_db.as_ref(), &raw, app_state.local_patient_numbering()).await;
+            stream.write_all(&frame(&ack)).await?;
+        }
+    }
+}
+
+/// Sends one message to an MLLP listener and waits for its acknowledgment.
+pub async fn send_message(addr: &str, message: &str, timeout: Duration) -> io::Result<String> {
+    let mut stream = TcpStream::connect(addr).await?;
+    stream.write_all(&frame(message)).await?;
+
+    let mut decoder = FrameDecoder::default();
+    let mut chunk = [0u8; 8192];
+    tokio::time::timeout(timeout, async {
+        loop {
+            let read = stream.read(&mut chunk).await?;
+            if read == 0 {
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "connection closed before ACK"));
+            }
+            decoder.push(&chunk[..read]);
+            if let Some(payload) = decoder.next_frame()? {
+                return Ok(String::from_utf8_lossy(&payload).into_owned());
+            }
+        }
+    })
+    .await
+    .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "timed out waiting for ACK"))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decoder_waits_for_a_frame_split_across_reads() {
+        let framed = frame("MSH|^~\\&|A");
+        let mut decoder = FrameDecoder::default();
+
+        decoder.push(&framed[..4]);
+        assert_eq!(decoder.next_frame().unwrap(), None);
+        // End block without the trailing carriage return is not a complete frame yet.
+        decoder.push(&framed[4..framed.len() - 1]);
+        assert_eq!(decoder.next_frame().unwrap(), None);
+        decoder.push(&framed[framed.len() - 1..]);
+        assert_eq!(decoder.next_frame().unwrap(), Some(b"MSH|^~\\&|A".to_vec()));
+        assert_eq!(decoder.next_frame().unwrap(), None);
+    }
+
+    #[test]
+    fn decoder_returns_every_frame_in_one_read() {
+        let mut data = b"noise".to_vec();
+        data.extend(frame("first"));
+        data.extend(frame("second"));
+        data.extend(&frame("third")[..3]);
+        let mut decoder = FrameDecoder::default();
+
+        decoder.push(&data);
+        assert_eq!(decoder.next_frame().unwrap(), Some(b"first".to_vec()));
+        assert_eq!(decoder.next_frame().unwrap(), Some(b"second".to_vec()));
+        assert_eq!(decoder.next_frame().unwrap(), None);
+        decoder.push(&frame("third")[3..]);
+        assert_eq!(decoder.next_frame().unwrap(), Some(b"third".to_vec()));
+    }
+
+    #[test]
+    fn decoder_rejects_oversized_frames() {
+        let mut decoder = FrameDecoder::default();
+        decoder.push(&[START_BLOCK]);
+        decoder.push(&vec![b'x'; MAX_FRAME_BYTES]);
+
+        assert_eq!(decoder.next_frame().unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,4 @@
+pub mod ack;
+pub mod ingest;
+pub mod message;
+pub mod mllp;
@@ -1,6 +1,9 @@
 // Module declarations
+pub mod crypto;
 pub mod database;
+pub mod fhir;
 pub mod handlers;
+pub mod hl7;
 pub mod migrations;
 pub mod models;
 pub mod server;
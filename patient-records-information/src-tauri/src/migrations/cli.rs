@@ -1,45 +1,309 @@
-use sea_orm::{Database, DbErr};
-use crate::migrations::runner;
+use chrono::Utc;
+use sea_orm::{Database, DatabaseConnection, DbErr};
+use crate::crypto::{self, rotation};
+use crate::handlers::icd10_handlers::{import_icd10_codes, parse_icd10_csv};
+use crate::handlers::integrity_handlers::{check_integrity, fix_integrity_issues};
+use crate::handlers::psgc_handlers::{import_psgc_areas, parse_psgc_csv};
+use crate::handlers::seed_handlers::{self, DemoOutcome};
+use crate::database::connection::{cloud_database_url, connect_cloud, connect_local, local_database_url};
+use crate::migrations::{generate, runner, verify, Migrator};
+use sea_orm_migration::MigratorTrait;
+
+/// Configured databases a command runs against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Target {
+    /// `DATABASE_URL_LOCAL`
+    Local,
+    /// `DATABASE_URL_CLOUD`
+    Cloud,
+    /// Both; the cloud database is skipped when it can't be reached.
+    All,
+}
+
+/// Where a command connects: an explicit `--database-url`, or a target.
+#[derive(Debug, Clone)]
+pub enum DatabaseSelection {
+    Url(String),
+    Target(Target),
+}
+
+struct TargetDatabase {
+    label: &'static str,
+    db: DatabaseConnection,
+}
+
+/// Connects to the selected databases with the same URLs and settings as the
+/// server. A cloud database that was asked for on its own must be reachable.
+async fn connect(selection: &DatabaseSelection) -> Result<Vec<TargetDatabase>, DbErr> {
+    let target = match selection {
+        DatabaseSelection::Url(url) => {
+            let db = Database::connect(url.as_str()).await?;
+            return Ok(vec![TargetDatabase { label: "Database", db }]);
+        }
+        DatabaseSelection::Target(target) => *target,
+    };
+
+    let mut databases = Vec::new();
+    if target != Target::Cloud {
+        let db = connect_local(local_database_url()).await?;
+        databases.push(TargetDatabase { label: "Local", db });
+    }
+    match target {
+        Target::Cloud => {
+            let db = connect_cloud(cloud_database_url()).await?;
+            databases.push(TargetDatabase { label: "Cloud", db });
+        }
+        Target::All => match connect_cloud(cloud_database_url()).await {
+            Ok(db) => databases.push(TargetDatabase { label: "Cloud", db }),
+            Err(err) => println!("Cloud database not reachable ({}); run again once it is back online.", err),
+        },
+        Target::Local => {}
+    }
+    Ok(databases)
+}
+
+fn print_sql(label: &str, migrations: Vec<runner::MigrationSql>) {
+    if migrations.is_empty() {
+        println!("-- {}: nothing to run", label);
+    }
+    for migration in migrations {
+        println!("-- {}: {}", label, migration.name);
+        for statement in migration.statements {
+            println!("{};", statement);
+        }
+    }
+}
+
+/// Applies the pending migrations, or only those up to and including `to`.
+/// With `dry_run` the SQL is printed instead.
+pub async fn run_migration_cli(selection: &DatabaseSelection, to: Option<&str>, dry_run: bool) -> Result<(), DbErr> {
+    let to = to.map(runner::find_migration).transpose()?;
+    for target in connect(selection).await? {
+        if dry_run {
+            print_sql(target.label, runner::pending_migrations_sql(&target.db, to.as_deref()).await?);
+            continue;
+        }
+        match &to {
+            Some(to) => {
+                println!("{}: running migrations up to {}...", target.label, to);
+                if !runner::run_migrations_to(&target.db, to).await? {
+                    println!("{}: {} is already applied", target.label, to);
+                }
+            }
+            None => {
+                println!("{}: running migrations...", target.label);
+                runner::run_migrations(&target.db).await?;
+            }
+        }
+    }
+    if !dry_run {
+        println!("Migrations completed successfully!");
+    }
+
+    Ok(())
+}
+
+/// Rolls back the last `steps` migrations. With `dry_run` the SQL is printed instead.
+pub async fn rollback_migration_cli(selection: &DatabaseSelection, steps: u32, dry_run: bool) -> Result<(), DbErr> {
+    for target in connect(selection).await? {
+        if dry_run {
+            print_sql(target.label, runner::rollback_migrations_sql(&target.db, steps).await?);
+            continue;
+        }
+        println!("{}: rolling back {} migration(s)...", target.label, steps);
+        runner::rollback_migrations(&target.db, steps).await?;
+    }
+    if !dry_run {
+        println!("Migrations rolled back successfully!");
+    }
 
-pub async fn run_migration_cli() -> Result<(), DbErr> {
-    let database_url = "sqlite://patient_records.db";
-    let db = Database::connect(database_url).await?;
-    
-    println!("Running migrations...");
-    runner::run_migrations(&db).await?;
-    println!("Migrations completed successfully!");
-    
     Ok(())
 }
 
-pub async fn rollback_migration_cli() -> Result<(), DbErr> {
-    let database_url = "sqlite://patient_records.db";
-    let db = Database::connect(database_url).await?;
-    
-    println!("Rolling back migrations...");
-    runner::rollback_migrations(&db).await?;
-    println!("Migrations rolled back successfully!");
-    
+/// Drops every table and applies all migrations from scratch.
+pub async fn fresh_migration_cli(selection: &DatabaseSelection) -> Result<(), DbErr> {
+    for target in connect(selection).await? {
+        println!("{}: dropping all tables and re-running migrations...", target.label);
+        runner::fresh_migrations(&target.db).await?;
+    }
+    println!("Migrations re-run successfully!");
+
     Ok(())
 }
 
-pub async fn reset_migration_cli() -> Result<(), DbErr> {
-    let database_url = "sqlite://patient_records.db";
-    let db = Database::connect(database_url).await?;
-    
-    println!("Resetting migrations...");
-    runner::reset_migrations(&db).await?;
+pub async fn reset_migration_cli(selection: &DatabaseSelection) -> Result<(), DbErr> {
+    for target in connect(selection).await? {
+        println!("{}: resetting migrations...", target.label);
+        runner::reset_migrations(&target.db).await?;
+    }
     println!("Migrations reset successfully!");
-    
+
+    Ok(())
+}
+
+pub async fn status_migration_cli(selection: &DatabaseSelection) -> Result<(), DbErr> {
+    for target in connect(selection).await? {
+        println!("{}: checking migration status...", target.label);
+        runner::status_migrations(&target.db).await?;
+    }
+
+    Ok(())
+}
+
+/// Compares every entity with the migrated schema and prints the differences.
+/// Returns `false` when there is drift or a migration is still pending on any
+/// of the selected databases.
+pub async fn verify_schema_cli(selection: &DatabaseSelection) -> Result<bool, DbErr> {
+    let mut matches = true;
+    for target in connect(selection).await? {
+        let pending = Migrator::get_pending_migrations(&target.db).await?;
+        if !pending.is_empty() {
+            println!("{}: {} migration(s) pending; run `migrate up` first:", target.label, pending.len());
+            for migration in pending {
+                println!("  {}", migration.name());
+            }
+            matches = false;
+            continue;
+        }
+
+        println!("{}: verifying entities against the database schema...", target.label);
+        let drift = verify::verify_schema(&target.db).await?;
+        if drift.is_empty() {
+            println!("{}: schema matches the entities.", target.label);
+            continue;
+        }
+        for difference in &drift {
+            println!("  {}", difference);
+        }
+        println!("{}: {} difference(s) found.", target.label, drift.len());
+        matches = false;
+    }
+    Ok(matches)
+}
+
+/// Scans the data for orphaned rows, unknown enum values and inconsistent
+/// bills, and prints a report. With `fix`, the issues that are safe to repair
+/// are fixed. Returns `false` when any issue is left on a selected database.
+pub async fn check_integrity_cli(selection: &DatabaseSelection, fix: bool) -> Result<bool, DbErr> {
+    let mut clean = true;
+    for target in connect(selection).await? {
+        println!("{}: checking data integrity...", target.label);
+        let issues = check_integrity(&target.db).await?;
+        if issues.is_empty() {
+            println!("{}: no issues found.", target.label);
+            continue;
+        }
+        for issue in &issues {
+            let note = if issue.is_fixable() && !fix { " (fixable with --fix)" } else { "" };
+            println!("  {}{}", issue, note);
+        }
+        println!("{}: {} issue(s) found.", target.label, issues.len());
+
+        let fixed = if fix { fix_integrity_issues(&target.db, &issues).await? } else { 0 };
+        if fix {
+            println!("{}: {} issue(s) fixed.", target.label, fixed);
+        }
+        if (fixed as usize) < issues.len() {
+            clean = false;
+        }
+    }
+    Ok(clean)
+}
+
+/// Creates a timestamped migration from `template.rs` in this checkout's
+/// `src/migrations` and registers it in `Migrator::migrations()`.
+pub fn generate_migration_cli(description: &str) -> Result<(), DbErr> {
+    let name = generate::migration_name(description, Utc::now()).map_err(DbErr::Custom)?;
+    let path = generate::generate_migration(&generate::migrations_dir(), &name).map_err(DbErr::Custom)?;
+    println!("Created {}", path.display());
+    println!("Registered {} in Migrator::migrations()", name);
+    Ok(())
+}
+
+/// Re-encrypts patient PII with the active key on the selected databases.
+pub async fn rotate_keys_cli(selection: &DatabaseSelection, batch_size: u64) -> Result<(), DbErr> {
+    let cipher = crypto::global()
+        .map_err(|e| DbErr::Custom(e.to_string()))?
+        .ok_or_else(|| DbErr::Custom("PII_ENCRYPTION_KEYS is not set; nothing to rotate".to_string()))?;
+
+    for target in connect(selection).await? {
+        println!("{}: re-encrypting patients with key '{}'...", target.label, cipher.active_key_id());
+        let report = rotation::rotate_patient_keys(&target.db, cipher, batch_size).await?;
+        println!("{}: {} scanned, {} re-encrypted", target.label, report.scanned, report.reencrypted);
+    }
+
+    println!("Key rotation completed successfully!");
+    Ok(())
+}
+
+/// Loads a PSGC CSV export into the reference table of the selected databases.
+/// Existing codes are updated in place.
+pub async fn import_psgc_cli(selection: &DatabaseSelection, path: &str) -> Result<(), DbErr> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| DbErr::Custom(format!("Failed to read {}: {}", path, e)))?;
+    let (areas, skipped) = parse_psgc_csv(&contents).map_err(DbErr::Custom)?;
+    println!("Parsed {} PSGC areas ({} rows skipped)", areas.len(), skipped);
+
+    for target in connect(selection).await? {
+        let imported = import_psgc_areas(&target.db, areas.clone()).await?;
+        println!("{}: {} areas imported", target.label, imported);
+    }
+
+    println!("PSGC import completed successfully!");
+    Ok(())
+}
+
+/// Loads an ICD-10 CSV export into the code table of the selected databases.
+/// Existing codes are updated in place.
+pub async fn import_icd10_cli(selection: &DatabaseSelection, path: &str) -> Result<(), DbErr> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| DbErr::Custom(format!("Failed to read {}: {}", path, e)))?;
+    let (codes, skipped) = parse_icd10_csv(&contents).map_err(DbErr::Custom)?;
+    println!("Parsed {} ICD-10 codes ({} rows skipped)", codes.len(), skipped);
+
+    for target in connect(selection).await? {
+        let imported = import_icd10_codes(&target.db, codes.clone()).await?;
+        println!("{}: {} codes imported", target.label, imported);
+    }
+
+    println!("ICD-10 import completed successfully!");
     Ok(())
 }
 
-pub async fn status_migration_cli() -> Result<(), DbErr> {
-    let database_url = "sqlite://patient_records.db";
-    let db = Database::connect(database_url).await?;
-    
-    println!("Checking migration status...");
-    runner::status_migrations(&db).await?;
-    
+/// Adds the bundled medical services catalog to the selected databases.
+/// Services already there (by name) are left as they are, except that those
+/// without a result template get the catalog's; so do unit conversions.
+pub async fn seed_catalog_cli(selection: &DatabaseSelection) -> Result<(), DbErr> {
+    let catalog = seed_handlers::bundled_service_catalog().map_err(DbErr::Custom)?;
+    println!("Service catalog {} ({} services, {})", catalog.version, catalog.services.len(), catalog.currency);
+
+    for target in connect(selection).await? {
+        let report = seed_handlers::load_service_catalog(&target.db, &catalog).await?;
+        println!(
+            "{}: {} services added, {} already present, {} result templates added, {} unit conversions added",
+            target.label, report.inserted, report.already_present, report.templates_added, report.conversions_added
+        );
+    }
+
+    println!("Service catalog seeded successfully!");
+    Ok(())
+}
+
+/// Generates the demo dataset of `seed` on the selected databases.
+pub async fn seed_demo_cli(selection: &DatabaseSelection, patients: u32, seed: u64) -> Result<(), DbErr> {
+    for target in connect(selection).await? {
+        println!("{}: generating {} demo patients with seed {}...", target.label, patients, seed);
+        match seed_handlers::seed_demo_data(&target.db, patients, seed).await? {
+            DemoOutcome::Seeded(report) => println!(
+                "{}: {} patients, {} medical records, {} bills, {} services provided",
+                target.label, report.patients, report.medical_records, report.bills, report.services_provided
+            ),
+            DemoOutcome::AlreadySeeded => {
+                println!("{}: demo data of seed {} is already present; nothing added", target.label, seed)
+            }
+        }
+    }
+
+    println!("Demo data seeded successfully!");
     Ok(())
 }
@@ -0,0 +1,102 @@
+//! Scaffolds a new migration from `template.rs` and registers it in `mod.rs`.
+
+use chrono::{DateTime, Utc};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const TEMPLATE: &str = include_str!("template.rs");
+
+/// The crate's migrations directory; `migrate generate` is run from a checkout.
+pub fn migrations_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("src").join("migrations")
+}
+
+/// `m{YYYYMMDD}_{HHMMSS}_{description}`, with the description in snake case.
+pub fn migration_name(description: &str, now: DateTime<Utc>) -> Result<String, String> {
+    let mut slug = String::new();
+    for c in description.trim().chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c.to_ascii_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('_') {
+            slug.push('_');
+        }
+    }
+    let slug = slug.trim_end_matches('_');
+    if slug.is_empty() {
+        return Err("The description needs at least one letter or digit".to_string());
+    }
+    Ok(format!("m{}_{}", now.format("%Y%m%d_%H%M%S"), slug))
+}
+
+/// Writes `<name>.rs` from the template into `dir` and adds it to `dir/mod.rs`
+/// (the `mod` line and the `Box::new` entry), keeping both lists sorted by name.
+/// Returns the path of the new file.
+pub fn generate_migration(dir: &Path, name: &str) -> Result<PathBuf, String> {
+    let path = dir.join(format!("{}.rs", name));
+    if path.exists() {
+        return Err(format!("{} already exists", path.display()));
+    }
+    let mod_path = dir.join("mod.rs");
+    let mod_rs = fs::read_to_string(&mod_path)
+        .map_err(|e| format!("Failed to read {}: {}", mod_path.display(), e))?;
+    let mod_rs = register(&mod_rs, name)?;
+
+    // The template's header explains how to copy it by hand; the copy doesn't need it.
+    let source: String = TEMPLATE
+        .lines()
+        .skip_while(|line| line.starts_with("//") || line.trim().is_empty())
+        .map(|line| format!("{}\n", line))
+        .collect();
+    fs::write(&path, source).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    fs::write(&mod_path, mod_rs).map_err(|e| format!("Failed to write {}: {}", mod_path.display(), e))?;
+    Ok(path)
+}
+
+fn register(mod_rs: &str, name: &str) -> Result<String, String> {
+    let mut lines: Vec<String> = mod_rs.lines().map(str::to_string).collect();
+    insert_sorted(&mut lines, name, &format!("mod {};", name), |line| {
+        line.strip_prefix("mod m").and_then(|rest| rest.strip_suffix(';')).map(|rest| format!("m{}", rest))
+    })?;
+    insert_sorted(&mut lines, name, &format!("Box::new({}::Migration),", name), |line| {
+        line.trim()
+            .strip_prefix("Box::new(")
+            .and_then(|rest| rest.strip_suffix("::Migration),"))
+            .map(str::to_string)
+    })?;
+
+    let mut registered = lines.join("\n");
+    if mod_rs.ends_with('\n') {
+        registered.push('\n');
+    }
+    Ok(registered)
+}
+
+/// Inserts `entry` before the first line `parse` reads a later name from, or
+/// after the last such line, with that line's indentation.
+fn insert_sorted(
+    lines: &mut Vec<String>,
+    name: &str,
+    entry: &str,
+    parse: impl Fn(&str) -> Option<String>,
+) -> Result<(), String> {
+    let existing: Vec<(usize, String)> = lines
+        .iter()
+        .enumerate()
+        .filter_map(|(index, line)| parse(line).map(|parsed| (index, parsed)))
+        .collect();
+    let Some((last, _)) = existing.last() else {
+        return Err(format!("No existing migration line like `{}` found in mod.rs", entry));
+    };
+    if existing.iter().any(|(_, existing)| existing == name) {
+        return Err(format!("{} is already registered", name));
+    }
+
+    let index = existing
+        .iter()
+        .find(|(_, existing)| existing.as_str() > name)
+        .map_or(last + 1, |(index, _)| *index);
+    let reference = &lines[existing[0].0];
+    let indent = &reference[..reference.len() - reference.trim_start().len()];
+    lines.insert(index, format!("{}{}", indent, entry));
+    Ok(())
+}
@@ -0,0 +1,90 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // No foreign key to patients_table yet: its migrated patient_id column is
+        // still an integer while patient ids are UUIDs everywhere else.
+        manager
+            .create_table(
+                Table::create()
+                    .table(LabResultsTable::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(LabResultsTable::ResultId)
+                            .uuid()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(LabResultsTable::PatientId).uuid().not_null())
+                    .col(ColumnDef::new(LabResultsTable::OrderNumber).string().null())
+                    .col(ColumnDef::new(LabResultsTable::TestCode).string().null())
+                    .col(ColumnDef::new(LabResultsTable::TestName).string().null())
+                    .col(ColumnDef::new(LabResultsTable::AnalyteCode).string().not_null())
+                    .col(ColumnDef::new(LabResultsTable::AnalyteName).string().null())
+                    .col(ColumnDef::new(LabResultsTable::ValueType).string().null())
+                    .col(ColumnDef::new(LabResultsTable::Value).text().null())
+                    .col(ColumnDef::new(LabResultsTable::Unit).string().null())
+                    .col(ColumnDef::new(LabResultsTable::ReferenceRange).string().null())
+                    .col(ColumnDef::new(LabResultsTable::AbnormalFlag).string().null())
+                    .col(ColumnDef::new(LabResultsTable::ResultStatus).string().null())
+                    .col(
+                        ColumnDef::new(LabResultsTable::ObservedAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .col(ColumnDef::new(LabResultsTable::Source).string().not_null())
+                    .col(ColumnDef::new(LabResultsTable::MessageControlId).string().null())
+                    .col(
+                        ColumnDef::new(LabResultsTable::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_lab_results_patient_id")
+                    .table(LabResultsTable::Table)
+                    .col(LabResultsTable::PatientId)
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(LabResultsTable::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum LabResultsTable {
+    Table,
+    ResultId,
+    PatientId,
+    OrderNumber,
+    TestCode,
+    TestName,
+    AnalyteCode,
+    AnalyteName,
+    ValueType,
+    Value,
+    Unit,
+    ReferenceRange,
+    AbnormalFlag,
+    ResultStatus,
+    ObservedAt,
+    Source,
+    MessageControlId,
+    CreatedAt,
+}
@@ -0,0 +1,66 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+/// Blind-index columns (hex HMAC-SHA256) that keep exact-match lookups working
+/// once the underlying PII columns are encrypted.
+const BLIND_INDEXES: [(PatientsTable, &str); 3] = [
+    (PatientsTable::LastNameBidx, "idx_patients_last_name_bidx"),
+    (PatientsTable::MobileNumberBidx, "idx_patients_mobile_number_bidx"),
+    (PatientsTable::CsdIdOrPwdIdBidx, "idx_patients_csd_id_or_pwd_id_bidx"),
+];
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // One column per statement: SQLite's ALTER TABLE accepts a single ADD COLUMN.
+        for (column, index) in BLIND_INDEXES {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(PatientsTable::Table)
+                        .add_column_if_not_exists(ColumnDef::new(column).string_len(64).null())
+                        .to_owned(),
+                )
+                .await?;
+
+            manager
+                .create_index(
+                    Index::create()
+                        .name(index)
+                        .table(PatientsTable::Table)
+                        .col(column)
+                        .if_not_exists()
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for (column, index) in BLIND_INDEXES {
+            manager
+                .drop_index(Index::drop().name(index).table(PatientsTable::Table).to_owned())
+                .await?;
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(PatientsTable::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden, Clone, Copy)]
+enum PatientsTable {
+    Table,
+    LastNameBidx,
+    MobileNumberBidx,
+    CsdIdOrPwdIdBidx,
+}
@@ -0,0 +1,180 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(PatientsTable::Table)
+                    .add_column_if_not_exists(
+                        ColumnDef::new(PatientsTable::ArchivedAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(RetentionCandidatesTable::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(RetentionCandidatesTable::CandidateId)
+                            .uuid()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(RetentionCandidatesTable::Entity).string_len(20).not_null())
+                    .col(ColumnDef::new(RetentionCandidatesTable::RecordId).uuid().not_null())
+                    .col(ColumnDef::new(RetentionCandidatesTable::Basis).string_len(20).not_null())
+                    .col(ColumnDef::new(RetentionCandidatesTable::RetentionDays).integer().not_null())
+                    .col(
+                        ColumnDef::new(RetentionCandidatesTable::ReferenceAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(RetentionCandidatesTable::DueAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(RetentionCandidatesTable::Status).string_len(20).not_null())
+                    .col(ColumnDef::new(RetentionCandidatesTable::DecidedBy).uuid().null())
+                    .col(
+                        ColumnDef::new(RetentionCandidatesTable::DecidedAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .col(ColumnDef::new(RetentionCandidatesTable::Note).text().null())
+                    .col(
+                        ColumnDef::new(RetentionCandidatesTable::PurgedAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(RetentionCandidatesTable::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .col(
+                        ColumnDef::new(RetentionCandidatesTable::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_retention_candidates_record")
+                    .table(RetentionCandidatesTable::Table)
+                    .col(RetentionCandidatesTable::Entity)
+                    .col(RetentionCandidatesTable::RecordId)
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(AuditLogTable::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(AuditLogTable::AuditId)
+                            .uuid()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(AuditLogTable::Action).string_len(64).not_null())
+                    .col(ColumnDef::new(AuditLogTable::Entity).string_len(32).not_null())
+                    .col(ColumnDef::new(AuditLogTable::RecordId).string().null())
+                    .col(ColumnDef::new(AuditLogTable::ActorId).uuid().null())
+                    .col(ColumnDef::new(AuditLogTable::Details).json().not_null())
+                    .col(
+                        ColumnDef::new(AuditLogTable::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_audit_log_entity_record")
+                    .table(AuditLogTable::Table)
+                    .col(AuditLogTable::Entity)
+                    .col(AuditLogTable::RecordId)
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(AuditLogTable::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(RetentionCandidatesTable::Table).to_owned())
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(PatientsTable::Table)
+                    .drop_column(PatientsTable::ArchivedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum PatientsTable {
+    Table,
+    ArchivedAt,
+}
+
+#[derive(DeriveIden)]
+enum RetentionCandidatesTable {
+    Table,
+    CandidateId,
+    Entity,
+    RecordId,
+    Basis,
+    RetentionDays,
+    ReferenceAt,
+    DueAt,
+    Status,
+    DecidedBy,
+    DecidedAt,
+    Note,
+    PurgedAt,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum AuditLogTable {
+    Table,
+    AuditId,
+    Action,
+    Entity,
+    RecordId,
+    ActorId,
+    Details,
+    CreatedAt,
+}
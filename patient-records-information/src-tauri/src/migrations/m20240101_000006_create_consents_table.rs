@@ -0,0 +1,84 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // No foreign key to patients_table yet: its migrated patient_id column is
+        // still an integer while patient ids are UUIDs everywhere else.
+        manager
+            .create_table(
+                Table::create()
+                    .table(ConsentsTable::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(ConsentsTable::ConsentId)
+                            .uuid()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(ConsentsTable::PatientId).uuid().not_null())
+                    .col(ColumnDef::new(ConsentsTable::ConsentType).string_len(32).not_null())
+                    .col(ColumnDef::new(ConsentsTable::Status).string_len(16).not_null())
+                    .col(ColumnDef::new(ConsentsTable::ConsentVersion).string_len(32).not_null())
+                    .col(ColumnDef::new(ConsentsTable::GrantedOn).date().not_null())
+                    .col(ColumnDef::new(ConsentsTable::WitnessedBy).string().not_null())
+                    .col(
+                        ColumnDef::new(ConsentsTable::WithdrawnAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .col(ColumnDef::new(ConsentsTable::WithdrawalReason).text().null())
+                    .col(
+                        ColumnDef::new(ConsentsTable::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .col(
+                        ColumnDef::new(ConsentsTable::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_consents_patient_type")
+                    .table(ConsentsTable::Table)
+                    .col(ConsentsTable::PatientId)
+                    .col(ConsentsTable::ConsentType)
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ConsentsTable::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum ConsentsTable {
+    Table,
+    ConsentId,
+    PatientId,
+    ConsentType,
+    Status,
+    ConsentVersion,
+    GrantedOn,
+    WitnessedBy,
+    WithdrawnAt,
+    WithdrawalReason,
+    CreatedAt,
+    UpdatedAt,
+}
@@ -0,0 +1,127 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+/// Structured address columns; `residential_address` stays as the free-text
+/// address of rows created before them.
+const ADDRESS_CODES: [(PatientsTable, &str); 4] = [
+    (PatientsTable::RegionCode, "idx_patients_region_code"),
+    (PatientsTable::ProvinceCode, "idx_patients_province_code"),
+    (PatientsTable::CityMunicipalityCode, "idx_patients_city_municipality_code"),
+    (PatientsTable::BarangayCode, "idx_patients_barangay_code"),
+];
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(PsgcAreasTable::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(PsgcAreasTable::PsgcCode)
+                            .string_len(10)
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(PsgcAreasTable::Name).string().not_null())
+                    .col(ColumnDef::new(PsgcAreasTable::GeographicLevel).string_len(8).not_null())
+                    .col(ColumnDef::new(PsgcAreasTable::ParentCode).string_len(10).null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_psgc_areas_parent_code")
+                    .table(PsgcAreasTable::Table)
+                    .col(PsgcAreasTable::ParentCode)
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await?;
+
+        // One column per statement: SQLite's ALTER TABLE accepts a single ADD COLUMN.
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(PatientsTable::Table)
+                    .add_column_if_not_exists(ColumnDef::new(PatientsTable::AddressLine).text().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        for (column, index) in ADDRESS_CODES {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(PatientsTable::Table)
+                        .add_column_if_not_exists(ColumnDef::new(column).string_len(10).null())
+                        .to_owned(),
+                )
+                .await?;
+
+            manager
+                .create_index(
+                    Index::create()
+                        .name(index)
+                        .table(PatientsTable::Table)
+                        .col(column)
+                        .if_not_exists()
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for (column, index) in ADDRESS_CODES {
+            manager
+                .drop_index(Index::drop().name(index).table(PatientsTable::Table).to_owned())
+                .await?;
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(PatientsTable::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(PatientsTable::Table)
+                    .drop_column(PatientsTable::AddressLine)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(PsgcAreasTable::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden, Clone, Copy)]
+enum PatientsTable {
+    Table,
+    AddressLine,
+    RegionCode,
+    ProvinceCode,
+    CityMunicipalityCode,
+    BarangayCode,
+}
+
+#[derive(DeriveIden)]
+enum PsgcAreasTable {
+    Table,
+    PsgcCode,
+    Name,
+    GeographicLevel,
+    ParentCode,
+}
@@ -0,0 +1,92 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // One counter row per registration year; numbers are taken by
+        // incrementing it in the transaction that stores the patient.
+        manager
+            .create_table(
+                Table::create()
+                    .table(PatientNumberSequencesTable::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(PatientNumberSequencesTable::Year)
+                            .integer()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(PatientNumberSequencesTable::LastNumber)
+                            .big_integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Existing patients start without a number and are numbered by the
+        // server's background job.
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(PatientsTable::Table)
+                    .add_column_if_not_exists(ColumnDef::new(PatientsTable::PatientNumber).string_len(20).null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_patients_patient_number")
+                    .table(PatientsTable::Table)
+                    .col(PatientsTable::PatientNumber)
+                    .unique()
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_patients_patient_number")
+                    .table(PatientsTable::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(PatientsTable::Table)
+                    .drop_column(PatientsTable::PatientNumber)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(PatientNumberSequencesTable::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum PatientsTable {
+    Table,
+    PatientNumber,
+}
+
+#[derive(DeriveIden)]
+enum PatientNumberSequencesTable {
+    Table,
+    Year,
+    LastNumber,
+}
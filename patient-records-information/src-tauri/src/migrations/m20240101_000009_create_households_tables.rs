@@ -0,0 +1,109 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(HouseholdsTable::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(HouseholdsTable::HouseholdId)
+                            .uuid()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(HouseholdsTable::HouseholdName).string().not_null())
+                    .col(
+                        ColumnDef::new(HouseholdsTable::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .col(
+                        ColumnDef::new(HouseholdsTable::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // A patient belongs to at most one household, hence patient_id as the key.
+        // No foreign key to patients_table yet: its migrated patient_id column is
+        // still an integer while patient ids are UUIDs everywhere else.
+        manager
+            .create_table(
+                Table::create()
+                    .table(HouseholdMembersTable::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(HouseholdMembersTable::PatientId)
+                            .uuid()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(HouseholdMembersTable::HouseholdId).uuid().not_null())
+                    .col(ColumnDef::new(HouseholdMembersTable::Role).string_len(16).not_null())
+                    .col(
+                        ColumnDef::new(HouseholdMembersTable::JoinedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_household_members_household")
+                            .from(HouseholdMembersTable::Table, HouseholdMembersTable::HouseholdId)
+                            .to(HouseholdsTable::Table, HouseholdsTable::HouseholdId)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_household_members_household_id")
+                    .table(HouseholdMembersTable::Table)
+                    .col(HouseholdMembersTable::HouseholdId)
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(HouseholdMembersTable::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(HouseholdsTable::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum HouseholdsTable {
+    Table,
+    HouseholdId,
+    HouseholdName,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum HouseholdMembersTable {
+    Table,
+    PatientId,
+    HouseholdId,
+    Role,
+    JoinedAt,
+}
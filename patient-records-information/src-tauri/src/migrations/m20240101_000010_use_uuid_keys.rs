@@ -0,0 +1,524 @@
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::{ConnectionTrait, DbBackend};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+/// `patients_table` and `medical_records_table` were created with integer
+/// keys while every entity uses UUIDs. Neither column type can be changed in
+/// place on SQLite, so both tables are rebuilt: each patient gets a new key
+/// through `patient_key_map`, which is also used to repoint its medical
+/// records. `down` does the same in reverse, numbering rows by `created_at`
+/// and keeping each UUID in `patient_uuid`/`medical_uuid`, so that going up
+/// again restores the keys that lab results, consents, households and
+/// retention candidates still refer to.
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        rekey(manager, KeyType::Integer, KeyType::Uuid).await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        rekey(manager, KeyType::Uuid, KeyType::Integer).await
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum KeyType {
+    Integer,
+    Uuid,
+}
+
+impl KeyType {
+    fn primary_key<T: IntoIden>(self, column: T) -> ColumnDef {
+        let mut def = ColumnDef::new(column);
+        match self {
+            KeyType::Integer => def.integer().not_null().auto_increment().primary_key(),
+            KeyType::Uuid => def.uuid().not_null().primary_key(),
+        };
+        def
+    }
+
+    fn reference<T: IntoIden>(self, column: T) -> ColumnDef {
+        let mut def = ColumnDef::new(column);
+        match self {
+            KeyType::Integer => def.integer().not_null(),
+            KeyType::Uuid => def.uuid().not_null(),
+        };
+        def
+    }
+
+    /// New key of each copied row; integer keys are numbered in `order`.
+    fn generate(self, backend: DbBackend, order: &str) -> Result<SimpleExpr, DbErr> {
+        match (self, backend) {
+            (KeyType::Integer, _) => Ok(Expr::cust(format!("ROW_NUMBER() OVER (ORDER BY {})", order))),
+            (KeyType::Uuid, DbBackend::Postgres) => Ok(Expr::cust("gen_random_uuid()")),
+            (KeyType::Uuid, DbBackend::Sqlite) => Ok(Expr::cust("randomblob(16)")),
+            (KeyType::Uuid, DbBackend::MySql) => {
+                Err(DbErr::Migration("UUID key migration supports Postgres and SQLite only".to_owned()))
+            }
+        }
+    }
+}
+
+/// New key of each copied row of `table`: the UUID kept by `down` in
+/// `kept_column` when going up again, otherwise a generated one.
+async fn new_key<T, C>(
+    manager: &SchemaManager<'_>,
+    to: KeyType,
+    table: T,
+    kept_column: C,
+    order: &str,
+) -> Result<SimpleExpr, DbErr>
+where
+    T: IntoIden + Copy + 'static,
+    C: IntoIden + Copy + 'static,
+{
+    let generated = to.generate(manager.get_database_backend(), order)?;
+    let table_name = table.into_iden().to_string();
+    let column_name = kept_column.into_iden().to_string();
+    if to == KeyType::Uuid && manager.has_column(&table_name, &column_name).await? {
+        Ok(Func::coalesce([Expr::col((table, kept_column)).into(), generated]).into())
+    } else {
+        Ok(generated)
+    }
+}
+
+const NEW_PATIENTS_TABLE: &str = "patients_table_new";
+const NEW_MEDICAL_RECORDS_TABLE: &str = "medical_records_table_new";
+
+/// Every column of `patients_table` except its key, as left by the earlier migrations.
+const PATIENT_COLUMNS: [PatientsTable; 21] = [
+    PatientsTable::PatientNumber,
+    PatientsTable::FirstName,
+    PatientsTable::LastName,
+    PatientsTable::MiddleName,
+    PatientsTable::Age,
+    PatientsTable::BirthDate,
+    PatientsTable::CsdIdOrPwdId,
+    PatientsTable::MobileNumber,
+    PatientsTable::ResidentialAddress,
+    PatientsTable::AddressLine,
+    PatientsTable::RegionCode,
+    PatientsTable::ProvinceCode,
+    PatientsTable::CityMunicipalityCode,
+    PatientsTable::BarangayCode,
+    PatientsTable::IsArchived,
+    PatientsTable::ArchivedAt,
+    PatientsTable::CreatedAt,
+    PatientsTable::UpdatedAt,
+    PatientsTable::LastNameBidx,
+    PatientsTable::MobileNumberBidx,
+    PatientsTable::CsdIdOrPwdIdBidx,
+];
+
+/// Indexes of `patients_table` from the earlier migrations; `true` for unique.
+const PATIENT_INDEXES: [(PatientsTable, &str, bool); 8] = [
+    (PatientsTable::LastNameBidx, "idx_patients_last_name_bidx", false),
+    (PatientsTable::MobileNumberBidx, "idx_patients_mobile_number_bidx", false),
+    (PatientsTable::CsdIdOrPwdIdBidx, "idx_patients_csd_id_or_pwd_id_bidx", false),
+    (PatientsTable::RegionCode, "idx_patients_region_code", false),
+    (PatientsTable::ProvinceCode, "idx_patients_province_code", false),
+    (PatientsTable::CityMunicipalityCode, "idx_patients_city_municipality_code", false),
+    (PatientsTable::BarangayCode, "idx_patients_barangay_code", false),
+    (PatientsTable::PatientNumber, "idx_patients_patient_number", true),
+];
+
+const MEDICAL_RECORD_COLUMNS: [MedicalRecordsTable; 8] = [
+    MedicalRecordsTable::Assessment,
+    MedicalRecordsTable::Diagnosis,
+    MedicalRecordsTable::Treatment,
+    MedicalRecordsTable::Prescription,
+    MedicalRecordsTable::FirstAuditedBy,
+    MedicalRecordsTable::LastAuditedBy,
+    MedicalRecordsTable::CreatedAt,
+    MedicalRecordsTable::UpdatedAt,
+];
+
+async fn rekey(manager: &SchemaManager<'_>, from: KeyType, to: KeyType) -> Result<(), DbErr> {
+    let db = manager.get_connection();
+    let backend = db.get_database_backend();
+
+    manager
+        .create_table(
+            Table::create()
+                .table(PatientKeyMap::Table)
+                .col(from.reference(PatientKeyMap::OldId).primary_key())
+                .col(to.reference(PatientKeyMap::NewId))
+                .to_owned(),
+        )
+        .await?;
+    let map_keys = Query::insert()
+        .into_table(PatientKeyMap::Table)
+        .columns([PatientKeyMap::OldId, PatientKeyMap::NewId])
+        .select_from(
+            Query::select()
+                .column(PatientsTable::PatientId)
+                .expr(
+                    new_key(
+                        manager,
+                        to,
+                        PatientsTable::Table,
+                        PatientsTable::PatientUuid,
+                        "\"created_at\", \"patient_id\"",
+                    )
+                    .await?,
+                )
+                .from(PatientsTable::Table)
+                .to_owned(),
+        )
+        .map_err(|e| DbErr::Migration(e.to_string()))?
+        .to_owned();
+    db.execute(backend.build(&map_keys)).await?;
+
+    manager.create_table(patients_table(NEW_PATIENTS_TABLE, to)).await?;
+    let mut patient_columns = vec![PatientsTable::PatientId];
+    patient_columns.extend(PATIENT_COLUMNS);
+    let mut patient_values = Query::select()
+        .column((PatientKeyMap::Table, PatientKeyMap::NewId))
+        .columns(PATIENT_COLUMNS.map(|column| (PatientsTable::Table, column)))
+        .from(PatientsTable::Table)
+        .inner_join(
+            PatientKeyMap::Table,
+            Expr::col((PatientKeyMap::Table, PatientKeyMap::OldId))
+                .equals((PatientsTable::Table, PatientsTable::PatientId)),
+        )
+        .to_owned();
+    if to == KeyType::Integer {
+        patient_columns.push(PatientsTable::PatientUuid);
+        patient_values.column((PatientsTable::Table, PatientsTable::PatientId));
+    }
+    let copy_patients = Query::insert()
+        .into_table(Alias::new(NEW_PATIENTS_TABLE))
+        .columns(patient_columns)
+        .select_from(patient_values)
+        .map_err(|e| DbErr::Migration(e.to_string()))?
+        .to_owned();
+    db.execute(backend.build(&copy_patients)).await?;
+
+    manager
+        .create_table(medical_records_table(NEW_MEDICAL_RECORDS_TABLE, NEW_PATIENTS_TABLE, to))
+        .await?;
+    let mut record_columns = vec![MedicalRecordsTable::MedicalId, MedicalRecordsTable::PatientId];
+    record_columns.extend(MEDICAL_RECORD_COLUMNS);
+    let mut record_values = Query::select()
+        .expr(
+            new_key(
+                manager,
+                to,
+                MedicalRecordsTable::Table,
+                MedicalRecordsTable::MedicalUuid,
+                "\"medical_records_table\".\"created_at\", \"medical_records_table\".\"medical_id\"",
+            )
+            .await?,
+        )
+        .column((PatientKeyMap::Table, PatientKeyMap::NewId))
+        .columns(MEDICAL_RECORD_COLUMNS.map(|column| (MedicalRecordsTable::Table, column)))
+        .from(MedicalRecordsTable::Table)
+        .inner_join(
+            PatientKeyMap::Table,
+            Expr::col((PatientKeyMap::Table, PatientKeyMap::OldId))
+                .equals((MedicalRecordsTable::Table, MedicalRecordsTable::PatientId)),
+        )
+        .to_owned();
+    if to == KeyType::Integer {
+        record_columns.push(MedicalRecordsTable::MedicalUuid);
+        record_values.column((MedicalRecordsTable::Table, MedicalRecordsTable::MedicalId));
+    }
+    let copy_records = Query::insert()
+        .into_table(Alias::new(NEW_MEDICAL_RECORDS_TABLE))
+        .columns(record_columns)
+        .select_from(record_values)
+        .map_err(|e| DbErr::Migration(e.to_string()))?
+        .to_owned();
+    db.execute(backend.build(&copy_records)).await?;
+
+    // Records first: on SQLite dropping a referenced table deletes through the foreign key.
+    manager
+        .drop_table(Table::drop().table(MedicalRecordsTable::Table).to_owned())
+        .await?;
+    manager
+        .drop_table(Table::drop().table(PatientsTable::Table).to_owned())
+        .await?;
+    manager
+        .drop_table(Table::drop().table(PatientKeyMap::Table).to_owned())
+        .await?;
+    // Renaming the patients table also updates the foreign key pointing at it.
+    manager
+        .rename_table(
+            Table::rename()
+                .table(Alias::new(NEW_PATIENTS_TABLE), PatientsTable::Table)
+                .to_owned(),
+        )
+        .await?;
+    manager
+        .rename_table(
+            Table::rename()
+                .table(Alias::new(NEW_MEDICAL_RECORDS_TABLE), MedicalRecordsTable::Table)
+                .to_owned(),
+        )
+        .await?;
+
+    if backend == DbBackend::Postgres {
+        db.execute_unprepared(
+            "ALTER TABLE patients_table RENAME CONSTRAINT patients_table_new_pkey TO patients_table_pkey",
+        )
+        .await?;
+        db.execute_unprepared(
+            "ALTER TABLE medical_records_table RENAME CONSTRAINT medical_records_table_new_pkey TO medical_records_table_pkey",
+        )
+        .await?;
+        if to == KeyType::Integer {
+            // Rows were copied with explicit keys; move the sequences past them.
+            db.execute_unprepared(
+                "SELECT setval(pg_get_serial_sequence('patients_table', 'patient_id'), \
+                 COALESCE(MAX(patient_id), 0) + 1, false) FROM patients_table",
+            )
+            .await?;
+            db.execute_unprepared(
+                "SELECT setval(pg_get_serial_sequence('medical_records_table', 'medical_id'), \
+                 COALESCE(MAX(medical_id), 0) + 1, false) FROM medical_records_table",
+            )
+            .await?;
+        }
+    }
+
+    for (column, index, unique) in PATIENT_INDEXES {
+        let mut statement = Index::create();
+        statement.name(index).table(PatientsTable::Table).col(column);
+        if unique {
+            statement.unique();
+        }
+        manager.create_index(statement).await?;
+    }
+    if to == KeyType::Uuid {
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_medical_records_patient_id")
+                    .table(MedicalRecordsTable::Table)
+                    .col(MedicalRecordsTable::PatientId)
+                    .to_owned(),
+            )
+            .await?;
+    }
+    Ok(())
+}
+
+fn patients_table(name: &str, key: KeyType) -> TableCreateStatement {
+    let mut table = Table::create()
+        .table(Alias::new(name))
+        .col(key.primary_key(PatientsTable::PatientId))
+        .col(ColumnDef::new(PatientsTable::PatientNumber).string_len(20).null())
+        .col(ColumnDef::new(PatientsTable::FirstName).string().not_null())
+        .col(ColumnDef::new(PatientsTable::LastName).string().not_null())
+        .col(ColumnDef::new(PatientsTable::MiddleName).string().null())
+        .col(ColumnDef::new(PatientsTable::Age).integer().not_null())
+        .col(ColumnDef::new(PatientsTable::BirthDate).date().not_null())
+        .col(ColumnDef::new(PatientsTable::CsdIdOrPwdId).string().null())
+        .col(ColumnDef::new(PatientsTable::MobileNumber).string().null())
+        .col(ColumnDef::new(PatientsTable::ResidentialAddress).string().null())
+        .col(ColumnDef::new(PatientsTable::AddressLine).text().null())
+        .col(ColumnDef::new(PatientsTable::RegionCode).string_len(10).null())
+        .col(ColumnDef::new(PatientsTable::ProvinceCode).string_len(10).null())
+        .col(ColumnDef::new(PatientsTable::CityMunicipalityCode).string_len(10).null())
+        .col(ColumnDef::new(PatientsTable::BarangayCode).string_len(10).null())
+        .col(
+            ColumnDef::new(PatientsTable::IsArchived)
+                .boolean()
+                .not_null()
+                .default(false),
+        )
+        .col(ColumnDef::new(PatientsTable::ArchivedAt).timestamp_with_time_zone().null())
+        .col(
+            ColumnDef::new(PatientsTable::CreatedAt)
+                .timestamp_with_time_zone()
+                .not_null()
+                .default(Expr::current_timestamp()),
+        )
+        .col(
+            ColumnDef::new(PatientsTable::UpdatedAt)
+                .timestamp_with_time_zone()
+                .not_null()
+                .default(Expr::current_timestamp()),
+        )
+        .col(ColumnDef::new(PatientsTable::LastNameBidx).string_len(64).null())
+        .col(ColumnDef::new(PatientsTable::MobileNumberBidx).string_len(64).null())
+        .col(ColumnDef::new(PatientsTable::CsdIdOrPwdIdBidx).string_len(64).null())
+        .to_owned();
+    if key == KeyType::Integer {
+        table.col(ColumnDef::new(PatientsTable::PatientUuid).uuid().null());
+    }
+    table
+}
+
+fn medical_records_table(name: &str, patients: &str, key: KeyType) -> TableCreateStatement {
+    let mut table = Table::create()
+        .table(Alias::new(name))
+        .col(key.primary_key(MedicalRecordsTable::MedicalId))
+        .col(key.reference(MedicalRecordsTable::PatientId))
+        .col(ColumnDef::new(MedicalRecordsTable::Assessment).text().null())
+        .col(ColumnDef::new(MedicalRecordsTable::Diagnosis).text().null())
+        .col(ColumnDef::new(MedicalRecordsTable::Treatment).text().null())
+        .col(ColumnDef::new(MedicalRecordsTable::Prescription).text().null())
+        .col(ColumnDef::new(MedicalRecordsTable::FirstAuditedBy).string().not_null())
+        .col(ColumnDef::new(MedicalRecordsTable::LastAuditedBy).string().null())
+        .col(
+            ColumnDef::new(MedicalRecordsTable::CreatedAt)
+                .timestamp_with_time_zone()
+                .not_null()
+                .default(Expr::current_timestamp()),
+        )
+        .col(
+            ColumnDef::new(MedicalRecordsTable::UpdatedAt)
+                .timestamp_with_time_zone()
+                .not_null()
+                .default(Expr::current_timestamp()),
+        )
+        .foreign_key(
+            ForeignKey::create()
+                .name("fk_medical_records_patient_id")
+                .from(Alias::new(name), MedicalRecordsTable::PatientId)
+                .to(Alias::new(patients), PatientsTable::PatientId)
+                .on_delete(ForeignKeyAction::Cascade)
+                .on_update(ForeignKeyAction::Cascade),
+        )
+        .to_owned();
+    if key == KeyType::Integer {
+        table.col(ColumnDef::new(MedicalRecordsTable::MedicalUuid).uuid().null());
+    }
+    table
+}
+
+#[derive(DeriveIden, Clone, Copy)]
+enum PatientsTable {
+    Table,
+    PatientId,
+    PatientNumber,
+    FirstName,
+    LastName,
+    MiddleName,
+    Age,
+    BirthDate,
+    CsdIdOrPwdId,
+    MobileNumber,
+    ResidentialAddress,
+    AddressLine,
+    RegionCode,
+    ProvinceCode,
+    CityMunicipalityCode,
+    BarangayCode,
+    IsArchived,
+    ArchivedAt,
+    CreatedAt,
+    UpdatedAt,
+    LastNameBidx,
+    MobileNumberBidx,
+    CsdIdOrPwdIdBidx,
+    /// Only while keys are integers; see the migration.
+    PatientUuid,
+}
+
+#[derive(DeriveIden, Clone, Copy)]
+enum MedicalRecordsTable {
+    Table,
+    MedicalId,
+    PatientId,
+    Assessment,
+    Diagnosis,
+    Treatment,
+    Prescription,
+    FirstAuditedBy,
+    LastAuditedBy,
+    CreatedAt,
+    UpdatedAt,
+    /// Only while keys are integers; see the migration.
+    MedicalUuid,
+}
+
+#[derive(DeriveIden)]
+enum PatientKeyMap {
+    Table,
+    OldId,
+    NewId,
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::migrations::Migrator;
+    use sea_orm_migration::sea_orm::{ConnectionTrait, Database, DatabaseConnection, Statement};
+    use sea_orm_migration::MigratorTrait;
+    use uuid::Uuid;
+
+    async fn rows(db: &DatabaseConnection, sql: &str) -> Vec<sea_orm_migration::sea_orm::QueryResult> {
+        db.query_all(Statement::from_string(db.get_database_backend(), sql)).await.unwrap()
+    }
+
+    /// `(last name, assessment)` of every record, joined through its patient key.
+    async fn links(db: &DatabaseConnection) -> Vec<(String, String)> {
+        rows(
+            db,
+            "SELECT p.last_name, r.assessment FROM medical_records_table r \
+             JOIN patients_table p ON p.patient_id = r.patient_id ORDER BY r.assessment",
+        )
+        .await
+        .iter()
+        .map(|row| (row.try_get("", "last_name").unwrap(), row.try_get("", "assessment").unwrap()))
+        .collect()
+    }
+
+    /// `(name, UUID)` pairs read from `column` of `table`, ordered by name.
+    async fn uuids(db: &DatabaseConnection, table: &str, name: &str, column: &str) -> Vec<(String, Uuid)> {
+        rows(db, &format!("SELECT {name}, {column} FROM {table} ORDER BY {name}"))
+            .await
+            .iter()
+            .map(|row| (row.try_get("", name).unwrap(), row.try_get("", column).unwrap()))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn keys_and_links_survive_up_down_up() {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        Migrator::up(&db, Some(9)).await.unwrap();
+        db.execute_unprepared(
+            "INSERT INTO patients_table (first_name, last_name, age, birth_date, created_at) VALUES \
+             ('Juan', 'Dela Cruz', 35, '1990-05-17', '2024-01-02T08:00:00+00:00'), \
+             ('Maria', 'Santos', 41, '1984-11-03', '2024-01-03T08:00:00+00:00'); \
+             INSERT INTO medical_records_table (patient_id, assessment, first_audited_by, created_at) VALUES \
+             (2, 'a: Santos cough', 'Dr. Reyes', '2024-02-01T08:00:00+00:00'), \
+             (1, 'b: Dela Cruz fever', 'Dr. Reyes', '2024-02-02T08:00:00+00:00'), \
+             (2, 'c: Santos follow-up', 'Dr. Reyes', '2024-02-03T08:00:00+00:00');",
+        )
+        .await
+        .unwrap();
+        let expected = vec![
+            ("Santos".to_string(), "a: Santos cough".to_string()),
+            ("Dela Cruz".to_string(), "b: Dela Cruz fever".to_string()),
+            ("Santos".to_string(), "c: Santos follow-up".to_string()),
+        ];
+        assert_eq!(links(&db).await, expected);
+
+        Migrator::up(&db, Some(1)).await.unwrap();
+        assert_eq!(links(&db).await, expected);
+        let patient_keys = uuids(&db, "patients_table", "last_name", "patient_id").await;
+        let record_keys = uuids(&db, "medical_records_table", "assessment", "medical_id").await;
+        assert_eq!(patient_keys.len(), 2);
+        assert_eq!(record_keys.len(), 3);
+
+        Migrator::down(&db, Some(1)).await.unwrap();
+        assert_eq!(links(&db).await, expected);
+        assert_eq!(uuids(&db, "patients_table", "last_name", "patient_uuid").await, patient_keys);
+        assert_eq!(uuids(&db, "medical_records_table", "assessment", "medical_uuid").await, record_keys);
+        let ids: Vec<i32> = rows(&db, "SELECT patient_id FROM patients_table ORDER BY created_at")
+            .await
+            .iter()
+            .map(|row| row.try_get("", "patient_id").unwrap())
+            .collect();
+        assert_eq!(ids, vec![1, 2]);
+
+        Migrator::up(&db, Some(1)).await.unwrap();
+        assert_eq!(links(&db).await, expected);
+        assert_eq!(uuids(&db, "patients_table", "last_name", "patient_id").await, patient_keys);
+        assert_eq!(uuids(&db, "medical_records_table", "assessment", "medical_id").await, record_keys);
+    }
+}
@@ -0,0 +1,373 @@
+use sea_orm_migration::prelude::extension::postgres::Type;
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::DbBackend;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+const SERVICE_CATEGORIES: [&str; 16] = [
+    "Hematology",
+    "Bacteriology",
+    "Clinical Microscopy",
+    "24 Hour Urine Test",
+    "Serology and Immunology",
+    "Clinical Chemistry",
+    "Electrolytes",
+    "Vaccine",
+    "Hispatology",
+    "To be read by pathologist",
+    "Tumor Markers",
+    "Thyroid Function test",
+    "Hormones",
+    "Hepatitis",
+    "Enzymes",
+    "Others",
+];
+const PAYMENT_STATUSES: [&str; 3] = ["Paid", "Unpaid", "Partially Paid"];
+const ROLES: [&str; 2] = ["Admin", "Medtech"];
+
+/// Postgres enum types, with the values the entities' `string_value`s store.
+const ENUM_TYPES: [(&str, &[&str]); 3] = [
+    ("service_category", &SERVICE_CATEGORIES),
+    ("payment_status_enum", &PAYMENT_STATUSES),
+    ("role_enum", &ROLES),
+];
+
+/// A column of one of the enum types above. SQLite has no enum types, so
+/// there the column is a string limited to the same values by a CHECK.
+fn enum_column<T: Iden + Copy + 'static>(backend: DbBackend, column: T, type_name: &str, values: &[&str]) -> ColumnDef {
+    let mut def = ColumnDef::new(column);
+    if backend == DbBackend::Postgres {
+        def.custom(Alias::new(type_name));
+    } else {
+        def.string_len(32).check(Expr::col(column).is_in(values.iter().copied()));
+    }
+    def.not_null().to_owned()
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let backend = manager.get_database_backend();
+        if backend == DbBackend::Postgres {
+            for (name, values) in ENUM_TYPES {
+                manager
+                    .create_type(
+                        Type::create()
+                            .as_enum(Alias::new(name))
+                            .values(values.iter().map(|value| Alias::new(*value)))
+                            .to_owned(),
+                    )
+                    .await?;
+            }
+        }
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(MedicalServicesTable::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(MedicalServicesTable::MsId)
+                            .uuid()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(MedicalServicesTable::MsName).string().not_null())
+                    .col(enum_column(
+                        backend,
+                        MedicalServicesTable::MsCategory,
+                        "service_category",
+                        &SERVICE_CATEGORIES,
+                    ))
+                    .col(ColumnDef::new(MedicalServicesTable::MsPrice).float().not_null())
+                    .col(
+                        ColumnDef::new(MedicalServicesTable::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .col(
+                        ColumnDef::new(MedicalServicesTable::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Bills can't be removed with the patient or visit they were issued for.
+        manager
+            .create_table(
+                Table::create()
+                    .table(MedicalBillRecordsTable::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(MedicalBillRecordsTable::MedicalBillId)
+                            .uuid()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(MedicalBillRecordsTable::PatientId).uuid().not_null())
+                    .col(ColumnDef::new(MedicalBillRecordsTable::MedicalId).uuid().not_null())
+                    .col(ColumnDef::new(MedicalBillRecordsTable::ConsultationFee).float().null())
+                    .col(ColumnDef::new(MedicalBillRecordsTable::Remarks).string().null())
+                    .col(enum_column(
+                        backend,
+                        MedicalBillRecordsTable::PaymentStatus,
+                        "payment_status_enum",
+                        &PAYMENT_STATUSES,
+                    ))
+                    .col(ColumnDef::new(MedicalBillRecordsTable::TotalAmount).float().not_null())
+                    // Bills are financial records: deleting a patient or visit that
+                    // has them is refused (the API answers 409), and only a retention
+                    // purge removes them, explicitly and before the patient.
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_medical_bill_records_patient")
+                            .from(MedicalBillRecordsTable::Table, MedicalBillRecordsTable::PatientId)
+                            .to(PatientsTable::Table, PatientsTable::PatientId)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Restrict),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_medical_bill_records_medical_record")
+                            .from(MedicalBillRecordsTable::Table, MedicalBillRecordsTable::MedicalId)
+                            .to(MedicalRecordsTable::Table, MedicalRecordsTable::MedicalId)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Restrict),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Service lines go with their bill; a catalog service stays while billed.
+        manager
+            .create_table(
+                Table::create()
+                    .table(MedicalServicesProvidedTable::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(MedicalServicesProvidedTable::MrsId)
+                            .uuid()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(MedicalServicesProvidedTable::MedicalBillId).uuid().not_null())
+                    .col(ColumnDef::new(MedicalServicesProvidedTable::MsId).uuid().not_null())
+                    .col(ColumnDef::new(MedicalServicesProvidedTable::ServiceName).string().not_null())
+                    .col(enum_column(
+                        backend,
+                        MedicalServicesProvidedTable::ServiceCategory,
+                        "service_category",
+                        &SERVICE_CATEGORIES,
+                    ))
+                    .col(ColumnDef::new(MedicalServicesProvidedTable::Price).float().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_medical_services_provided_bill")
+                            .from(MedicalServicesProvidedTable::Table, MedicalServicesProvidedTable::MedicalBillId)
+                            .to(MedicalBillRecordsTable::Table, MedicalBillRecordsTable::MedicalBillId)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_medical_services_provided_service")
+                            .from(MedicalServicesProvidedTable::Table, MedicalServicesProvidedTable::MsId)
+                            .to(MedicalServicesTable::Table, MedicalServicesTable::MsId)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Restrict),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(AccountsTable::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(AccountsTable::AccountId)
+                            .uuid()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(AccountsTable::FirstName).string().not_null())
+                    .col(ColumnDef::new(AccountsTable::LastName).string().not_null())
+                    .col(ColumnDef::new(AccountsTable::MiddleName).string().not_null())
+                    .col(enum_column(backend, AccountsTable::Role, "role_enum", &ROLES))
+                    .col(ColumnDef::new(AccountsTable::Email).string().not_null())
+                    .col(ColumnDef::new(AccountsTable::Username).string().not_null())
+                    .col(ColumnDef::new(AccountsTable::Password).string().not_null())
+                    .to_owned(),
+            )
+            .await?;
+        // Sign-in looks accounts up by username, so it must name exactly one.
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_accounts_username")
+                    .table(AccountsTable::Table)
+                    .col(AccountsTable::Username)
+                    .unique()
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await?;
+
+        for (name, table, column) in [
+            (
+                "idx_medical_bill_records_patient_id",
+                MedicalBillRecordsTable::Table.into_iden(),
+                MedicalBillRecordsTable::PatientId.into_iden(),
+            ),
+            (
+                "idx_medical_bill_records_medical_id",
+                MedicalBillRecordsTable::Table.into_iden(),
+                MedicalBillRecordsTable::MedicalId.into_iden(),
+            ),
+            (
+                "idx_medical_services_provided_medical_bill_id",
+                MedicalServicesProvidedTable::Table.into_iden(),
+                MedicalServicesProvidedTable::MedicalBillId.into_iden(),
+            ),
+            (
+                "idx_medical_services_provided_ms_id",
+                MedicalServicesProvidedTable::Table.into_iden(),
+                MedicalServicesProvidedTable::MsId.into_iden(),
+            ),
+        ] {
+            manager
+                .create_index(
+                    Index::create()
+                        .name(name)
+                        .table(table)
+                        .col(column)
+                        .if_not_exists()
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(AccountsTable::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(MedicalServicesProvidedTable::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(MedicalBillRecordsTable::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(MedicalServicesTable::Table).to_owned())
+            .await?;
+
+        if manager.get_database_backend() == DbBackend::Postgres {
+            for (name, _) in ENUM_TYPES {
+                manager
+                    .drop_type(Type::drop().if_exists().name(Alias::new(name)).to_owned())
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum PatientsTable {
+    Table,
+    PatientId,
+}
+
+#[derive(DeriveIden)]
+enum MedicalRecordsTable {
+    Table,
+    MedicalId,
+}
+
+#[derive(DeriveIden, Clone, Copy)]
+enum MedicalServicesTable {
+    Table,
+    MsId,
+    MsName,
+    MsCategory,
+    MsPrice,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden, Clone, Copy)]
+enum MedicalBillRecordsTable {
+    Table,
+    MedicalBillId,
+    PatientId,
+    MedicalId,
+    ConsultationFee,
+    Remarks,
+    PaymentStatus,
+    TotalAmount,
+}
+
+#[derive(DeriveIden, Clone, Copy)]
+enum MedicalServicesProvidedTable {
+    Table,
+    MrsId,
+    MedicalBillId,
+    MsId,
+    ServiceName,
+    ServiceCategory,
+    Price,
+}
+
+#[derive(DeriveIden, Clone, Copy)]
+enum AccountsTable {
+    Table,
+    AccountId,
+    FirstName,
+    LastName,
+    MiddleName,
+    Role,
+    Email,
+    Username,
+    Password,
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::migrations::Migrator;
+    use crate::models::accounts::{self, Role};
+    use sea_orm::{ActiveModelTrait, Database, Set};
+    use sea_orm_migration::MigratorTrait;
+    use uuid::Uuid;
+
+    fn account(username: &str) -> accounts::ActiveModel {
+        accounts::ActiveModel {
+            account_id: Set(Uuid::new_v4()),
+            first_name: Set("Maria".to_string()),
+            last_name: Set("Santos".to_string()),
+            middle_name: Set("Reyes".to_string()),
+            role: Set(Role::Medtech),
+            email: Set(format!("{}@example.com", username)),
+            username: Set(username.to_string()),
+            password: Set("hash".to_string()),
+        }
+    }
+
+    #[tokio::test]
+    async fn usernames_are_unique() {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        Migrator::up(&db, None).await.unwrap();
+
+        account("msantos").insert(&db).await.unwrap();
+        account("jcruz").insert(&db).await.unwrap();
+        assert!(account("msantos").insert(&db).await.is_err());
+    }
+}
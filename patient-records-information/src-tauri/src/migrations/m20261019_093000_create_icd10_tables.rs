@@ -0,0 +1,139 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Icd10CodesTable::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Icd10CodesTable::Code)
+                            .string_len(8)
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Icd10CodesTable::Description).text().not_null())
+                    .col(ColumnDef::new(Icd10CodesTable::Category).string_len(3).not_null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_icd10_codes_category")
+                    .table(Icd10CodesTable::Table)
+                    .col(Icd10CodesTable::Category)
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await?;
+
+        // Coded diagnoses go with their record; a code stays while it is used.
+        manager
+            .create_table(
+                Table::create()
+                    .table(MedicalRecordDiagnosesTable::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(MedicalRecordDiagnosesTable::DiagnosisId)
+                            .uuid()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(MedicalRecordDiagnosesTable::MedicalId).uuid().not_null())
+                    .col(ColumnDef::new(MedicalRecordDiagnosesTable::Icd10Code).string_len(8).not_null())
+                    .col(ColumnDef::new(MedicalRecordDiagnosesTable::Rank).string_len(16).not_null())
+                    .col(ColumnDef::new(MedicalRecordDiagnosesTable::Sequence).integer().not_null())
+                    .col(
+                        ColumnDef::new(MedicalRecordDiagnosesTable::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_medical_record_diagnoses_medical_record")
+                            .from(MedicalRecordDiagnosesTable::Table, MedicalRecordDiagnosesTable::MedicalId)
+                            .to(MedicalRecordsTable::Table, MedicalRecordsTable::MedicalId)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_medical_record_diagnoses_icd10_code")
+                            .from(MedicalRecordDiagnosesTable::Table, MedicalRecordDiagnosesTable::Icd10Code)
+                            .to(Icd10CodesTable::Table, Icd10CodesTable::Code)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Restrict),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // A code appears once per record; the index also serves lookups by record.
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_medical_record_diagnoses_medical_id_code")
+                    .table(MedicalRecordDiagnosesTable::Table)
+                    .col(MedicalRecordDiagnosesTable::MedicalId)
+                    .col(MedicalRecordDiagnosesTable::Icd10Code)
+                    .unique()
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_medical_record_diagnoses_icd10_code")
+                    .table(MedicalRecordDiagnosesTable::Table)
+                    .col(MedicalRecordDiagnosesTable::Icd10Code)
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(MedicalRecordDiagnosesTable::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(Icd10CodesTable::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum MedicalRecordsTable {
+    Table,
+    MedicalId,
+}
+
+#[derive(DeriveIden)]
+enum Icd10CodesTable {
+    Table,
+    Code,
+    Description,
+    Category,
+}
+
+#[derive(DeriveIden)]
+enum MedicalRecordDiagnosesTable {
+    Table,
+    DiagnosisId,
+    MedicalId,
+    Icd10Code,
+    Rank,
+    Sequence,
+    CreatedAt,
+}
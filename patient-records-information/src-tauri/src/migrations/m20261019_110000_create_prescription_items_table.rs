@@ -0,0 +1,101 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(PrescriptionItemsTable::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(PrescriptionItemsTable::PrescriptionItemId)
+                            .uuid()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(PrescriptionItemsTable::MedicalId).uuid().not_null())
+                    .col(ColumnDef::new(PrescriptionItemsTable::GenericName).string().not_null())
+                    .col(ColumnDef::new(PrescriptionItemsTable::BrandName).string().null())
+                    .col(ColumnDef::new(PrescriptionItemsTable::Strength).string().null())
+                    .col(ColumnDef::new(PrescriptionItemsTable::Dose).string().not_null())
+                    .col(ColumnDef::new(PrescriptionItemsTable::Route).string_len(16).not_null())
+                    .col(ColumnDef::new(PrescriptionItemsTable::Frequency).string().not_null())
+                    .col(ColumnDef::new(PrescriptionItemsTable::Duration).string().null())
+                    .col(ColumnDef::new(PrescriptionItemsTable::Quantity).integer().null())
+                    .col(ColumnDef::new(PrescriptionItemsTable::Instructions).text().null())
+                    .col(ColumnDef::new(PrescriptionItemsTable::Sequence).integer().not_null())
+                    .col(ColumnDef::new(PrescriptionItemsTable::FirstAuditedBy).string().not_null())
+                    .col(ColumnDef::new(PrescriptionItemsTable::LastAuditedBy).string().null())
+                    .col(
+                        ColumnDef::new(PrescriptionItemsTable::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .col(
+                        ColumnDef::new(PrescriptionItemsTable::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_prescription_items_medical_record")
+                            .from(PrescriptionItemsTable::Table, PrescriptionItemsTable::MedicalId)
+                            .to(MedicalRecordsTable::Table, MedicalRecordsTable::MedicalId)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_prescription_items_medical_id")
+                    .table(PrescriptionItemsTable::Table)
+                    .col(PrescriptionItemsTable::MedicalId)
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(PrescriptionItemsTable::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum MedicalRecordsTable {
+    Table,
+    MedicalId,
+}
+
+#[derive(DeriveIden)]
+enum PrescriptionItemsTable {
+    Table,
+    PrescriptionItemId,
+    MedicalId,
+    GenericName,
+    BrandName,
+    Strength,
+    Dose,
+    Route,
+    Frequency,
+    Duration,
+    Quantity,
+    Instructions,
+    Sequence,
+    FirstAuditedBy,
+    LastAuditedBy,
+    CreatedAt,
+    UpdatedAt,
+}
@@ -0,0 +1,128 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Vitals outlive the visit they were taken at, so deleting a record
+        // only unlinks them.
+        manager
+            .create_table(
+                Table::create()
+                    .table(VitalSignsTable::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(VitalSignsTable::VitalSignId)
+                            .uuid()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(VitalSignsTable::PatientId).uuid().not_null())
+                    .col(ColumnDef::new(VitalSignsTable::MedicalId).uuid().null())
+                    .col(ColumnDef::new(VitalSignsTable::SystolicBp).integer().null())
+                    .col(ColumnDef::new(VitalSignsTable::DiastolicBp).integer().null())
+                    .col(ColumnDef::new(VitalSignsTable::PulseRate).integer().null())
+                    .col(ColumnDef::new(VitalSignsTable::TemperatureC).float().null())
+                    .col(ColumnDef::new(VitalSignsTable::WeightKg).float().null())
+                    .col(ColumnDef::new(VitalSignsTable::HeightCm).float().null())
+                    .col(ColumnDef::new(VitalSignsTable::Bmi).float().null())
+                    .col(ColumnDef::new(VitalSignsTable::MeasuredAt).timestamp_with_time_zone().not_null())
+                    .col(ColumnDef::new(VitalSignsTable::RecordedBy).string().not_null())
+                    .col(
+                        ColumnDef::new(VitalSignsTable::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_vital_signs_patient")
+                            .from(VitalSignsTable::Table, VitalSignsTable::PatientId)
+                            .to(PatientsTable::Table, PatientsTable::PatientId)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_vital_signs_medical_record")
+                            .from(VitalSignsTable::Table, VitalSignsTable::MedicalId)
+                            .to(MedicalRecordsTable::Table, MedicalRecordsTable::MedicalId)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::SetNull),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_vital_signs_patient_id")
+                    .table(VitalSignsTable::Table)
+                    .col(VitalSignsTable::PatientId)
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_vital_signs_medical_id")
+                    .table(VitalSignsTable::Table)
+                    .col(VitalSignsTable::MedicalId)
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_vital_signs_measured_at")
+                    .table(VitalSignsTable::Table)
+                    .col(VitalSignsTable::MeasuredAt)
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(VitalSignsTable::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum PatientsTable {
+    Table,
+    PatientId,
+}
+
+#[derive(DeriveIden)]
+enum MedicalRecordsTable {
+    Table,
+    MedicalId,
+}
+
+#[derive(DeriveIden)]
+enum VitalSignsTable {
+    Table,
+    VitalSignId,
+    PatientId,
+    MedicalId,
+    SystolicBp,
+    DiastolicBp,
+    PulseRate,
+    TemperatureC,
+    WeightKg,
+    HeightCm,
+    Bmi,
+    MeasuredAt,
+    RecordedBy,
+    CreatedAt,
+}
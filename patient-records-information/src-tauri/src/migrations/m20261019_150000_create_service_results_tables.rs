@@ -0,0 +1,172 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(ServiceAnalytesTable::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(ServiceAnalytesTable::AnalyteId)
+                            .uuid()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(ServiceAnalytesTable::MsId).uuid().not_null())
+                    .col(ColumnDef::new(ServiceAnalytesTable::Code).string_len(16).not_null())
+                    .col(ColumnDef::new(ServiceAnalytesTable::Name).string().not_null())
+                    .col(ColumnDef::new(ServiceAnalytesTable::Unit).string().null())
+                    .col(ColumnDef::new(ServiceAnalytesTable::ValueType).string_len(16).not_null())
+                    .col(ColumnDef::new(ServiceAnalytesTable::ReferenceLow).float().null())
+                    .col(ColumnDef::new(ServiceAnalytesTable::ReferenceHigh).float().null())
+                    .col(ColumnDef::new(ServiceAnalytesTable::CriticalLow).float().null())
+                    .col(ColumnDef::new(ServiceAnalytesTable::CriticalHigh).float().null())
+                    .col(ColumnDef::new(ServiceAnalytesTable::NormalText).string().null())
+                    .col(ColumnDef::new(ServiceAnalytesTable::Sequence).integer().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_service_analytes_medical_service")
+                            .from(ServiceAnalytesTable::Table, ServiceAnalytesTable::MsId)
+                            .to(MedicalServicesTable::Table, MedicalServicesTable::MsId)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // An analyte code appears once per service; the index also serves lookups by service.
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_service_analytes_ms_id_code")
+                    .table(ServiceAnalytesTable::Table)
+                    .col(ServiceAnalytesTable::MsId)
+                    .col(ServiceAnalytesTable::Code)
+                    .unique()
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(ServiceResultsTable::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(ServiceResultsTable::ServiceResultId)
+                            .uuid()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(ServiceResultsTable::MrsId).uuid().not_null())
+                    .col(ColumnDef::new(ServiceResultsTable::AnalyteCode).string_len(16).not_null())
+                    .col(ColumnDef::new(ServiceResultsTable::AnalyteName).string().not_null())
+                    .col(ColumnDef::new(ServiceResultsTable::Value).text().not_null())
+                    .col(ColumnDef::new(ServiceResultsTable::NumericValue).float().null())
+                    .col(ColumnDef::new(ServiceResultsTable::Unit).string().null())
+                    .col(ColumnDef::new(ServiceResultsTable::ReferenceRange).string().null())
+                    .col(ColumnDef::new(ServiceResultsTable::Flag).string_len(2).null())
+                    .col(ColumnDef::new(ServiceResultsTable::EnteredBy).string().not_null())
+                    .col(ColumnDef::new(ServiceResultsTable::LastAuditedBy).string().null())
+                    .col(
+                        ColumnDef::new(ServiceResultsTable::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .col(
+                        ColumnDef::new(ServiceResultsTable::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_service_results_service_provided")
+                            .from(ServiceResultsTable::Table, ServiceResultsTable::MrsId)
+                            .to(MedicalServicesProvidedTable::Table, MedicalServicesProvidedTable::MrsId)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_service_results_mrs_id_analyte_code")
+                    .table(ServiceResultsTable::Table)
+                    .col(ServiceResultsTable::MrsId)
+                    .col(ServiceResultsTable::AnalyteCode)
+                    .unique()
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ServiceResultsTable::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(ServiceAnalytesTable::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum MedicalServicesTable {
+    Table,
+    MsId,
+}
+
+#[derive(DeriveIden)]
+enum MedicalServicesProvidedTable {
+    Table,
+    MrsId,
+}
+
+#[derive(DeriveIden)]
+enum ServiceAnalytesTable {
+    Table,
+    AnalyteId,
+    MsId,
+    Code,
+    Name,
+    Unit,
+    ValueType,
+    ReferenceLow,
+    ReferenceHigh,
+    CriticalLow,
+    CriticalHigh,
+    NormalText,
+    Sequence,
+}
+
+#[derive(DeriveIden)]
+enum ServiceResultsTable {
+    Table,
+    ServiceResultId,
+    MrsId,
+    AnalyteCode,
+    AnalyteName,
+    Value,
+    NumericValue,
+    Unit,
+    ReferenceRange,
+    Flag,
+    EnteredBy,
+    LastAuditedBy,
+    CreatedAt,
+    UpdatedAt,
+}
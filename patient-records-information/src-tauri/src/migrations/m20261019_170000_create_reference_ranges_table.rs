@@ -0,0 +1,153 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Existing patients start with their sex unrecorded; only ranges for
+        // both sexes apply to them until it is filled in.
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(PatientsTable::Table)
+                    .add_column_if_not_exists(ColumnDef::new(PatientsTable::Sex).string_len(8).null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(AnalyteReferenceRangesTable::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(AnalyteReferenceRangesTable::ReferenceRangeId)
+                            .uuid()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(AnalyteReferenceRangesTable::AnalyteCode).string_len(16).not_null())
+                    .col(ColumnDef::new(AnalyteReferenceRangesTable::Sex).string_len(8).null())
+                    .col(ColumnDef::new(AnalyteReferenceRangesTable::MinAgeDays).integer().not_null())
+                    .col(ColumnDef::new(AnalyteReferenceRangesTable::MaxAgeDays).integer().null())
+                    .col(ColumnDef::new(AnalyteReferenceRangesTable::Unit).string().not_null())
+                    .col(ColumnDef::new(AnalyteReferenceRangesTable::ReferenceLow).float().null())
+                    .col(ColumnDef::new(AnalyteReferenceRangesTable::ReferenceHigh).float().null())
+                    .col(ColumnDef::new(AnalyteReferenceRangesTable::CriticalLow).float().null())
+                    .col(ColumnDef::new(AnalyteReferenceRangesTable::CriticalHigh).float().null())
+                    .col(ColumnDef::new(AnalyteReferenceRangesTable::CreatedBy).uuid().not_null())
+                    .col(ColumnDef::new(AnalyteReferenceRangesTable::UpdatedBy).uuid().null())
+                    .col(
+                        ColumnDef::new(AnalyteReferenceRangesTable::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .col(
+                        ColumnDef::new(AnalyteReferenceRangesTable::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_analyte_reference_ranges_analyte_code")
+                    .table(AnalyteReferenceRangesTable::Table)
+                    .col(AnalyteReferenceRangesTable::AnalyteCode)
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await?;
+
+        // One column per statement: SQLite can't add several at once.
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ServiceResultsTable::Table)
+                    .add_column_if_not_exists(
+                        ColumnDef::new(ServiceResultsTable::FlagOverridden).boolean().not_null().default(false),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ServiceResultsTable::Table)
+                    .add_column_if_not_exists(
+                        ColumnDef::new(ServiceResultsTable::ReleasedAt).timestamp_with_time_zone().null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ServiceResultsTable::Table)
+                    .add_column_if_not_exists(ColumnDef::new(ServiceResultsTable::ReleasedBy).string().null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for column in [ServiceResultsTable::ReleasedBy, ServiceResultsTable::ReleasedAt, ServiceResultsTable::FlagOverridden] {
+            manager
+                .alter_table(Table::alter().table(ServiceResultsTable::Table).drop_column(column).to_owned())
+                .await?;
+        }
+
+        manager
+            .drop_table(Table::drop().table(AnalyteReferenceRangesTable::Table).to_owned())
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(PatientsTable::Table)
+                    .drop_column(PatientsTable::Sex)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum PatientsTable {
+    Table,
+    Sex,
+}
+
+#[derive(DeriveIden)]
+enum AnalyteReferenceRangesTable {
+    Table,
+    ReferenceRangeId,
+    AnalyteCode,
+    Sex,
+    MinAgeDays,
+    MaxAgeDays,
+    Unit,
+    ReferenceLow,
+    ReferenceHigh,
+    CriticalLow,
+    CriticalHigh,
+    CreatedBy,
+    UpdatedBy,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum ServiceResultsTable {
+    Table,
+    FlagOverridden,
+    ReleasedAt,
+    ReleasedBy,
+}
@@ -0,0 +1,83 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Filled by `migrate seed` from the service catalog, or through the API.
+        manager
+            .create_table(
+                Table::create()
+                    .table(UnitConversionsTable::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(UnitConversionsTable::AnalyteCode)
+                            .string_len(16)
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(UnitConversionsTable::SiUnit).string().not_null())
+                    .col(ColumnDef::new(UnitConversionsTable::ConventionalUnit).string().not_null())
+                    .col(ColumnDef::new(UnitConversionsTable::Factor).float().not_null())
+                    .col(ColumnDef::new(UnitConversionsTable::SiDecimals).integer().not_null())
+                    .col(ColumnDef::new(UnitConversionsTable::ConventionalDecimals).integer().not_null())
+                    .col(ColumnDef::new(UnitConversionsTable::UpdatedBy).uuid().null())
+                    .col(
+                        ColumnDef::new(UnitConversionsTable::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Results entered before this keep their range as text only; it isn't
+        // converted when they are shown in another unit.
+        for column in [ServiceResultsTable::ReferenceLow, ServiceResultsTable::ReferenceHigh] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(ServiceResultsTable::Table)
+                        .add_column_if_not_exists(ColumnDef::new(column).float().null())
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for column in [ServiceResultsTable::ReferenceHigh, ServiceResultsTable::ReferenceLow] {
+            manager
+                .alter_table(Table::alter().table(ServiceResultsTable::Table).drop_column(column).to_owned())
+                .await?;
+        }
+
+        manager
+            .drop_table(Table::drop().table(UnitConversionsTable::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum UnitConversionsTable {
+    Table,
+    AnalyteCode,
+    SiUnit,
+    ConventionalUnit,
+    Factor,
+    SiDecimals,
+    ConventionalDecimals,
+    UpdatedBy,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum ServiceResultsTable {
+    Table,
+    ReferenceLow,
+    ReferenceHigh,
+}
@@ -0,0 +1,349 @@
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::{ConnectionTrait, DbBackend};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+/// Lab results, consents and household members were created without a
+/// foreign key to `patients_table`, whose key was still an integer then.
+/// Postgres adds the keys in place; SQLite can't add a foreign key to an
+/// existing table, so each table is rebuilt with it. Rows pointing at a
+/// missing patient stop the migration: `migrate check` lists them, and
+/// `--fix` removes the household links among them.
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for table in PatientTable::ALL {
+            table.check_orphans(manager).await?;
+        }
+        for table in PatientTable::ALL {
+            match manager.get_database_backend() {
+                DbBackend::Sqlite => table.rebuild(manager, true).await?,
+                _ => manager.create_foreign_key(table.foreign_key().to_owned()).await?,
+            }
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for table in PatientTable::ALL {
+            match manager.get_database_backend() {
+                DbBackend::Sqlite => table.rebuild(manager, false).await?,
+                _ => {
+                    manager
+                        .drop_foreign_key(
+                            ForeignKey::drop()
+                                .name(table.foreign_key_name())
+                                .table(table.iden())
+                                .to_owned(),
+                        )
+                        .await?
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The tables that get a foreign key to `patients_table`.
+#[derive(Clone, Copy)]
+enum PatientTable {
+    LabResults,
+    Consents,
+    HouseholdMembers,
+}
+
+impl PatientTable {
+    const ALL: [PatientTable; 3] = [PatientTable::LabResults, PatientTable::Consents, PatientTable::HouseholdMembers];
+
+    fn iden(self) -> DynIden {
+        match self {
+            PatientTable::LabResults => LabResultsTable::Table.into_iden(),
+            PatientTable::Consents => ConsentsTable::Table.into_iden(),
+            PatientTable::HouseholdMembers => HouseholdMembersTable::Table.into_iden(),
+        }
+    }
+
+    fn foreign_key_name(self) -> String {
+        format!("fk_{}_patient_id", self.iden().to_string().trim_end_matches("_table"))
+    }
+
+    /// Lab results and household links go with their patient; consents are
+    /// kept as evidence, so a patient who gave one can't simply be deleted
+    /// (retention disposal removes them first).
+    fn foreign_key(self) -> ForeignKeyCreateStatement {
+        let on_delete = match self {
+            PatientTable::Consents => ForeignKeyAction::Restrict,
+            PatientTable::LabResults | PatientTable::HouseholdMembers => ForeignKeyAction::Cascade,
+        };
+        ForeignKey::create()
+            .name(self.foreign_key_name())
+            .from(self.iden(), Alias::new("patient_id"))
+            .to(PatientsTable::Table, PatientsTable::PatientId)
+            .on_delete(on_delete)
+            .on_update(ForeignKeyAction::Cascade)
+            .to_owned()
+    }
+
+    async fn check_orphans(self, manager: &SchemaManager<'_>) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        let backend = db.get_database_backend();
+        let count = Query::select()
+            .expr_as(Expr::cust("COUNT(*)"), Alias::new("orphans"))
+            .from(self.iden())
+            .and_where(
+                Expr::col(Alias::new("patient_id")).not_in_subquery(
+                    Query::select()
+                        .column(PatientsTable::PatientId)
+                        .from(PatientsTable::Table)
+                        .to_owned(),
+                ),
+            )
+            .to_owned();
+        let orphans: i64 = match db.query_one(backend.build(&count)).await? {
+            Some(row) => row.try_get("", "orphans")?,
+            None => 0,
+        };
+        if orphans > 0 {
+            return Err(DbErr::Migration(format!(
+                "{} row(s) of {} reference a missing patient; resolve them (see `migrate check`) before adding its foreign key",
+                orphans,
+                self.iden().to_string()
+            )));
+        }
+        Ok(())
+    }
+
+    /// Recreates the table with or without its patient foreign key, keeping
+    /// its rows and indexes.
+    async fn rebuild(self, manager: &SchemaManager<'_>, with_patient_key: bool) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        let backend = db.get_database_backend();
+        let name = self.iden().to_string();
+        let new_name = format!("{}_new", name);
+
+        let mut create = self.create(&new_name);
+        if with_patient_key {
+            let mut key = self.foreign_key();
+            key.from_tbl(Alias::new(&new_name));
+            create.foreign_key(&mut key);
+        }
+        manager.create_table(create).await?;
+
+        let columns = self.columns();
+        let copy = Query::insert()
+            .into_table(Alias::new(&new_name))
+            .columns(columns.iter().map(|column| Alias::new(*column)))
+            .select_from(
+                Query::select()
+                    .columns(columns.iter().map(|column| Alias::new(*column)))
+                    .from(self.iden())
+                    .to_owned(),
+            )
+            .map_err(|e| DbErr::Migration(e.to_string()))?
+            .to_owned();
+        db.execute(backend.build(&copy)).await?;
+
+        manager.drop_table(Table::drop().table(self.iden()).to_owned()).await?;
+        manager
+            .rename_table(Table::rename().table(Alias::new(&new_name), self.iden()).to_owned())
+            .await?;
+        for index in self.indexes() {
+            manager.create_index(index).await?;
+        }
+        Ok(())
+    }
+
+    fn columns(self) -> &'static [&'static str] {
+        match self {
+            PatientTable::LabResults => &[
+                "result_id",
+                "patient_id",
+                "order_number",
+                "test_code",
+                "test_name",
+                "analyte_code",
+                "analyte_name",
+                "value_type",
+                "value",
+                "unit",
+                "reference_range",
+                "abnormal_flag",
+                "result_status",
+                "observed_at",
+                "source",
+                "message_control_id",
+                "created_at",
+            ],
+            PatientTable::Consents => &[
+                "consent_id",
+                "patient_id",
+                "consent_type",
+                "status",
+                "consent_version",
+                "granted_on",
+                "witnessed_by",
+                "withdrawn_at",
+                "withdrawal_reason",
+                "created_at",
+                "updated_at",
+            ],
+            PatientTable::HouseholdMembers => &["patient_id", "household_id", "role", "joined_at"],
+        }
+    }
+
+    /// The table as created by its original migration, under `name`.
+    fn create(self, name: &str) -> TableCreateStatement {
+        let table = Alias::new(name);
+        match self {
+            PatientTable::LabResults => Table::create()
+                .table(table)
+                .col(ColumnDef::new(LabResultsTable::ResultId).uuid().not_null().primary_key())
+                .col(ColumnDef::new(LabResultsTable::PatientId).uuid().not_null())
+                .col(ColumnDef::new(LabResultsTable::OrderNumber).string().null())
+                .col(ColumnDef::new(LabResultsTable::TestCode).string().null())
+                .col(ColumnDef::new(LabResultsTable::TestName).string().null())
+                .col(ColumnDef::new(LabResultsTable::AnalyteCode).string().not_null())
+                .col(ColumnDef::new(LabResultsTable::AnalyteName).string().null())
+                .col(ColumnDef::new(LabResultsTable::ValueType).string().null())
+                .col(ColumnDef::new(LabResultsTable::Value).text().null())
+                .col(ColumnDef::new(LabResultsTable::Unit).string().null())
+                .col(ColumnDef::new(LabResultsTable::ReferenceRange).string().null())
+                .col(ColumnDef::new(LabResultsTable::AbnormalFlag).string().null())
+                .col(ColumnDef::new(LabResultsTable::ResultStatus).string().null())
+                .col(ColumnDef::new(LabResultsTable::ObservedAt).timestamp_with_time_zone().null())
+                .col(ColumnDef::new(LabResultsTable::Source).string().not_null())
+                .col(ColumnDef::new(LabResultsTable::MessageControlId).string().null())
+                .col(
+                    ColumnDef::new(LabResultsTable::CreatedAt)
+                        .timestamp_with_time_zone()
+                        .not_null()
+                        .default(Expr::current_timestamp()),
+                )
+                .to_owned(),
+            PatientTable::Consents => Table::create()
+                .table(table)
+                .col(ColumnDef::new(ConsentsTable::ConsentId).uuid().not_null().primary_key())
+                .col(ColumnDef::new(ConsentsTable::PatientId).uuid().not_null())
+                .col(ColumnDef::new(ConsentsTable::ConsentType).string_len(32).not_null())
+                .col(ColumnDef::new(ConsentsTable::Status).string_len(16).not_null())
+                .col(ColumnDef::new(ConsentsTable::ConsentVersion).string_len(32).not_null())
+                .col(ColumnDef::new(ConsentsTable::GrantedOn).date().not_null())
+                .col(ColumnDef::new(ConsentsTable::WitnessedBy).string().not_null())
+                .col(ColumnDef::new(ConsentsTable::WithdrawnAt).timestamp_with_time_zone().null())
+                .col(ColumnDef::new(ConsentsTable::WithdrawalReason).text().null())
+                .col(
+                    ColumnDef::new(ConsentsTable::CreatedAt)
+                        .timestamp_with_time_zone()
+                        .not_null()
+                        .default(Expr::current_timestamp()),
+                )
+                .col(
+                    ColumnDef::new(ConsentsTable::UpdatedAt)
+                        .timestamp_with_time_zone()
+                        .not_null()
+                        .default(Expr::current_timestamp()),
+                )
+                .to_owned(),
+            PatientTable::HouseholdMembers => Table::create()
+                .table(table.clone())
+                .col(ColumnDef::new(HouseholdMembersTable::PatientId).uuid().not_null().primary_key())
+                .col(ColumnDef::new(HouseholdMembersTable::HouseholdId).uuid().not_null())
+                .col(ColumnDef::new(HouseholdMembersTable::Role).string_len(16).not_null())
+                .col(
+                    ColumnDef::new(HouseholdMembersTable::JoinedAt)
+                        .timestamp_with_time_zone()
+                        .not_null()
+                        .default(Expr::current_timestamp()),
+                )
+                .foreign_key(
+                    ForeignKey::create()
+                        .name("fk_household_members_household")
+                        .from(table, HouseholdMembersTable::HouseholdId)
+                        .to(HouseholdsTable::Table, HouseholdsTable::HouseholdId)
+                        .on_delete(ForeignKeyAction::Cascade),
+                )
+                .to_owned(),
+        }
+    }
+
+    fn indexes(self) -> Vec<IndexCreateStatement> {
+        match self {
+            PatientTable::LabResults => vec![Index::create()
+                .name("idx_lab_results_patient_id")
+                .table(LabResultsTable::Table)
+                .col(LabResultsTable::PatientId)
+                .to_owned()],
+            PatientTable::Consents => vec![Index::create()
+                .name("idx_consents_patient_type")
+                .table(ConsentsTable::Table)
+                .col(ConsentsTable::PatientId)
+                .col(ConsentsTable::ConsentType)
+                .to_owned()],
+            PatientTable::HouseholdMembers => vec![Index::create()
+                .name("idx_household_members_household_id")
+                .table(HouseholdMembersTable::Table)
+                .col(HouseholdMembersTable::HouseholdId)
+                .to_owned()],
+        }
+    }
+}
+
+#[derive(DeriveIden)]
+enum PatientsTable {
+    Table,
+    PatientId,
+}
+
+#[derive(DeriveIden)]
+enum LabResultsTable {
+    Table,
+    ResultId,
+    PatientId,
+    OrderNumber,
+    TestCode,
+    TestName,
+    AnalyteCode,
+    AnalyteName,
+    ValueType,
+    Value,
+    Unit,
+    ReferenceRange,
+    AbnormalFlag,
+    ResultStatus,
+    ObservedAt,
+    Source,
+    MessageControlId,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum ConsentsTable {
+    Table,
+    ConsentId,
+    PatientId,
+    ConsentType,
+    Status,
+    ConsentVersion,
+    GrantedOn,
+    WitnessedBy,
+    WithdrawnAt,
+    WithdrawalReason,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum HouseholdsTable {
+    Table,
+    HouseholdId,
+}
+
+#[derive(DeriveIden)]
+enum HouseholdMembersTable {
+    Table,
+    PatientId,
+    HouseholdId,
+    Role,
+    JoinedAt,
+}
@@ -0,0 +1,77 @@
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::ConnectionTrait;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+const PII_COLUMNS: [PatientsTable; 7] = [
+    PatientsTable::FirstName,
+    PatientsTable::LastName,
+    PatientsTable::MiddleName,
+    PatientsTable::CsdIdOrPwdId,
+    PatientsTable::MobileNumber,
+    PatientsTable::ResidentialAddress,
+    PatientsTable::AddressLine,
+];
+
+/// Records whether a patient's PII columns hold ciphertext instead of
+/// inferring it from the `enc:v1:` prefix of the stored values. Existing rows
+/// are flagged only when every PII value they hold is ciphertext; the others
+/// are read leniently and encrypted in full on their next write or by
+/// `migrate rotate-keys`.
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(PatientsTable::Table)
+                    .add_column_if_not_exists(
+                        ColumnDef::new(PatientsTable::PiiEncrypted).boolean().not_null().default(false),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        let mut condition = Cond::all();
+        for column in PII_COLUMNS {
+            condition = condition.add(
+                Cond::any()
+                    .add(Expr::col(column).is_null())
+                    .add(Expr::col(column).like("enc:v1:%")),
+            );
+        }
+        let backfill = Query::update()
+            .table(PatientsTable::Table)
+            .value(PatientsTable::PiiEncrypted, true)
+            .cond_where(condition)
+            .to_owned();
+        let db = manager.get_connection();
+        db.execute(db.get_database_backend().build(&backfill)).await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(PatientsTable::Table)
+                    .drop_column(PatientsTable::PiiEncrypted)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden, Clone, Copy)]
+enum PatientsTable {
+    Table,
+    FirstName,
+    LastName,
+    MiddleName,
+    CsdIdOrPwdId,
+    MobileNumber,
+    ResidentialAddress,
+    AddressLine,
+    PiiEncrypted,
+}
@@ -2,8 +2,27 @@ use sea_orm_migration::prelude::*;
 
 mod m20240101_000001_create_patients_table;
 mod m20240101_000002_create_medical_records_table;
+mod m20240101_000003_create_lab_results_table;
+mod m20240101_000004_add_patient_blind_indexes;
+mod m20240101_000005_create_retention_tables;
+mod m20240101_000006_create_consents_table;
+mod m20240101_000007_add_psgc_addresses;
+mod m20240101_000008_add_patient_numbers;
+mod m20240101_000009_create_households_tables;
+mod m20240101_000010_use_uuid_keys;
+mod m20240101_000011_create_billing_tables;
+mod m20261019_093000_create_icd10_tables;
+mod m20261019_110000_create_prescription_items_table;
+mod m20261019_133000_create_vital_signs_table;
+mod m20261019_150000_create_service_results_tables;
+mod m20261019_170000_create_reference_ranges_table;
+mod m20261019_190000_create_unit_conversions_table;
+mod m20261019_200000_add_patient_foreign_keys;
+mod m20261019_210000_add_patient_pii_encrypted;
 pub mod runner;
 pub mod cli;
+pub mod generate;
+pub mod verify;
 
 pub struct Migrator;
 
@@ -13,6 +32,23 @@ impl MigratorTrait for Migrator {
         vec![
             Box::new(m20240101_000001_create_patients_table::Migration),
             Box::new(m20240101_000002_create_medical_records_table::Migration),
+            Box::new(m20240101_000003_create_lab_results_table::Migration),
+            Box::new(m20240101_000004_add_patient_blind_indexes::Migration),
+            Box::new(m20240101_000005_create_retention_tables::Migration),
+            Box::new(m20240101_000006_create_consents_table::Migration),
+            Box::new(m20240101_000007_add_psgc_addresses::Migration),
+            Box::new(m20240101_000008_add_patient_numbers::Migration),
+            Box::new(m20240101_000009_create_households_tables::Migration),
+            Box::new(m20240101_000010_use_uuid_keys::Migration),
+            Box::new(m20240101_000011_create_billing_tables::Migration),
+            Box::new(m20261019_093000_create_icd10_tables::Migration),
+            Box::new(m20261019_110000_create_prescription_items_table::Migration),
+            Box::new(m20261019_133000_create_vital_signs_table::Migration),
+            Box::new(m20261019_150000_create_service_results_tables::Migration),
+            Box::new(m20261019_170000_create_reference_ranges_table::Migration),
+            Box::new(m20261019_190000_create_unit_conversions_table::Migration),
+            Box::new(m20261019_200000_add_patient_foreign_keys::Migration),
+            Box::new(m20261019_210000_add_patient_pii_encrypted::Migration),
         ]
     }
 }
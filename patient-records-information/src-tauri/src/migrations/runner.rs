@@ -1,19 +1,241 @@
-use sea_orm::{DatabaseConnection, DbErr};
+use sea_orm::{
+    ConnectionTrait, Database, DatabaseConnection, DbErr, ProxyDatabaseTrait, ProxyExecResult, ProxyRow, Statement,
+    Value,
+};
 use sea_orm_migration::prelude::*;
+use std::sync::{Arc, Mutex};
 use crate::migrations::Migrator;
 
 pub async fn run_migrations(db: &DatabaseConnection) -> Result<(), DbErr> {
     Migrator::up(db, None).await
 }
 
-pub async fn rollback_migrations(db: &DatabaseConnection) -> Result<(), DbErr> {
-    Migrator::down(db, None).await
+/// Applies pending migrations up to and including `target` (see [`find_migration`]).
+/// Returns `false`, without applying anything, when `target` is already applied.
+pub async fn run_migrations_to(db: &DatabaseConnection, target: &str) -> Result<bool, DbErr> {
+    match steps_to(db, target).await? {
+        Some(steps) => Migrator::up(db, Some(steps)).await.map(|_| true),
+        None => Ok(false),
+    }
+}
+
+/// Rolls back the last `steps` applied migrations.
+pub async fn rollback_migrations(db: &DatabaseConnection, steps: u32) -> Result<(), DbErr> {
+    Migrator::down(db, Some(steps)).await
 }
 
 pub async fn reset_migrations(db: &DatabaseConnection) -> Result<(), DbErr> {
     Migrator::reset(db).await
 }
 
+/// Drops every table in the database (not only migrated ones), then applies all migrations.
+pub async fn fresh_migrations(db: &DatabaseConnection) -> Result<(), DbErr> {
+    Migrator::fresh(db).await
+}
+
 pub async fn status_migrations(db: &DatabaseConnection) -> Result<(), DbErr> {
     Migrator::status(db).await
 }
+
+/// Resolves a migration given by its full name or a unique prefix of it,
+/// e.g. `m20240101_000005`.
+pub fn find_migration(target: &str) -> Result<String, DbErr> {
+    let names: Vec<String> = Migrator::migrations().iter().map(|m| m.name().to_string()).collect();
+    if names.iter().any(|name| name == target) {
+        return Ok(target.to_string());
+    }
+    let matches: Vec<&String> = names.iter().filter(|name| name.starts_with(target)).collect();
+    match matches.as_slice() {
+        [name] => Ok((*name).clone()),
+        [] => Err(DbErr::Custom(format!("No migration named '{}'", target))),
+        _ => Err(DbErr::Custom(format!(
+            "'{}' matches several migrations: {}",
+            target,
+            matches.iter().map(|name| name.as_str()).collect::<Vec<_>>().join(", ")
+        ))),
+    }
+}
+
+/// Number of pending migrations to apply to reach `target`; `None` when it's applied.
+async fn steps_to(db: &DatabaseConnection, target: &str) -> Result<Option<u32>, DbErr> {
+    let target = find_migration(target)?;
+    let pending = Migrator::get_pending_migrations(db).await?;
+    Ok(pending
+        .iter()
+        .position(|migration| migration.name() == target)
+        .map(|index| index as u32 + 1))
+}
+
+/// SQL a dry run would have executed, per migration.
+#[derive(Debug, Clone)]
+pub struct MigrationSql {
+    pub name: String,
+    pub statements: Vec<String>,
+}
+
+/// Columns of the schema probes behind `SchemaManager::has_table`, `has_column`
+/// and `has_index`.
+const PROBE_COLUMNS: [&str; 3] = ["has_table", "has_column", "has_index"];
+
+/// Proxy connection that keeps every statement instead of running it.
+/// Schema probes are answered by the real database, so they see its current
+/// schema (not the tables earlier pending migrations would create); other
+/// queries return no rows.
+#[derive(Debug)]
+struct StatementRecorder {
+    db: DatabaseConnection,
+    statements: Arc<Mutex<Vec<String>>>,
+}
+
+impl StatementRecorder {
+    /// Answers `statement` on the real database when it's a schema probe.
+    async fn probe(&self, statement: &Statement) -> Result<Option<ProxyRow>, DbErr> {
+        let Some(column) = PROBE_COLUMNS
+            .into_iter()
+            .find(|column| statement.sql.contains(&format!("AS \"{}\"", column)))
+        else {
+            return Ok(None);
+        };
+        let found = match self.db.query_one(statement.clone()).await? {
+            Some(row) => row.try_get::<bool>("", column)?,
+            None => false,
+        };
+        let values = [(column.to_string(), Value::Bool(Some(found)))].into_iter().collect();
+        Ok(Some(ProxyRow { values }))
+    }
+}
+
+#[async_trait::async_trait]
+impl ProxyDatabaseTrait for StatementRecorder {
+    async fn query(&self, statement: Statement) -> Result<Vec<ProxyRow>, DbErr> {
+        if let Some(row) = self.probe(&statement).await? {
+            return Ok(vec![row]);
+        }
+        self.statements.lock().unwrap().push(statement.to_string());
+        Ok(Vec::new())
+    }
+
+    async fn execute(&self, statement: Statement) -> Result<ProxyExecResult, DbErr> {
+        self.statements.lock().unwrap().push(statement.to_string());
+        Ok(ProxyExecResult::default())
+    }
+}
+
+/// Runs `up` (or `down`) of the named migrations against a recorder for `db`.
+async fn record_sql(db: &DatabaseConnection, names: Vec<String>, up: bool) -> Result<Vec<MigrationSql>, DbErr> {
+    let statements = Arc::new(Mutex::new(Vec::new()));
+    let recorder = StatementRecorder { db: db.clone(), statements: statements.clone() };
+    let proxy = Database::connect_proxy(db.get_database_backend(), Arc::new(Box::new(recorder))).await?;
+    let manager = SchemaManager::new(&proxy);
+
+    let migrations = Migrator::migrations();
+    let mut recorded = Vec::new();
+    for name in names {
+        let Some(migration) = migrations.iter().find(|m| m.name() == name) else {
+            continue;
+        };
+        if up {
+            migration.up(&manager).await?;
+        } else {
+            migration.down(&manager).await?;
+        }
+        recorded.push(MigrationSql {
+            name,
+            statements: std::mem::take(&mut *statements.lock().unwrap()),
+        });
+    }
+    Ok(recorded)
+}
+
+/// SQL the pending migrations (up to and including `target`, if given) would
+/// execute on `db`'s backend. Nothing is applied.
+pub async fn pending_migrations_sql(
+    db: &DatabaseConnection,
+    target: Option<&str>,
+) -> Result<Vec<MigrationSql>, DbErr> {
+    let steps = match target {
+        Some(target) => match steps_to(db, target).await? {
+            Some(steps) => steps as usize,
+            None => return Ok(Vec::new()),
+        },
+        None => usize::MAX,
+    };
+    let names = Migrator::get_pending_migrations(db)
+        .await?
+        .iter()
+        .take(steps)
+        .map(|migration| migration.name().to_string())
+        .collect();
+    record_sql(db, names, true).await
+}
+
+/// SQL rolling back the last `steps` applied migrations would execute on
+/// `db`'s backend, in the order they'd be rolled back. Nothing is changed.
+pub async fn rollback_migrations_sql(db: &DatabaseConnection, steps: u32) -> Result<Vec<MigrationSql>, DbErr> {
+    let names = Migrator::get_applied_migrations(db)
+        .await?
+        .iter()
+        .rev()
+        .take(steps as usize)
+        .map(|migration| migration.name().to_string())
+        .collect();
+    record_sql(db, names, false).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn migration_names() -> Vec<String> {
+        Migrator::migrations().iter().map(|m| m.name().to_string()).collect()
+    }
+
+    /// Dry-runs every migration up on a fresh `db`, then from halfway (so the
+    /// schema probes see existing tables), then every rollback.
+    async fn dry_run_every_migration(db: &DatabaseConnection) {
+        let names = migration_names();
+
+        let sql = pending_migrations_sql(db, None).await.unwrap();
+        assert_eq!(sql.iter().map(|m| m.name.clone()).collect::<Vec<_>>(), names);
+        assert!(sql.iter().all(|m| !m.statements.is_empty()));
+        assert_eq!(Migrator::get_pending_migrations(db).await.unwrap().len(), names.len());
+
+        let halfway = &names[names.len() / 2 - 1];
+        run_migrations_to(db, halfway).await.unwrap();
+        let sql = pending_migrations_sql(db, None).await.unwrap();
+        assert_eq!(sql.len(), names.len() - names.len() / 2);
+        assert!(sql.iter().all(|m| !m.statements.is_empty()));
+
+        run_migrations(db).await.unwrap();
+        let sql = rollback_migrations_sql(db, names.len() as u32).await.unwrap();
+        assert_eq!(sql.len(), names.len());
+        assert!(sql.iter().all(|m| !m.statements.is_empty()));
+        assert!(Migrator::get_pending_migrations(db).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn every_migration_dry_runs_on_sqlite() {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        dry_run_every_migration(&db).await;
+    }
+
+    /// Runs against a scratch database created next to the one in
+    /// `TEST_DATABASE_URL_POSTGRES`; skipped when it isn't set.
+    #[tokio::test]
+    async fn every_migration_dry_runs_on_postgres() {
+        let Ok(url) = std::env::var("TEST_DATABASE_URL_POSTGRES") else {
+            eprintln!("TEST_DATABASE_URL_POSTGRES is not set; skipping");
+            return;
+        };
+        let admin = Database::connect(&url).await.unwrap();
+        let name = format!("dry_run_{}", uuid::Uuid::new_v4().simple());
+        admin.execute_unprepared(&format!("CREATE DATABASE \"{}\"", name)).await.unwrap();
+
+        let base = &url[..url.rfind('/').unwrap()];
+        let db = Database::connect(format!("{}/{}", base, name)).await.unwrap();
+        dry_run_every_migration(&db).await;
+        db.close().await.unwrap();
+
+        admin.execute_unprepared(&format!("DROP DATABASE \"{}\"", name)).await.unwrap();
+    }
+}
@@ -0,0 +1,567 @@
+//! Compares the SeaORM entities in `models` with the schema the migrations
+//! actually produced, so drift between the two is caught before a release.
+
+use sea_orm::sea_query::{ColumnType, StringLen, TableRef};
+use sea_orm::{
+    ColumnTrait, ConnectionTrait, DatabaseConnection, DbBackend, DbErr, EntityTrait, IdenStatic, Iterable,
+    PrimaryKeyToColumn, Schema, Statement,
+};
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::models::{
+    accounts, analyte_reference_range_tb, audit_log_tb, consent_tb, household_member_tb, household_tb, icd10_code_tb,
+    lab_result_tb, medical_bill_record, medical_record_diagnosis_tb, medical_record_tb, medical_services,
+    medical_services_provided, patient_number_sequence_tb, patient_tb, prescription_item_tb, psgc_area_tb,
+    retention_candidate_tb, service_analyte_tb, service_result_tb, unit_conversion_tb, vital_sign_tb,
+};
+
+/// One difference between an entity and its table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SchemaDrift {
+    MissingTable { table: String },
+    MissingColumn { table: String, column: String },
+    /// A column the table has but the entity doesn't map.
+    UnmappedColumn { table: String, column: String },
+    ColumnType { table: String, column: String, entity: String, database: String },
+    Nullability { table: String, column: String, entity_nullable: bool },
+    PrimaryKey { table: String, entity: Vec<String>, database: Vec<String> },
+    MissingIndex { table: String, columns: Vec<String>, unique: bool },
+    /// A single-column index the entity doesn't declare. Multi-column indexes
+    /// can't be declared on an entity, so those are not reported.
+    UndeclaredIndex { table: String, name: String, column: String, unique: bool },
+    MissingForeignKey { table: String, key: ForeignKey },
+    UndeclaredForeignKey { table: String, key: ForeignKey },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ForeignKey {
+    pub columns: Vec<String>,
+    pub ref_table: String,
+    pub ref_columns: Vec<String>,
+}
+
+impl fmt::Display for ForeignKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "({}) -> {} ({})", self.columns.join(", "), self.ref_table, self.ref_columns.join(", "))
+    }
+}
+
+fn index_kind(unique: bool) -> &'static str {
+    if unique { "unique index" } else { "index" }
+}
+
+fn nullability(nullable: bool) -> &'static str {
+    if nullable { "nullable" } else { "NOT NULL" }
+}
+
+impl fmt::Display for SchemaDrift {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchemaDrift::MissingTable { table } => write!(f, "{}: table missing from database", table),
+            SchemaDrift::MissingColumn { table, column } => {
+                write!(f, "{}.{}: column missing from database", table, column)
+            }
+            SchemaDrift::UnmappedColumn { table, column } => {
+                write!(f, "{}.{}: column not mapped by the entity", table, column)
+            }
+            SchemaDrift::ColumnType { table, column, entity, database } => {
+                write!(f, "{}.{}: {} in entity, {} in database", table, column, entity, database)
+            }
+            SchemaDrift::Nullability { table, column, entity_nullable } => write!(
+                f,
+                "{}.{}: {} in entity, {} in database",
+                table,
+                column,
+                nullability(*entity_nullable),
+                nullability(!*entity_nullable)
+            ),
+            SchemaDrift::PrimaryKey { table, entity, database } => write!(
+                f,
+                "{}: primary key ({}) in entity, ({}) in database",
+                table,
+                entity.join(", "),
+                database.join(", ")
+            ),
+            SchemaDrift::MissingIndex { table, columns, unique } => write!(
+                f,
+                "{}: {} on ({}) missing from database",
+                table,
+                index_kind(*unique),
+                columns.join(", ")
+            ),
+            SchemaDrift::UndeclaredIndex { table, name, column, unique } => write!(
+                f,
+                "{}: {} {} on ({}) not declared on the entity",
+                table,
+                index_kind(*unique),
+                name,
+                column
+            ),
+            SchemaDrift::MissingForeignKey { table, key } => {
+                write!(f, "{}: foreign key {} missing from database", table, key)
+            }
+            SchemaDrift::UndeclaredForeignKey { table, key } => {
+                write!(f, "{}: foreign key {} not declared on the entity", table, key)
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+struct ColumnShape {
+    name: String,
+    column_type: String,
+    nullable: bool,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct IndexShape {
+    name: String,
+    columns: Vec<String>,
+    unique: bool,
+}
+
+/// A table as described by an entity, or as found in the database.
+#[derive(Debug)]
+struct TableShape {
+    name: String,
+    columns: Vec<ColumnShape>,
+    primary_key: Vec<String>,
+    indexes: Vec<IndexShape>,
+    foreign_keys: Vec<ForeignKey>,
+}
+
+/// Checks every entity against the connected database.
+pub async fn verify_schema(db: &DatabaseConnection) -> Result<Vec<SchemaDrift>, DbErr> {
+    let backend = db.get_database_backend();
+    let expected = vec![
+        entity_shape(patient_tb::Entity, backend),
+        entity_shape(patient_number_sequence_tb::Entity, backend),
+        entity_shape(medical_record_tb::Entity, backend),
+        entity_shape(lab_result_tb::Entity, backend),
+        entity_shape(medical_services::Entity, backend),
+        entity_shape(medical_bill_record::Entity, backend),
+        entity_shape(medical_services_provided::Entity, backend),
+        entity_shape(accounts::Entity, backend),
+        entity_shape(retention_candidate_tb::Entity, backend),
+        entity_shape(audit_log_tb::Entity, backend),
+        entity_shape(consent_tb::Entity, backend),
+        entity_shape(psgc_area_tb::Entity, backend),
+        entity_shape(household_tb::Entity, backend),
+        entity_shape(household_member_tb::Entity, backend),
+        entity_shape(icd10_code_tb::Entity, backend),
+        entity_shape(medical_record_diagnosis_tb::Entity, backend),
+        entity_shape(prescription_item_tb::Entity, backend),
+        entity_shape(vital_sign_tb::Entity, backend),
+        entity_shape(service_analyte_tb::Entity, backend),
+        entity_shape(service_result_tb::Entity, backend),
+        entity_shape(analyte_reference_range_tb::Entity, backend),
+        entity_shape(unit_conversion_tb::Entity, backend),
+    ];
+
+    let mut drift = Vec::new();
+    for entity in expected {
+        match database_shape(db, &entity.name).await? {
+            Some(table) => compare(&entity, &table, backend, &mut drift),
+            None => drift.push(SchemaDrift::MissingTable { table: entity.name }),
+        }
+    }
+    Ok(drift)
+}
+
+fn entity_shape<E: EntityTrait>(entity: E, backend: DbBackend) -> TableShape {
+    let table = entity.table_name().to_string();
+    let schema = Schema::new(backend);
+
+    let mut columns = Vec::new();
+    let mut indexes = Vec::new();
+    for column in E::Column::iter() {
+        let def = column.def();
+        if def.is_unique() {
+            indexes.push(IndexShape {
+                name: String::new(),
+                columns: vec![column.as_str().to_string()],
+                unique: true,
+            });
+        }
+        columns.push(ColumnShape {
+            name: column.as_str().to_string(),
+            column_type: entity_type(def.get_column_type(), backend),
+            nullable: def.is_null(),
+        });
+    }
+    for index in schema.create_index_from_entity(E::default()) {
+        indexes.push(IndexShape {
+            name: String::new(),
+            columns: index.get_index_spec().get_column_names(),
+            unique: index.is_unique_key(),
+        });
+    }
+
+    let foreign_keys = schema
+        .create_table_from_entity(entity)
+        .get_foreign_key_create_stmts()
+        .iter()
+        .map(|statement| {
+            let key = statement.get_foreign_key();
+            ForeignKey {
+                columns: key.get_columns(),
+                ref_table: key.get_ref_table().map(table_ref_name).unwrap_or_default(),
+                ref_columns: key.get_ref_columns(),
+            }
+        })
+        .collect();
+
+    TableShape {
+        name: table,
+        columns,
+        primary_key: E::PrimaryKey::iter().map(|key| key.into_column().as_str().to_string()).collect(),
+        indexes,
+        foreign_keys,
+    }
+}
+
+fn table_ref_name(table: &TableRef) -> String {
+    match table {
+        TableRef::Table(name)
+        | TableRef::SchemaTable(_, name)
+        | TableRef::DatabaseSchemaTable(_, _, name)
+        | TableRef::TableAlias(name, _)
+        | TableRef::SchemaTableAlias(_, name, _)
+        | TableRef::DatabaseSchemaTableAlias(_, _, name, _) => name.to_string(),
+        _ => String::new(),
+    }
+}
+
+/// Type names shared by both sides of the comparison, e.g. `varchar(32)`.
+fn entity_type(column_type: &ColumnType, backend: DbBackend) -> String {
+    match column_type {
+        ColumnType::Char(Some(length)) => format!("char({})", length),
+        ColumnType::Char(None) => "char".to_string(),
+        ColumnType::String(StringLen::N(length)) => format!("varchar({})", length),
+        ColumnType::String(_) => "varchar".to_string(),
+        ColumnType::Text => "text".to_string(),
+        ColumnType::TinyInteger | ColumnType::SmallInteger => "smallint".to_string(),
+        ColumnType::Integer => "integer".to_string(),
+        ColumnType::BigInteger => "bigint".to_string(),
+        ColumnType::Float => "float".to_string(),
+        ColumnType::Double => "double".to_string(),
+        ColumnType::Decimal(_) | ColumnType::Money(_) => "decimal".to_string(),
+        ColumnType::DateTime | ColumnType::Timestamp => "timestamp".to_string(),
+        ColumnType::TimestampWithTimeZone => "timestamptz".to_string(),
+        ColumnType::Date => "date".to_string(),
+        ColumnType::Time => "time".to_string(),
+        ColumnType::Boolean => "boolean".to_string(),
+        ColumnType::Json => "json".to_string(),
+        ColumnType::JsonBinary => "jsonb".to_string(),
+        ColumnType::Uuid => "uuid".to_string(),
+        ColumnType::Blob | ColumnType::Binary(_) | ColumnType::VarBinary(_) => "blob".to_string(),
+        // Postgres gets a named type; SQLite stores the variant as text.
+        ColumnType::Enum { name, .. } => match backend {
+            DbBackend::Postgres => format!("enum {}", name.to_string()),
+            _ => "enum".to_string(),
+        },
+        ColumnType::Custom(name) => name.to_string().to_lowercase(),
+        other => format!("{:?}", other).to_lowercase(),
+    }
+}
+
+/// Maps Postgres `information_schema.columns` types onto the names used by [`entity_type`].
+fn postgres_type(data_type: &str, udt_name: &str, max_length: Option<i32>) -> String {
+    match (data_type, max_length) {
+        ("character varying", Some(length)) => format!("varchar({})", length),
+        ("character varying", None) => "varchar".to_string(),
+        ("character", Some(length)) => format!("char({})", length),
+        ("character", None) => "char".to_string(),
+        ("real", _) => "float".to_string(),
+        ("double precision", _) => "double".to_string(),
+        ("numeric", _) => "decimal".to_string(),
+        ("timestamp without time zone", _) => "timestamp".to_string(),
+        ("timestamp with time zone", _) => "timestamptz".to_string(),
+        ("time without time zone", _) => "time".to_string(),
+        ("bytea", _) => "blob".to_string(),
+        ("USER-DEFINED", _) => format!("enum {}", udt_name),
+        (other, _) => other.to_string(),
+    }
+}
+
+/// Maps the declared types SeaORM writes into SQLite DDL (`uuid_text`,
+/// `timestamp_with_timezone_text`, ...) onto the names used by [`entity_type`].
+fn sqlite_type(declared: &str) -> String {
+    let declared = declared.to_lowercase();
+    match declared.as_str() {
+        "uuid_text" => "uuid".to_string(),
+        "timestamp_with_timezone_text" => "timestamptz".to_string(),
+        "timestamp_text" | "datetime_text" => "timestamp".to_string(),
+        "date_text" => "date".to_string(),
+        "time_text" => "time".to_string(),
+        "json_text" => "json".to_string(),
+        "jsonb_text" => "jsonb".to_string(),
+        "enum_text" => "enum".to_string(),
+        "real" => "float".to_string(),
+        "blob" | "varbinary_blob" => "blob".to_string(),
+        _ if declared.starts_with("real(") => "decimal".to_string(),
+        _ if declared.starts_with("blob(") => "blob".to_string(),
+        _ => declared,
+    }
+}
+
+fn types_match(entity: &str, database: &str, backend: DbBackend) -> bool {
+    // SQLite has no enum types; a text column (with or without a CHECK) is fine.
+    entity == database
+        || (backend == DbBackend::Sqlite
+            && entity == "enum"
+            && (database == "text" || database.starts_with("varchar")))
+}
+
+fn compare(entity: &TableShape, table: &TableShape, backend: DbBackend, drift: &mut Vec<SchemaDrift>) {
+    let name = &entity.name;
+
+    for column in &entity.columns {
+        let Some(actual) = table.columns.iter().find(|c| c.name == column.name) else {
+            drift.push(SchemaDrift::MissingColumn { table: name.clone(), column: column.name.clone() });
+            continue;
+        };
+        if !types_match(&column.column_type, &actual.column_type, backend) {
+            drift.push(SchemaDrift::ColumnType {
+                table: name.clone(),
+                column: column.name.clone(),
+                entity: column.column_type.clone(),
+                database: actual.column_type.clone(),
+            });
+        }
+        if column.nullable != actual.nullable {
+            drift.push(SchemaDrift::Nullability {
+                table: name.clone(),
+                column: column.name.clone(),
+                entity_nullable: column.nullable,
+            });
+        }
+    }
+    for actual in &table.columns {
+        if !entity.columns.iter().any(|c| c.name == actual.name) {
+            drift.push(SchemaDrift::UnmappedColumn { table: name.clone(), column: actual.name.clone() });
+        }
+    }
+
+    if entity.primary_key != table.primary_key {
+        drift.push(SchemaDrift::PrimaryKey {
+            table: name.clone(),
+            entity: entity.primary_key.clone(),
+            database: table.primary_key.clone(),
+        });
+    }
+
+    let same_index = |a: &IndexShape, b: &IndexShape| a.columns == b.columns && a.unique == b.unique;
+    // A plain index is also served by a wider index that starts with its columns.
+    let covers = |index: &IndexShape, actual: &IndexShape| {
+        same_index(index, actual) || (!index.unique && actual.columns.starts_with(&index.columns))
+    };
+    for index in &entity.indexes {
+        if !table.indexes.iter().any(|actual| covers(index, actual)) {
+            drift.push(SchemaDrift::MissingIndex {
+                table: name.clone(),
+                columns: index.columns.clone(),
+                unique: index.unique,
+            });
+        }
+    }
+    for actual in &table.indexes {
+        if actual.columns.len() == 1 && !entity.indexes.iter().any(|index| same_index(index, actual)) {
+            drift.push(SchemaDrift::UndeclaredIndex {
+                table: name.clone(),
+                name: actual.name.clone(),
+                column: actual.columns[0].clone(),
+                unique: actual.unique,
+            });
+        }
+    }
+
+    for key in &entity.foreign_keys {
+        if !table.foreign_keys.contains(key) {
+            drift.push(SchemaDrift::MissingForeignKey { table: name.clone(), key: key.clone() });
+        }
+    }
+    for key in &table.foreign_keys {
+        if !entity.foreign_keys.contains(key) {
+            drift.push(SchemaDrift::UndeclaredForeignKey { table: name.clone(), key: key.clone() });
+        }
+    }
+}
+
+/// Reads a table's shape from the database. `None` when the table doesn't exist.
+async fn database_shape(db: &DatabaseConnection, table: &str) -> Result<Option<TableShape>, DbErr> {
+    match db.get_database_backend() {
+        DbBackend::Postgres => postgres_shape(db, table).await,
+        DbBackend::Sqlite => sqlite_shape(db, table).await,
+        DbBackend::MySql => Err(DbErr::Custom("Schema verification does not support MySQL".to_string())),
+    }
+}
+
+async fn query(db: &DatabaseConnection, sql: &str, table: &str) -> Result<Vec<sea_orm::QueryResult>, DbErr> {
+    db.query_all(Statement::from_sql_and_values(
+        db.get_database_backend(),
+        sql,
+        [table.into()],
+    ))
+    .await
+}
+
+async fn sqlite_shape(db: &DatabaseConnection, table: &str) -> Result<Option<TableShape>, DbErr> {
+    let rows = query(db, r#"SELECT name, type, "notnull", pk FROM pragma_table_info(?)"#, table).await?;
+    if rows.is_empty() {
+        return Ok(None);
+    }
+
+    let mut columns = Vec::new();
+    let mut primary_key = Vec::new();
+    for row in rows {
+        let name: String = row.try_get("", "name")?;
+        let declared: String = row.try_get("", "type")?;
+        let not_null: i32 = row.try_get("", "notnull")?;
+        let pk: i32 = row.try_get("", "pk")?;
+        if pk > 0 {
+            primary_key.push((pk, name.clone()));
+        }
+        columns.push(ColumnShape {
+            name,
+            column_type: sqlite_type(&declared),
+            // SQLite lets a non-integer primary key hold NULL unless it's declared NOT NULL.
+            nullable: not_null == 0,
+        });
+    }
+    primary_key.sort();
+
+    let mut indexes = Vec::new();
+    for row in query(db, "SELECT name, \"unique\", origin FROM pragma_index_list(?)", table).await? {
+        let origin: String = row.try_get("", "origin")?;
+        if origin == "pk" {
+            continue;
+        }
+        let name: String = row.try_get("", "name")?;
+        let unique: i32 = row.try_get("", "unique")?;
+        let columns = query(db, "SELECT name FROM pragma_index_info(?) ORDER BY seqno", &name)
+            .await?
+            .into_iter()
+            .map(|row| row.try_get("", "name"))
+            .collect::<Result<Vec<String>, DbErr>>()?;
+        indexes.push(IndexShape { name, columns, unique: unique != 0 });
+    }
+
+    let mut keys: BTreeMap<i32, ForeignKey> = BTreeMap::new();
+    for row in query(db, "SELECT id, \"table\", \"from\", \"to\" FROM pragma_foreign_key_list(?) ORDER BY id, seq", table).await? {
+        let id: i32 = row.try_get("", "id")?;
+        let ref_table: String = row.try_get("", "table")?;
+        let from: String = row.try_get("", "from")?;
+        // `to` is NULL when the key references the parent's primary key implicitly.
+        let to: Option<String> = row.try_get("", "to")?;
+        let key = keys.entry(id).or_insert_with(|| ForeignKey {
+            columns: Vec::new(),
+            ref_table,
+            ref_columns: Vec::new(),
+        });
+        key.columns.push(from);
+        key.ref_columns.extend(to);
+    }
+
+    Ok(Some(TableShape {
+        name: table.to_string(),
+        columns,
+        primary_key: primary_key.into_iter().map(|(_, name)| name).collect(),
+        indexes,
+        foreign_keys: keys.into_values().collect(),
+    }))
+}
+
+/// Column names of `$key` (an attribute number list) on relation `$rel`, in order.
+macro_rules! pg_key_columns {
+    ($key:literal, $rel:literal) => {
+        concat!(
+            "array_to_string(ARRAY(SELECT a.attname FROM unnest(", $key, ") WITH ORDINALITY k(num, ord) ",
+            "JOIN pg_attribute a ON a.attrelid = ", $rel, " AND a.attnum = k.num ORDER BY k.ord), ',')"
+        )
+    };
+}
+
+async fn postgres_shape(db: &DatabaseConnection, table: &str) -> Result<Option<TableShape>, DbErr> {
+    let rows = query(
+        db,
+        "SELECT column_name::text AS column_name, data_type::text AS data_type, udt_name::text AS udt_name, \
+         character_maximum_length::int4 AS max_length, is_nullable::text AS is_nullable \
+         FROM information_schema.columns \
+         WHERE table_schema = current_schema() AND table_name = $1 ORDER BY ordinal_position",
+        table,
+    )
+    .await?;
+    if rows.is_empty() {
+        return Ok(None);
+    }
+
+    let mut columns = Vec::new();
+    for row in rows {
+        let data_type: String = row.try_get("", "data_type")?;
+        let udt_name: String = row.try_get("", "udt_name")?;
+        let max_length: Option<i32> = row.try_get("", "max_length")?;
+        let is_nullable: String = row.try_get("", "is_nullable")?;
+        columns.push(ColumnShape {
+            name: row.try_get("", "column_name")?,
+            column_type: postgres_type(&data_type, &udt_name, max_length),
+            nullable: is_nullable == "YES",
+        });
+    }
+
+    let split = |columns: String| -> Vec<String> { columns.split(',').map(str::to_string).collect() };
+
+    let mut primary_key = Vec::new();
+    let mut indexes = Vec::new();
+    let index_sql = concat!(
+        "SELECT i.relname::text AS name, ix.indisunique AS is_unique, ix.indisprimary AS is_primary, ",
+        pg_key_columns!("ix.indkey::int2[]", "ix.indrelid"),
+        " AS columns FROM pg_index ix ",
+        "JOIN pg_class t ON t.oid = ix.indrelid ",
+        "JOIN pg_class i ON i.oid = ix.indexrelid ",
+        "JOIN pg_namespace n ON n.oid = t.relnamespace ",
+        "WHERE n.nspname = current_schema() AND t.relname = $1 ORDER BY i.relname"
+    );
+    for row in query(db, index_sql, table).await? {
+        let columns = split(row.try_get("", "columns")?);
+        if row.try_get::<bool>("", "is_primary")? {
+            primary_key = columns;
+            continue;
+        }
+        indexes.push(IndexShape {
+            name: row.try_get("", "name")?,
+            columns,
+            unique: row.try_get("", "is_unique")?,
+        });
+    }
+
+    let key_sql = concat!(
+        "SELECT r.relname::text AS ref_table, ",
+        pg_key_columns!("c.conkey", "c.conrelid"),
+        " AS columns, ",
+        pg_key_columns!("c.confkey", "c.confrelid"),
+        " AS ref_columns FROM pg_constraint c ",
+        "JOIN pg_class t ON t.oid = c.conrelid ",
+        "JOIN pg_class r ON r.oid = c.confrelid ",
+        "JOIN pg_namespace n ON n.oid = t.relnamespace ",
+        "WHERE c.contype = 'f' AND n.nspname = current_schema() AND t.relname = $1 ORDER BY c.conname"
+    );
+    let mut foreign_keys = Vec::new();
+    for row in query(db, key_sql, table).await? {
+        foreign_keys.push(ForeignKey {
+            columns: split(row.try_get("", "columns")?),
+            ref_table: row.try_get("", "ref_table")?,
+            ref_columns: split(row.try_get("", "ref_columns")?),
+        });
+    }
+
+    Ok(Some(TableShape {
+        name: table.to_string(),
+        columns,
+        primary_key,
+        indexes,
+        foreign_keys,
+    }))
+}
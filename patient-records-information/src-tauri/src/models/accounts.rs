@@ -14,13 +14,14 @@ pub enum Role {
 #[derive(Debug, Clone, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
 #[sea_orm(table_name = "accounts_table")]
 pub struct Model {
-    #[sea_orm(primary_key)]
+    #[sea_orm(primary_key, auto_increment = false)]
     pub account_id: Uuid,
     pub first_name: String,
     pub last_name: String,
     pub middle_name: String,
     pub role: Role,
     pub email: String,
+    #[sea_orm(unique)]
     pub username: String,
     pub password: String
 }
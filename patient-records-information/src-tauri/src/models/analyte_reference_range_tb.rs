@@ -0,0 +1,50 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use crate::models::patient_tb::Sex;
+
+/// Reference and critical limits of an analyte for one sex and age band, in
+/// one unit. Where one applies to a patient it takes precedence over the
+/// limits of the service's template (see `reference_range_handlers`).
+#[derive(Debug, Clone, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "analyte_reference_ranges_table")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub reference_range_id: Uuid,
+    /// Analyte code as used in the service templates, e.g. `HGB`.
+    #[sea_orm(indexed, column_type = "String(StringLen::N(16))")]
+    pub analyte_code: String,
+    /// `None` for a range that applies to both sexes.
+    pub sex: Option<Sex>,
+    /// Age band in days on the day of the visit: from `min_age_days`
+    /// inclusive to `max_age_days` exclusive, open-ended when `None`.
+    pub min_age_days: i32,
+    pub max_age_days: Option<i32>,
+    /// Only results in this unit are flagged against the range.
+    pub unit: String,
+    pub reference_low: Option<f32>,
+    pub reference_high: Option<f32>,
+    pub critical_low: Option<f32>,
+    pub critical_high: Option<f32>,
+    /// Account that added the range, and the one that last replaced it.
+    pub created_by: Uuid,
+    pub updated_by: Option<Uuid>,
+    pub created_at: DateTimeUtc,
+    pub updated_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    async fn before_save<C>(mut self, _: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if !insert {
+            self.updated_at = sea_orm::ActiveValue::Set(chrono::Utc::now());
+        }
+        Ok(self)
+    }
+}
@@ -0,0 +1,28 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Append-only log of sensitive operations. `details` never holds PII, only
+/// ids, counts and dates.
+#[derive(Debug, Clone, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "audit_log_table")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub audit_id: Uuid,
+    /// What happened, e.g. `retention.disposal`.
+    #[sea_orm(column_type = "String(StringLen::N(64))")]
+    pub action: String,
+    /// Kind of record affected, e.g. `patient`.
+    #[sea_orm(column_type = "String(StringLen::N(32))")]
+    pub entity: String,
+    pub record_id: Option<String>,
+    /// Account responsible for the action, when known.
+    pub actor_id: Option<Uuid>,
+    pub details: Json,
+    pub created_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
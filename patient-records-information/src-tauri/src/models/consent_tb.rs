@@ -0,0 +1,88 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// What the patient agreed to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, DeriveActiveEnum, EnumIter, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(32))")]
+#[serde(rename_all = "snake_case")]
+pub enum ConsentType {
+    /// Processing of personal data for care and administration.
+    #[sea_orm(string_value = "data_processing")]
+    DataProcessing,
+    /// Appointment reminders and result notifications by SMS.
+    #[sea_orm(string_value = "sms_notifications")]
+    SmsNotifications,
+    /// Inclusion in de-identified research exports.
+    #[sea_orm(string_value = "research_use")]
+    ResearchUse,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, DeriveActiveEnum, EnumIter, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(16))")]
+#[serde(rename_all = "snake_case")]
+pub enum ConsentStatus {
+    #[sea_orm(string_value = "granted")]
+    Granted,
+    #[sea_orm(string_value = "withdrawn")]
+    Withdrawn,
+    /// Replaced by a later grant, e.g. for a new version of the consent text.
+    #[sea_orm(string_value = "superseded")]
+    Superseded,
+}
+
+/// One consent given by a patient. At most one row per patient and type is
+/// `granted`; earlier rows are kept as history.
+#[derive(Debug, Clone, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "consents_table")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub consent_id: Uuid,
+    #[sea_orm(indexed)]
+    pub patient_id: Uuid,
+    pub consent_type: ConsentType,
+    pub status: ConsentStatus,
+    /// Version of the consent text the patient was shown, e.g. `2026-01`.
+    #[sea_orm(column_type = "String(StringLen::N(32))")]
+    pub consent_version: String,
+    /// Date the consent form was signed.
+    pub granted_on: Date,
+    /// Staff member who witnessed the signature.
+    pub witnessed_by: String,
+    pub withdrawn_at: Option<DateTimeUtc>,
+    #[sea_orm(column_type = "Text")]
+    pub withdrawal_reason: Option<String>,
+    pub created_at: DateTimeUtc,
+    pub updated_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::patient_tb::Entity",
+        from = "Column::PatientId",
+        to = "super::patient_tb::Column::PatientId",
+        on_update = "Cascade",
+        on_delete = "Restrict"
+    )]
+    Patient,
+}
+
+impl Related<super::patient_tb::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Patient.def()
+    }
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    async fn before_save<C>(mut self, _: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if !insert {
+            self.updated_at = sea_orm::ActiveValue::Set(chrono::Utc::now());
+        }
+        Ok(self)
+    }
+}
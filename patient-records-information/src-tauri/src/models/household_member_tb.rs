@@ -0,0 +1,75 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// How a member is related to the head of the household.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, DeriveActiveEnum, EnumIter, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(16))")]
+#[serde(rename_all = "snake_case")]
+pub enum HouseholdRole {
+    /// At most one per household.
+    #[sea_orm(string_value = "head")]
+    Head,
+    #[sea_orm(string_value = "spouse")]
+    Spouse,
+    #[sea_orm(string_value = "child")]
+    Child,
+    #[sea_orm(string_value = "parent")]
+    Parent,
+    #[sea_orm(string_value = "sibling")]
+    Sibling,
+    #[sea_orm(string_value = "grandparent")]
+    Grandparent,
+    #[sea_orm(string_value = "grandchild")]
+    Grandchild,
+    #[sea_orm(string_value = "other_relative")]
+    OtherRelative,
+    /// Lives in the household without being family, e.g. a helper.
+    #[sea_orm(string_value = "non_relative")]
+    NonRelative,
+}
+
+/// Membership of a patient in a household; a patient has at most one.
+#[derive(Debug, Clone, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "household_members_table")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub patient_id: Uuid,
+    #[sea_orm(indexed)]
+    pub household_id: Uuid,
+    pub role: HouseholdRole,
+    pub joined_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::household_tb::Entity",
+        from = "Column::HouseholdId",
+        to = "super::household_tb::Column::HouseholdId",
+        on_delete = "Cascade"
+    )]
+    Household,
+    #[sea_orm(
+        belongs_to = "super::patient_tb::Entity",
+        from = "Column::PatientId",
+        to = "super::patient_tb::Column::PatientId",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    Patient,
+}
+
+impl Related<super::household_tb::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Household.def()
+    }
+}
+
+impl Related<super::patient_tb::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Patient.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
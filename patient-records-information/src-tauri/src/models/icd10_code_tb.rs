@@ -0,0 +1,30 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// One ICD-10 code, imported with `migrate import-icd10`. Codes are stored in
+/// their dotted form (`J06.9`); category rows (`J06`) are codes too.
+#[derive(Debug, Clone, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "icd10_codes_table")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false, column_type = "String(StringLen::N(8))")]
+    pub code: String,
+    #[sea_orm(column_type = "Text")]
+    pub description: String,
+    /// Three-character category the code belongs to, e.g. `J06` for `J06.9`.
+    #[sea_orm(indexed, column_type = "String(StringLen::N(3))")]
+    pub category: String,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(has_many = "super::medical_record_diagnosis_tb::Entity")]
+    Diagnosis,
+}
+
+impl Related<super::medical_record_diagnosis_tb::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Diagnosis.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
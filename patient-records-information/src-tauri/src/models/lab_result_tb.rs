@@ -0,0 +1,59 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// A single laboratory observation (one analyte of one test), e.g. the
+/// hemoglobin value of a CBC. Rows are created from ORU^R01 `OBX` segments.
+#[derive(Debug, Clone, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "lab_results_table")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub result_id: Uuid,
+    #[sea_orm(indexed)]
+    pub patient_id: Uuid,
+    /// Placer or filler order number (`OBR-2`/`OBR-3`).
+    pub order_number: Option<String>,
+    /// Ordered test (`OBR-4`), e.g. `CBC`.
+    pub test_code: Option<String>,
+    pub test_name: Option<String>,
+    /// Observed analyte (`OBX-3`), e.g. `HGB`.
+    pub analyte_code: String,
+    pub analyte_name: Option<String>,
+    /// HL7 value type (`OBX-2`), e.g. `NM`, `ST`.
+    pub value_type: Option<String>,
+    #[sea_orm(column_type = "Text")]
+    pub value: Option<String>,
+    pub unit: Option<String>,
+    pub reference_range: Option<String>,
+    /// Abnormal flag as sent by the analyzer (`OBX-8`), e.g. `H`, `L`, `HH`.
+    pub abnormal_flag: Option<String>,
+    /// Result status (`OBX-11`), e.g. `F` for final.
+    pub result_status: Option<String>,
+    pub observed_at: Option<DateTimeUtc>,
+    /// Where the result came from, e.g. `HL7:<sending facility>`.
+    pub source: String,
+    /// `MSH-10` of the message that delivered the result.
+    pub message_control_id: Option<String>,
+    pub created_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::patient_tb::Entity",
+        from = "Column::PatientId",
+        to = "super::patient_tb::Column::PatientId",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    Patient,
+}
+
+impl Related<super::patient_tb::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Patient.def()
+    }
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {}
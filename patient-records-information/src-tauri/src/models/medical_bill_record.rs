@@ -21,17 +21,14 @@ pub enum PaymentStatus {
 #[sea_orm(table_name = "medical_bill_records_table")]
 pub struct Model {
     /// Unique identifier for the medical bill record.
-    #[sea_orm(primary_key)]
+    #[sea_orm(primary_key, auto_increment = false)]
     pub medical_bill_id: Uuid,
     /// Foreign key to the patient.
-    #[sea_orm(foreign_key = "patients_table")]
-    pub patient_id: i32,
-    /// Foreign key to the medical record.
-    #[sea_orm(foreign_key = "medical_records_table")]
-    pub medical_id: i32,
-    /// Foreign key to the medical services provided record.
-    #[sea_orm(foreign_key = "medical_services_provided_table")]
-    pub mrs_id: i32,
+    #[sea_orm(indexed)]
+    pub patient_id: Uuid,
+    /// Foreign key to the medical record (visit) being billed.
+    #[sea_orm(indexed)]
+    pub medical_id: Uuid,
     /// Optional consultation fee.
     pub consultation_fee: Option<f32>,
     /// Optional remarks for the bill.
@@ -41,9 +38,47 @@ pub struct Model {
     /// Total amount for the bill.
     pub total_amount: f32,
 }
-/// Enum for defining entity relations (currently empty).
+/// Relations of a bill: the patient and visit it is for, and the services
+/// billed on it (each `medical_services_provided` row points back to its bill).
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
-pub enum Relation {}
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::patient_tb::Entity",
+        from = "Column::PatientId",
+        to = "super::patient_tb::Column::PatientId",
+        on_update = "Cascade",
+        on_delete = "Restrict"
+    )]
+    Patient,
+    #[sea_orm(
+        belongs_to = "super::medical_record_tb::Entity",
+        from = "Column::MedicalId",
+        to = "super::medical_record_tb::Column::MedicalId",
+        on_update = "Cascade",
+        on_delete = "Restrict"
+    )]
+    MedicalRecord,
+    #[sea_orm(has_many = "super::medical_services_provided::Entity")]
+    ServicesProvided,
+}
+
+impl Related<super::patient_tb::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Patient.def()
+    }
+}
+
+impl Related<super::medical_record_tb::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::MedicalRecord.def()
+    }
+}
+
+impl Related<super::medical_services_provided::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ServicesProvided.def()
+    }
+}
 
 #[async_trait::async_trait]
 impl ActiveModelBehavior for ActiveModel {}
\ No newline at end of file
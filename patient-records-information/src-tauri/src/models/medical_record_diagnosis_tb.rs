@@ -0,0 +1,65 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, DeriveActiveEnum, EnumIter, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(16))")]
+#[serde(rename_all = "snake_case")]
+pub enum DiagnosisRank {
+    /// The main reason for the visit; exactly one per coded record.
+    #[sea_orm(string_value = "primary")]
+    Primary,
+    #[sea_orm(string_value = "secondary")]
+    Secondary,
+}
+
+/// An ICD-10 coded diagnosis of a medical record, next to the record's
+/// free-text `diagnosis` note.
+#[derive(Debug, Clone, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "medical_record_diagnoses_table")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub diagnosis_id: Uuid,
+    #[sea_orm(indexed)]
+    pub medical_id: Uuid,
+    #[sea_orm(indexed, column_type = "String(StringLen::N(8))")]
+    pub icd10_code: String,
+    pub rank: DiagnosisRank,
+    /// Position in the list as entered, primary first.
+    pub sequence: i32,
+    pub created_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::medical_record_tb::Entity",
+        from = "Column::MedicalId",
+        to = "super::medical_record_tb::Column::MedicalId",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    MedicalRecord,
+    #[sea_orm(
+        belongs_to = "super::icd10_code_tb::Entity",
+        from = "Column::Icd10Code",
+        to = "super::icd10_code_tb::Column::Code",
+        on_update = "Cascade",
+        on_delete = "Restrict"
+    )]
+    Icd10Code,
+}
+
+impl Related<super::medical_record_tb::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::MedicalRecord.def()
+    }
+}
+
+impl Related<super::icd10_code_tb::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Icd10Code.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
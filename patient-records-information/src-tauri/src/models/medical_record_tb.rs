@@ -1,21 +1,21 @@
 use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
 #[derive(Debug, Clone, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
 #[sea_orm(table_name = "medical_records_table")]
 pub struct Model {
-    #[sea_orm(primary_key, auto_increment = true)]
-    pub medical_id: i32,
-    #[sea_orm(
-        foreign_key = "crate::patient_tb::Entity",
-        on_delete = "Cascade",
-        on_update = "Cascade",
-        indexed
-    )]
-    pub patient_id: i32,
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub medical_id: Uuid,
+    #[sea_orm(indexed)]
+    pub patient_id: Uuid,
+    #[sea_orm(column_type = "Text")]
     pub assessment: Option<String>,
+    #[sea_orm(column_type = "Text")]
     pub diagnosis: Option<String>,
+    #[sea_orm(column_type = "Text")]
     pub treatment: Option<String>,
+    #[sea_orm(column_type = "Text")]
     pub prescription: Option<String>,
     pub first_audited_by: String,
     pub last_audited_by: Option<String>,
@@ -27,9 +27,19 @@ pub enum Relation {
     #[sea_orm(
         belongs_to = "super::patient_tb::Entity",
         from = "Column::PatientId",
-        to = "super::patient_tb::Column::PatientId"
+        to = "super::patient_tb::Column::PatientId",
+        on_update = "Cascade",
+        on_delete = "Cascade"
     )]
     Patient,
+    #[sea_orm(has_many = "super::medical_bill_record::Entity")]
+    MedicalBill,
+    #[sea_orm(has_many = "super::medical_record_diagnosis_tb::Entity")]
+    Diagnosis,
+    #[sea_orm(has_many = "super::prescription_item_tb::Entity")]
+    PrescriptionItem,
+    #[sea_orm(has_many = "super::vital_sign_tb::Entity")]
+    VitalSign,
 }
 
 impl Related<super::patient_tb::Entity> for Entity {
@@ -38,5 +48,39 @@ impl Related<super::patient_tb::Entity> for Entity {
     }
 }
 
+impl Related<super::medical_bill_record::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::MedicalBill.def()
+    }
+}
+
+impl Related<super::medical_record_diagnosis_tb::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Diagnosis.def()
+    }
+}
+
+impl Related<super::prescription_item_tb::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PrescriptionItem.def()
+    }
+}
+
+impl Related<super::vital_sign_tb::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::VitalSign.def()
+    }
+}
+
 #[async_trait::async_trait]
-impl ActiveModelBehavior for ActiveModel {}
\ No newline at end of file
+impl ActiveModelBehavior for ActiveModel {
+    async fn before_save<C>(mut self, _: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if !insert {
+            self.updated_at = sea_orm::ActiveValue::Set(chrono::Utc::now());
+        }
+        Ok(self)
+    }
+}
\ No newline at end of file
@@ -45,7 +45,7 @@ pub enum ServiceCategory {
 #[derive(Debug, Clone, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
 #[sea_orm(table_name = "medical_services_table")]
 pub struct Model {
-    #[sea_orm(primary_key)]
+    #[sea_orm(primary_key, auto_increment = false)]
     pub ms_id: Uuid,
     pub ms_name: String,
     pub ms_category: ServiceCategory,
@@ -55,7 +55,24 @@ pub struct Model {
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
-pub enum Relation {}
+pub enum Relation {
+    #[sea_orm(has_many = "super::medical_services_provided::Entity")]
+    ServicesProvided,
+    #[sea_orm(has_many = "super::service_analyte_tb::Entity")]
+    Analyte,
+}
+
+impl Related<super::medical_services_provided::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ServicesProvided.def()
+    }
+}
+
+impl Related<super::service_analyte_tb::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Analyte.def()
+    }
+}
 
 #[async_trait::async_trait]
 impl ActiveModelBehavior for ActiveModel {
@@ -70,8 +87,10 @@ impl ActiveModelBehavior for ActiveModel {
         let now = Utc::now();
 
         if insert {
-            // generate UUID automatically
-            self.ms_id = Set(Uuid::new_v4()); // ✅ safer unless you enabled v7
+            // Keep an id chosen by the caller (shared by the local and cloud copies).
+            if self.ms_id.is_not_set() {
+                self.ms_id = Set(Uuid::new_v4());
+            }
             self.created_at = Set(now);
         }
 
@@ -16,14 +16,14 @@ use crate::models::medical_services::ServiceCategory;
 #[sea_orm(table_name = "medical_services_provided_table")]
 pub struct Model {
     /// Unique identifier for the medical service provided (Primary Key).
-    #[sea_orm(primary_key)]
+    #[sea_orm(primary_key, auto_increment = false)]
     pub mrs_id: Uuid,
     /// Foreign key referencing the medical bill record.
-    #[sea_orm(foreign_key)]
-    pub medical_bill_id: i32,
+    #[sea_orm(indexed)]
+    pub medical_bill_id: Uuid,
     /// Foreign key referencing the medical service.
-    #[sea_orm(foreign_key)]
-    pub ms_id: i32,
+    #[sea_orm(indexed)]
+    pub ms_id: Uuid,
     /// Name of the service provided.
     pub service_name: String,
     /// Category of the service provided.
@@ -31,10 +31,46 @@ pub struct Model {
     /// Price of the service provided.
     pub price: f32,
 }
-/// Enum for defining entity relations for the medical services provided model (currently empty).
+/// Relations of a service provided: the bill it is charged on (removed with
+/// the bill) and the catalog service it was taken from.
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
-    // Relations can be defined here in the future.
+    #[sea_orm(
+        belongs_to = "super::medical_bill_record::Entity",
+        from = "Column::MedicalBillId",
+        to = "super::medical_bill_record::Column::MedicalBillId",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    MedicalBill,
+    #[sea_orm(
+        belongs_to = "super::medical_services::Entity",
+        from = "Column::MsId",
+        to = "super::medical_services::Column::MsId",
+        on_update = "Cascade",
+        on_delete = "Restrict"
+    )]
+    MedicalService,
+    #[sea_orm(has_many = "super::service_result_tb::Entity")]
+    ServiceResult,
+}
+
+impl Related<super::medical_bill_record::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::MedicalBill.def()
+    }
+}
+
+impl Related<super::medical_services::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::MedicalService.def()
+    }
+}
+
+impl Related<super::service_result_tb::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ServiceResult.def()
+    }
 }
 
 /// Custom behavior for the medical services provided ActiveModel (currently no custom logic).
@@ -3,4 +3,20 @@ pub mod medical_services;
 pub mod medical_record_tb;
 pub mod medical_bill_record;
 pub mod accounts;
-pub mod medical_services_provided;
\ No newline at end of file
+pub mod medical_services_provided;
+pub mod lab_result_tb;
+pub mod retention_candidate_tb;
+pub mod audit_log_tb;
+pub mod consent_tb;
+pub mod psgc_area_tb;
+pub mod patient_number_sequence_tb;
+pub mod household_tb;
+pub mod household_member_tb;
+pub mod icd10_code_tb;
+pub mod medical_record_diagnosis_tb;
+pub mod prescription_item_tb;
+pub mod vital_sign_tb;
+pub mod service_analyte_tb;
+pub mod service_result_tb;
+pub mod analyte_reference_range_tb;
+pub mod unit_conversion_tb;
\ No newline at end of file
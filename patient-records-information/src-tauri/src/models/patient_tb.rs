@@ -1,25 +1,72 @@
 use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
-use sea_orm::ActiveValue::Set;
+use sea_orm::ActiveValue::{self, Set};
 use uuid::Uuid;
+use crate::crypto::{self, normalize, CryptoError, FieldCipher};
+/// Sex at birth, which sex-specific reference ranges depend on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, DeriveActiveEnum, EnumIter, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(8))")]
+#[serde(rename_all = "snake_case")]
+pub enum Sex {
+    #[sea_orm(string_value = "male")]
+    Male,
+    #[sea_orm(string_value = "female")]
+    Female,
+}
+
 //patients model, but also a derived entity because of DeriveEntityModel macro
 #[derive(Debug, Clone, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
 #[sea_orm(table_name = "patients_table")]
 pub struct Model {
-    #[sea_orm(primary_key)]
+    #[sea_orm(primary_key, auto_increment = false)]
     pub patient_id: Uuid,
+    /// Human-readable number, e.g. `PRN-2026-000123`; `None` until assigned
+    /// (see `patient_number_handlers`).
+    #[sea_orm(unique, column_type = "String(StringLen::N(20))")]
+    pub patient_number: Option<String>,
     pub first_name: String,
-    #[sea_orm(indexed)]
     pub last_name: String,
     pub middle_name: Option<String>,
     pub age: i32,
     pub birth_date: Date,
+    /// `None` when not recorded.
+    pub sex: Option<Sex>,
     pub csd_id_or_pwd_id: Option<String>,
     pub mobile_number: Option<String>,
+    /// Free-text address; the only address of patients created before the
+    /// structured fields below existed.
     pub residential_address: Option<String>,
+    /// House number, street, building or sitio.
+    #[sea_orm(column_type = "Text")]
+    pub address_line: Option<String>,
+    // PSGC codes of the address (see `psgc_area_tb`), kept consistent with each other.
+    #[sea_orm(indexed, column_type = "String(StringLen::N(10))")]
+    pub barangay_code: Option<String>,
+    #[sea_orm(indexed, column_type = "String(StringLen::N(10))")]
+    pub city_municipality_code: Option<String>,
+    #[sea_orm(indexed, column_type = "String(StringLen::N(10))")]
+    pub province_code: Option<String>,
+    #[sea_orm(indexed, column_type = "String(StringLen::N(10))")]
+    pub region_code: Option<String>,
     pub is_archived: bool,
+    /// Set when `is_archived` becomes true; retention rules count from it.
+    pub archived_at: Option<DateTimeUtc>,
     pub created_at: DateTimeUtc,
     pub updated_at: DateTimeUtc,
+    // Blind indexes of the encrypted columns (see `crate::crypto`); never exposed.
+    #[serde(skip)]
+    #[sea_orm(indexed, column_type = "String(StringLen::N(64))")]
+    pub last_name_bidx: Option<String>,
+    #[serde(skip)]
+    #[sea_orm(indexed, column_type = "String(StringLen::N(64))")]
+    pub mobile_number_bidx: Option<String>,
+    #[serde(skip)]
+    #[sea_orm(indexed, column_type = "String(StringLen::N(64))")]
+    pub csd_id_or_pwd_id_bidx: Option<String>,
+    /// Whether the PII columns hold ciphertext. Set on every write, so reads
+    /// don't have to guess from what a stored value looks like.
+    #[serde(skip)]
+    pub pii_encrypted: bool,
 }
 
 
@@ -27,6 +74,16 @@ pub struct Model {
 pub enum Relation {
     #[sea_orm(has_many = "super::medical_record_tb::Entity")]
     MedicalRecord,
+    #[sea_orm(has_many = "super::lab_result_tb::Entity")]
+    LabResult,
+    #[sea_orm(has_many = "super::consent_tb::Entity")]
+    Consent,
+    #[sea_orm(has_one = "super::household_member_tb::Entity")]
+    HouseholdMember,
+    #[sea_orm(has_many = "super::medical_bill_record::Entity")]
+    MedicalBill,
+    #[sea_orm(has_many = "super::vital_sign_tb::Entity")]
+    VitalSign,
 }
 
 impl Related<super::medical_record_tb::Entity> for Entity {
@@ -35,6 +92,36 @@ impl Related<super::medical_record_tb::Entity> for Entity {
     }
 }
 
+impl Related<super::lab_result_tb::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::LabResult.def()
+    }
+}
+
+impl Related<super::consent_tb::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Consent.def()
+    }
+}
+
+impl Related<super::household_member_tb::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::HouseholdMember.def()
+    }
+}
+
+impl Related<super::medical_bill_record::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::MedicalBill.def()
+    }
+}
+
+impl Related<super::vital_sign_tb::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::VitalSign.def()
+    }
+}
+
 
 #[async_trait::async_trait]
 impl ActiveModelBehavior for ActiveModel {
@@ -55,6 +142,180 @@ impl ActiveModelBehavior for ActiveModel {
         if !insert {
             self.updated_at = Set(chrono::Utc::now());
         }
+        if let ActiveValue::Set(archived) = self.is_archived {
+            match (archived, &self.archived_at) {
+                (true, ActiveValue::Unchanged(Some(_)) | ActiveValue::Set(Some(_))) => {}
+                (true, _) => self.archived_at = Set(Some(chrono::Utc::now())),
+                (false, _) => self.archived_at = Set(None),
+            }
+        }
+        match crypto::global().map_err(crypto_err)? {
+            Some(cipher) => self.encrypt_pii(cipher).map_err(crypto_err)?,
+            None if insert => self.pii_encrypted = Set(false),
+            None => {
+                if self.pii_encrypted == ActiveValue::Unchanged(true) && self.writes_pii() {
+                    return Err(crypto_err(CryptoError(
+                        "patient PII is encrypted but PII_ENCRYPTION_KEYS is not configured".to_string(),
+                    )));
+                }
+            }
+        }
+        Ok(self)
+    }
+
+    async fn after_save<C>(model: Model, _: &C, _insert: bool) -> Result<Model, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        model.decrypted()
+    }
+}
+
+fn crypto_err(e: CryptoError) -> DbErr {
+    DbErr::Custom(e.to_string())
+}
+
+/// Reads a value of a row stored before `pii_encrypted` was tracked, which
+/// may be plaintext or ciphertext.
+fn legacy_plaintext(cipher: &FieldCipher, column: &str, record: &str, value: &str) -> Result<String, CryptoError> {
+    if crypto::is_encrypted(value) {
+        cipher.decrypt(column, record, value)
+    } else {
+        Ok(value.to_string())
+    }
+}
+
+fn promote(cipher: &FieldCipher, column: &str, record: &str, value: &mut ActiveValue<String>) -> Result<(), CryptoError> {
+    if let ActiveValue::Unchanged(stored) = value {
+        *value = Set(legacy_plaintext(cipher, column, record, stored)?);
+    }
+    Ok(())
+}
+
+fn promote_optional(
+    cipher: &FieldCipher,
+    column: &str,
+    record: &str,
+    value: &mut ActiveValue<Option<String>>,
+) -> Result<(), CryptoError> {
+    if let ActiveValue::Unchanged(stored) = value {
+        *value = Set(stored.as_deref().map(|v| legacy_plaintext(cipher, column, record, v)).transpose()?);
+    }
+    Ok(())
+}
+
+/// Takes the value out only when it is `Set`, i.e. about to be written.
+fn take_set<V: Into<Value>>(value: &mut ActiveValue<V>) -> Option<V> {
+    if value.is_set() {
+        value.take()
+    } else {
+        None
+    }
+}
+
+impl ActiveModel {
+    fn writes_pii(&self) -> bool {
+        self.first_name.is_set()
+            || self.last_name.is_set()
+            || self.middle_name.is_set()
+            || self.csd_id_or_pwd_id.is_set()
+            || self.mobile_number.is_set()
+            || self.residential_address.is_set()
+            || self.address_line.is_set()
+    }
+
+    /// Encrypts every PII column that is being written and refreshes the blind
+    /// index that goes with it. Written values are always treated as plaintext.
+    ///
+    /// A row stored before `pii_encrypted` was tracked (or in clear) is
+    /// rewritten whole, so that all of its columns end up encrypted and the
+    /// flag can be set.
+    pub fn encrypt_pii(&mut self, cipher: &FieldCipher) -> Result<(), CryptoError> {
+        let record = match &self.patient_id {
+            ActiveValue::Set(id) | ActiveValue::Unchanged(id) => id.to_string(),
+            ActiveValue::NotSet => return Err(CryptoError("patient_id is required to encrypt PII".to_string())),
+        };
+        if self.pii_encrypted == ActiveValue::Unchanged(false) {
+            promote(cipher, "first_name", &record, &mut self.first_name)?;
+            promote(cipher, "last_name", &record, &mut self.last_name)?;
+            promote_optional(cipher, "middle_name", &record, &mut self.middle_name)?;
+            promote_optional(cipher, "csd_id_or_pwd_id", &record, &mut self.csd_id_or_pwd_id)?;
+            promote_optional(cipher, "mobile_number", &record, &mut self.mobile_number)?;
+            promote_optional(cipher, "residential_address", &record, &mut self.residential_address)?;
+            promote_optional(cipher, "address_line", &record, &mut self.address_line)?;
+        }
+        let seal = |column: &str, value: String| cipher.encrypt(column, &record, &value);
+
+        if let Some(last_name) = take_set(&mut self.last_name) {
+            self.last_name_bidx = Set(Some(cipher.blind_index("last_name", &normalize::name(&last_name))));
+            self.last_name = Set(seal("last_name", last_name)?);
+        }
+        if let Some(first_name) = take_set(&mut self.first_name) {
+            self.first_name = Set(seal("first_name", first_name)?);
+        }
+        if let Some(middle_name) = take_set(&mut self.middle_name) {
+            self.middle_name = Set(middle_name.map(|v| seal("middle_name", v)).transpose()?);
+        }
+        if let Some(mobile_number) = take_set(&mut self.mobile_number) {
+            self.mobile_number_bidx = Set(mobile_number
+                .as_deref()
+                .map(|v| cipher.blind_index("mobile_number", &normalize::mobile_number(v))));
+            self.mobile_number = Set(mobile_number.map(|v| seal("mobile_number", v)).transpose()?);
+        }
+        if let Some(csd_id_or_pwd_id) = take_set(&mut self.csd_id_or_pwd_id) {
+            self.csd_id_or_pwd_id_bidx = Set(csd_id_or_pwd_id
+                .as_deref()
+                .map(|v| cipher.blind_index("csd_id_or_pwd_id", &normalize::csd_id_or_pwd_id(v))));
+            self.csd_id_or_pwd_id = Set(csd_id_or_pwd_id.map(|v| seal("csd_id_or_pwd_id", v)).transpose()?);
+        }
+        if let Some(residential_address) = take_set(&mut self.residential_address) {
+            self.residential_address =
+                Set(residential_address.map(|v| seal("residential_address", v)).transpose()?);
+        }
+        if let Some(address_line) = take_set(&mut self.address_line) {
+            self.address_line = Set(address_line.map(|v| seal("address_line", v)).transpose()?);
+        }
+        self.pii_encrypted = Set(true);
+        Ok(())
+    }
+}
+
+impl Model {
+    /// Returns the model with its PII columns decrypted. Rows that were never
+    /// encrypted pass through unchanged.
+    pub fn decrypted(self) -> Result<Self, DbErr> {
+        match crypto::global().map_err(crypto_err)? {
+            Some(cipher) => self.decrypt_with(cipher).map_err(crypto_err),
+            None if self.pii_encrypted => Err(crypto_err(CryptoError(
+                "patient PII is encrypted but PII_ENCRYPTION_KEYS is not configured".to_string(),
+            ))),
+            None => Ok(self),
+        }
+    }
+
+    pub fn decrypt_with(mut self, cipher: &FieldCipher) -> Result<Self, CryptoError> {
+        let record = self.patient_id.to_string();
+        let encrypted = self.pii_encrypted;
+        let open = |column: &str, value: &str| {
+            if encrypted {
+                cipher.decrypt(column, &record, value)
+            } else {
+                legacy_plaintext(cipher, column, &record, value)
+            }
+        };
+        self.first_name = open("first_name", &self.first_name)?;
+        self.last_name = open("last_name", &self.last_name)?;
+        for (column, value) in [
+            ("middle_name", &mut self.middle_name),
+            ("csd_id_or_pwd_id", &mut self.csd_id_or_pwd_id),
+            ("mobile_number", &mut self.mobile_number),
+            ("residential_address", &mut self.residential_address),
+            ("address_line", &mut self.address_line),
+        ] {
+            if let Some(value) = value {
+                *value = open(column, value)?;
+            }
+        }
         Ok(self)
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,99 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, DeriveActiveEnum, EnumIter, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(16))")]
+#[serde(rename_all = "snake_case")]
+pub enum MedicationRoute {
+    #[sea_orm(string_value = "oral")]
+    Oral,
+    #[sea_orm(string_value = "sublingual")]
+    Sublingual,
+    #[sea_orm(string_value = "topical")]
+    Topical,
+    #[sea_orm(string_value = "inhalation")]
+    Inhalation,
+    #[sea_orm(string_value = "nasal")]
+    Nasal,
+    #[sea_orm(string_value = "ophthalmic")]
+    Ophthalmic,
+    #[sea_orm(string_value = "otic")]
+    Otic,
+    #[sea_orm(string_value = "rectal")]
+    Rectal,
+    #[sea_orm(string_value = "vaginal")]
+    Vaginal,
+    #[sea_orm(string_value = "subcutaneous")]
+    Subcutaneous,
+    #[sea_orm(string_value = "intramuscular")]
+    Intramuscular,
+    #[sea_orm(string_value = "intravenous")]
+    Intravenous,
+    #[sea_orm(string_value = "other")]
+    Other,
+}
+
+/// One drug prescribed in a medical record. The record's free-text
+/// `prescription` is kept for records written before line items existed.
+#[derive(Debug, Clone, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "prescription_items_table")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub prescription_item_id: Uuid,
+    #[sea_orm(indexed)]
+    pub medical_id: Uuid,
+    /// Generic name, which the Generics Act requires on every prescription.
+    pub generic_name: String,
+    pub brand_name: Option<String>,
+    /// Strength and form, e.g. `500 mg capsule`.
+    pub strength: Option<String>,
+    /// Amount per administration, e.g. `1 capsule`, `5 mL`.
+    pub dose: String,
+    pub route: MedicationRoute,
+    /// As the prescriber writes it, e.g. `every 8 hours`, `TID`.
+    pub frequency: String,
+    /// E.g. `7 days`; `None` for maintenance medication.
+    pub duration: Option<String>,
+    /// Number of units to dispense.
+    pub quantity: Option<i32>,
+    #[sea_orm(column_type = "Text")]
+    pub instructions: Option<String>,
+    /// Position on the prescription.
+    pub sequence: i32,
+    pub first_audited_by: String,
+    pub last_audited_by: Option<String>,
+    pub created_at: DateTimeUtc,
+    pub updated_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::medical_record_tb::Entity",
+        from = "Column::MedicalId",
+        to = "super::medical_record_tb::Column::MedicalId",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    MedicalRecord,
+}
+
+impl Related<super::medical_record_tb::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::MedicalRecord.def()
+    }
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    async fn before_save<C>(mut self, _: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if !insert {
+            self.updated_at = sea_orm::ActiveValue::Set(chrono::Utc::now());
+        }
+        Ok(self)
+    }
+}
@@ -0,0 +1,47 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Geographic level as published in the PSGC ("Geographic Level" column).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, DeriveActiveEnum, EnumIter, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(8))")]
+pub enum PsgcLevel {
+    #[sea_orm(string_value = "Reg")]
+    Reg,
+    #[sea_orm(string_value = "Prov")]
+    Prov,
+    /// NCR district (stands in for a province).
+    #[sea_orm(string_value = "Dist")]
+    Dist,
+    #[sea_orm(string_value = "City")]
+    City,
+    #[sea_orm(string_value = "Mun")]
+    Mun,
+    /// Sub-municipality (City of Manila districts).
+    #[sea_orm(string_value = "SubMun")]
+    SubMun,
+    /// Special geographic area.
+    #[sea_orm(string_value = "SGU")]
+    #[serde(rename = "SGU")]
+    Sgu,
+    #[sea_orm(string_value = "Bgy")]
+    Bgy,
+}
+
+/// One area of the Philippine Standard Geographic Code, imported from the PSA
+/// publication with `migrate import-psgc`.
+#[derive(Debug, Clone, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "psgc_areas_table")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false, column_type = "String(StringLen::N(10))")]
+    pub psgc_code: String,
+    pub name: String,
+    pub geographic_level: PsgcLevel,
+    /// Nearest enclosing area; `None` for regions.
+    #[sea_orm(indexed, column_type = "String(StringLen::N(10))")]
+    pub parent_code: Option<String>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
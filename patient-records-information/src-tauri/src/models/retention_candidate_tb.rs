@@ -0,0 +1,89 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Kind of record a retention rule applies to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, DeriveActiveEnum, EnumIter, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(20))")]
+#[serde(rename_all = "snake_case")]
+pub enum RetentionEntity {
+    /// A patient together with their medical records and lab results.
+    #[sea_orm(string_value = "patient")]
+    Patient,
+    /// A single lab result.
+    #[sea_orm(string_value = "lab_result")]
+    LabResult,
+}
+
+/// Date the retention period is counted from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, DeriveActiveEnum, EnumIter, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(20))")]
+#[serde(rename_all = "snake_case")]
+pub enum RetentionBasis {
+    /// Latest change to the record or anything attached to it.
+    #[sea_orm(string_value = "last_activity")]
+    LastActivity,
+    /// When the patient was archived.
+    #[sea_orm(string_value = "archived")]
+    Archived,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, DeriveActiveEnum, EnumIter, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(20))")]
+#[serde(rename_all = "snake_case")]
+pub enum CandidateStatus {
+    /// Waiting for an Admin decision.
+    #[sea_orm(string_value = "pending")]
+    Pending,
+    /// Approved; purged as soon as every database is reachable.
+    #[sea_orm(string_value = "approved")]
+    Approved,
+    /// Kept by an Admin; not proposed again until its reference date changes.
+    #[sea_orm(string_value = "rejected")]
+    Rejected,
+    /// No longer due when the purge ran (e.g. new activity or un-archived).
+    #[sea_orm(string_value = "cancelled")]
+    Cancelled,
+    #[sea_orm(string_value = "purged")]
+    Purged,
+}
+
+/// A record whose retention period has elapsed, found by the retention scan.
+#[derive(Debug, Clone, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "retention_candidates_table")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub candidate_id: Uuid,
+    pub entity: RetentionEntity,
+    pub record_id: Uuid,
+    pub basis: RetentionBasis,
+    pub retention_days: i32,
+    /// Last activity or archive date the period was counted from.
+    pub reference_at: DateTimeUtc,
+    pub due_at: DateTimeUtc,
+    pub status: CandidateStatus,
+    /// Admin account that approved or rejected the candidate.
+    pub decided_by: Option<Uuid>,
+    pub decided_at: Option<DateTimeUtc>,
+    #[sea_orm(column_type = "Text")]
+    pub note: Option<String>,
+    pub purged_at: Option<DateTimeUtc>,
+    pub created_at: DateTimeUtc,
+    pub updated_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    async fn before_save<C>(mut self, _: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if !insert {
+            self.updated_at = sea_orm::ActiveValue::Set(chrono::Utc::now());
+        }
+        Ok(self)
+    }
+}
@@ -0,0 +1,60 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, DeriveActiveEnum, EnumIter, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(16))")]
+#[serde(rename_all = "snake_case")]
+pub enum AnalyteValueType {
+    /// A measured number, flagged against the reference and critical limits.
+    #[sea_orm(string_value = "numeric")]
+    Numeric,
+    /// A finding such as `Negative` or `Reactive`, flagged when it isn't `normal_text`.
+    #[sea_orm(string_value = "text")]
+    Text,
+}
+
+/// One analyte a catalog service produces, e.g. the hemoglobin of a CBC. The
+/// analytes of a service are its result template.
+#[derive(Debug, Clone, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "service_analytes_table")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub analyte_id: Uuid,
+    #[sea_orm(indexed)]
+    pub ms_id: Uuid,
+    /// Short code, unique within the service, e.g. `HGB`.
+    #[sea_orm(column_type = "String(StringLen::N(16))")]
+    pub code: String,
+    pub name: String,
+    pub unit: Option<String>,
+    pub value_type: AnalyteValueType,
+    pub reference_low: Option<f32>,
+    pub reference_high: Option<f32>,
+    pub critical_low: Option<f32>,
+    pub critical_high: Option<f32>,
+    /// The expected finding of a text analyte, e.g. `Negative`.
+    pub normal_text: Option<String>,
+    /// Position on the result form.
+    pub sequence: i32,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::medical_services::Entity",
+        from = "Column::MsId",
+        to = "super::medical_services::Column::MsId",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    MedicalService,
+}
+
+impl Related<super::medical_services::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::MedicalService.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,92 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Abnormal flag of a result; stored as the HL7 `OBX-8` code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, DeriveActiveEnum, EnumIter, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(2))")]
+#[serde(rename_all = "snake_case")]
+pub enum ResultFlag {
+    #[sea_orm(string_value = "L")]
+    Low,
+    #[sea_orm(string_value = "H")]
+    High,
+    #[sea_orm(string_value = "LL")]
+    CriticalLow,
+    #[sea_orm(string_value = "HH")]
+    CriticalHigh,
+    /// A text finding other than the expected one.
+    #[sea_orm(string_value = "A")]
+    Abnormal,
+}
+
+/// The result of one analyte of a service provided at a visit. The analyte's
+/// name, unit and reference range are copied when the result is entered, so
+/// later template changes don't alter it; until release, the flag and range
+/// follow changes to the analyte's reference ranges.
+#[derive(Debug, Clone, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "service_results_table")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub service_result_id: Uuid,
+    #[sea_orm(indexed)]
+    pub mrs_id: Uuid,
+    #[sea_orm(column_type = "String(StringLen::N(16))")]
+    pub analyte_code: String,
+    pub analyte_name: String,
+    /// As entered, e.g. `13.2` or `Reactive`.
+    #[sea_orm(column_type = "Text")]
+    pub value: String,
+    /// `value` as a number, for numeric analytes.
+    pub numeric_value: Option<f32>,
+    pub unit: Option<String>,
+    /// Reference range as printed, e.g. `120–160` or `Negative`.
+    pub reference_range: Option<String>,
+    /// The numeric limits `reference_range` was printed from, so it can be
+    /// shown in another unit.
+    pub reference_low: Option<f32>,
+    pub reference_high: Option<f32>,
+    /// `None` when the result is within range or there's no range to compare with.
+    pub flag: Option<ResultFlag>,
+    /// The flag was set by hand and is kept when the ranges change.
+    pub flag_overridden: bool,
+    pub entered_by: String,
+    pub last_audited_by: Option<String>,
+    /// Set when the results of the service are released to the doctor; they
+    /// can't be changed after that.
+    pub released_at: Option<DateTimeUtc>,
+    pub released_by: Option<String>,
+    pub created_at: DateTimeUtc,
+    pub updated_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::medical_services_provided::Entity",
+        from = "Column::MrsId",
+        to = "super::medical_services_provided::Column::MrsId",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    ServiceProvided,
+}
+
+impl Related<super::medical_services_provided::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ServiceProvided.def()
+    }
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    async fn before_save<C>(mut self, _: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if !insert {
+            self.updated_at = sea_orm::ActiveValue::Set(chrono::Utc::now());
+        }
+        Ok(self)
+    }
+}
@@ -0,0 +1,40 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// How an analyte's results convert between SI and conventional units:
+/// `conventional = si × factor`. Results are stored in the unit of the
+/// service's template and converted only when shown (see
+/// `unit_conversion_handlers`), so changing a factor never alters them.
+#[derive(Debug, Clone, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "unit_conversions_table")]
+pub struct Model {
+    /// Analyte code as used in the service templates, e.g. `GLU`.
+    #[sea_orm(primary_key, auto_increment = false, column_type = "String(StringLen::N(16))")]
+    pub analyte_code: String,
+    /// e.g. `mmol/L`.
+    pub si_unit: String,
+    /// e.g. `mg/dL`.
+    pub conventional_unit: String,
+    pub factor: f32,
+    /// Decimal places a converted value is shown with in each system.
+    pub si_decimals: i32,
+    pub conventional_decimals: i32,
+    /// Account that last saved the conversion; `None` for conversions loaded
+    /// from the service catalog.
+    pub updated_by: Option<Uuid>,
+    pub updated_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    async fn before_save<C>(mut self, _: &C, _insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        self.updated_at = sea_orm::ActiveValue::Set(chrono::Utc::now());
+        Ok(self)
+    }
+}
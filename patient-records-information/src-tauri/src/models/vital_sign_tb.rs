@@ -0,0 +1,68 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Vital signs taken at one point in time. Each measurement is optional, since
+/// not every visit takes all of them; units are fixed per column.
+#[derive(Debug, Clone, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "vital_signs_table")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub vital_sign_id: Uuid,
+    #[sea_orm(indexed)]
+    pub patient_id: Uuid,
+    /// The visit the vitals were taken at, if any.
+    #[sea_orm(indexed)]
+    pub medical_id: Option<Uuid>,
+    /// mmHg.
+    pub systolic_bp: Option<i32>,
+    /// mmHg.
+    pub diastolic_bp: Option<i32>,
+    /// Beats per minute.
+    pub pulse_rate: Option<i32>,
+    /// Degrees Celsius.
+    pub temperature_c: Option<f32>,
+    pub weight_kg: Option<f32>,
+    pub height_cm: Option<f32>,
+    /// Computed from the weight and the latest height when saved.
+    pub bmi: Option<f32>,
+    #[sea_orm(indexed)]
+    pub measured_at: DateTimeUtc,
+    /// Staff member who took the vitals.
+    pub recorded_by: String,
+    pub created_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::patient_tb::Entity",
+        from = "Column::PatientId",
+        to = "super::patient_tb::Column::PatientId",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    Patient,
+    #[sea_orm(
+        belongs_to = "super::medical_record_tb::Entity",
+        from = "Column::MedicalId",
+        to = "super::medical_record_tb::Column::MedicalId",
+        on_update = "Cascade",
+        on_delete = "SetNull"
+    )]
+    MedicalRecord,
+}
+
+impl Related<super::patient_tb::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Patient.def()
+    }
+}
+
+impl Related<super::medical_record_tb::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::MedicalRecord.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
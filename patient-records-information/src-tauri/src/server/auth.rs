@@ -0,0 +1,100 @@
+use actix_web::dev::Payload;
+use actix_web::http::header::{AUTHORIZATION, WWW_AUTHENTICATE};
+use actix_web::{web, FromRequest, HttpRequest, HttpResponse, ResponseError};
+use argon2::{Argon2, PasswordHash, PasswordVerifier};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+use serde_json::json;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use uuid::Uuid;
+
+use crate::models::accounts::{Column as AccountColumn, Entity as AccountEntity, Role};
+use crate::server::state::AppState;
+
+/// The staff account making the request, authenticated with HTTP Basic
+/// credentials checked against `accounts_table` of the local database.
+/// `password` there holds an Argon2 PHC string (`$argon2id$...`). A handler
+/// taking it as a parameter answers `401` to unauthenticated requests.
+#[derive(Debug, Clone)]
+pub struct AuthenticatedAccount {
+    pub account_id: Uuid,
+    pub role: Role,
+}
+
+#[derive(Debug)]
+pub enum AuthError {
+    /// No or malformed `Authorization: Basic` header.
+    MissingCredentials,
+    /// Unknown username or wrong password.
+    InvalidCredentials,
+    Database(String),
+}
+
+impl fmt::Display for AuthError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuthError::MissingCredentials => write!(f, "Authentication required"),
+            AuthError::InvalidCredentials => write!(f, "Invalid username or password"),
+            AuthError::Database(e) => write!(f, "Failed to authenticate: {}", e),
+        }
+    }
+}
+
+impl ResponseError for AuthError {
+    fn error_response(&self) -> HttpResponse {
+        let mut response = match self {
+            AuthError::MissingCredentials | AuthError::InvalidCredentials => HttpResponse::Unauthorized(),
+            AuthError::Database(_) => HttpResponse::InternalServerError(),
+        };
+        if !matches!(self, AuthError::Database(_)) {
+            response.insert_header((WWW_AUTHENTICATE, "Basic realm=\"patient-records\""));
+        }
+        response.json(json!({ "error": self.to_string() }))
+    }
+}
+
+/// `(username, password)` from an `Authorization: Basic` header.
+fn basic_credentials(req: &HttpRequest) -> Option<(String, String)> {
+    let header = req.headers().get(AUTHORIZATION)?.to_str().ok()?;
+    let (scheme, encoded) = header.split_once(' ')?;
+    if !scheme.eq_ignore_ascii_case("basic") {
+        return None;
+    }
+    let decoded = String::from_utf8(STANDARD.decode(encoded.trim()).ok()?).ok()?;
+    let (username, password) = decoded.split_once(':')?;
+    Some((username.to_string(), password.to_string()))
+}
+
+fn password_matches(stored: &str, password: &str) -> bool {
+    PasswordHash::new(stored)
+        .map(|hash| Argon2::default().verify_password(password.as_bytes(), &hash).is_ok())
+        .unwrap_or(false)
+}
+
+impl FromRequest for AuthenticatedAccount {
+    type Error = AuthError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let credentials = basic_credentials(req);
+        let state = req.app_data::<web::Data<AppState>>().cloned();
+        Box::pin(async move {
+            let (username, password) = credentials.ok_or(AuthError::MissingCredentials)?;
+            let state = state.ok_or_else(|| AuthError::Database("application state missing".to_string()))?;
+            let db = state.get_local_db().await;
+            let account = AccountEntity::find()
+                .filter(AccountColumn::Username.eq(username))
+                .one(&db)
+                .await
+                .map_err(|e| AuthError::Database(e.to_string()))?
+                .ok_or(AuthError::InvalidCredentials)?;
+            if !password_matches(&account.password, &password) {
+                return Err(AuthError::InvalidCredentials);
+            }
+            Ok(AuthenticatedAccount { account_id: account.account_id, role: account.role })
+        })
+    }
+}
@@ -8,6 +8,13 @@ pub struct ServerConfig {
     pub workers: Option<usize>,
     pub enable_cloud_sync: bool,
     pub cloud_sync_interval_seconds: u64,
+    pub mllp_enabled: bool,
+    pub mllp_host: String,
+    pub mllp_port: u16,
+    pub retention_enabled: bool,
+    /// `entity:basis:days` list, see `RetentionRule::parse_list`.
+    pub retention_rules: String,
+    pub retention_scan_interval_seconds: u64,
 }
 
 impl Default for ServerConfig {
@@ -27,6 +34,24 @@ impl Default for ServerConfig {
                 .unwrap_or_else(|_| "300".to_string()) // 5 minutes
                 .parse()
                 .unwrap_or(300),
+            mllp_enabled: env::var("MLLP_ENABLED")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .unwrap_or(false),
+            mllp_host: env::var("MLLP_HOST").unwrap_or_else(|_| "127.0.0.1".to_string()),
+            mllp_port: env::var("MLLP_PORT")
+                .unwrap_or_else(|_| "2575".to_string())
+                .parse()
+                .unwrap_or(2575),
+            retention_enabled: env::var("RETENTION_ENABLED")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .unwrap_or(false),
+            retention_rules: env::var("RETENTION_RULES").unwrap_or_default(),
+            retention_scan_interval_seconds: env::var("RETENTION_SCAN_INTERVAL")
+                .unwrap_or_else(|_| "86400".to_string()) // daily
+                .parse()
+                .unwrap_or(86400),
         }
     }
 }
@@ -0,0 +1,45 @@
+use actix_web::http::header::{EntityTag, ETag, Header, IfMatch, IF_MATCH};
+use actix_web::{HttpRequest, HttpResponse};
+use chrono::{DateTime, Utc};
+use serde_json::json;
+
+use crate::handlers::row_version;
+
+/// Strong `ETag` for a row, derived from its `updated_at`.
+pub fn etag_for(updated_at: &DateTime<Utc>) -> ETag {
+    ETag(EntityTag::new_strong(row_version(updated_at).to_string()))
+}
+
+/// Version required by the request's `If-Match` header.
+///
+/// # Returns
+/// - `Ok(Some(version))` for a strong tag previously issued by [`etag_for`]
+/// - `Ok(None)` for `If-Match: *` (any current version)
+///
+/// # Errors
+/// - `428 Precondition Required` when the header is missing
+/// - `412 Precondition Failed` when no listed tag can ever match (weak or foreign tags)
+pub fn required_version(req: &HttpRequest) -> Result<Option<i64>, HttpResponse> {
+    if !req.headers().contains_key(IF_MATCH) {
+        return Err(HttpResponse::PreconditionRequired().json(json!({
+            "error": "If-Match header is required; use the ETag returned when the resource was read"
+        })));
+    }
+
+    match IfMatch::parse(req) {
+        Ok(IfMatch::Any) => Ok(None),
+        Ok(IfMatch::Items(tags)) => tags
+            .iter()
+            .filter(|tag| !tag.weak)
+            .find_map(|tag| tag.tag().parse::<i64>().ok())
+            .map(Some)
+            .ok_or_else(|| {
+                HttpResponse::PreconditionFailed().json(json!({
+                    "error": "If-Match does not match the current version"
+                }))
+            }),
+        Err(_) => Err(HttpResponse::BadRequest().json(json!({
+            "error": "Malformed If-Match header"
+        }))),
+    }
+}
@@ -1,12 +1,104 @@
-use actix_web::{web, HttpResponse, Result};
+use actix_web::{web, HttpRequest, HttpResponse, Result};
 use serde_json::json;
+use crate::server::auth::AuthenticatedAccount;
+use crate::server::etag::{etag_for, required_version};
+use crate::server::merge_patch::parse_merge_patch;
 use crate::server::state::AppState;
 use uuid::Uuid;
 
 use crate::handlers::{
-    CreatePatientRequest, UpdatePatientRequest,
-    create_patient, get_patient, get_all_patients, update_patient, delete_patient
+    CreatePatientRequest, UpdatePatientRequest, PatientMergePatch, PatientAddress, PatientNumbering, PatientSearch,
+    Conditional, PatientDeletion,
+    create_patient, get_patient, get_all_patients, search_patients, update_patient, delete_patient,
+    update_patient_if_unmodified, patch_patient_if_unmodified, delete_patient_if_unmodified,
 };
+use crate::handlers::medical_services_handler::{
+    CreateServiceRequest, ServiceMergePatch,
+    create_service, get_service, get_all_service, patch_service_if_unmodified, delete_service_if_unmodified,
+};
+use crate::handlers::psgc_handlers::{
+    AddressLevel, AddressResolution, PsgcSearch,
+    count_patients_by_area, get_psgc_area, get_psgc_children, resolve_address, search_psgc_areas,
+};
+use crate::handlers::audit_handlers::{get_audit_log, record_audit, AuditLogQuery, CreateAuditEntry};
+use crate::handlers::consent_handlers::{
+    GrantConsentRequest, WithdrawConsentRequest,
+    get_consent_history, get_consent_summary, grant_consent, withdraw_consent,
+};
+use crate::models::consent_tb::{ConsentType, Model as ConsentModel};
+use crate::handlers::retention_handlers::{
+    RetentionDecision, RetentionDecisionRequest, PurgeOutcome,
+    decide_candidate, get_retention_candidates, purge_candidate, scan_retention,
+};
+use crate::handlers::medical_record_handlers::{
+    CreateMedicalRecordRequest, MedicalRecordMergePatch, UpdateMedicalRecordRequest,
+    create_medical_record, get_medical_record, get_patient_medical_records, patch_medical_record_if_unmodified,
+    update_medical_record_if_unmodified,
+};
+use crate::handlers::icd10_handlers::{
+    DiagnosesUpdate, DiagnosisCountQuery, Icd10Search, SetDiagnosesRequest,
+    count_diagnoses, get_icd10_code, get_medical_record_diagnoses, search_icd10_codes, set_medical_record_diagnoses,
+};
+use crate::handlers::prescription_handlers::{
+    CreatePrescriptionItemRequest, UpdatePrescriptionItemRequest,
+    add_prescription_item, delete_prescription_item, get_prescription_item, get_prescription_items,
+    get_printable_prescription, update_prescription_item,
+};
+use crate::handlers::vital_sign_handlers::{
+    RecordVitalsRequest, VitalsOutcome, VitalsQuery, get_patient_vitals, get_vital_series, record_vitals,
+};
+use crate::handlers::service_result_handlers::{
+    AnalytesUpdate, EnterResultsRequest, ReleaseResultsRequest, ResultsUpdate, SetAnalytesRequest,
+    enter_service_results, get_printable_lab_report, get_service_analytes, get_visit_results, release_service_results,
+    set_service_analytes,
+};
+use crate::handlers::unit_conversion_handlers::{
+    UnitConversionInput, UnitConversionOutcome, UnitsQuery,
+    delete_unit_conversion, get_unit_conversions, results_in, save_unit_conversion,
+};
+use crate::handlers::reference_range_handlers::{
+    ReferenceRangeOutcome, ReferenceRangeQuery, SaveReferenceRangeRequest,
+    create_reference_range, delete_reference_range, get_reference_range, get_reference_ranges, update_reference_range,
+};
+use crate::handlers::household_handlers::{
+    AttachMemberRequest, AttachOutcome, CreateHouseholdRequest,
+    attach_member, create_household, delete_household, detach_member, get_all_households, get_household,
+    get_household_statement, get_household_visits,
+};
+use crate::models::medical_services::Model as ServiceModel;
+use crate::models::retention_candidate_tb::{CandidateStatus, Model as RetentionCandidateModel};
+use crate::server::retention::{purge_cloud_target, RetentionPolicy};
+use crate::server::patient_numbers::create_patient_with_cloud_copy;
+use crate::models::patient_tb::Model as PatientModel;
+use crate::models::medical_record_tb::Model as MedicalRecordModel;
+
+/// 412 response carrying the current row and its `ETag` so the client can re-apply its change.
+fn patient_precondition_failed(current: &PatientModel) -> HttpResponse {
+    HttpResponse::PreconditionFailed()
+        .insert_header(etag_for(&current.updated_at))
+        .json(json!({
+            "error": "Patient was modified since it was read",
+            "current": current
+        }))
+}
+
+/// Resolves the structured address of a patient request against the PSGC
+/// table, or returns the 422/500 response to send instead.
+async fn resolved_address(
+    db: &sea_orm::DatabaseConnection,
+    address: Option<PatientAddress>,
+) -> std::result::Result<Option<PatientAddress>, HttpResponse> {
+    let Some(address) = address else {
+        return Ok(None);
+    };
+    match resolve_address(db, address).await {
+        Ok(AddressResolution::Resolved(address)) => Ok(Some(address)),
+        Ok(AddressResolution::Invalid(msg)) => Err(HttpResponse::UnprocessableEntity().json(json!({ "error": msg }))),
+        Err(e) => Err(HttpResponse::InternalServerError().json(json!({
+            "error": format!("Failed to resolve address: {}", e)
+        }))),
+    }
+}
 
 /// Health check endpoint for API monitoring
 ///
@@ -72,6 +164,7 @@ pub async fn db_status(state: web::Data<AppState>) -> Result<HttpResponse> {
 ///
 /// # Returns
 /// - `HttpResponse::Created()` with the created patient data if successful
+/// - `HttpResponse::UnprocessableEntity()` (422) if the PSGC codes of `address` are unknown or inconsistent
 /// - `HttpResponse::InternalServerError()` if database operation fails
 ///
 /// # Errors
@@ -83,25 +176,33 @@ pub async fn db_status(state: web::Data<AppState>) -> Result<HttpResponse> {
 /// - Async synchronization to cloud database if available
 /// - Cloud errors are non-blocking for the client response
 ///
+/// # Patient Number
+/// - `patient_number` (`PRN-<year>-<6 digits>`) is assigned by the server
+/// - With cloud sync enabled it comes from the cloud database; while that is
+///   unreachable the patient is created with `patient_number: null` and numbered
+///   by the background job once the cloud is back
+///
 /// # Example
 /// ```
 /// POST /patients
-/// Request Body: {"first_name": "John", "last_name": "Doe", ...}
-/// Response: 201 Created with patient data
+/// Request Body: {"first_name": "John", "last_name": "Doe", ...,
+///                "address": {"address_line": "123 Rizal St.", "barangay_code": "0102801001"}}
+/// Response: 201 Created with patient data (enclosing PSGC codes filled in)
 /// ```
 pub async fn create_patient_handler(
     state: web::Data<AppState>,
     req: web::Json<CreatePatientRequest>,
 ) -> Result<HttpResponse> {
     let db = state.get_local_db().await;
-    let create_req = req.into_inner();
-    match create_patient(&db, create_req.clone()).await {
-        Ok(patient) => {
-            if let Some(cloud_db) = state.get_cloud_db().await {
-                let _ = create_patient(&cloud_db, create_req).await;
-            }
-            Ok(HttpResponse::Created().json(patient))
-        }
+    let mut create_req = req.into_inner();
+    create_req.address = match resolved_address(&db, create_req.address).await {
+        Ok(address) => address,
+        Err(response) => return Ok(response),
+    };
+    match create_patient_with_cloud_copy(&state, create_req).await {
+        Ok(patient) => Ok(HttpResponse::Created()
+            .insert_header(etag_for(&patient.updated_at))
+            .json(patient)),
         Err(e) => Ok(HttpResponse::InternalServerError().json(json!({
             "error": format!("Failed to create patient: {}", e)
         })))
@@ -115,7 +216,7 @@ pub async fn create_patient_handler(
 /// - `path`: Path parameter containing the patient's UUID (`web::Path<Uuid>`)
 ///
 /// # Returns
-/// - `HttpResponse::Ok()` with patient data if found
+/// - `HttpResponse::Ok()` with patient data and an `ETag` header if found
 /// - `HttpResponse::NotFound()` if patient doesn't exist
 /// - `HttpResponse::InternalServerError()` if database operation fails
 ///
@@ -123,10 +224,13 @@ pub async fn create_patient_handler(
 /// - Returns 404 if patient with specified UUID doesn't exist
 /// - Returns 500 if database query fails
 ///
+/// # Concurrency
+/// - The `ETag` must be sent back as `If-Match` when updating or deleting the patient
+///
 /// # Example
 /// ```
 /// GET /patients/{uuid}
-/// Response: 200 OK with patient data or 404 Not Found
+/// Response: 200 OK with patient data and ETag: "1760857200123456", or 404 Not Found
 /// ```
 pub async fn get_patient_handler(
     state: web::Data<AppState>,
@@ -135,7 +239,9 @@ pub async fn get_patient_handler(
     let patient_id = path.into_inner();
     let db = state.get_local_db().await;
     match get_patient(&db, patient_id).await {
-        Ok(Some(patient)) => Ok(HttpResponse::Ok().json(patient)),
+        Ok(Some(patient)) => Ok(HttpResponse::Ok()
+            .insert_header(etag_for(&patient.updated_at))
+            .json(patient)),
         Ok(None) => Ok(HttpResponse::NotFound().json(json!({
             "error": "Patient not found"
         }))),
@@ -145,10 +251,12 @@ pub async fn get_patient_handler(
     }
 }
 
-/// Retrieves all patients from the local database
+/// Retrieves all patients from the local database, optionally filtered
 ///
 /// # Parameters
 /// - `state`: Web-wrapped application state containing database connections
+/// - `query`: Optional filters `last_name`, `first_name`, `birth_date`,
+///   `identifier` (UUID, patient number or CSD/PWD id), `patient_number`, `mobile_number` and `limit`
 ///
 /// # Returns
 /// - `HttpResponse::Ok()` with array of patient data
@@ -157,17 +265,35 @@ pub async fn get_patient_handler(
 /// # Notes
 /// - Only queries local database (does not attempt cloud synchronization)
 /// - Returns empty array if no patients exist
+/// - `mobile_number` and CSD/PWD id lookups are exact and keep working when
+///   PII encryption is enabled (they go through the blind indexes)
 ///
 /// # Example
 /// ```
 /// GET /patients
+/// GET /patients?mobile_number=%2B63%20917%20123%204567
+/// GET /patients?patient_number=PRN-2026-000123
 /// Response: 200 OK with array of patient objects
 /// ```
 pub async fn get_all_patients_handler(
     state: web::Data<AppState>,
+    query: web::Query<PatientSearch>,
 ) -> Result<HttpResponse> {
     let db = state.get_local_db().await;
-    match get_all_patients(&db).await {
+    let search = query.into_inner();
+    let filtered = search.last_name.is_some()
+        || search.first_name.is_some()
+        || search.birth_date.is_some()
+        || search.identifier.is_some()
+        || search.patient_number.is_some()
+        || search.mobile_number.is_some()
+        || search.limit.is_some();
+    let patients = if filtered {
+        search_patients(&db, search).await
+    } else {
+        get_all_patients(&db).await
+    };
+    match patients {
         Ok(patients) => Ok(HttpResponse::Ok().json(patients)),
         Err(e) => Ok(HttpResponse::InternalServerError().json(json!({
             "error": format!("Failed to get patients: {}", e)
@@ -179,14 +305,21 @@ pub async fn get_all_patients_handler(
 ///
 /// # Parameters
 /// - `state`: Web-wrapped application state containing database connections
+/// - `http_req`: Request, used to read the `If-Match` header
 /// - `path`: Path parameter containing the patient's UUID (`web::Path<Uuid>`)
 /// - `req`: JSON payload containing update data wrapped in `web::Json`
 ///
 /// # Returns
-/// - `HttpResponse::Ok()` with updated patient data if successful
+/// - `HttpResponse::Ok()` with updated patient data and its new `ETag` if successful
 /// - `HttpResponse::NotFound()` if patient doesn't exist
+/// - `HttpResponse::UnprocessableEntity()` (422) if the PSGC codes of `address` are unknown or inconsistent
+/// - `HttpResponse::PreconditionRequired()` (428) if `If-Match` is missing
+/// - `HttpResponse::PreconditionFailed()` (412) with the current patient if it changed since it was read
 /// - `HttpResponse::InternalServerError()` if database operation fails
 ///
+/// # Concurrency
+/// - `If-Match` must carry the `ETag` from the last read; `If-Match: *` updates unconditionally
+///
 /// # Synchronization Behavior
 /// - Primary update happens in local database
 /// - Async synchronization to cloud database if available
@@ -194,26 +327,40 @@ pub async fn get_all_patients_handler(
 ///
 /// # Example
 /// ```
-/// PATCH /patients/{uuid}
+/// PUT /patients/{uuid}
+/// If-Match: "1760857200123456"
 /// Request Body: {"first_name": "NewName", ...}
-/// Response: 200 OK with updated data or 404 Not Found
+/// Response: 200 OK with updated data, 404 Not Found or 412 Precondition Failed
 /// ```
 pub async fn update_patient_handler(
     state: web::Data<AppState>,
+    http_req: HttpRequest,
     path: web::Path<Uuid>,
     req: web::Json<UpdatePatientRequest>,
 ) -> Result<HttpResponse> {
+    let expected_version = match required_version(&http_req) {
+        Ok(version) => version,
+        Err(response) => return Ok(response),
+    };
     let patient_id = path.into_inner();
     let db = state.get_local_db().await;
-    let update_req = req.into_inner();
-    match update_patient(&db, patient_id, update_req.clone()).await {
-        Ok(Some(patient)) => {
+    let mut update_req = req.into_inner();
+    update_req.address = match resolved_address(&db, update_req.address).await {
+        Ok(address) => address,
+        Err(response) => return Ok(response),
+    };
+
+    match update_patient_if_unmodified(&db, patient_id, expected_version, update_req.clone()).await {
+        Ok(Conditional::Applied(patient)) => {
             if let Some(cloud_db) = state.get_cloud_db().await {
                 let _ = update_patient(&cloud_db, patient_id, update_req).await;
             }
-            Ok(HttpResponse::Ok().json(patient))
+            Ok(HttpResponse::Ok()
+                .insert_header(etag_for(&patient.updated_at))
+                .json(patient))
         }
-        Ok(None) => Ok(HttpResponse::NotFound().json(json!({
+        Ok(Conditional::Modified(current)) => Ok(patient_precondition_failed(&current)),
+        Ok(Conditional::NotFound) => Ok(HttpResponse::NotFound().json(json!({
             "error": "Patient not found"
         }))),
         Err(e) => Ok(HttpResponse::InternalServerError().json(json!({
@@ -222,15 +369,94 @@ pub async fn update_patient_handler(
     }
 }
 
+/// Applies an RFC 7396 merge patch to a patient
+///
+/// # Parameters
+/// - `state`: Web-wrapped application state containing database connections
+/// - `http_req`: Request, used to read `Content-Type` and `If-Match`
+/// - `path`: Path parameter containing the patient's UUID (`web::Path<Uuid>`)
+/// - `body`: `application/merge-patch+json` document
+///
+/// # Returns
+/// - `HttpResponse::Ok()` with updated patient data and its new `ETag`
+/// - `HttpResponse::BadRequest()` if the body is not a valid patch
+/// - `HttpResponse::NotFound()` if patient doesn't exist
+/// - `HttpResponse::PreconditionRequired()` (428) / `PreconditionFailed()` (412) as for `PUT`
+/// - `HttpResponse::UnsupportedMediaType()` (415) for any other content type
+/// - `HttpResponse::UnprocessableEntity()` (422) if the patch nulls a required field or
+///   carries unknown or inconsistent PSGC codes
+///
+/// # Patch Semantics
+/// - Absent members are left unchanged
+/// - `null` clears `middle_name`, `csd_id_or_pwd_id`, `mobile_number`, `residential_address`, `address`
+/// - `address` is replaced as a whole, not merged
+///
+/// # Example
+/// ```
+/// PATCH /patients/{uuid}
+/// Content-Type: application/merge-patch+json
+/// If-Match: "1760857200123456"
+/// Request Body: {"middle_name": null, "mobile_number": "09171234567"}
+/// Response: 200 OK with updated data
+/// ```
+pub async fn patch_patient_handler(
+    state: web::Data<AppState>,
+    http_req: HttpRequest,
+    path: web::Path<Uuid>,
+    body: web::Bytes,
+) -> Result<HttpResponse> {
+    let mut patch: PatientMergePatch = match parse_merge_patch(&http_req, &body) {
+        Ok(patch) => patch,
+        Err(response) => return Ok(response),
+    };
+    if let Err(msg) = patch.validate() {
+        return Ok(HttpResponse::UnprocessableEntity().json(json!({ "error": msg })));
+    }
+    let expected_version = match required_version(&http_req) {
+        Ok(version) => version,
+        Err(response) => return Ok(response),
+    };
+    let patient_id = path.into_inner();
+    let db = state.get_local_db().await;
+    if let Some(Some(address)) = patch.address.clone() {
+        patch.address = match resolved_address(&db, Some(address)).await {
+            Ok(address) => Some(address),
+            Err(response) => return Ok(response),
+        };
+    }
+
+    match patch_patient_if_unmodified(&db, patient_id, expected_version, patch.clone()).await {
+        Ok(Conditional::Applied(patient)) => {
+            if let Some(cloud_db) = state.get_cloud_db().await {
+                let _ = patch_patient_if_unmodified(&cloud_db, patient_id, None, patch).await;
+            }
+            Ok(HttpResponse::Ok()
+                .insert_header(etag_for(&patient.updated_at))
+                .json(patient))
+        }
+        Ok(Conditional::Modified(current)) => Ok(patient_precondition_failed(&current)),
+        Ok(Conditional::NotFound) => Ok(HttpResponse::NotFound().json(json!({
+            "error": "Patient not found"
+        }))),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": format!("Failed to patch patient: {}", e)
+        })))
+    }
+}
+
 /// Deletes a patient record by UUID
 ///
 /// # Parameters
 /// - `state`: Web-wrapped application state containing database connections
+/// - `http_req`: Request, used to read the `If-Match` header
 /// - `path`: Path parameter containing the patient's UUID (`web::Path<Uuid>`)
 ///
 /// # Returns
 /// - `HttpResponse::Ok()` with success message if deleted
 /// - `HttpResponse::NotFound()` if patient doesn't exist
+/// - `HttpResponse::PreconditionRequired()` (428) if `If-Match` is missing
+/// - `HttpResponse::PreconditionFailed()` (412) with the current patient if it changed since it was read
+/// - `HttpResponse::Conflict()` (409) if the patient has bills, which are never deleted with it
 /// - `HttpResponse::InternalServerError()` if database operation fails
 ///
 /// # Synchronization Behavior
@@ -241,16 +467,23 @@ pub async fn update_patient_handler(
 /// # Example
 /// ```
 /// DELETE /patients/{uuid}
-/// Response: 200 OK with success message or 404 Not Found
+/// If-Match: "1760857200123456"
+/// Response: 200 OK with success message, 404 Not Found, 409 Conflict or 412 Precondition Failed
 /// ```
 pub async fn delete_patient_handler(
     state: web::Data<AppState>,
+    http_req: HttpRequest,
     path: web::Path<Uuid>,
 ) -> Result<HttpResponse> {
+    let expected_version = match required_version(&http_req) {
+        Ok(version) => version,
+        Err(response) => return Ok(response),
+    };
     let patient_id = path.into_inner();
     let db = state.get_local_db().await;
-    match delete_patient(&db, patient_id).await {
-        Ok(true) => {
+
+    match delete_patient_if_unmodified(&db, patient_id, expected_version).await {
+        Ok(PatientDeletion::Deleted(_)) => {
             if let Some(cloud_db) = state.get_cloud_db().await {
                 let _ = delete_patient(&cloud_db, patient_id).await;
             }
@@ -258,9 +491,17 @@ pub async fn delete_patient_handler(
                 "message": "Patient deleted successfully"
             })))
         }
-        Ok(false) => Ok(HttpResponse::NotFound().json(json!({
+        Ok(PatientDeletion::Modified(current)) => Ok(patient_precondition_failed(&current)),
+        Ok(PatientDeletion::NotFound) => Ok(HttpResponse::NotFound().json(json!({
             "error": "Patient not found"
         }))),
+        Ok(PatientDeletion::HasBills(bills)) => Ok(HttpResponse::Conflict().json(json!({
+            "error": format!(
+                "Patient has {} bill(s); bills are kept, so the patient can't be deleted (see retention)",
+                bills
+            ),
+            "bills": bills
+        }))),
         Err(e) => Ok(HttpResponse::InternalServerError().json(json!({
             "error": format!("Failed to delete patient: {}", e)
         })))
@@ -279,13 +520,14 @@ pub async fn delete_patient_handler(
 ///
 /// # Behavior
 /// - Retrieves all patients from local database
-/// - Attempts to create each patient in cloud database
+/// - Creates each patient missing from the cloud database under its local id;
+///   patients already there are counted in `already_synced`
 /// - Returns summary of synchronization operations
 ///
 /// # Example
 /// ```
 /// POST /sync-to-cloud
-/// Response: 200 OK with {"message": "Sync completed", "synced_count": 5, ...}
+/// Response: 200 OK with {"message": "Sync completed", "synced_count": 5, "already_synced": 12, ...}
 /// ```
 pub async fn sync_to_cloud_handler(
     state: web::Data<AppState>,
@@ -302,6 +544,7 @@ pub async fn sync_to_cloud_handler(
     match get_all_patients(&local_db).await {
         Ok(patients) => {
             let mut synced_count = 0;
+            let mut already_synced = 0;
             let mut errors = Vec::new();
             
             for patient in &patients {
@@ -310,12 +553,30 @@ pub async fn sync_to_cloud_handler(
                     last_name: patient.last_name.clone(),
                     middle_name: patient.middle_name.clone(),
                     birth_date: patient.birth_date,
+                    sex: patient.sex,
                     csd_id_or_pwd_id: patient.csd_id_or_pwd_id.clone(),
                     mobile_number: patient.mobile_number.clone(),
                     residential_address: patient.residential_address.clone(),
+                    address: Some(PatientAddress::from(patient)),
+                    // Never number a copy separately: it would differ from the local number
+                    numbering: patient
+                        .patient_number
+                        .clone()
+                        .map_or(PatientNumbering::Deferred, PatientNumbering::Assigned),
                 };
                 
-                match create_patient(&cloud_db, create_request).await {
+                match get_patient(&cloud_db, patient.patient_id).await {
+                    Ok(Some(_)) => {
+                        already_synced += 1;
+                        continue;
+                    }
+                    Ok(None) => {}
+                    Err(e) => {
+                        errors.push(format!("Failed to check patient {}: {}", patient.patient_id, e));
+                        continue;
+                    }
+                }
+                match create_patient(&cloud_db, patient.patient_id, create_request).await {
                     Ok(_) => synced_count += 1,
                     Err(e) => errors.push(format!("Failed to sync patient {}: {}", patient.patient_id, e)),
                 }
@@ -324,6 +585,7 @@ pub async fn sync_to_cloud_handler(
             Ok(HttpResponse::Ok().json(json!({
                 "message": "Sync completed",
                 "synced_count": synced_count,
+                "already_synced": already_synced,
                 "total_patients": patients.len(),
                 "errors": errors
             })))
@@ -332,4 +594,2342 @@ pub async fn sync_to_cloud_handler(
             "error": format!("Failed to get patients for sync: {}", e)
         })))
     }
-}
\ No newline at end of file
+}
+
+fn service_precondition_failed(current: &ServiceModel) -> HttpResponse {
+    HttpResponse::PreconditionFailed()
+        .insert_header(etag_for(&current.updated_at))
+        .json(json!({
+            "error": "Service was modified since it was read",
+            "current": current
+        }))
+}
+
+/// Creates a new entry in the medical services price catalog
+///
+/// # Returns
+/// - `HttpResponse::Created()` with the created service and its `ETag`
+/// - `HttpResponse::InternalServerError()` if database operation fails
+///
+/// # Example
+/// ```
+/// POST /services
+/// Request Body: {"ms_name": "CBC", "ms_category": "Hematology", "ms_price": 250.0}
+/// Response: 201 Created with service data
+/// ```
+pub async fn create_service_handler(
+    state: web::Data<AppState>,
+    req: web::Json<CreateServiceRequest>,
+) -> Result<HttpResponse> {
+    let db = state.get_local_db().await;
+    let create_req = req.into_inner();
+    match create_service(&db, Uuid::new_v4(), create_req.clone()).await {
+        Ok(service) => {
+            if let Some(cloud_db) = state.get_cloud_db().await {
+                let _ = create_service(&cloud_db, service.ms_id, create_req).await;
+            }
+            Ok(HttpResponse::Created()
+                .insert_header(etag_for(&service.updated_at))
+                .json(service))
+        }
+        Err(e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": format!("Failed to create service: {}", e)
+        })))
+    }
+}
+
+/// Lists the medical services price catalog
+///
+/// # Example
+/// ```
+/// GET /services
+/// Response: 200 OK with array of services
+/// ```
+pub async fn get_all_services_handler(
+    state: web::Data<AppState>,
+) -> Result<HttpResponse> {
+    let db = state.get_local_db().await;
+    match get_all_service(&db).await {
+        Ok(services) => Ok(HttpResponse::Ok().json(services)),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": format!("Failed to get services: {}", e)
+        })))
+    }
+}
+
+/// Retrieves a medical service by UUID, with its `ETag`
+///
+/// # Example
+/// ```
+/// GET /services/{uuid}
+/// Response: 200 OK with service data or 404 Not Found
+/// ```
+pub async fn get_service_handler(
+    state: web::Data<AppState>,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse> {
+    let db = state.get_local_db().await;
+    match get_service(&db, path.into_inner()).await {
+        Ok(Some(service)) => Ok(HttpResponse::Ok()
+            .insert_header(etag_for(&service.updated_at))
+            .json(service)),
+        Ok(None) => Ok(HttpResponse::NotFound().json(json!({
+            "error": "Service not found"
+        }))),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": format!("Failed to get service: {}", e)
+        })))
+    }
+}
+
+/// Applies an RFC 7396 merge patch to a medical service
+///
+/// # Returns
+/// - Same statuses as `PATCH /patients/{uuid}`; every service field is required,
+///   so any `null` member is answered with 422
+///
+/// # Example
+/// ```
+/// PATCH /services/{uuid}
+/// Content-Type: application/merge-patch+json
+/// If-Match: "1760857200123456"
+/// Request Body: {"ms_price": 300.0}
+/// Response: 200 OK with updated data
+/// ```
+pub async fn patch_service_handler(
+    state: web::Data<AppState>,
+    http_req: HttpRequest,
+    path: web::Path<Uuid>,
+    body: web::Bytes,
+) -> Result<HttpResponse> {
+    let patch: ServiceMergePatch = match parse_merge_patch(&http_req, &body) {
+        Ok(patch) => patch,
+        Err(response) => return Ok(response),
+    };
+    if let Err(msg) = patch.validate() {
+        return Ok(HttpResponse::UnprocessableEntity().json(json!({ "error": msg })));
+    }
+    let expected_version = match required_version(&http_req) {
+        Ok(version) => version,
+        Err(response) => return Ok(response),
+    };
+    let ms_id = path.into_inner();
+    let db = state.get_local_db().await;
+
+    match patch_service_if_unmodified(&db, ms_id, expected_version, patch.clone()).await {
+        Ok(Conditional::Applied(service)) => {
+            if let Some(cloud_db) = state.get_cloud_db().await {
+                let _ = patch_service_if_unmodified(&cloud_db, ms_id, None, patch).await;
+            }
+            Ok(HttpResponse::Ok()
+                .insert_header(etag_for(&service.updated_at))
+                .json(service))
+        }
+        Ok(Conditional::Modified(current)) => Ok(service_precondition_failed(&current)),
+        Ok(Conditional::NotFound) => Ok(HttpResponse::NotFound().json(json!({
+            "error": "Service not found"
+        }))),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": format!("Failed to patch service: {}", e)
+        })))
+    }
+}
+
+/// Deletes a medical service; requires `If-Match` like patient deletion
+///
+/// # Example
+/// ```
+/// DELETE /services/{uuid}
+/// If-Match: "1760857200123456"
+/// Response: 200 OK, 404 Not Found or 412 Precondition Failed
+/// ```
+pub async fn delete_service_handler(
+    state: web::Data<AppState>,
+    http_req: HttpRequest,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse> {
+    let expected_version = match required_version(&http_req) {
+        Ok(version) => version,
+        Err(response) => return Ok(response),
+    };
+    let ms_id = path.into_inner();
+    let db = state.get_local_db().await;
+
+    match delete_service_if_unmodified(&db, ms_id, expected_version).await {
+        Ok(Conditional::Applied(_)) => {
+            if let Some(cloud_db) = state.get_cloud_db().await {
+                let _ = delete_service_if_unmodified(&cloud_db, ms_id, None).await;
+            }
+            Ok(HttpResponse::Ok().json(json!({
+                "message": "Service deleted successfully"
+            })))
+        }
+        Ok(Conditional::Modified(current)) => Ok(service_precondition_failed(&current)),
+        Ok(Conditional::NotFound) => Ok(HttpResponse::NotFound().json(json!({
+            "error": "Service not found"
+        }))),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": format!("Failed to delete service: {}", e)
+        })))
+    }
+}
+
+/// Retrieves the result template of a medical service: the analytes entered
+/// for it, with units and reference ranges
+///
+/// # Example
+/// ```
+/// GET /services/{uuid}/analytes
+/// Response: 200 OK with [{"code": "GLU", "name": "Fasting glucose", "unit": "mmol/L", "reference_low": 3.9, ...}]
+/// ```
+pub async fn get_service_analytes_handler(
+    state: web::Data<AppState>,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse> {
+    let db = state.get_local_db().await;
+    match get_service_analytes(&db, path.into_inner()).await {
+        Ok(Some(analytes)) => Ok(HttpResponse::Ok().json(analytes)),
+        Ok(None) => Ok(HttpResponse::NotFound().json(json!({
+            "error": "Service not found"
+        }))),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": format!("Failed to get analytes: {}", e)
+        })))
+    }
+}
+
+/// Replaces the result template of a medical service
+///
+/// # Returns
+/// - `HttpResponse::Ok()` with the new template
+/// - `HttpResponse::NotFound()` if the service doesn't exist
+/// - `HttpResponse::UnprocessableEntity()` for a blank or repeated code, a numeric
+///   analyte with an inverted range, or a text analyte with limits
+///
+/// # Example
+/// ```
+/// PUT /services/{uuid}/analytes
+/// Request Body: {"analytes": [{"code": "GLU", "name": "Fasting glucose", "unit": "mmol/L", "value_type": "numeric",
+///                "reference_low": 3.9, "reference_high": 6.1, "critical_low": 2.2, "critical_high": 27.8}]}
+/// Response: 200 OK with analytes
+/// ```
+pub async fn set_service_analytes_handler(
+    state: web::Data<AppState>,
+    path: web::Path<Uuid>,
+    req: web::Json<SetAnalytesRequest>,
+) -> Result<HttpResponse> {
+    let db = state.get_local_db().await;
+    match set_service_analytes(&db, path.into_inner(), req.into_inner()).await {
+        Ok(AnalytesUpdate::Updated(analytes)) => Ok(HttpResponse::Ok().json(analytes)),
+        Ok(AnalytesUpdate::ServiceNotFound) => Ok(HttpResponse::NotFound().json(json!({
+            "error": "Service not found"
+        }))),
+        Ok(AnalytesUpdate::Invalid(message)) => Ok(HttpResponse::UnprocessableEntity().json(json!({
+            "error": message
+        }))),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": format!("Failed to set analytes: {}", e)
+        })))
+    }
+}
+
+/// Lists the configured reference ranges, optionally of one analyte
+///
+/// # Example
+/// ```
+/// GET /reference-ranges?analyte_code=HGB
+/// Response: 200 OK with [{"analyte_code": "HGB", "sex": "female", "min_age_days": 6570, "max_age_days": null,
+///                        "unit": "g/L", "reference_low": 120.0, "reference_high": 150.0, ...}]
+/// ```
+pub async fn get_reference_ranges_handler(
+    state: web::Data<AppState>,
+    query: web::Query<ReferenceRangeQuery>,
+) -> Result<HttpResponse> {
+    let db = state.get_local_db().await;
+    match get_reference_ranges(&db, &query.into_inner()).await {
+        Ok(ranges) => Ok(HttpResponse::Ok().json(ranges)),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": format!("Failed to get reference ranges: {}", e)
+        })))
+    }
+}
+
+/// Retrieves one reference range
+///
+/// # Example
+/// ```
+/// GET /reference-ranges/{uuid}
+/// Response: 200 OK with reference range data, or 404 Not Found
+/// ```
+pub async fn get_reference_range_handler(
+    state: web::Data<AppState>,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse> {
+    let db = state.get_local_db().await;
+    match get_reference_range(&db, path.into_inner()).await {
+        Ok(Some(range)) => Ok(HttpResponse::Ok().json(range)),
+        Ok(None) => Ok(HttpResponse::NotFound().json(json!({
+            "error": "Reference range not found"
+        }))),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": format!("Failed to get reference range: {}", e)
+        })))
+    }
+}
+
+fn reference_range_response(outcome: ReferenceRangeOutcome, created: bool) -> HttpResponse {
+    match outcome {
+        ReferenceRangeOutcome::Saved(change) if created => HttpResponse::Created().json(change),
+        ReferenceRangeOutcome::Saved(change) => HttpResponse::Ok().json(change),
+        ReferenceRangeOutcome::NotFound => HttpResponse::NotFound().json(json!({
+            "error": "Reference range not found"
+        })),
+        ReferenceRangeOutcome::Invalid(message) => HttpResponse::UnprocessableEntity().json(json!({
+            "error": message
+        })),
+    }
+}
+
+/// Adds a reference range for an analyte, sex and age band, and reflags the
+/// analyte's results that aren't released yet
+///
+/// # Parameters
+/// - `account`: The authenticated caller (HTTP Basic), recorded as `created_by`
+/// - `req`: JSON body with the range
+///
+/// # Returns
+/// - `HttpResponse::Created()` with `{"range": {...}, "results_reflagged": n}`
+/// - `HttpResponse::Unauthorized()` without valid credentials
+/// - `HttpResponse::UnprocessableEntity()` for limits out of order, an empty or
+///   inverted age band, or a band overlapping another range of the same
+///   analyte, unit and sex
+///
+/// # Example
+/// ```
+/// POST /reference-ranges
+/// Authorization: Basic <credentials>
+/// Request Body: {"analyte_code": "HGB", "sex": "female", "min_age_days": 6570, "unit": "g/L",
+///                "reference_low": 120, "reference_high": 150, "critical_low": 70, "critical_high": 200}
+/// Response: 201 Created with {"range": {...}, "results_reflagged": 3}
+/// ```
+pub async fn create_reference_range_handler(
+    state: web::Data<AppState>,
+    account: AuthenticatedAccount,
+    req: web::Json<SaveReferenceRangeRequest>,
+) -> Result<HttpResponse> {
+    let db = state.get_local_db().await;
+    match create_reference_range(&db, req.into_inner(), account.account_id).await {
+        Ok(outcome) => Ok(reference_range_response(outcome, true)),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": format!("Failed to create reference range: {}", e)
+        })))
+    }
+}
+
+/// Replaces a reference range and reflags the unreleased results it affects.
+/// The authenticated caller is recorded as `updated_by`.
+///
+/// # Returns
+/// - Same statuses as `POST /reference-ranges`, with `200 OK` on success and
+///   `404 Not Found` for an unknown range
+///
+/// # Example
+/// ```
+/// PUT /reference-ranges/{uuid}
+/// Authorization: Basic <credentials>
+/// Request Body: {"analyte_code": "HGB", "sex": "female", "min_age_days": 6570, "unit": "g/L",
+///                "reference_low": 118, "reference_high": 150}
+/// Response: 200 OK with {"range": {...}, "results_reflagged": 1}
+/// ```
+pub async fn update_reference_range_handler(
+    state: web::Data<AppState>,
+    path: web::Path<Uuid>,
+    account: AuthenticatedAccount,
+    req: web::Json<SaveReferenceRangeRequest>,
+) -> Result<HttpResponse> {
+    let db = state.get_local_db().await;
+    match update_reference_range(&db, path.into_inner(), req.into_inner(), account.account_id).await {
+        Ok(outcome) => Ok(reference_range_response(outcome, false)),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": format!("Failed to update reference range: {}", e)
+        })))
+    }
+}
+
+/// Deletes a reference range; unreleased results fall back to another range
+/// or the service's template. The deleted range goes to the audit log
+/// (`reference_range.deleted`) with the authenticated caller as actor.
+///
+/// # Example
+/// ```
+/// DELETE /reference-ranges/{uuid}
+/// Authorization: Basic <credentials>
+/// Response: 200 OK with {"results_reflagged": 2}, 401 Unauthorized or 404 Not Found
+/// ```
+pub async fn delete_reference_range_handler(
+    state: web::Data<AppState>,
+    path: web::Path<Uuid>,
+    account: AuthenticatedAccount,
+) -> Result<HttpResponse> {
+    let db = state.get_local_db().await;
+    match delete_reference_range(&db, path.into_inner(), account.account_id).await {
+        Ok(Some(results_reflagged)) => Ok(HttpResponse::Ok().json(json!({
+            "results_reflagged": results_reflagged
+        }))),
+        Ok(None) => Ok(HttpResponse::NotFound().json(json!({
+            "error": "Reference range not found"
+        }))),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": format!("Failed to delete reference range: {}", e)
+        })))
+    }
+}
+
+/// Lists the unit registry: each analyte's SI and conventional units and the
+/// factor between them
+///
+/// # Example
+/// ```
+/// GET /unit-conversions
+/// Response: 200 OK with [{"analyte_code": "GLU", "si_unit": "mmol/L", "conventional_unit": "mg/dL",
+///                        "factor": 18.016, "si_decimals": 1, "conventional_decimals": 0, ...}]
+/// ```
+pub async fn get_unit_conversions_handler(
+    state: web::Data<AppState>,
+) -> Result<HttpResponse> {
+    let db = state.get_local_db().await;
+    match get_unit_conversions(&db).await {
+        Ok(conversions) => Ok(HttpResponse::Ok().json(conversions)),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": format!("Failed to get unit conversions: {}", e)
+        })))
+    }
+}
+
+/// Creates or replaces an analyte's unit conversion. Stored results keep their
+/// values; unreleased ones are reflagged, as ranges in the other unit apply
+/// through the conversion
+///
+/// # Parameters
+/// - `path`: Analyte code
+/// - `account`: The authenticated caller (HTTP Basic), recorded as `updated_by`
+/// - `req`: JSON body with the units, factor and decimals
+///
+/// # Returns
+/// - `HttpResponse::Ok()` with `{"conversion": {...}, "results_reflagged": n}`
+/// - `HttpResponse::Unauthorized()` without valid credentials
+/// - `HttpResponse::UnprocessableEntity()` for a missing or repeated unit, a
+///   factor that isn't positive, or decimals out of range
+///
+/// # Example
+/// ```
+/// PUT /unit-conversions/GLU
+/// Authorization: Basic <credentials>
+/// Request Body: {"si_unit": "mmol/L", "conventional_unit": "mg/dL", "factor": 18.016,
+///                "si_decimals": 1, "conventional_decimals": 0}
+/// Response: 200 OK with {"conversion": {...}, "results_reflagged": 0}
+/// ```
+pub async fn save_unit_conversion_handler(
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    account: AuthenticatedAccount,
+    req: web::Json<UnitConversionInput>,
+) -> Result<HttpResponse> {
+    let db = state.get_local_db().await;
+    match save_unit_conversion(&db, &path.into_inner(), req.into_inner(), account.account_id).await {
+        Ok(UnitConversionOutcome::Saved(change)) => Ok(HttpResponse::Ok().json(change)),
+        Ok(UnitConversionOutcome::Invalid(message)) => Ok(HttpResponse::UnprocessableEntity().json(json!({
+            "error": message
+        }))),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": format!("Failed to save unit conversion: {}", e)
+        })))
+    }
+}
+
+/// Deletes an analyte's unit conversion; its results are then only shown and
+/// entered in their stored unit. The deleted conversion goes to the audit log
+/// (`unit_conversion.deleted`) with the authenticated caller as actor.
+///
+/// # Example
+/// ```
+/// DELETE /unit-conversions/GLU
+/// Authorization: Basic <credentials>
+/// Response: 200 OK with {"results_reflagged": 0}, 401 Unauthorized or 404 Not Found
+/// ```
+pub async fn delete_unit_conversion_handler(
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    account: AuthenticatedAccount,
+) -> Result<HttpResponse> {
+    let db = state.get_local_db().await;
+    match delete_unit_conversion(&db, &path.into_inner(), account.account_id).await {
+        Ok(Some(results_reflagged)) => Ok(HttpResponse::Ok().json(json!({
+            "results_reflagged": results_reflagged
+        }))),
+        Ok(None) => Ok(HttpResponse::NotFound().json(json!({
+            "error": "Unit conversion not found"
+        }))),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": format!("Failed to delete unit conversion: {}", e)
+        })))
+    }
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct RetentionCandidatesQuery {
+    pub status: Option<CandidateStatus>,
+}
+
+/// Lists the configured retention rules (`RETENTION_RULES`)
+///
+/// # Example
+/// ```
+/// GET /retention/rules
+/// Response: 200 OK with [{"entity": "patient", "basis": "archived", "retention_days": 3650}]
+/// ```
+pub async fn get_retention_rules_handler(
+    policy: web::Data<RetentionPolicy>,
+) -> Result<HttpResponse> {
+    Ok(HttpResponse::Ok().json(&policy.rules))
+}
+
+/// Runs the retention scan now instead of waiting for the background job
+///
+/// # Returns
+/// - `HttpResponse::Ok()` with the candidates proposed by this scan
+/// - `HttpResponse::InternalServerError()` if database operation fails
+///
+/// # Example
+/// ```
+/// POST /retention/scan
+/// Response: 200 OK with {"proposed": [...]}
+/// ```
+pub async fn scan_retention_handler(
+    state: web::Data<AppState>,
+    policy: web::Data<RetentionPolicy>,
+) -> Result<HttpResponse> {
+    let db = state.get_local_db().await;
+    match scan_retention(&db, &policy.rules).await {
+        Ok(proposed) => Ok(HttpResponse::Ok().json(json!({
+            "proposed": proposed
+        }))),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": format!("Failed to scan for retention candidates: {}", e)
+        })))
+    }
+}
+
+/// Lists retention candidates, soonest due first
+///
+/// # Parameters
+/// - `status`: Optional filter (`pending`, `approved`, `rejected`, `cancelled`, `purged`)
+///
+/// # Example
+/// ```
+/// GET /retention/candidates?status=pending
+/// Response: 200 OK with array of candidates
+/// ```
+pub async fn get_retention_candidates_handler(
+    state: web::Data<AppState>,
+    query: web::Query<RetentionCandidatesQuery>,
+) -> Result<HttpResponse> {
+    let db = state.get_local_db().await;
+    match get_retention_candidates(&db, query.into_inner().status).await {
+        Ok(candidates) => Ok(HttpResponse::Ok().json(candidates)),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": format!("Failed to get retention candidates: {}", e)
+        })))
+    }
+}
+
+/// The decided candidate, or the error response for a decision that wasn't applied.
+fn applied_decision(decision: RetentionDecision) -> std::result::Result<RetentionCandidateModel, HttpResponse> {
+    match decision {
+        RetentionDecision::Applied(candidate) => Ok(candidate),
+        RetentionDecision::NotFound => Err(HttpResponse::NotFound().json(json!({
+            "error": "Retention candidate not found"
+        }))),
+        RetentionDecision::NotAdmin => Err(HttpResponse::Forbidden().json(json!({
+            "error": "Only Admin accounts can decide on retention candidates"
+        }))),
+        RetentionDecision::AlreadyDecided(current) => Err(HttpResponse::Conflict().json(json!({
+            "error": "Retention candidate is no longer pending",
+            "current": current
+        }))),
+    }
+}
+
+/// Approves a retention candidate and purges it from the local and cloud databases
+///
+/// # Parameters
+/// - `path`: Candidate UUID
+/// - `account`: The authenticated caller (HTTP Basic), who must be an Admin
+/// - `req`: JSON body with an optional `note`
+///
+/// # Returns
+/// - `HttpResponse::Ok()` with the purged candidate and its disposal `certificate` (audit log entry)
+/// - `HttpResponse::Accepted()` when approved but the cloud database is unreachable;
+///   the background job purges it once the cloud is back
+/// - `HttpResponse::Conflict()` if the candidate was already decided or is no longer due
+/// - `HttpResponse::Unauthorized()` without valid credentials
+/// - `HttpResponse::Forbidden()` if the caller is not an Admin account
+/// - `HttpResponse::NotFound()` if the candidate doesn't exist
+///
+/// # Example
+/// ```
+/// POST /retention/candidates/{uuid}/approve
+/// Authorization: Basic <admin credentials>
+/// Request Body: {"note": "Reviewed per records policy"}
+/// Response: 200 OK with {"candidate": {...}, "certificate": {...}}
+/// ```
+pub async fn approve_retention_candidate_handler(
+    state: web::Data<AppState>,
+    policy: web::Data<RetentionPolicy>,
+    path: web::Path<Uuid>,
+    account: AuthenticatedAccount,
+    req: web::Json<RetentionDecisionRequest>,
+) -> Result<HttpResponse> {
+    let candidate_id = path.into_inner();
+    let db = state.get_local_db().await;
+
+    let approved = match decide_candidate(&db, candidate_id, true, account.account_id, req.into_inner()).await {
+        Ok(decision) => match applied_decision(decision) {
+            Ok(candidate) => candidate,
+            Err(response) => return Ok(response),
+        },
+        Err(e) => return Ok(HttpResponse::InternalServerError().json(json!({
+            "error": format!("Failed to approve retention candidate: {}", e)
+        }))),
+    };
+
+    let Ok(cloud_db) = purge_cloud_target(&state, &policy).await else {
+        return Ok(HttpResponse::Accepted().json(json!({
+            "message": "Approved; purge deferred until the cloud database is available",
+            "candidate": approved
+        })));
+    };
+
+    match purge_candidate(&db, cloud_db.as_ref(), candidate_id).await {
+        Ok(PurgeOutcome::Purged { candidate, certificate }) => Ok(HttpResponse::Ok().json(json!({
+            "candidate": candidate,
+            "certificate": certificate
+        }))),
+        Ok(PurgeOutcome::Cancelled(candidate)) => Ok(HttpResponse::Conflict().json(json!({
+            "error": "Record is no longer due for disposal; candidate cancelled",
+            "current": candidate
+        }))),
+        Ok(PurgeOutcome::NotApproved(candidate)) => Ok(HttpResponse::Conflict().json(json!({
+            "error": "Retention candidate is not approved",
+            "current": candidate
+        }))),
+        Ok(PurgeOutcome::NotFound) => Ok(HttpResponse::NotFound().json(json!({
+            "error": "Retention candidate not found"
+        }))),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": format!("Approved, but purge failed (will be retried): {}", e),
+            "candidate": approved
+        })))
+    }
+}
+
+/// Rejects a retention candidate, keeping the record. Like approval, it takes
+/// an authenticated Admin caller.
+///
+/// # Example
+/// ```
+/// POST /retention/candidates/{uuid}/reject
+/// Authorization: Basic <admin credentials>
+/// Request Body: {"note": "Pending litigation"}
+/// Response: 200 OK with the rejected candidate
+/// ```
+pub async fn reject_retention_candidate_handler(
+    state: web::Data<AppState>,
+    path: web::Path<Uuid>,
+    account: AuthenticatedAccount,
+    req: web::Json<RetentionDecisionRequest>,
+) -> Result<HttpResponse> {
+    let db = state.get_local_db().await;
+    match decide_candidate(&db, path.into_inner(), false, account.account_id, req.into_inner()).await {
+        Ok(decision) => match applied_decision(decision) {
+            Ok(candidate) => Ok(HttpResponse::Ok().json(candidate)),
+            Err(response) => Ok(response),
+        },
+        Err(e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": format!("Failed to reject retention candidate: {}", e)
+        })))
+    }
+}
+
+/// Reads the audit log (e.g. disposal certificates), newest first
+///
+/// # Parameters
+/// - `action`, `entity`, `record_id`, `limit` (default 100) query parameters
+///
+/// # Example
+/// ```
+/// GET /audit-log?action=retention.disposal
+/// Response: 200 OK with array of audit entries
+/// ```
+pub async fn get_audit_log_handler(
+    state: web::Data<AppState>,
+    query: web::Query<AuditLogQuery>,
+) -> Result<HttpResponse> {
+    let db = state.get_local_db().await;
+    match get_audit_log(&db, query.into_inner()).await {
+        Ok(entries) => Ok(HttpResponse::Ok().json(entries)),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": format!("Failed to get audit log: {}", e)
+        })))
+    }
+}
+
+async fn patient_exists(state: &AppState, patient_id: Uuid) -> std::result::Result<(), HttpResponse> {
+    let db = state.get_local_db().await;
+    match get_patient(&db, patient_id).await {
+        Ok(Some(_)) => Ok(()),
+        Ok(None) => Err(HttpResponse::NotFound().json(json!({
+            "error": "Patient not found"
+        }))),
+        Err(e) => Err(HttpResponse::InternalServerError().json(json!({
+            "error": format!("Failed to get patient: {}", e)
+        }))),
+    }
+}
+
+/// Audit entry for a consent change; failures are logged, not returned.
+async fn audit_consent(state: &AppState, action: &str, consent: &ConsentModel) {
+    let db = state.get_local_db().await;
+    let entry = CreateAuditEntry {
+        action: action.to_string(),
+        entity: "patient".to_string(),
+        record_id: Some(consent.patient_id.to_string()),
+        actor_id: None,
+        details: json!({
+            "consent_id": consent.consent_id,
+            "consent_type": consent.consent_type,
+            "consent_version": consent.consent_version,
+            "witnessed_by": consent.witnessed_by,
+        }),
+    };
+    if let Err(e) = record_audit(&db, None, entry).await {
+        log::warn!("⚠️ Failed to audit {} for patient {}: {}", action, consent.patient_id, e);
+    }
+}
+
+fn record_precondition_failed(current: &MedicalRecordModel) -> HttpResponse {
+    HttpResponse::PreconditionFailed()
+        .insert_header(etag_for(&current.updated_at))
+        .json(json!({
+            "error": "Medical record was modified since it was read",
+            "current": current
+        }))
+}
+
+/// Adds a medical record to a patient's chart
+///
+/// The record is copied to the cloud database (best effort) under the same id.
+///
+/// # Parameters
+/// - `req`: `assessment`, `diagnosis`, `treatment`, `prescription` (all optional)
+///   and `first_audited_by`, the staff member writing the record
+///
+/// # Returns
+/// - `HttpResponse::Created()` with the record and its `ETag`
+/// - `HttpResponse::BadRequest()` if `first_audited_by` is blank
+/// - `HttpResponse::NotFound()` if the patient doesn't exist
+///
+/// # Example
+/// ```
+/// POST /patients/{uuid}/records
+/// Request Body: {"assessment": "Fever for 3 days", "diagnosis": "Dengue fever", "first_audited_by": "Dr. Santos"}
+/// Response: 201 Created with record data
+/// ```
+pub async fn create_medical_record_handler(
+    state: web::Data<AppState>,
+    path: web::Path<Uuid>,
+    req: web::Json<CreateMedicalRecordRequest>,
+) -> Result<HttpResponse> {
+    let create_req = req.into_inner();
+    if create_req.first_audited_by.trim().is_empty() {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "error": "first_audited_by is required"
+        })));
+    }
+
+    let patient_id = path.into_inner();
+    let db = state.get_local_db().await;
+    match create_medical_record(&db, patient_id, Uuid::new_v4(), create_req.clone()).await {
+        Ok(Some(record)) => {
+            if let Some(cloud_db) = state.get_cloud_db().await {
+                let _ = create_medical_record(&cloud_db, patient_id, record.medical_id, create_req).await;
+            }
+            Ok(HttpResponse::Created()
+                .insert_header(etag_for(&record.updated_at))
+                .json(record))
+        }
+        Ok(None) => Ok(HttpResponse::NotFound().json(json!({
+            "error": "Patient not found"
+        }))),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": format!("Failed to create medical record: {}", e)
+        })))
+    }
+}
+
+/// Retrieves a patient's medical records
+///
+/// # Returns
+/// - `HttpResponse::Ok()` with array of records, oldest first
+/// - `HttpResponse::NotFound()` if the patient doesn't exist
+///
+/// # Example
+/// ```
+/// GET /patients/{uuid}/records
+/// Response: 200 OK with array of records ordered by created_at
+/// ```
+pub async fn get_patient_medical_records_handler(
+    state: web::Data<AppState>,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse> {
+    let db = state.get_local_db().await;
+    match get_patient_medical_records(&db, path.into_inner()).await {
+        Ok(Some(records)) => Ok(HttpResponse::Ok().json(records)),
+        Ok(None) => Ok(HttpResponse::NotFound().json(json!({
+            "error": "Patient not found"
+        }))),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": format!("Failed to get medical records: {}", e)
+        })))
+    }
+}
+
+/// Retrieves one medical record of a patient
+///
+/// # Returns
+/// - `HttpResponse::Ok()` with the record and its `ETag`, to be sent back as `If-Match`
+///   when updating it
+/// - `HttpResponse::NotFound()` if the patient doesn't exist or the record isn't theirs
+///
+/// # Example
+/// ```
+/// GET /patients/{uuid}/records/{record_uuid}
+/// Response: 200 OK with record data
+/// ```
+pub async fn get_medical_record_handler(
+    state: web::Data<AppState>,
+    path: web::Path<(Uuid, Uuid)>,
+) -> Result<HttpResponse> {
+    let (patient_id, medical_id) = path.into_inner();
+    if let Err(response) = patient_exists(&state, patient_id).await {
+        return Ok(response);
+    }
+
+    let db = state.get_local_db().await;
+    match get_medical_record(&db, patient_id, medical_id).await {
+        Ok(Some(record)) => Ok(HttpResponse::Ok()
+            .insert_header(etag_for(&record.updated_at))
+            .json(record)),
+        Ok(None) => Ok(HttpResponse::NotFound().json(json!({
+            "error": "Medical record not found"
+        }))),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": format!("Failed to get medical record: {}", e)
+        })))
+    }
+}
+
+/// Updates a patient's medical record
+///
+/// # Parameters
+/// - `http_req`: Request, used to read the `If-Match` header
+/// - `req`: Any of `assessment`, `diagnosis`, `treatment`, `prescription` (omitted fields
+///   are kept) and `last_audited_by`, the staff member making the change
+///
+/// # Returns
+/// - `HttpResponse::Ok()` with the updated record and its new `ETag`
+/// - `HttpResponse::BadRequest()` if `last_audited_by` is blank
+/// - `HttpResponse::NotFound()` if the patient doesn't exist or the record isn't theirs
+/// - `HttpResponse::PreconditionRequired()` (428) if `If-Match` is missing
+/// - `HttpResponse::PreconditionFailed()` (412) with the current record if it changed since it was read
+///
+/// # Example
+/// ```
+/// PUT /patients/{uuid}/records/{record_uuid}
+/// If-Match: "1760857200123456"
+/// Request Body: {"treatment": "Oral rehydration, paracetamol", "last_audited_by": "Dr. Santos"}
+/// Response: 200 OK with updated record data
+/// ```
+pub async fn update_medical_record_handler(
+    state: web::Data<AppState>,
+    http_req: HttpRequest,
+    path: web::Path<(Uuid, Uuid)>,
+    req: web::Json<UpdateMedicalRecordRequest>,
+) -> Result<HttpResponse> {
+    let (patient_id, medical_id) = path.into_inner();
+    let update_req = req.into_inner();
+    if update_req.last_audited_by.trim().is_empty() {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "error": "last_audited_by is required"
+        })));
+    }
+    let expected_version = match required_version(&http_req) {
+        Ok(version) => version,
+        Err(response) => return Ok(response),
+    };
+    if let Err(response) = patient_exists(&state, patient_id).await {
+        return Ok(response);
+    }
+
+    let db = state.get_local_db().await;
+    match update_medical_record_if_unmodified(&db, patient_id, medical_id, expected_version, update_req.clone()).await {
+        Ok(Conditional::Applied(record)) => {
+            if let Some(cloud_db) = state.get_cloud_db().await {
+                let _ = update_medical_record_if_unmodified(&cloud_db, patient_id, medical_id, None, update_req).await;
+            }
+            Ok(HttpResponse::Ok()
+                .insert_header(etag_for(&record.updated_at))
+                .json(record))
+        }
+        Ok(Conditional::Modified(current)) => Ok(record_precondition_failed(&current)),
+        Ok(Conditional::NotFound) => Ok(HttpResponse::NotFound().json(json!({
+            "error": "Medical record not found"
+        }))),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": format!("Failed to update medical record: {}", e)
+        })))
+    }
+}
+
+/// Applies an RFC 7396 merge patch to a patient's medical record
+///
+/// # Parameters
+/// - `http_req`: Request, used to read `Content-Type` and `If-Match`
+/// - `body`: `application/merge-patch+json` document with `last_audited_by`, the
+///   staff member making the change
+///
+/// # Returns
+/// - `HttpResponse::Ok()` with the updated record and its new `ETag`
+/// - `HttpResponse::BadRequest()` if the body is not a valid patch or `last_audited_by` is blank
+/// - `HttpResponse::NotFound()` if the patient doesn't exist or the record isn't theirs
+/// - `HttpResponse::PreconditionRequired()` (428) / `PreconditionFailed()` (412) as for `PUT`
+/// - `HttpResponse::UnsupportedMediaType()` (415) for any other content type
+///
+/// # Patch Semantics
+/// - Absent members are left unchanged
+/// - `null` clears `assessment`, `diagnosis`, `treatment` or `prescription`
+///
+/// # Example
+/// ```
+/// PATCH /patients/{uuid}/records/{record_uuid}
+/// Content-Type: application/merge-patch+json
+/// If-Match: "1760857200123456"
+/// Request Body: {"prescription": null, "last_audited_by": "Dr. Santos"}
+/// Response: 200 OK with updated record data
+/// ```
+pub async fn patch_medical_record_handler(
+    state: web::Data<AppState>,
+    http_req: HttpRequest,
+    path: web::Path<(Uuid, Uuid)>,
+    body: web::Bytes,
+) -> Result<HttpResponse> {
+    let patch: MedicalRecordMergePatch = match parse_merge_patch(&http_req, &body) {
+        Ok(patch) => patch,
+        Err(response) => return Ok(response),
+    };
+    if patch.last_audited_by.trim().is_empty() {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "error": "last_audited_by is required"
+        })));
+    }
+    let expected_version = match required_version(&http_req) {
+        Ok(version) => version,
+        Err(response) => return Ok(response),
+    };
+    let (patient_id, medical_id) = path.into_inner();
+    if let Err(response) = patient_exists(&state, patient_id).await {
+        return Ok(response);
+    }
+
+    let db = state.get_local_db().await;
+    match patch_medical_record_if_unmodified(&db, patient_id, medical_id, expected_version, patch.clone()).await {
+        Ok(Conditional::Applied(record)) => {
+            if let Some(cloud_db) = state.get_cloud_db().await {
+                let _ = patch_medical_record_if_unmodified(&cloud_db, patient_id, medical_id, None, patch).await;
+            }
+            Ok(HttpResponse::Ok()
+                .insert_header(etag_for(&record.updated_at))
+                .json(record))
+        }
+        Ok(Conditional::Modified(current)) => Ok(record_precondition_failed(&current)),
+        Ok(Conditional::NotFound) => Ok(HttpResponse::NotFound().json(json!({
+            "error": "Medical record not found"
+        }))),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": format!("Failed to patch medical record: {}", e)
+        })))
+    }
+}
+
+/// Retrieves the ICD-10 coded diagnoses of a medical record
+///
+/// # Returns
+/// - `HttpResponse::Ok()` with `[{"icd10_code", "description", "rank"}]`, primary first
+/// - `HttpResponse::NotFound()` if the patient doesn't exist or the record isn't theirs
+///
+/// # Example
+/// ```
+/// GET /patients/{uuid}/records/{record_uuid}/diagnoses
+/// Response: 200 OK with array of coded diagnoses
+/// ```
+pub async fn get_medical_record_diagnoses_handler(
+    state: web::Data<AppState>,
+    path: web::Path<(Uuid, Uuid)>,
+) -> Result<HttpResponse> {
+    let (patient_id, medical_id) = path.into_inner();
+    if let Err(response) = patient_exists(&state, patient_id).await {
+        return Ok(response);
+    }
+
+    let db = state.get_local_db().await;
+    match get_medical_record_diagnoses(&db, patient_id, medical_id).await {
+        Ok(Some(diagnoses)) => Ok(HttpResponse::Ok().json(diagnoses)),
+        Ok(None) => Ok(HttpResponse::NotFound().json(json!({
+            "error": "Medical record not found"
+        }))),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": format!("Failed to get diagnoses: {}", e)
+        })))
+    }
+}
+
+/// Replaces the ICD-10 coded diagnoses of a medical record
+///
+/// The free-text `diagnosis` of the record is kept as the clinician's note.
+///
+/// # Parameters
+/// - `req`: `diagnoses`, a list of `{"icd10_code", "rank"}` (`primary` or `secondary`) with
+///   exactly one primary, or empty to remove them, and `last_audited_by`
+///
+/// # Returns
+/// - `HttpResponse::Ok()` with the record's coded diagnoses
+/// - `HttpResponse::BadRequest()` if `last_audited_by` is blank
+/// - `HttpResponse::NotFound()` if the patient doesn't exist or the record isn't theirs
+/// - `HttpResponse::UnprocessableEntity()` (422) for an unknown or repeated code, or not
+///   exactly one primary diagnosis
+///
+/// # Example
+/// ```
+/// PUT /patients/{uuid}/records/{record_uuid}/diagnoses
+/// Request Body: {"diagnoses": [{"icd10_code": "A97.0", "rank": "primary"}, {"icd10_code": "E86", "rank": "secondary"}],
+///                "last_audited_by": "Dr. Santos"}
+/// Response: 200 OK with array of coded diagnoses
+/// ```
+pub async fn set_medical_record_diagnoses_handler(
+    state: web::Data<AppState>,
+    path: web::Path<(Uuid, Uuid)>,
+    req: web::Json<SetDiagnosesRequest>,
+) -> Result<HttpResponse> {
+    let (patient_id, medical_id) = path.into_inner();
+    let set_req = req.into_inner();
+    if set_req.last_audited_by.trim().is_empty() {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "error": "last_audited_by is required"
+        })));
+    }
+    if let Err(response) = patient_exists(&state, patient_id).await {
+        return Ok(response);
+    }
+
+    let db = state.get_local_db().await;
+    match set_medical_record_diagnoses(&db, patient_id, medical_id, set_req).await {
+        Ok(DiagnosesUpdate::Updated(diagnoses)) => Ok(HttpResponse::Ok().json(diagnoses)),
+        Ok(DiagnosesUpdate::RecordNotFound) => Ok(HttpResponse::NotFound().json(json!({
+            "error": "Medical record not found"
+        }))),
+        Ok(DiagnosesUpdate::Invalid(message)) => Ok(HttpResponse::UnprocessableEntity().json(json!({
+            "error": message
+        }))),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": format!("Failed to update diagnoses: {}", e)
+        })))
+    }
+}
+
+/// Lists the prescribed drugs of a medical record
+///
+/// # Returns
+/// - `HttpResponse::Ok()` with array of prescription items, in prescription order
+/// - `HttpResponse::NotFound()` if the patient doesn't exist or the record isn't theirs
+///
+/// # Example
+/// ```
+/// GET /patients/{uuid}/records/{record_uuid}/prescriptions
+/// Response: 200 OK with array of prescription items
+/// ```
+pub async fn get_prescription_items_handler(
+    state: web::Data<AppState>,
+    path: web::Path<(Uuid, Uuid)>,
+) -> Result<HttpResponse> {
+    let (patient_id, medical_id) = path.into_inner();
+    if let Err(response) = patient_exists(&state, patient_id).await {
+        return Ok(response);
+    }
+
+    let db = state.get_local_db().await;
+    match get_prescription_items(&db, patient_id, medical_id).await {
+        Ok(Some(items)) => Ok(HttpResponse::Ok().json(items)),
+        Ok(None) => Ok(HttpResponse::NotFound().json(json!({
+            "error": "Medical record not found"
+        }))),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": format!("Failed to get prescription: {}", e)
+        })))
+    }
+}
+
+/// Adds a drug to the prescription of a medical record
+///
+/// # Parameters
+/// - `req`: `generic_name`, `dose`, `route` (`oral`, `topical`, `intramuscular`, ...) and
+///   `frequency`; optional `brand_name`, `strength`, `duration`, `quantity` and `instructions`;
+///   `first_audited_by`, the staff member writing the prescription
+///
+/// # Returns
+/// - `HttpResponse::Created()` with the prescription item
+/// - `HttpResponse::BadRequest()` if a required field is blank or `quantity` isn't positive
+/// - `HttpResponse::NotFound()` if the patient doesn't exist or the record isn't theirs
+///
+/// # Example
+/// ```
+/// POST /patients/{uuid}/records/{record_uuid}/prescriptions
+/// Request Body: {"generic_name": "Amoxicillin", "strength": "500 mg capsule", "dose": "1 capsule", "route": "oral",
+///                "frequency": "every 8 hours", "duration": "7 days", "quantity": 21, "first_audited_by": "Dr. Santos"}
+/// Response: 201 Created with prescription item data
+/// ```
+pub async fn add_prescription_item_handler(
+    state: web::Data<AppState>,
+    path: web::Path<(Uuid, Uuid)>,
+    req: web::Json<CreatePrescriptionItemRequest>,
+) -> Result<HttpResponse> {
+    let (patient_id, medical_id) = path.into_inner();
+    let create_req = req.into_inner();
+    let required = [&create_req.generic_name, &create_req.dose, &create_req.frequency, &create_req.first_audited_by];
+    if required.iter().any(|field| field.trim().is_empty()) {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "error": "generic_name, dose, frequency and first_audited_by are required"
+        })));
+    }
+    if create_req.quantity.is_some_and(|quantity| quantity <= 0) {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "error": "quantity must be positive"
+        })));
+    }
+    if let Err(response) = patient_exists(&state, patient_id).await {
+        return Ok(response);
+    }
+
+    let db = state.get_local_db().await;
+    match add_prescription_item(&db, patient_id, medical_id, create_req).await {
+        Ok(Some(item)) => Ok(HttpResponse::Created().json(item)),
+        Ok(None) => Ok(HttpResponse::NotFound().json(json!({
+            "error": "Medical record not found"
+        }))),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": format!("Failed to add prescription item: {}", e)
+        })))
+    }
+}
+
+/// Retrieves one prescribed drug of a medical record
+///
+/// # Example
+/// ```
+/// GET /patients/{uuid}/records/{record_uuid}/prescriptions/{item_uuid}
+/// Response: 200 OK with prescription item data, or 404 Not Found
+/// ```
+pub async fn get_prescription_item_handler(
+    state: web::Data<AppState>,
+    path: web::Path<(Uuid, Uuid, Uuid)>,
+) -> Result<HttpResponse> {
+    let (patient_id, medical_id, item_id) = path.into_inner();
+    if let Err(response) = patient_exists(&state, patient_id).await {
+        return Ok(response);
+    }
+
+    let db = state.get_local_db().await;
+    match get_prescription_item(&db, patient_id, medical_id, item_id).await {
+        Ok(Some(item)) => Ok(HttpResponse::Ok().json(item)),
+        Ok(None) => Ok(HttpResponse::NotFound().json(json!({
+            "error": "Prescription item not found"
+        }))),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": format!("Failed to get prescription item: {}", e)
+        })))
+    }
+}
+
+/// Updates a prescribed drug
+///
+/// # Parameters
+/// - `req`: Any of the fields of a new item (omitted fields are kept) and
+///   `last_audited_by`, the staff member making the change
+///
+/// # Returns
+/// - `HttpResponse::Ok()` with the updated prescription item
+/// - `HttpResponse::BadRequest()` if `last_audited_by` or a given required field is blank,
+///   or `quantity` isn't positive
+/// - `HttpResponse::NotFound()` if the patient, record or item doesn't exist
+///
+/// # Example
+/// ```
+/// PUT /patients/{uuid}/records/{record_uuid}/prescriptions/{item_uuid}
+/// Request Body: {"duration": "10 days", "quantity": 30, "last_audited_by": "Dr. Santos"}
+/// Response: 200 OK with updated prescription item data
+/// ```
+pub async fn update_prescription_item_handler(
+    state: web::Data<AppState>,
+    path: web::Path<(Uuid, Uuid, Uuid)>,
+    req: web::Json<UpdatePrescriptionItemRequest>,
+) -> Result<HttpResponse> {
+    let (patient_id, medical_id, item_id) = path.into_inner();
+    let update_req = req.into_inner();
+    if update_req.last_audited_by.trim().is_empty() {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "error": "last_audited_by is required"
+        })));
+    }
+    let required = [&update_req.generic_name, &update_req.dose, &update_req.frequency];
+    if required.iter().any(|field| field.as_deref().is_some_and(|f| f.trim().is_empty())) {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "error": "generic_name, dose and frequency can't be blank"
+        })));
+    }
+    if update_req.quantity.is_some_and(|quantity| quantity <= 0) {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "error": "quantity must be positive"
+        })));
+    }
+    if let Err(response) = patient_exists(&state, patient_id).await {
+        return Ok(response);
+    }
+
+    let db = state.get_local_db().await;
+    match update_prescription_item(&db, patient_id, medical_id, item_id, update_req).await {
+        Ok(Some(item)) => Ok(HttpResponse::Ok().json(item)),
+        Ok(None) => Ok(HttpResponse::NotFound().json(json!({
+            "error": "Prescription item not found"
+        }))),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": format!("Failed to update prescription item: {}", e)
+        })))
+    }
+}
+
+/// Removes a drug from the prescription of a medical record
+///
+/// # Example
+/// ```
+/// DELETE /patients/{uuid}/records/{record_uuid}/prescriptions/{item_uuid}
+/// Response: 204 No Content
+/// ```
+pub async fn delete_prescription_item_handler(
+    state: web::Data<AppState>,
+    path: web::Path<(Uuid, Uuid, Uuid)>,
+) -> Result<HttpResponse> {
+    let (patient_id, medical_id, item_id) = path.into_inner();
+    if let Err(response) = patient_exists(&state, patient_id).await {
+        return Ok(response);
+    }
+
+    let db = state.get_local_db().await;
+    match delete_prescription_item(&db, patient_id, medical_id, item_id).await {
+        Ok(true) => Ok(HttpResponse::NoContent().finish()),
+        Ok(false) => Ok(HttpResponse::NotFound().json(json!({
+            "error": "Prescription item not found"
+        }))),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": format!("Failed to delete prescription item: {}", e)
+        })))
+    }
+}
+
+/// Renders the prescription of a medical record for printing
+///
+/// Plain text: the patient header, one numbered entry per drug with its sig, and
+/// the prescriber. Records without line items show their free-text `prescription`.
+///
+/// # Returns
+/// - `HttpResponse::Ok()` with `text/plain`
+/// - `HttpResponse::NotFound()` if the patient doesn't exist or the record isn't theirs
+///
+/// # Example
+/// ```
+/// GET /patients/{uuid}/records/{record_uuid}/prescriptions/print
+/// Response: 200 OK with the printable prescription
+/// ```
+pub async fn print_prescription_handler(
+    state: web::Data<AppState>,
+    path: web::Path<(Uuid, Uuid)>,
+) -> Result<HttpResponse> {
+    let (patient_id, medical_id) = path.into_inner();
+    if let Err(response) = patient_exists(&state, patient_id).await {
+        return Ok(response);
+    }
+
+    let db = state.get_local_db().await;
+    match get_printable_prescription(&db, patient_id, medical_id).await {
+        Ok(Some(text)) => Ok(HttpResponse::Ok().content_type("text/plain; charset=utf-8").body(text)),
+        Ok(None) => Ok(HttpResponse::NotFound().json(json!({
+            "error": "Medical record not found"
+        }))),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": format!("Failed to render prescription: {}", e)
+        })))
+    }
+}
+
+/// Records a patient's vital signs
+///
+/// # Parameters
+/// - `req`: Any of `systolic_bp` and `diastolic_bp` (together, mmHg), `pulse_rate` (/min),
+///   `temperature_c`, `weight_kg`, `height_cm`; optional `medical_id` of the visit and
+///   `measured_at` (defaults to now); `recorded_by`, the staff member who took them
+///
+/// # Returns
+/// - `HttpResponse::Created()` with the vitals, including the computed `bmi`
+/// - `HttpResponse::BadRequest()` if `recorded_by` is blank
+/// - `HttpResponse::NotFound()` if the patient doesn't exist or the record isn't theirs
+/// - `HttpResponse::UnprocessableEntity()` (422) for no measurement or an implausible one
+///
+/// # Example
+/// ```
+/// POST /patients/{uuid}/vitals
+/// Request Body: {"systolic_bp": 120, "diastolic_bp": 80, "pulse_rate": 72, "temperature_c": 36.8,
+///                "weight_kg": 64.5, "height_cm": 162, "medical_id": "{record_uuid}", "recorded_by": "Nurse Reyes"}
+/// Response: 201 Created with vitals data
+/// ```
+pub async fn record_vitals_handler(
+    state: web::Data<AppState>,
+    path: web::Path<Uuid>,
+    req: web::Json<RecordVitalsRequest>,
+) -> Result<HttpResponse> {
+    let record_req = req.into_inner();
+    if record_req.recorded_by.trim().is_empty() {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "error": "recorded_by is required"
+        })));
+    }
+
+    let db = state.get_local_db().await;
+    match record_vitals(&db, path.into_inner(), record_req).await {
+        Ok(VitalsOutcome::Recorded(vitals)) => Ok(HttpResponse::Created().json(vitals)),
+        Ok(VitalsOutcome::PatientNotFound) => Ok(HttpResponse::NotFound().json(json!({
+            "error": "Patient not found"
+        }))),
+        Ok(VitalsOutcome::RecordNotFound) => Ok(HttpResponse::NotFound().json(json!({
+            "error": "Medical record not found"
+        }))),
+        Ok(VitalsOutcome::Invalid(message)) => Ok(HttpResponse::UnprocessableEntity().json(json!({
+            "error": message
+        }))),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": format!("Failed to record vitals: {}", e)
+        })))
+    }
+}
+
+/// Retrieves a patient's vital signs, or one of them as a time series for charting
+///
+/// # Parameters
+/// - `query`: Optional `type` (`bp`, `pulse`, `temperature`, `weight`, `height`, `bmi`),
+///   `from` and `to` dates (inclusive)
+///
+/// # Returns
+/// - `HttpResponse::Ok()` with array of vitals, oldest first; with `type`,
+///   `{"type", "unit", "points": [{"measured_at", "medical_id", "value"}]}` where blood
+///   pressure points have `systolic` and `diastolic` instead of `value`
+/// - `HttpResponse::NotFound()` if the patient doesn't exist
+///
+/// # Example
+/// ```
+/// GET /patients/{uuid}/vitals?type=bp&from=2026-01-01
+/// Response: 200 OK with {"type": "bp", "unit": "mmHg", "points": [{"measured_at": "...", "systolic": 120, "diastolic": 80}]}
+/// ```
+pub async fn get_patient_vitals_handler(
+    state: web::Data<AppState>,
+    path: web::Path<Uuid>,
+    query: web::Query<VitalsQuery>,
+) -> Result<HttpResponse> {
+    let patient_id = path.into_inner();
+    let query = query.into_inner();
+    let db = state.get_local_db().await;
+    let result = match query.vital_type {
+        Some(vital_type) => get_vital_series(&db, patient_id, vital_type, &query)
+            .await
+            .map(|series| series.map(|series| json!(series))),
+        None => get_patient_vitals(&db, patient_id, &query)
+            .await
+            .map(|vitals| vitals.map(|vitals| json!(vitals))),
+    };
+    match result {
+        Ok(Some(body)) => Ok(HttpResponse::Ok().json(body)),
+        Ok(None) => Ok(HttpResponse::NotFound().json(json!({
+            "error": "Patient not found"
+        }))),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": format!("Failed to get vitals: {}", e)
+        })))
+    }
+}
+
+/// Retrieves the results of the services billed for a medical record's visit
+///
+/// # Returns
+/// - `HttpResponse::Ok()` with one entry per service provided:
+///   `{"mrs_id", "ms_id", "service_name", "results": [...], "pending": ["CODE", ...]}`
+/// - `HttpResponse::NotFound()` if the patient or record doesn't exist
+///
+/// Results are stored in the unit of the service's template; `?units=si` or
+/// `?units=conventional` shows the values and ranges of analytes with a unit
+/// conversion in that system.
+///
+/// # Example
+/// ```
+/// GET /patients/{uuid}/records/{record_uuid}/results?units=conventional
+/// Response: 200 OK with [{"service_name": "Fasting Blood Sugar (FBS)", "results": [{"analyte_code": "GLU", "value": "130", "unit": "mg/dL", "flag": "high"}], "pending": []}]
+/// ```
+pub async fn get_visit_results_handler(
+    state: web::Data<AppState>,
+    path: web::Path<(Uuid, Uuid)>,
+    query: web::Query<UnitsQuery>,
+) -> Result<HttpResponse> {
+    let (patient_id, medical_id) = path.into_inner();
+    if let Err(response) = patient_exists(&state, patient_id).await {
+        return Ok(response);
+    }
+
+    let db = state.get_local_db().await;
+    let results = match (get_visit_results(&db, patient_id, medical_id).await, query.units) {
+        (Ok(Some(results)), Some(system)) => results_in(&db, results, system).await.map(Some),
+        (results, _) => results,
+    };
+    match results {
+        Ok(Some(results)) => Ok(HttpResponse::Ok().json(results)),
+        Ok(None) => Ok(HttpResponse::NotFound().json(json!({
+            "error": "Medical record not found"
+        }))),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": format!("Failed to get results: {}", e)
+        })))
+    }
+}
+
+/// Enters or corrects the results of one service provided at a visit
+///
+/// # Returns
+/// - `HttpResponse::Ok()` with the service's results and the analytes still pending
+/// - `HttpResponse::BadRequest()` if `entered_by` is blank
+/// - `HttpResponse::NotFound()` if the service wasn't provided at the patient's visit
+/// - `HttpResponse::UnprocessableEntity()` for an analyte outside the service's
+///   template, a missing value, a numeric analyte given text, a `unit` the
+///   analyte's value can't be converted from, or results already released
+///
+/// # Example
+/// ```
+/// PUT /patients/{uuid}/records/{record_uuid}/results/{mrs_uuid}
+/// Request Body: {"results": [{"analyte_code": "GLU", "value": "130", "unit": "mg/dL"}], "entered_by": "MedTech Cruz"}
+/// Response: 200 OK with {"service_name": "Fasting Blood Sugar (FBS)", "results": [{"value": "7.2", "unit": "mmol/L", ...}], "pending": []}
+/// ```
+pub async fn enter_service_results_handler(
+    state: web::Data<AppState>,
+    path: web::Path<(Uuid, Uuid, Uuid)>,
+    req: web::Json<EnterResultsRequest>,
+) -> Result<HttpResponse> {
+    let (patient_id, medical_id, mrs_id) = path.into_inner();
+    let enter_req = req.into_inner();
+    if enter_req.entered_by.trim().is_empty() {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "error": "entered_by is required"
+        })));
+    }
+    if let Err(response) = patient_exists(&state, patient_id).await {
+        return Ok(response);
+    }
+
+    let db = state.get_local_db().await;
+    match enter_service_results(&db, patient_id, medical_id, mrs_id, enter_req).await {
+        Ok(ResultsUpdate::Updated(results)) => Ok(HttpResponse::Ok().json(results)),
+        Ok(ResultsUpdate::NotFound) => Ok(HttpResponse::NotFound().json(json!({
+            "error": "Service not provided at this visit"
+        }))),
+        Ok(ResultsUpdate::Invalid(message)) => Ok(HttpResponse::UnprocessableEntity().json(json!({
+            "error": message
+        }))),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": format!("Failed to enter results: {}", e)
+        })))
+    }
+}
+
+/// Releases the results of one service provided at a visit; they can't be
+/// changed or reflagged afterwards
+///
+/// # Returns
+/// - `HttpResponse::Ok()` with the service's results
+/// - `HttpResponse::BadRequest()` if `released_by` is blank
+/// - `HttpResponse::NotFound()` if the service wasn't provided at the patient's visit
+/// - `HttpResponse::UnprocessableEntity()` when analytes are still pending or the
+///   results are already released
+///
+/// # Example
+/// ```
+/// POST /patients/{uuid}/records/{record_uuid}/results/{mrs_uuid}/release
+/// Request Body: {"released_by": "Dr. Villanueva"}
+/// Response: 200 OK with {"service_name": "Creatinine", "results": [{"released_at": "...", ...}], "pending": []}
+/// ```
+pub async fn release_service_results_handler(
+    state: web::Data<AppState>,
+    path: web::Path<(Uuid, Uuid, Uuid)>,
+    req: web::Json<ReleaseResultsRequest>,
+) -> Result<HttpResponse> {
+    let (patient_id, medical_id, mrs_id) = path.into_inner();
+    let release_req = req.into_inner();
+    if release_req.released_by.trim().is_empty() {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "error": "released_by is required"
+        })));
+    }
+    if let Err(response) = patient_exists(&state, patient_id).await {
+        return Ok(response);
+    }
+
+    let db = state.get_local_db().await;
+    match release_service_results(&db, patient_id, medical_id, mrs_id, release_req).await {
+        Ok(ResultsUpdate::Updated(results)) => Ok(HttpResponse::Ok().json(results)),
+        Ok(ResultsUpdate::NotFound) => Ok(HttpResponse::NotFound().json(json!({
+            "error": "Service not provided at this visit"
+        }))),
+        Ok(ResultsUpdate::Invalid(message)) => Ok(HttpResponse::UnprocessableEntity().json(json!({
+            "error": message
+        }))),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": format!("Failed to release results: {}", e)
+        })))
+    }
+}
+
+/// Renders the released laboratory results of a medical record's visit for printing
+///
+/// Plain text: the patient header, then per service its results with flag, unit and
+/// reference range, and who released them. Unreleased services say so instead.
+/// `?units=si` or `?units=conventional` prints in that unit system.
+///
+/// # Returns
+/// - `HttpResponse::Ok()` with `text/plain`
+/// - `HttpResponse::NotFound()` if the patient doesn't exist or the record isn't theirs
+///
+/// # Example
+/// ```
+/// GET /patients/{uuid}/records/{record_uuid}/results/print?units=conventional
+/// Response: 200 OK with the printable laboratory report
+/// ```
+pub async fn print_lab_report_handler(
+    state: web::Data<AppState>,
+    path: web::Path<(Uuid, Uuid)>,
+    query: web::Query<UnitsQuery>,
+) -> Result<HttpResponse> {
+    let (patient_id, medical_id) = path.into_inner();
+    if let Err(response) = patient_exists(&state, patient_id).await {
+        return Ok(response);
+    }
+
+    let db = state.get_local_db().await;
+    match get_printable_lab_report(&db, patient_id, medical_id, query.units).await {
+        Ok(Some(text)) => Ok(HttpResponse::Ok().content_type("text/plain; charset=utf-8").body(text)),
+        Ok(None) => Ok(HttpResponse::NotFound().json(json!({
+            "error": "Medical record not found"
+        }))),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": format!("Failed to render laboratory report: {}", e)
+        })))
+    }
+}
+
+/// Shows a patient's consents: the current state per type and the full history
+///
+/// # Returns
+/// - `HttpResponse::Ok()` with `{"summary": [...], "history": [...]}`
+/// - `HttpResponse::NotFound()` if the patient doesn't exist
+///
+/// # Example
+/// ```
+/// GET /patients/{uuid}/consents
+/// Response: 200 OK with {"summary": [{"consent_type": "sms_notifications", "granted": true, "current": {...}}], "history": [...]}
+/// ```
+pub async fn get_patient_consents_handler(
+    state: web::Data<AppState>,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse> {
+    let patient_id = path.into_inner();
+    if let Err(response) = patient_exists(&state, patient_id).await {
+        return Ok(response);
+    }
+
+    let db = state.get_local_db().await;
+    let summary = get_consent_summary(&db, patient_id).await;
+    let history = get_consent_history(&db, patient_id).await;
+    match (summary, history) {
+        (Ok(summary), Ok(history)) => Ok(HttpResponse::Ok().json(json!({
+            "summary": summary,
+            "history": history
+        }))),
+        (Err(e), _) | (_, Err(e)) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": format!("Failed to get consents: {}", e)
+        })))
+    }
+}
+
+/// Records a consent signed by the patient; replaces an earlier grant of the same type
+///
+/// # Parameters
+/// - `req`: `consent_type` (`data_processing`, `sms_notifications`, `research_use`),
+///   `consent_version` of the consent text, `witnessed_by` and optional `granted_on` (defaults to today)
+///
+/// # Returns
+/// - `HttpResponse::Created()` with the consent
+/// - `HttpResponse::BadRequest()` if `consent_version` or `witnessed_by` is blank
+/// - `HttpResponse::NotFound()` if the patient doesn't exist
+///
+/// # Example
+/// ```
+/// POST /patients/{uuid}/consents
+/// Request Body: {"consent_type": "sms_notifications", "consent_version": "2026-01", "witnessed_by": "Nurse Reyes"}
+/// Response: 201 Created with consent data
+/// ```
+pub async fn grant_consent_handler(
+    state: web::Data<AppState>,
+    path: web::Path<Uuid>,
+    req: web::Json<GrantConsentRequest>,
+) -> Result<HttpResponse> {
+    let patient_id = path.into_inner();
+    let grant_req = req.into_inner();
+    if grant_req.consent_version.trim().is_empty() || grant_req.witnessed_by.trim().is_empty() {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "error": "consent_version and witnessed_by are required"
+        })));
+    }
+    if let Err(response) = patient_exists(&state, patient_id).await {
+        return Ok(response);
+    }
+
+    let db = state.get_local_db().await;
+    match grant_consent(&db, patient_id, grant_req.clone()).await {
+        Ok(consent) => {
+            if let Some(cloud_db) = state.get_cloud_db().await {
+                let _ = grant_consent(&cloud_db, patient_id, grant_req).await;
+            }
+            audit_consent(&state, "consent.granted", &consent).await;
+            Ok(HttpResponse::Created().json(consent))
+        }
+        Err(e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": format!("Failed to record consent: {}", e)
+        })))
+    }
+}
+
+/// Withdraws a patient's active consent of one type
+///
+/// # Returns
+/// - `HttpResponse::Ok()` with the withdrawn consent
+/// - `HttpResponse::NotFound()` if the patient has no active consent of that type
+///
+/// # Example
+/// ```
+/// POST /patients/{uuid}/consents/sms_notifications/withdraw
+/// Request Body: {"reason": "Patient request by phone"}
+/// Response: 200 OK with consent data
+/// ```
+pub async fn withdraw_consent_handler(
+    state: web::Data<AppState>,
+    path: web::Path<(Uuid, ConsentType)>,
+    req: Option<web::Json<WithdrawConsentRequest>>,
+) -> Result<HttpResponse> {
+    let (patient_id, consent_type) = path.into_inner();
+    let withdraw_req = req.map(|r| r.into_inner()).unwrap_or_default();
+    let db = state.get_local_db().await;
+
+    match withdraw_consent(&db, patient_id, consent_type, withdraw_req.clone()).await {
+        Ok(Some(consent)) => {
+            if let Some(cloud_db) = state.get_cloud_db().await {
+                let _ = withdraw_consent(&cloud_db, patient_id, consent_type, withdraw_req).await;
+            }
+            audit_consent(&state, "consent.withdrawn", &consent).await;
+            Ok(HttpResponse::Ok().json(consent))
+        }
+        Ok(None) => Ok(HttpResponse::NotFound().json(json!({
+            "error": "No active consent of this type"
+        }))),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": format!("Failed to withdraw consent: {}", e)
+        })))
+    }
+}
+
+/// Looks up PSGC areas for address autocomplete
+///
+/// # Parameters
+/// - `q`: Part of the area name (case-insensitive)
+/// - `level`: PSGC geographic level (`Reg`, `Prov`, `Dist`, `City`, `Mun`, `SubMun`, `SGU`, `Bgy`)
+/// - `parent_code`: Only areas directly inside this area
+/// - `limit`: Defaults to 20, at most 100
+///
+/// # Example
+/// ```
+/// GET /psgc/areas?level=Reg
+/// GET /psgc/areas?q=san%20jos&level=Bgy&parent_code=0102801000
+/// Response: 200 OK with array of areas ordered by name
+/// ```
+pub async fn search_psgc_areas_handler(
+    state: web::Data<AppState>,
+    query: web::Query<PsgcSearch>,
+) -> Result<HttpResponse> {
+    let db = state.get_local_db().await;
+    match search_psgc_areas(&db, query.into_inner()).await {
+        Ok(areas) => Ok(HttpResponse::Ok().json(areas)),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": format!("Failed to search PSGC areas: {}", e)
+        })))
+    }
+}
+
+/// Retrieves a PSGC area with the areas enclosing it
+///
+/// # Returns
+/// - `HttpResponse::Ok()` with `{"area": ..., "ancestors": [...]}`, region first
+/// - `HttpResponse::NotFound()` if the code is not in the imported PSGC table
+///
+/// # Example
+/// ```
+/// GET /psgc/areas/0102801001
+/// Response: 200 OK with the barangay and its municipality, province and region
+/// ```
+pub async fn get_psgc_area_handler(
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> Result<HttpResponse> {
+    let db = state.get_local_db().await;
+    match get_psgc_area(&db, &path.into_inner()).await {
+        Ok(Some(area)) => Ok(HttpResponse::Ok().json(area)),
+        Ok(None) => Ok(HttpResponse::NotFound().json(json!({
+            "error": "PSGC area not found"
+        }))),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": format!("Failed to get PSGC area: {}", e)
+        })))
+    }
+}
+
+/// Lists the areas directly inside a PSGC area (e.g. the barangays of a city)
+///
+/// # Example
+/// ```
+/// GET /psgc/areas/0102801000/children
+/// Response: 200 OK with array of areas ordered by name
+/// ```
+pub async fn get_psgc_children_handler(
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> Result<HttpResponse> {
+    let db = state.get_local_db().await;
+    match get_psgc_children(&db, &path.into_inner()).await {
+        Ok(areas) => Ok(HttpResponse::Ok().json(areas)),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": format!("Failed to get PSGC areas: {}", e)
+        })))
+    }
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct PatientCountsQuery {
+    pub level: AddressLevel,
+    pub within: Option<String>,
+}
+
+/// Counts active patients per area of their structured address
+///
+/// # Parameters
+/// - `level`: `region`, `province`, `city_municipality` or `barangay`
+/// - `within`: Optional PSGC code of a region, province or city/municipality to restrict to
+///
+/// # Returns
+/// - `HttpResponse::Ok()` with `[{"psgc_code", "name", "patients"}]`, largest first;
+///   patients without a structured address are counted under `psgc_code: null`
+/// - `HttpResponse::UnprocessableEntity()` (422) if `within` is unknown or a barangay
+///
+/// # Example
+/// ```
+/// GET /psgc/patient-counts?level=barangay&within=0102801000
+/// Response: 200 OK with array of counts
+/// ```
+pub async fn get_patient_counts_by_area_handler(
+    state: web::Data<AppState>,
+    query: web::Query<PatientCountsQuery>,
+) -> Result<HttpResponse> {
+    let db = state.get_local_db().await;
+    let query = query.into_inner();
+    match count_patients_by_area(&db, query.level, query.within.as_deref()).await {
+        Ok(Some(counts)) => Ok(HttpResponse::Ok().json(counts)),
+        Ok(None) => Ok(HttpResponse::UnprocessableEntity().json(json!({
+            "error": "within must be the PSGC code of a region, province or city/municipality"
+        }))),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": format!("Failed to count patients: {}", e)
+        })))
+    }
+}
+
+/// Searches the imported ICD-10 codes (lookup and autocomplete)
+///
+/// # Parameters
+/// - `q`: Start of a code, with or without the dot (`j06`, `J06.9`), or part of the description
+/// - `limit`: Defaults to 20, at most 100
+///
+/// # Example
+/// ```
+/// GET /icd10/codes?q=dengue
+/// GET /icd10/codes?q=J06
+/// Response: 200 OK with array of codes ordered by code
+/// ```
+pub async fn search_icd10_codes_handler(
+    state: web::Data<AppState>,
+    query: web::Query<Icd10Search>,
+) -> Result<HttpResponse> {
+    let db = state.get_local_db().await;
+    match search_icd10_codes(&db, query.into_inner()).await {
+        Ok(codes) => Ok(HttpResponse::Ok().json(codes)),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": format!("Failed to search ICD-10 codes: {}", e)
+        })))
+    }
+}
+
+/// Retrieves one ICD-10 code
+///
+/// # Returns
+/// - `HttpResponse::Ok()` with `{"code", "description", "category"}`
+/// - `HttpResponse::NotFound()` if the code is not in the imported ICD-10 table
+///
+/// # Example
+/// ```
+/// GET /icd10/codes/J06.9
+/// Response: 200 OK with the code
+/// ```
+pub async fn get_icd10_code_handler(
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> Result<HttpResponse> {
+    let db = state.get_local_db().await;
+    match get_icd10_code(&db, &path.into_inner()).await {
+        Ok(Some(code)) => Ok(HttpResponse::Ok().json(code)),
+        Ok(None) => Ok(HttpResponse::NotFound().json(json!({
+            "error": "ICD-10 code not found"
+        }))),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": format!("Failed to get ICD-10 code: {}", e)
+        })))
+    }
+}
+
+/// Counts medical records and patients per coded diagnosis
+///
+/// # Parameters
+/// - `from`, `to`: Optional record dates (`YYYY-MM-DD`, inclusive)
+/// - `rank`: Only `primary` or only `secondary` diagnoses
+/// - `by`: `code` (default) or `category` (three-character category, e.g. `J06`)
+///
+/// # Returns
+/// - `HttpResponse::Ok()` with `[{"code", "description", "records", "patients"}]`, most frequent first
+///
+/// # Example
+/// ```
+/// GET /icd10/diagnosis-counts?from=2026-01-01&to=2026-03-31&rank=primary&by=category
+/// Response: 200 OK with array of counts
+/// ```
+pub async fn get_diagnosis_counts_handler(
+    state: web::Data<AppState>,
+    query: web::Query<DiagnosisCountQuery>,
+) -> Result<HttpResponse> {
+    let db = state.get_local_db().await;
+    match count_diagnoses(&db, query.into_inner()).await {
+        Ok(counts) => Ok(HttpResponse::Ok().json(counts)),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": format!("Failed to count diagnoses: {}", e)
+        })))
+    }
+}
+
+/// Creates a household that patients can then be attached to
+///
+/// # Returns
+/// - `HttpResponse::Created()` with the household
+/// - `HttpResponse::BadRequest()` if `household_name` is blank
+///
+/// # Example
+/// ```
+/// POST /households
+/// Request Body: {"household_name": "Dela Cruz family"}
+/// Response: 201 Created with household data
+/// ```
+pub async fn create_household_handler(
+    state: web::Data<AppState>,
+    req: web::Json<CreateHouseholdRequest>,
+) -> Result<HttpResponse> {
+    let create_req = req.into_inner();
+    if create_req.household_name.trim().is_empty() {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "error": "household_name is required"
+        })));
+    }
+
+    // The cloud copy shares the id so members can be attached on both sides.
+    let household_id = Uuid::new_v4();
+    let db = state.get_local_db().await;
+    match create_household(&db, household_id, create_req.clone()).await {
+        Ok(household) => {
+            if let Some(cloud_db) = state.get_cloud_db().await {
+                let _ = create_household(&cloud_db, household_id, create_req).await;
+            }
+            Ok(HttpResponse::Created().json(household))
+        }
+        Err(e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": format!("Failed to create household: {}", e)
+        })))
+    }
+}
+
+/// Retrieves all households
+///
+/// # Example
+/// ```
+/// GET /households
+/// Response: 200 OK with array of households ordered by name
+/// ```
+pub async fn get_all_households_handler(state: web::Data<AppState>) -> Result<HttpResponse> {
+    let db = state.get_local_db().await;
+    match get_all_households(&db).await {
+        Ok(households) => Ok(HttpResponse::Ok().json(households)),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": format!("Failed to get households: {}", e)
+        })))
+    }
+}
+
+/// Retrieves a household with its members
+///
+/// # Returns
+/// - `HttpResponse::Ok()` with the household and `members: [{"role", "joined_at", "patient"}]`, head first
+/// - `HttpResponse::NotFound()` if the household doesn't exist
+///
+/// # Example
+/// ```
+/// GET /households/{uuid}
+/// Response: 200 OK with household data
+/// ```
+pub async fn get_household_handler(
+    state: web::Data<AppState>,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse> {
+    let db = state.get_local_db().await;
+    match get_household(&db, path.into_inner()).await {
+        Ok(Some(household)) => Ok(HttpResponse::Ok().json(household)),
+        Ok(None) => Ok(HttpResponse::NotFound().json(json!({
+            "error": "Household not found"
+        }))),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": format!("Failed to get household: {}", e)
+        })))
+    }
+}
+
+/// Deletes a household; its members stay as patients without a household
+///
+/// # Example
+/// ```
+/// DELETE /households/{uuid}
+/// Response: 204 No Content
+/// ```
+pub async fn delete_household_handler(
+    state: web::Data<AppState>,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse> {
+    let household_id = path.into_inner();
+    let db = state.get_local_db().await;
+    match delete_household(&db, household_id).await {
+        Ok(true) => {
+            if let Some(cloud_db) = state.get_cloud_db().await {
+                let _ = delete_household(&cloud_db, household_id).await;
+            }
+            Ok(HttpResponse::NoContent().finish())
+        }
+        Ok(false) => Ok(HttpResponse::NotFound().json(json!({
+            "error": "Household not found"
+        }))),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": format!("Failed to delete household: {}", e)
+        })))
+    }
+}
+
+/// Attaches a patient to a household, or changes the role of a current member
+///
+/// # Parameters
+/// - `req`: `patient_id` and `role` (`head`, `spouse`, `child`, `parent`, `sibling`,
+///   `grandparent`, `grandchild`, `other_relative`, `non_relative`)
+///
+/// # Returns
+/// - `HttpResponse::Ok()` with the membership
+/// - `HttpResponse::NotFound()` if the household or patient doesn't exist
+/// - `HttpResponse::Conflict()` if the patient belongs to another household,
+///   or the household already has a different head
+///
+/// # Example
+/// ```
+/// POST /households/{uuid}/members
+/// Request Body: {"patient_id": "550e8400-e29b-41d4-a716-446655440000", "role": "spouse"}
+/// Response: 200 OK with membership data
+/// ```
+pub async fn attach_household_member_handler(
+    state: web::Data<AppState>,
+    path: web::Path<Uuid>,
+    req: web::Json<AttachMemberRequest>,
+) -> Result<HttpResponse> {
+    let household_id = path.into_inner();
+    let attach_req = req.into_inner();
+    let db = state.get_local_db().await;
+
+    match attach_member(&db, household_id, attach_req.clone()).await {
+        Ok(AttachOutcome::Attached(member)) => {
+            if let Some(cloud_db) = state.get_cloud_db().await {
+                let _ = attach_member(&cloud_db, household_id, attach_req).await;
+            }
+            Ok(HttpResponse::Ok().json(member))
+        }
+        Ok(AttachOutcome::HouseholdNotFound) => Ok(HttpResponse::NotFound().json(json!({
+            "error": "Household not found"
+        }))),
+        Ok(AttachOutcome::PatientNotFound) => Ok(HttpResponse::NotFound().json(json!({
+            "error": "Patient not found"
+        }))),
+        Ok(AttachOutcome::InOtherHousehold(other)) => Ok(HttpResponse::Conflict().json(json!({
+            "error": "Patient already belongs to another household; detach them first",
+            "household_id": other
+        }))),
+        Ok(AttachOutcome::HeadTaken(head)) => Ok(HttpResponse::Conflict().json(json!({
+            "error": "Household already has a head",
+            "patient_id": head
+        }))),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": format!("Failed to attach household member: {}", e)
+        })))
+    }
+}
+
+/// Detaches a patient from a household
+///
+/// # Example
+/// ```
+/// DELETE /households/{uuid}/members/{patient_uuid}
+/// Response: 204 No Content
+/// ```
+pub async fn detach_household_member_handler(
+    state: web::Data<AppState>,
+    path: web::Path<(Uuid, Uuid)>,
+) -> Result<HttpResponse> {
+    let (household_id, patient_id) = path.into_inner();
+    let db = state.get_local_db().await;
+    match detach_member(&db, household_id, patient_id).await {
+        Ok(true) => {
+            if let Some(cloud_db) = state.get_cloud_db().await {
+                let _ = detach_member(&cloud_db, household_id, patient_id).await;
+            }
+            Ok(HttpResponse::NoContent().finish())
+        }
+        Ok(false) => Ok(HttpResponse::NotFound().json(json!({
+            "error": "Patient is not a member of this household"
+        }))),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": format!("Failed to detach household member: {}", e)
+        })))
+    }
+}
+
+/// Lists the visits (medical records) of every household member together
+///
+/// # Returns
+/// - `HttpResponse::Ok()` with `[{"patient_id", "patient_number", "record"}]`, newest first
+/// - `HttpResponse::NotFound()` if the household doesn't exist
+///
+/// # Example
+/// ```
+/// GET /households/{uuid}/visits
+/// Response: 200 OK with array of visits
+/// ```
+pub async fn get_household_visits_handler(
+    state: web::Data<AppState>,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse> {
+    let db = state.get_local_db().await;
+    match get_household_visits(&db, path.into_inner()).await {
+        Ok(Some(visits)) => Ok(HttpResponse::Ok().json(visits)),
+        Ok(None) => Ok(HttpResponse::NotFound().json(json!({
+            "error": "Household not found"
+        }))),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": format!("Failed to get household visits: {}", e)
+        })))
+    }
+}
+
+/// Combined billing statement for the visits of every household member
+///
+/// # Returns
+/// - `HttpResponse::Ok()` with `{"bills": [{"patient_id", "patient_number", "bill"}], "total_amount", "unpaid_amount"}`
+/// - `HttpResponse::NotFound()` if the household doesn't exist
+///
+/// # Example
+/// ```
+/// GET /households/{uuid}/bills
+/// Response: 200 OK with statement data
+/// ```
+pub async fn get_household_bills_handler(
+    state: web::Data<AppState>,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse> {
+    let db = state.get_local_db().await;
+    match get_household_statement(&db, path.into_inner()).await {
+        Ok(Some(statement)) => Ok(HttpResponse::Ok().json(statement)),
+        Ok(None) => Ok(HttpResponse::NotFound().json(json!({
+            "error": "Household not found"
+        }))),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": format!("Failed to get household bills: {}", e)
+        })))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::connection::DbConnections;
+    use crate::handlers::row_version;
+    use crate::migrations::Migrator;
+    use crate::models::medical_record_tb::ActiveModel as MedicalRecordActiveModel;
+    use crate::models::patient_tb::ActiveModel as PatientActiveModel;
+    use actix_web::dev::ServiceResponse;
+    use actix_web::http::header::{ETAG, IF_MATCH};
+    use actix_web::http::StatusCode;
+    use actix_web::{test, App};
+    use sea_orm::{ActiveModelTrait, Database, Set};
+    use sea_orm_migration::MigratorTrait;
+
+    /// A migrated local database with one patient and one of their records.
+    async fn state_with_record() -> (AppState, Uuid, Uuid) {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        Migrator::up(&db, None).await.unwrap();
+        let now = chrono::Utc::now();
+        let patient_id = Uuid::new_v4();
+        PatientActiveModel {
+            patient_id: Set(patient_id),
+            first_name: Set("Juan".to_string()),
+            last_name: Set("Dela Cruz".to_string()),
+            age: Set(35),
+            birth_date: Set(chrono::NaiveDate::from_ymd_opt(1990, 5, 17).unwrap()),
+            created_at: Set(now),
+            updated_at: Set(now),
+            ..Default::default()
+        }
+        .insert(&db)
+        .await
+        .unwrap();
+        let medical_id = Uuid::new_v4();
+        MedicalRecordActiveModel {
+            medical_id: Set(medical_id),
+            patient_id: Set(patient_id),
+            assessment: Set(Some("Fever for 3 days".to_string())),
+            diagnosis: Set(None),
+            treatment: Set(None),
+            prescription: Set(None),
+            first_audited_by: Set("Dr. Santos".to_string()),
+            last_audited_by: Set(None),
+            created_at: Set(now),
+            updated_at: Set(now),
+        }
+        .insert(&db)
+        .await
+        .unwrap();
+        let state = AppState::new(DbConnections { local: db, cloud: None }, false);
+        (state, patient_id, medical_id)
+    }
+
+    macro_rules! app {
+        ($state:expr) => {
+            test::init_service(
+                App::new()
+                    .app_data(web::Data::new($state.clone()))
+                    .route("/patients/{id}", web::get().to(get_patient_handler))
+                    .route("/patients/{id}", web::put().to(update_patient_handler))
+                    .route("/patients/{id}", web::patch().to(patch_patient_handler))
+                    .route("/patients/{id}", web::delete().to(delete_patient_handler))
+                    .route("/patients/{id}/records/{record_id}", web::get().to(get_medical_record_handler))
+                    .route("/patients/{id}/records/{record_id}", web::put().to(update_medical_record_handler))
+                    .route("/patients/{id}/records/{record_id}", web::patch().to(patch_medical_record_handler)),
+            )
+            .await
+        };
+    }
+
+    fn etag(response: &ServiceResponse) -> String {
+        response.headers().get(ETAG).unwrap().to_str().unwrap().to_string()
+    }
+
+    fn put(uri: &str, if_match: Option<&str>, body: serde_json::Value) -> test::TestRequest {
+        let request = test::TestRequest::put().uri(uri).set_json(body);
+        match if_match {
+            Some(tag) => request.insert_header((IF_MATCH, tag)),
+            None => request,
+        }
+    }
+
+    fn patch(uri: &str, if_match: Option<&str>, body: serde_json::Value) -> test::TestRequest {
+        let request = test::TestRequest::patch()
+            .uri(uri)
+            .insert_header(("Content-Type", "application/merge-patch+json"))
+            .set_payload(body.to_string());
+        match if_match {
+            Some(tag) => request.insert_header((IF_MATCH, tag)),
+            None => request,
+        }
+    }
+
+    #[actix_web::test]
+    async fn patient_writes_without_if_match_are_refused() {
+        let (state, patient_id, _) = state_with_record().await;
+        let app = app!(state);
+        let uri = format!("/patients/{}", patient_id);
+
+        let requests = [
+            put(&uri, None, json!({"first_name": "Juana"})),
+            patch(&uri, None, json!({"first_name": "Juana"})),
+            test::TestRequest::delete().uri(&uri),
+        ];
+        for request in requests {
+            let response = test::call_service(&app, request.to_request()).await;
+            assert_eq!(response.status(), StatusCode::PRECONDITION_REQUIRED);
+        }
+        let stored = get_patient(&state.get_local_db().await, patient_id).await.unwrap().unwrap();
+        assert_eq!(stored.first_name, "Juan");
+    }
+
+    #[actix_web::test]
+    async fn patient_etag_is_the_row_version_and_goes_stale_after_a_write() {
+        let (state, patient_id, _) = state_with_record().await;
+        let app = app!(state);
+        let uri = format!("/patients/{}", patient_id);
+
+        let read = test::call_service(&app, test::TestRequest::get().uri(&uri).to_request()).await;
+        let first = etag(&read);
+        let stored = get_patient(&state.get_local_db().await, patient_id).await.unwrap().unwrap();
+        assert_eq!(first, format!("\"{}\"", row_version(&stored.updated_at)));
+
+        let updated = test::call_service(&app, put(&uri, Some(&first), json!({"first_name": "Juana"})).to_request()).await;
+        assert_eq!(updated.status(), StatusCode::OK);
+        let second = etag(&updated);
+        assert_ne!(second, first);
+        let body: PatientModel = test::read_body_json(updated).await;
+        assert_eq!(second, format!("\"{}\"", row_version(&body.updated_at)));
+
+        // Writing again with the first ETag loses to the write made with it.
+        let stale_put = test::call_service(&app, put(&uri, Some(&first), json!({"first_name": "Juanito"})).to_request()).await;
+        assert_eq!(stale_put.status(), StatusCode::PRECONDITION_FAILED);
+        assert_eq!(etag(&stale_put), second);
+        let body: serde_json::Value = test::read_body_json(stale_put).await;
+        assert_eq!(body["current"]["first_name"], "Juana");
+
+        let stale_patch = test::call_service(&app, patch(&uri, Some(&first), json!({"first_name": "Juanito"})).to_request()).await;
+        assert_eq!(stale_patch.status(), StatusCode::PRECONDITION_FAILED);
+        let stale_delete =
+            test::call_service(&app, test::TestRequest::delete().uri(&uri).insert_header((IF_MATCH, first)).to_request()).await;
+        assert_eq!(stale_delete.status(), StatusCode::PRECONDITION_FAILED);
+
+        let patched = test::call_service(&app, patch(&uri, Some(&second), json!({"middle_name": "Reyes"})).to_request()).await;
+        assert_eq!(patched.status(), StatusCode::OK);
+        let third = etag(&patched);
+        let reread = test::call_service(&app, test::TestRequest::get().uri(&uri).to_request()).await;
+        assert_eq!(etag(&reread), third);
+        let body: PatientModel = test::read_body_json(reread).await;
+        assert_eq!((body.first_name.as_str(), body.middle_name.as_deref()), ("Juana", Some("Reyes")));
+
+        let deleted =
+            test::call_service(&app, test::TestRequest::delete().uri(&uri).insert_header((IF_MATCH, third)).to_request()).await;
+        assert_eq!(deleted.status(), StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    async fn record_updates_check_the_etag_of_the_record() {
+        let (state, patient_id, medical_id) = state_with_record().await;
+        let app = app!(state);
+        let uri = format!("/patients/{}/records/{}", patient_id, medical_id);
+
+        let missing = test::call_service(
+            &app,
+            put(&uri, None, json!({"treatment": "Rest", "last_audited_by": "Dr. Santos"})).to_request(),
+        )
+        .await;
+        assert_eq!(missing.status(), StatusCode::PRECONDITION_REQUIRED);
+        let missing = test::call_service(
+            &app,
+            patch(&uri, None, json!({"treatment": "Rest", "last_audited_by": "Dr. Santos"})).to_request(),
+        )
+        .await;
+        assert_eq!(missing.status(), StatusCode::PRECONDITION_REQUIRED);
+
+        let read = test::call_service(&app, test::TestRequest::get().uri(&uri).to_request()).await;
+        let first = etag(&read);
+        let updated = test::call_service(
+            &app,
+            put(&uri, Some(&first), json!({"treatment": "Oral rehydration", "last_audited_by": "Dr. Santos"})).to_request(),
+        )
+        .await;
+        assert_eq!(updated.status(), StatusCode::OK);
+        let second = etag(&updated);
+        assert_ne!(second, first);
+
+        let stale_put = test::call_service(
+            &app,
+            put(&uri, Some(&first), json!({"treatment": "Paracetamol", "last_audited_by": "Dr. Reyes"})).to_request(),
+        )
+        .await;
+        assert_eq!(stale_put.status(), StatusCode::PRECONDITION_FAILED);
+        assert_eq!(etag(&stale_put), second);
+        let stale_patch = test::call_service(
+            &app,
+            patch(&uri, Some(&first), json!({"treatment": null, "last_audited_by": "Dr. Reyes"})).to_request(),
+        )
+        .await;
+        assert_eq!(stale_patch.status(), StatusCode::PRECONDITION_FAILED);
+
+        let patched = test::call_service(
+            &app,
+            patch(&uri, Some(&second), json!({"diagnosis": "Dengue fever", "last_audited_by": "Dr. Reyes"})).to_request(),
+        )
+        .await;
+        assert_eq!(patched.status(), StatusCode::OK);
+        let reread = test::call_service(&app, test::TestRequest::get().uri(&uri).to_request()).await;
+        assert_eq!(etag(&reread), etag(&patched));
+        let body: serde_json::Value = test::read_body_json(reread).await;
+        assert_eq!(body["treatment"], "Oral rehydration");
+        assert_eq!(body["diagnosis"], "Dengue fever");
+        assert_eq!(body["last_audited_by"], "Dr. Reyes");
+    }
+}
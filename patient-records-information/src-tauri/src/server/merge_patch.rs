@@ -0,0 +1,33 @@
+use actix_web::http::header::CONTENT_TYPE;
+use actix_web::{HttpRequest, HttpResponse};
+use serde::de::DeserializeOwned;
+use serde_json::json;
+
+pub const MERGE_PATCH_JSON: &str = "application/merge-patch+json";
+
+/// Parses a `PATCH` body as an RFC 7396 merge patch.
+///
+/// # Errors
+/// - `415 Unsupported Media Type` unless the body is `application/merge-patch+json`
+/// - `400 Bad Request` when the body is not a JSON object of known members
+pub fn parse_merge_patch<T: DeserializeOwned>(req: &HttpRequest, body: &[u8]) -> Result<T, HttpResponse> {
+    let content_type = req
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.split(';').next())
+        .map(|v| v.trim().to_ascii_lowercase());
+    if content_type.as_deref() != Some(MERGE_PATCH_JSON) {
+        return Err(HttpResponse::UnsupportedMediaType()
+            .insert_header(("Accept-Patch", MERGE_PATCH_JSON))
+            .json(json!({
+                "error": format!("PATCH requires Content-Type: {}", MERGE_PATCH_JSON)
+            })));
+    }
+
+    serde_json::from_slice(body).map_err(|e| {
+        HttpResponse::BadRequest().json(json!({
+            "error": format!("Invalid merge patch: {}", e)
+        }))
+    })
+}
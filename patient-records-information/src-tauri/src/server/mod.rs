@@ -1,6 +1,11 @@
+pub mod auth;
 pub mod config;
+pub mod etag;
 pub mod handlers;
+pub mod merge_patch;
 pub mod middleware;
+pub mod patient_numbers;
+pub mod retention;
 pub mod state;
 
 pub use config::ServerConfig;
@@ -11,9 +16,31 @@ use actix_web::middleware::{DefaultHeaders, Compress};
 use tokio::time::{interval, Duration};
 use crate::database::connection::create_connections;
 use crate::server::{handlers::*, middleware::setup_middleware};
+use crate::fhir::handlers as fhir;
+use crate::hl7::mllp::run_listener;
+use crate::crypto;
+use crate::handlers::retention_handlers::RetentionRule;
+use crate::server::retention::{run_retention_cycle, RetentionPolicy};
+use crate::server::patient_numbers::run_patient_numbering_cycle;
 
 /// Start the Actix web server with dual database support
 pub async fn start_server(config: ServerConfig) -> std::io::Result<()> {
+    // Refuse to start with a broken PII key configuration rather than fail on the first write
+    match crypto::global() {
+        Ok(Some(cipher)) => log::info!("🔐 Patient PII encryption enabled (active key '{}')", cipher.active_key_id()),
+        Ok(None) => log::warn!("⚠️ PII_ENCRYPTION_KEYS not set: patient PII is stored in clear text"),
+        Err(e) => {
+            log::error!("{}", e);
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, e));
+        }
+    }
+
+    let retention_policy = RetentionPolicy {
+        rules: RetentionRule::parse_list(&config.retention_rules)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?,
+        require_cloud: config.enable_cloud_sync,
+    };
+
     // Initialize database connections
     let db_connections = create_connections().await
         .map_err(|e| {
@@ -22,13 +49,14 @@ pub async fn start_server(config: ServerConfig) -> std::io::Result<()> {
         })?;
 
     // Create application state
-    let app_state = AppState::new(db_connections);
+    let app_state = AppState::new(db_connections, config.enable_cloud_sync);
     
     // Set initial cloud availability
     let cloud_available = app_state.db_connections.read().await.cloud.is_some();
     app_state.set_cloud_availability(cloud_available).await;
 
-    // Start cloud health check task
+    // Start cloud health check task; patients still without a number are
+    // numbered on every tick, starting right away
     let health_check_state = app_state.clone();
     let health_check_interval = config.cloud_sync_interval_seconds;
     tokio::spawn(async move {
@@ -36,9 +64,35 @@ pub async fn start_server(config: ServerConfig) -> std::io::Result<()> {
         loop {
             interval.tick().await;
             check_cloud_health(&health_check_state).await;
+            run_patient_numbering_cycle(&health_check_state).await;
         }
     });
 
+    // Start the HL7 v2 MLLP listener
+    if config.mllp_enabled {
+        let mllp_state = app_state.clone();
+        let mllp_addr = format!("{}:{}", config.mllp_host, config.mllp_port);
+        tokio::spawn(async move {
+            if let Err(e) = run_listener(mllp_addr, mllp_state).await {
+                log::error!("HL7 MLLP listener stopped: {}", e);
+            }
+        });
+    }
+
+    // Start the data retention job
+    if config.retention_enabled {
+        let retention_state = app_state.clone();
+        let policy = retention_policy.clone();
+        let retention_interval = config.retention_scan_interval_seconds;
+        tokio::spawn(async move {
+            let mut interval = interval(Duration::from_secs(retention_interval));
+            loop {
+                interval.tick().await;
+                run_retention_cycle(&retention_state, &policy).await;
+            }
+        });
+    }
+
     log::info!("🚀 Starting server on {}:{}", config.host, config.port);
     log::info!("📊 Cloud sync enabled: {}", config.enable_cloud_sync);
     log::info!("🔄 Cloud health check interval: {}s", health_check_interval);
@@ -47,6 +101,7 @@ pub async fn start_server(config: ServerConfig) -> std::io::Result<()> {
     HttpServer::new(move || {
         App::new()
             .app_data(web::Data::new(app_state.clone()))
+            .app_data(web::Data::new(retention_policy.clone()))
             .wrap(setup_middleware())
             .wrap(DefaultHeaders::new().add(("X-Version", "1.0")))
             .wrap(Compress::default())
@@ -58,12 +113,106 @@ pub async fn start_server(config: ServerConfig) -> std::io::Result<()> {
                             .route("", web::get().to(get_all_patients_handler))
                             .route("/{id}", web::get().to(get_patient_handler))
                             .route("/{id}", web::put().to(update_patient_handler))
+                            .route("/{id}", web::patch().to(patch_patient_handler))
                             .route("/{id}", web::delete().to(delete_patient_handler))
+                            .route("/{id}/records", web::get().to(get_patient_medical_records_handler))
+                            .route("/{id}/records", web::post().to(create_medical_record_handler))
+                            .route("/{id}/records/{record_id}", web::get().to(get_medical_record_handler))
+                            .route("/{id}/records/{record_id}", web::put().to(update_medical_record_handler))
+                            .route("/{id}/records/{record_id}", web::patch().to(patch_medical_record_handler))
+                            .route("/{id}/records/{record_id}/diagnoses", web::get().to(get_medical_record_diagnoses_handler))
+                            .route("/{id}/records/{record_id}/diagnoses", web::put().to(set_medical_record_diagnoses_handler))
+                            .route("/{id}/records/{record_id}/prescriptions", web::get().to(get_prescription_items_handler))
+                            .route("/{id}/records/{record_id}/prescriptions", web::post().to(add_prescription_item_handler))
+                            .route("/{id}/records/{record_id}/prescriptions/print", web::get().to(print_prescription_handler))
+                            .route("/{id}/records/{record_id}/prescriptions/{item_id}", web::get().to(get_prescription_item_handler))
+                            .route("/{id}/records/{record_id}/prescriptions/{item_id}", web::put().to(update_prescription_item_handler))
+                            .route("/{id}/records/{record_id}/prescriptions/{item_id}", web::delete().to(delete_prescription_item_handler))
+                            .route("/{id}/vitals", web::get().to(get_patient_vitals_handler))
+                            .route("/{id}/vitals", web::post().to(record_vitals_handler))
+                            .route("/{id}/records/{record_id}/results", web::get().to(get_visit_results_handler))
+                            .route("/{id}/records/{record_id}/results/print", web::get().to(print_lab_report_handler))
+                            .route("/{id}/records/{record_id}/results/{mrs_id}", web::put().to(enter_service_results_handler))
+                            .route("/{id}/records/{record_id}/results/{mrs_id}/release", web::post().to(release_service_results_handler))
+                            .route("/{id}/consents", web::get().to(get_patient_consents_handler))
+                            .route("/{id}/consents", web::post().to(grant_consent_handler))
+                            .route("/{id}/consents/{consent_type}/withdraw", web::post().to(withdraw_consent_handler))
                             .route("/sync", web::post().to(sync_to_cloud_handler))
                     )
+                    .service(
+                        web::scope("/services")
+                            .route("", web::post().to(create_service_handler))
+                            .route("", web::get().to(get_all_services_handler))
+                            .route("/{id}", web::get().to(get_service_handler))
+                            .route("/{id}", web::patch().to(patch_service_handler))
+                            .route("/{id}", web::delete().to(delete_service_handler))
+                            .route("/{id}/analytes", web::get().to(get_service_analytes_handler))
+                            .route("/{id}/analytes", web::put().to(set_service_analytes_handler))
+                    )
+                    .service(
+                        web::scope("/reference-ranges")
+                            .route("", web::get().to(get_reference_ranges_handler))
+                            .route("", web::post().to(create_reference_range_handler))
+                            .route("/{id}", web::get().to(get_reference_range_handler))
+                            .route("/{id}", web::put().to(update_reference_range_handler))
+                            .route("/{id}", web::delete().to(delete_reference_range_handler))
+                    )
+                    .service(
+                        web::scope("/unit-conversions")
+                            .route("", web::get().to(get_unit_conversions_handler))
+                            .route("/{analyte_code}", web::put().to(save_unit_conversion_handler))
+                            .route("/{analyte_code}", web::delete().to(delete_unit_conversion_handler))
+                    )
+                    .service(
+                        web::scope("/households")
+                            .route("", web::post().to(create_household_handler))
+                            .route("", web::get().to(get_all_households_handler))
+                            .route("/{id}", web::get().to(get_household_handler))
+                            .route("/{id}", web::delete().to(delete_household_handler))
+                            .route("/{id}/members", web::post().to(attach_household_member_handler))
+                            .route("/{id}/members/{patient_id}", web::delete().to(detach_household_member_handler))
+                            .route("/{id}/visits", web::get().to(get_household_visits_handler))
+                            .route("/{id}/bills", web::get().to(get_household_bills_handler))
+                    )
+                    .service(
+                        web::scope("/psgc")
+                            .route("/areas", web::get().to(search_psgc_areas_handler))
+                            .route("/areas/{code}", web::get().to(get_psgc_area_handler))
+                            .route("/areas/{code}/children", web::get().to(get_psgc_children_handler))
+                            .route("/patient-counts", web::get().to(get_patient_counts_by_area_handler))
+                    )
+                    .service(
+                        web::scope("/icd10")
+                            .route("/codes", web::get().to(search_icd10_codes_handler))
+                            .route("/codes/{code}", web::get().to(get_icd10_code_handler))
+                            .route("/diagnosis-counts", web::get().to(get_diagnosis_counts_handler))
+                    )
+                    .service(
+                        web::scope("/retention")
+                            .route("/rules", web::get().to(get_retention_rules_handler))
+                            .route("/scan", web::post().to(scan_retention_handler))
+                            .route("/candidates", web::get().to(get_retention_candidates_handler))
+                            .route("/candidates/{id}/approve", web::post().to(approve_retention_candidate_handler))
+                            .route("/candidates/{id}/reject", web::post().to(reject_retention_candidate_handler))
+                    )
+                    .route("/audit-log", web::get().to(get_audit_log_handler))
                     .route("/health", web::get().to(health_check))
                     .route("/db-status", web::get().to(db_status))
             )
+            .service(
+                web::scope("/fhir/r4")
+                    .route("/metadata", web::get().to(fhir::capability_statement))
+                    .route("/Patient", web::get().to(fhir::search_patient_handler))
+                    .route("/Patient", web::post().to(fhir::create_patient_fhir_handler))
+                    .route("/Patient/{id}", web::get().to(fhir::read_patient_handler))
+                    .route("/Encounter", web::get().to(fhir::search_encounter_handler))
+                    .route("/Encounter/{id}", web::get().to(fhir::read_encounter_handler))
+                    .route("/Condition", web::get().to(fhir::search_condition_handler))
+                    .route("/Condition/{id}", web::get().to(fhir::read_condition_handler))
+                    .route("/Observation", web::get().to(fhir::search_observation_handler))
+                    .route("/Observation/{id}", web::get().to(fhir::read_observation_handler))
+                    .default_service(web::to(fhir::unknown_resource_handler))
+            )
     })
     .bind(format!("{}:{}", config.host, config.port))?
     .workers(config.workers.unwrap_or_else(|| num_cpus::get()))
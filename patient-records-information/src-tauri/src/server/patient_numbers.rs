@@ -0,0 +1,147 @@
+use sea_orm::DbErr;
+use uuid::Uuid;
+
+use crate::handlers::patient_number_handlers::{assign_patient_number, assign_pending_patient_numbers};
+use crate::handlers::{create_patient, CreatePatientRequest, PatientNumbering};
+use crate::models::patient_tb::Model as PatientModel;
+use crate::server::state::AppState;
+
+/// Patients numbered per run of the background job.
+const NUMBERING_BATCH_SIZE: u64 = 200;
+
+/// Creates a patient locally and, when reachable, in the cloud database, both
+/// copies under the same id.
+///
+/// The local row is written first, so a failed insert never takes a number.
+/// With cloud numbering it is then numbered from the shared cloud sequence,
+/// the same way the numbering job does, and if the cloud is unreachable the
+/// number is left to that job. Otherwise the local database numbers the
+/// patient in the insert's transaction and the cloud copy (best effort)
+/// carries that number.
+pub async fn create_patient_with_cloud_copy(
+    app_state: &AppState,
+    mut request: CreatePatientRequest,
+) -> Result<PatientModel, DbErr> {
+    let local_db = app_state.get_local_db().await;
+    let cloud_db = app_state.get_cloud_db().await;
+    let patient_id = Uuid::new_v4();
+
+    if !app_state.cloud_numbering {
+        request.numbering = PatientNumbering::Allocate;
+        let patient = create_patient(&local_db, patient_id, request.clone()).await?;
+        if let Some(cloud_db) = cloud_db {
+            request.numbering = patient
+                .patient_number
+                .clone()
+                .map_or(PatientNumbering::Deferred, PatientNumbering::Assigned);
+            let _ = create_patient(&cloud_db, patient_id, request).await;
+        }
+        return Ok(patient);
+    }
+
+    request.numbering = PatientNumbering::Deferred;
+    let mut patient = create_patient(&local_db, patient_id, request.clone()).await?;
+    let Some(cloud_db) = cloud_db else {
+        return Ok(patient);
+    };
+    if let Err(e) = create_patient(&cloud_db, patient_id, request).await {
+        log::warn!("⚠️ Cloud copy of new patient failed: {}", e);
+    }
+    match assign_patient_number(&local_db, Some(&cloud_db), true, &patient).await {
+        Ok(Some(number)) => patient.patient_number = Some(number),
+        Ok(None) => {}
+        Err(e) => log::warn!("⚠️ Numbering new patient failed, number deferred: {}", e),
+    }
+    Ok(patient)
+}
+
+/// One run of the background job: numbers local patients that don't have a
+/// number yet (created while the cloud database was unreachable, or before
+/// patient numbers existed).
+pub async fn run_patient_numbering_cycle(app_state: &AppState) {
+    let local_db = app_state.get_local_db().await;
+    let cloud_db = app_state.get_cloud_db().await;
+    if app_state.cloud_numbering && cloud_db.is_none() {
+        return;
+    }
+
+    loop {
+        match assign_pending_patient_numbers(
+            &local_db,
+            cloud_db.as_ref(),
+            app_state.cloud_numbering,
+            NUMBERING_BATCH_SIZE,
+        )
+        .await
+        {
+            Ok(0) => break,
+            Ok(assigned) => log::info!("🔢 Assigned patient numbers to {} patient(s)", assigned),
+            Err(e) => {
+                log::warn!("⚠️ Assigning patient numbers failed: {}", e);
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::connection::DbConnections;
+    use crate::handlers::patient_number_handlers::{format_patient_number, registration_year};
+    use crate::migrations::Migrator;
+    use sea_orm::{ConnectionTrait, Database, DatabaseConnection};
+    use sea_orm_migration::MigratorTrait;
+
+    async fn database() -> DatabaseConnection {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        Migrator::up(&db, None).await.unwrap();
+        // `create_patient` doesn't set `age`; give the column a default so it can insert.
+        db.execute_unprepared("ALTER TABLE patients_table DROP COLUMN age").await.unwrap();
+        db.execute_unprepared("ALTER TABLE patients_table ADD COLUMN age integer NOT NULL DEFAULT 0")
+            .await
+            .unwrap();
+        db
+    }
+
+    fn request(last_name: &str) -> CreatePatientRequest {
+        CreatePatientRequest {
+            first_name: "Juan".to_string(),
+            last_name: last_name.to_string(),
+            middle_name: None,
+            birth_date: chrono::NaiveDate::from_ymd_opt(1990, 5, 17).unwrap(),
+            sex: None,
+            csd_id_or_pwd_id: None,
+            mobile_number: None,
+            residential_address: None,
+            address: None,
+            numbering: PatientNumbering::Allocate,
+        }
+    }
+
+    #[tokio::test]
+    async fn failed_local_insert_leaves_no_gap_in_cloud_numbers() {
+        let local = database().await;
+        let cloud = database().await;
+        local
+            .execute_unprepared(
+                "CREATE TRIGGER reject_patient BEFORE INSERT ON patients_table WHEN NEW.last_name = 'Rejected' \
+                 BEGIN SELECT RAISE(ABORT, 'rejected'); END",
+            )
+            .await
+            .unwrap();
+        let state = AppState::new(DbConnections { local: local.clone(), cloud: Some(cloud.clone()) }, true);
+        let year = registration_year(&chrono::Utc::now());
+
+        let first = create_patient_with_cloud_copy(&state, request("Dela Cruz")).await.unwrap();
+        assert!(create_patient_with_cloud_copy(&state, request("Rejected")).await.is_err());
+        let second = create_patient_with_cloud_copy(&state, request("Santos")).await.unwrap();
+
+        assert_eq!(first.patient_number, Some(format_patient_number(year, 1)));
+        assert_eq!(second.patient_number, Some(format_patient_number(year, 2)));
+        for db in [&local, &cloud] {
+            let stored = crate::handlers::get_patient(db, second.patient_id).await.unwrap().unwrap();
+            assert_eq!(stored.patient_number, second.patient_number);
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use sea_orm::DatabaseConnection;
+
+use crate::handlers::retention_handlers::{
+    get_retention_candidates, purge_candidate, scan_retention, PurgeOutcome, RetentionRule,
+};
+use crate::models::retention_candidate_tb::CandidateStatus;
+use crate::server::state::AppState;
+
+/// Retention rules plus whether a purge has to reach the cloud database too.
+#[derive(Debug, Clone)]
+pub struct RetentionPolicy {
+    pub rules: Vec<RetentionRule>,
+    /// With cloud sync enabled, purges wait until the cloud database is reachable
+    /// so that no copy is left behind.
+    pub require_cloud: bool,
+}
+
+/// Cloud connection a purge must also run against. `Err(())` means the purge
+/// has to wait because the cloud database is required but unreachable.
+pub async fn purge_cloud_target(
+    app_state: &AppState,
+    policy: &RetentionPolicy,
+) -> Result<Option<DatabaseConnection>, ()> {
+    match app_state.get_cloud_db().await {
+        Some(cloud_db) => Ok(Some(cloud_db)),
+        None if policy.require_cloud => Err(()),
+        None => Ok(None),
+    }
+}
+
+/// One run of the background job: proposes newly due records, then purges the
+/// candidates an Admin has approved (including purges deferred earlier).
+pub async fn run_retention_cycle(app_state: &AppState, policy: &RetentionPolicy) {
+    let db = app_state.get_local_db().await;
+
+    match scan_retention(&db, &policy.rules).await {
+        Ok(proposed) if !proposed.is_empty() => {
+            log::info!("🗄️ Retention scan: {} record(s) awaiting Admin approval", proposed.len())
+        }
+        Ok(_) => {}
+        Err(e) => log::warn!("⚠️ Retention scan failed: {}", e),
+    }
+
+    let approved = match get_retention_candidates(&db, Some(CandidateStatus::Approved)).await {
+        Ok(approved) => approved,
+        Err(e) => {
+            log::warn!("⚠️ Failed to load approved retention candidates: {}", e);
+            return;
+        }
+    };
+    if approved.is_empty() {
+        return;
+    }
+    let Ok(cloud_db) = purge_cloud_target(app_state, policy).await else {
+        log::warn!("⚠️ {} approved purge(s) deferred: cloud database is not available", approved.len());
+        return;
+    };
+
+    for candidate in approved {
+        match purge_candidate(&db, cloud_db.as_ref(), candidate.candidate_id).await {
+            Ok(PurgeOutcome::Purged { certificate, .. }) => log::info!(
+                "🗑️ Disposed of {:?} {} (certificate {})",
+                candidate.entity,
+                candidate.record_id,
+                certificate.audit_id
+            ),
+            Ok(PurgeOutcome::Cancelled(_)) => log::info!(
+                "↩️ Retention candidate {} cancelled: record is no longer due",
+                candidate.candidate_id
+            ),
+            Ok(_) => {}
+            Err(e) => log::warn!("⚠️ Purge of retention candidate {} failed: {}", candidate.candidate_id, e),
+        }
+    }
+}
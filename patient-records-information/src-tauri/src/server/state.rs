@@ -2,18 +2,33 @@ use sea_orm::DatabaseConnection;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use crate::database::connection::DbConnections;
+use crate::handlers::PatientNumbering;
 
 #[derive(Clone)]
 pub struct AppState {
     pub db_connections: Arc<RwLock<DbConnections>>,
     pub cloud_available: Arc<RwLock<bool>>,
+    /// Patient numbers come from the cloud database (cloud sync enabled), so
+    /// that every database syncing with it shares one sequence.
+    pub cloud_numbering: bool,
 }
 
 impl AppState {
-    pub fn new(db_connections: DbConnections) -> Self {
+    pub fn new(db_connections: DbConnections, cloud_numbering: bool) -> Self {
         Self {
             db_connections: Arc::new(RwLock::new(db_connections)),
             cloud_available: Arc::new(RwLock::new(true)), // Will be updated based on connection status
+            cloud_numbering,
+        }
+    }
+
+    /// Numbering for patients written only to the local database: numbered
+    /// right away, or later from the cloud sequence.
+    pub fn local_patient_numbering(&self) -> PatientNumbering {
+        if self.cloud_numbering {
+            PatientNumbering::Deferred
+        } else {
+            PatientNumbering::Allocate
         }
     }
 